@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use warp::log::{Info, Log};
+
+const LOG_DIR: &str = "logs";
+
+#[derive(Debug, Serialize)]
+pub struct AccessLogEntry {
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: f64,
+    pub remote_addr: Option<String>,
+}
+
+// Имя файла содержит дату, поэтому при смене дня запись сама переходит в новый файл
+fn log_path(dir: &Path, now: DateTime<Utc>) -> PathBuf {
+    dir.join(format!("access.{}.log", now.format("%Y-%m-%d")))
+}
+
+fn write_entry(dir: &Path, entry: &AccessLogEntry, now: DateTime<Utc>) {
+    if let Ok(log_entry) = serde_json::to_string(entry) {
+        let _ = std::fs::create_dir_all(dir);
+        if let Ok(mut file) = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path(dir, now))
+        {
+            use std::io::Write;
+            let _ = writeln!(file, "{}", log_entry);
+        }
+    }
+}
+
+fn log_request(dir: &Path, info: Info<'_>) {
+    let now = Utc::now();
+    let entry = AccessLogEntry {
+        timestamp: now.to_rfc3339(),
+        method: info.method().to_string(),
+        path: info.path().to_string(),
+        status: info.status().as_u16(),
+        latency_ms: info.elapsed().as_secs_f64() * 1000.0,
+        remote_addr: info.remote_addr().map(|addr| addr.to_string()),
+    };
+    write_entry(dir, &entry, now);
+}
+
+// Пишет по JSON-строке на каждый запрос в logs/access.YYYY-MM-DD.log
+pub fn access_log_filter(enabled: bool) -> Log<impl Fn(Info<'_>) + Clone + Send + Sync> {
+    access_log_filter_in(enabled.then(|| PathBuf::from(LOG_DIR)))
+}
+
+// Без каталога запросы не записываются
+fn access_log_filter_in(dir: Option<PathBuf>) -> Log<impl Fn(Info<'_>) + Clone + Send + Sync> {
+    let dir = dir.map(Arc::new);
+    warp::log::custom(move |info| {
+        if let Some(dir) = &dir {
+            log_request(dir, info);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    fn logged_lines(dir: &Path) -> Vec<serde_json::Value> {
+        let mut lines = Vec::new();
+        for file in std::fs::read_dir(dir).unwrap() {
+            let content = std::fs::read_to_string(file.unwrap().path()).unwrap();
+            lines.extend(content.lines().map(|line| serde_json::from_str(line).unwrap()));
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn metrics_request_is_logged_once_with_its_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let routes = warp::path("metrics")
+            .map(|| "{}")
+            .with(access_log_filter_in(Some(dir.path().to_path_buf())));
+
+        let response = warp::test::request().method("GET").path("/metrics").reply(&routes).await;
+        assert_eq!(response.status(), 200);
+
+        let lines = logged_lines(dir.path());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["method"], "GET");
+        assert_eq!(lines[0]["path"], "/metrics");
+        assert_eq!(lines[0]["status"], 200);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::monitor::SystemMetrics;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregationWindow {
+    Hourly,
+    Daily,
+}
+
+impl AggregationWindow {
+    fn duration(self) -> TimeDelta {
+        match self {
+            AggregationWindow::Hourly => TimeDelta::hours(1),
+            AggregationWindow::Daily => TimeDelta::days(1),
+        }
+    }
+}
+
+// Метрики за один период; сетевой трафик - сумма байт за период
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregatedMetrics {
+    pub period_start: DateTime<Utc>,
+    pub samples: usize,
+    pub cpu_avg: f64,
+    pub cpu_max: f64,
+    pub memory_avg: f64,
+    pub memory_max: f64,
+    pub disk_avg: f64,
+    pub network_rx_bytes: f64,
+    pub network_tx_bytes: f64,
+    pub processes_avg: f64,
+}
+
+pub fn average(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+pub fn maximum(values: impl Iterator<Item = f64>) -> f64 {
+    values.reduce(f64::max).unwrap_or(0.0)
+}
+
+// Записи группируются по началу часа или суток в UTC, периоды без записей пропускаются
+pub fn aggregate(metrics: &[SystemMetrics], window: AggregationWindow) -> Vec<AggregatedMetrics> {
+    let mut periods: BTreeMap<DateTime<Utc>, Vec<&SystemMetrics>> = BTreeMap::new();
+    for metric in metrics {
+        let start = metric.timestamp.duration_trunc(window.duration()).unwrap_or(metric.timestamp);
+        periods.entry(start).or_default().push(metric);
+    }
+
+    periods
+        .into_iter()
+        .map(|(period_start, samples)| AggregatedMetrics {
+            period_start,
+            samples: samples.len(),
+            cpu_avg: average(samples.iter().map(|m| m.cpu_usage as f64)),
+            cpu_max: maximum(samples.iter().map(|m| m.cpu_usage as f64)),
+            memory_avg: average(samples.iter().map(|m| m.memory_usage_percent as f64)),
+            memory_max: maximum(samples.iter().map(|m| m.memory_usage_percent as f64)),
+            disk_avg: average(samples.iter().map(|m| m.disk_usage_percent as f64)),
+            network_rx_bytes: samples.iter().map(|m| m.network_rx as f64).sum(),
+            network_tx_bytes: samples.iter().map(|m| m.network_tx as f64).sum(),
+            processes_avg: average(samples.iter().map(|m| m.processes_count as f64)),
+        })
+        .collect()
+}
+
+// Точка графика /history/network: скорость сети между соседними записями
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkRate {
+    pub ts: i64,
+    pub rx_bps: u64,
+    pub tx_bps: u64,
+}
+
+// network_rx и network_tx уже хранят байты, полученные с предыдущего цикла сбора,
+// поэтому скорость - это значение записи, деленное на время с предыдущей записи
+pub fn network_rates(metrics: &[SystemMetrics]) -> Vec<NetworkRate> {
+    metrics
+        .windows(2)
+        .filter_map(|pair| {
+            let (previous, current) = (&pair[0], &pair[1]);
+            let elapsed_secs = (current.timestamp - previous.timestamp).as_seconds_f64();
+            if elapsed_secs <= 0.0 {
+                return None;
+            }
+            Some(NetworkRate {
+                ts: current.timestamp.timestamp_millis(),
+                rx_bps: (current.network_rx as f64 / elapsed_secs) as u64,
+                tx_bps: (current.network_tx as f64 / elapsed_secs) as u64,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregationMeta {
+    // Записей истории в запрошенном диапазоне
+    pub total_records: usize,
+    pub periods: usize,
+}
+
+// Ответ /history/hourly и /history/daily
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AggregatedHistory {
+    pub data: Vec<AggregatedMetrics>,
+    pub meta: AggregationMeta,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot(second: u32, rx: u64, tx: u64) -> SystemMetrics {
+        let mut metrics = SystemMetrics::sample(Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, second).unwrap(), 0.0);
+        metrics.network_rx = rx;
+        metrics.network_tx = tx;
+        metrics
+    }
+
+    #[test]
+    fn rate_is_derived_from_adjacent_snapshots() {
+        let rates = network_rates(&[snapshot(0, 999, 999), snapshot(5, 50_000, 1_002)]);
+        assert_eq!(
+            rates,
+            [NetworkRate {
+                ts: Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 5).unwrap().timestamp_millis(),
+                rx_bps: 10_000,
+                tx_bps: 200,
+            }]
+        );
+    }
+
+    #[test]
+    fn snapshots_without_elapsed_time_are_skipped() {
+        assert!(network_rates(&[snapshot(0, 100, 100)]).is_empty());
+        let rates = network_rates(&[snapshot(0, 0, 0), snapshot(0, 100, 100), snapshot(2, 300, 50)]);
+        assert_eq!(rates.len(), 1);
+        assert_eq!((rates[0].rx_bps, rates[0].tx_bps), (150, 25));
+    }
+}
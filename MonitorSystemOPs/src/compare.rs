@@ -0,0 +1,155 @@
+use crate::aggregate::{aggregate, average, maximum, AggregatedMetrics, AggregationWindow};
+use crate::monitor::error::MonitorError;
+use crate::monitor::SystemMetrics;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::IsTerminal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+type PeriodValue = fn(&[AggregatedMetrics]) -> f64;
+
+// Значение периода по часовым агрегатам: каждый час весит одинаково, сколько бы
+// записей в нем ни было
+const COMPARED_METRICS: [(&str, PeriodValue); 8] = [
+    ("cpu_avg", |hours| average(hours.iter().map(|h| h.cpu_avg))),
+    ("cpu_max", |hours| maximum(hours.iter().map(|h| h.cpu_max))),
+    ("memory_avg", |hours| average(hours.iter().map(|h| h.memory_avg))),
+    ("memory_max", |hours| maximum(hours.iter().map(|h| h.memory_max))),
+    ("disk_avg", |hours| average(hours.iter().map(|h| h.disk_avg))),
+    ("network_rx_bytes_per_hour", |hours| average(hours.iter().map(|h| h.network_rx_bytes))),
+    ("network_tx_bytes_per_hour", |hours| average(hours.iter().map(|h| h.network_tx_bytes))),
+    ("processes_avg", |hours| average(hours.iter().map(|h| h.processes_avg))),
+];
+
+// Изменение метрики периода B относительно периода A; снижение считается улучшением
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDelta {
+    pub metric: &'static str,
+    pub period_a: f64,
+    pub period_b: f64,
+    pub delta: f64,
+    // None, если в периоде A значение нулевое
+    pub delta_percent: Option<f64>,
+    pub improved: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub period_a_hours: usize,
+    pub period_b_hours: usize,
+    pub metrics: Vec<MetricDelta>,
+}
+
+pub fn compare_periods(period_a: &[SystemMetrics], period_b: &[SystemMetrics]) -> Result<ComparisonReport> {
+    for (period, metrics) in [("A", period_a), ("B", period_b)] {
+        if metrics.is_empty() {
+            return Err(MonitorError::NoMetrics { period: period.to_string() }.into());
+        }
+    }
+
+    let hours_a = aggregate(period_a, AggregationWindow::Hourly);
+    let hours_b = aggregate(period_b, AggregationWindow::Hourly);
+    let metrics = COMPARED_METRICS
+        .iter()
+        .map(|(metric, value)| {
+            let (a, b) = (value(&hours_a), value(&hours_b));
+            let delta = b - a;
+            MetricDelta {
+                metric,
+                period_a: a,
+                period_b: b,
+                delta,
+                delta_percent: (a != 0.0).then(|| delta / a * 100.0),
+                improved: delta < 0.0,
+            }
+        })
+        .collect();
+
+    Ok(ComparisonReport { period_a_hours: hours_a.len(), period_b_hours: hours_b.len(), metrics })
+}
+
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+impl ComparisonReport {
+    // Улучшения выделяются зеленым, только если вывод идет в терминал
+    pub fn print_table(&self) {
+        let colored = std::io::stdout().is_terminal();
+        println!("📊 Сравнение периодов: A - {} ч, B - {} ч", self.period_a_hours, self.period_b_hours);
+        println!();
+        println!("{:<26} {:>16} {:>16} {:>16} {:>10}", "Метрика", "Период A", "Период B", "Изменение", "%");
+        for row in &self.metrics {
+            let percent = row.delta_percent.map_or_else(|| "-".to_string(), |p| format!("{:+.1}", p));
+            let line = format!(
+                "{:<26} {:>16.1} {:>16.1} {:>+16.1} {:>10}",
+                row.metric, row.period_a, row.period_b, row.delta, percent
+            );
+            if colored && row.improved {
+                println!("{}{}{}", GREEN, line, RESET);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn metric(report: &ComparisonReport, metric: &str) -> MetricDelta {
+        report.metrics.iter().find(|row| row.metric == metric).unwrap().clone()
+    }
+
+    #[test]
+    fn cpu_average_delta_between_periods() {
+        // Час 10:00 со средним 30 весит столько же, сколько час 11:00 с одной записью 50
+        let period_a = [
+            SystemMetrics::sample(at(1, 10, 0), 20.0),
+            SystemMetrics::sample(at(1, 10, 30), 40.0),
+            SystemMetrics::sample(at(1, 11, 0), 50.0),
+        ];
+        let period_b = [SystemMetrics::sample(at(8, 10, 0), 30.0)];
+        let report = compare_periods(&period_a, &period_b).unwrap();
+
+        assert_eq!((report.period_a_hours, report.period_b_hours), (2, 1));
+        let cpu_avg = metric(&report, "cpu_avg");
+        assert_eq!((cpu_avg.period_a, cpu_avg.period_b, cpu_avg.delta), (40.0, 30.0, -10.0));
+        assert_eq!(cpu_avg.delta_percent, Some(-25.0));
+        assert!(cpu_avg.improved);
+
+        let cpu_max = metric(&report, "cpu_max");
+        assert_eq!(cpu_max.delta, -20.0);
+    }
+
+    #[test]
+    fn growth_is_not_an_improvement() {
+        let period_a = [SystemMetrics::sample(at(1, 10, 0), 0.0)];
+        let period_b = [SystemMetrics::sample(at(8, 10, 0), 15.0)];
+        let cpu_avg = metric(&compare_periods(&period_a, &period_b).unwrap(), "cpu_avg");
+        assert_eq!(cpu_avg.delta, 15.0);
+        assert_eq!(cpu_avg.delta_percent, None);
+        assert!(!cpu_avg.improved);
+    }
+
+    #[test]
+    fn empty_period_is_an_error() {
+        let period = [SystemMetrics::sample(at(1, 10, 0), 10.0)];
+        let error = compare_periods(&period, &[]).unwrap_err();
+        match error.downcast_ref::<MonitorError>() {
+            Some(MonitorError::NoMetrics { period }) => assert_eq!(period, "B"),
+            other => panic!("ожидалась NoMetrics, получено {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::convert::Infallible;
+use std::io::Write;
+use warp::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use warp::hyper::{self, Body};
+use warp::reply::{Reply, Response};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionSettings {
+    pub level: u32,
+    pub threshold_bytes: usize,
+}
+
+// Клиент принимает gzip, если он указан в Accept-Encoding без q=0
+pub fn accepts_gzip(accept_encoding: Option<&str>) -> bool {
+    accept_encoding.is_some_and(|value| {
+        value.split(',').any(|encoding| {
+            let mut parts = encoding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or("");
+            let rejected = parts.any(|p| p.replace(' ', "") == "q=0");
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !rejected
+        })
+    })
+}
+
+pub fn gzip(data: &[u8], level: u32) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level.min(9)));
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+// Сжимает тело ответа, если клиент поддерживает gzip и ответ больше порога
+pub async fn compress_reply(
+    accept_encoding: Option<String>,
+    reply: impl Reply,
+    settings: CompressionSettings,
+) -> Result<Response, Infallible> {
+    let response = reply.into_response();
+    if response.headers().contains_key(CONTENT_ENCODING) {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Ошибка чтения ответа для сжатия: {}", e);
+            return Ok(Response::from_parts(parts, Body::empty()));
+        }
+    };
+
+    parts.headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+
+    if bytes.len() <= settings.threshold_bytes || !accepts_gzip(accept_encoding.as_deref()) {
+        return Ok(Response::from_parts(parts, Body::from(bytes)));
+    }
+
+    match gzip(&bytes, settings.level) {
+        Ok(compressed) => {
+            parts.headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+            Ok(Response::from_parts(parts, Body::from(compressed)))
+        }
+        Err(e) => {
+            eprintln!("Ошибка сжатия ответа: {}", e);
+            Ok(Response::from_parts(parts, Body::from(bytes)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use warp::hyper::body::Bytes;
+    use warp::Filter;
+
+    const SETTINGS: CompressionSettings = CompressionSettings { level: 6, threshold_bytes: 1024 };
+
+    // Возвращает тело запроса как ответ, пропуская его через сжатие
+    fn echo() -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+        warp::post()
+            .and(warp::header::optional::<String>("accept-encoding"))
+            .and(warp::body::bytes())
+            .and_then(|encoding, body: Bytes| compress_reply(encoding, body.to_vec(), SETTINGS))
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| b"metrics "[i % 8]).collect()
+    }
+
+    #[tokio::test]
+    async fn large_body_is_gzipped_and_decompresses_to_the_original() {
+        let original = payload(64 * 1024);
+        let response = warp::test::request()
+            .method("POST")
+            .header("accept-encoding", "deflate, gzip;q=0.8")
+            .body(original.clone())
+            .reply(&echo())
+            .await;
+
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[VARY], "Accept-Encoding");
+        assert!(response.body().len() < original.len());
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&response.body()[..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, original);
+    }
+
+    #[tokio::test]
+    async fn small_body_or_no_gzip_support_is_sent_as_is() {
+        for (len, encoding) in [(512, "gzip"), (64 * 1024, "gzip;q=0"), (64 * 1024, "br")] {
+            let original = payload(len);
+            let response = warp::test::request()
+                .method("POST")
+                .header("accept-encoding", encoding)
+                .body(original.clone())
+                .reply(&echo())
+                .await;
+            assert!(!response.headers().contains_key(CONTENT_ENCODING), "{} {}", len, encoding);
+            assert_eq!(response.body()[..], original[..]);
+        }
+    }
+
+    #[test]
+    fn accept_encoding_is_parsed_per_entry() {
+        assert!(accepts_gzip(Some("GZIP")));
+        assert!(accepts_gzip(Some("br, *")));
+        assert!(!accepts_gzip(Some("gzip; q=0")));
+        assert!(!accepts_gzip(None));
+    }
+}
@@ -1,17 +1,128 @@
 use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::time::SystemTime;
 use anyhow::Result;
 
+use crate::monitor::error::MonitorError;
+use crate::notification::{NotificationTemplate, SlackWebhookChannel};
+use crate::security::SecurityManager;
+
+const CONFIG_PATH: &str = "config/config.toml";
+const ENV_PREFIX: &str = "MONITORSYS";
+const PROTECTED_SUFFIX: &str = "_protected";
+
 const DEFAULT_CONFIG: &str = r#"
+[config]
+# Изменения config.toml применяются без перезапуска; при true измененные поля
+# сначала выводятся в консоль со старыми и новыми значениями
+hot_reload_preview = false
+
 [monitoring]
 interval_seconds = 5
+update_check_interval_minutes = 60
+# Сколько уровней дерева процессов (/process-tree) собирается, считая корневые процессы
+tree_depth = 3
+# Сколько миллисекунд ждать команду PowerShell/WMI, прежде чем прервать ее
+wmi_timeout_ms = 5000
+
+# Задачи планировщика Windows, за выполнением которых нужно следить
+# [[monitoring.scheduled_tasks]]
+# name = "NightlyBackup"
+# label = "Ночное резервное копирование"
+# max_missed_interval_minutes = 1500
+
+# Пулы приложений IIS; на машинах без IIS проверка пропускается
+# [[monitoring.iis_app_pools]]
+# name = "DefaultAppPool"
+
+# Сетевые ресурсы (UNC-пути), доступность которых проверяется в каждом цикле
+# [[monitoring.network_shares]]
+# path = '\\fileserver\reports'
+# label = "Отчеты"
+
+# Папки журналов и баз данных, рост которых нужно отслеживать; max_depth - глубина обхода
+# [[monitoring.folder_watches]]
+# path = 'D:\Logs'
+# label = "Журналы приложения"
+# warn_above_gb = 50.0
+# max_depth = 16
+
+# Контроллеры домена: проверяется TCP-подключение к порту LDAP
+# [[monitoring.ldap_checks]]
+# host = "dc01.corp.local"
+# port = 389
+# label = "Контроллер домена"
+
+# Учетные записи служб, у пароля которых проверяется срок действия (нужен модуль ActiveDirectory)
+# [[monitoring.service_accounts]]
+# username = 'CORP\svc-monitor'
 
 [storage]
 max_records = 1000
+# Файл истории больше этого размера читается через отображение в память
+mmap_threshold_mb = 4
+# Копировать историю в metrics.json.bak на время удаления старых записей
+backup_before_cleanup = true
 
 [web]
 host = "127.0.0.1"
 port = 8080
+access_log_enabled = true
+gzip_level = 6
+compression_threshold_bytes = 4096
+cors_origins = ["http://localhost", "http://127.0.0.1"]
+
+[web.tls]
+enabled = false
+cert_path = "config/tls/cert.pem"
+key_path = "config/tls/key.pem"
+
+# Ограничение частоты запросов с одного IP: до capacity запросов подряд,
+# затем refill_per_sec запросов в секунду; сверх лимита сервер отвечает 429
+[web.rate_limit]
+enabled = true
+capacity = 60
+refill_per_sec = 10.0
+
+[alerts]
+cpu_usage = 90.0
+memory_usage = 90.0
+disk_warning = 80.0
+disk_critical = 90.0
+pending_updates = 10
+web_response_warning_ms = 1000
+
+# Пороги для отдельных дисков заменяют disk_warning/disk_critical
+# [[alerts.disk_overrides]]
+# drive = "D:"
+# warning = 95.0
+# critical = 98.0
+
+[notifications]
+# Повторное уведомление об аномалиях - не раньше чем через столько минут
+anomaly_cooldown_minutes = 15
+
+# Подстановки: {level}, {message}, {service}, {timestamp}, {hostname}
+[notifications.templates.start_body]
+body = "Служба мониторинга запущена"
+
+[notifications.templates.stop_body]
+body = "Служба мониторинга остановлена"
+
+[notifications.templates.error_body]
+body = "Ошибка: {message}"
+
+[notifications.templates.anomaly_body]
+body = "Обнаружены аномалии: {message}"
+
+# Отправка уведомлений во входящие вебхуки Slack
+# [[notifications.slack_webhooks]]
+# webhook_url = "https://hooks.slack.com/services/..."
+# или webhook_url_protected = "..." - вывод команды config protect
+# channel = "alerts"
 "#;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -19,36 +130,563 @@ pub struct Config {
     pub monitoring: MonitoringConfig,
     pub storage: StorageConfig,
     pub web: WebConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default, rename = "config")]
+    pub reload: ReloadConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ReloadConfig {
+    pub hot_reload_preview: bool,
+}
+
+// Поле, значение которого различается в двух конфигурациях; отсутствующее значение - "—"
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[CONFIG CHANGE] {}: {} → {}", self.field, self.old_value, self.new_value)
+    }
+}
+
+// Поля сравниваются по TOML-представлению; массивы сравниваются целиком
+pub fn diff_configs(old: &Config, new: &Config) -> Vec<ConfigChange> {
+    let (Ok(old), Ok(new)) = (toml::Value::try_from(old), toml::Value::try_from(new)) else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    diff_values("", Some(&old), Some(&new), &mut changes);
+    changes
+}
+
+fn diff_values(path: &str, old: Option<&toml::Value>, new: Option<&toml::Value>, changes: &mut Vec<ConfigChange>) {
+    if let (Some(toml::Value::Table(old)), Some(toml::Value::Table(new))) = (old, new) {
+        let added = new.keys().filter(|key| !old.contains_key(*key));
+        for key in old.keys().chain(added) {
+            let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            diff_values(&field, old.get(key), new.get(key), changes);
+        }
+    } else if old != new {
+        let display = |value: Option<&toml::Value>| value.map_or("—".to_string(), toml::Value::to_string);
+        changes.push(ConfigChange {
+            field: path.to_string(),
+            old_value: display(old),
+            new_value: display(new),
+        });
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub anomaly_cooldown_minutes: u64,
+    pub templates: NotificationTemplates,
+    pub slack_webhooks: Vec<SlackWebhookChannel>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self { anomaly_cooldown_minutes: 15, templates: NotificationTemplates::default(), slack_webhooks: Vec::new() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NotificationTemplates {
+    pub start_body: NotificationTemplate,
+    pub stop_body: NotificationTemplate,
+    pub error_body: NotificationTemplate,
+    pub anomaly_body: NotificationTemplate,
+}
+
+impl Default for NotificationTemplates {
+    fn default() -> Self {
+        Self {
+            start_body: NotificationTemplate::new("Служба мониторинга запущена"),
+            stop_body: NotificationTemplate::new("Служба мониторинга остановлена"),
+            error_body: NotificationTemplate::new("Ошибка: {message}"),
+            anomaly_body: NotificationTemplate::new("Обнаружены аномалии: {message}"),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MonitoringConfig {
     pub interval_seconds: u64,
+    #[serde(default = "default_update_check_interval")]
+    pub update_check_interval_minutes: u64,
+    #[serde(default = "default_tree_depth")]
+    pub tree_depth: usize,
+    #[serde(default = "default_wmi_timeout_ms")]
+    pub wmi_timeout_ms: u64,
+    #[serde(default)]
+    pub scheduled_tasks: Vec<ScheduledTaskConfig>,
+    #[serde(default)]
+    pub iis_app_pools: Vec<IisPoolConfig>,
+    #[serde(default)]
+    pub network_shares: Vec<NetworkShareConfig>,
+    #[serde(default)]
+    pub ldap_checks: Vec<LdapCheckConfig>,
+    #[serde(default)]
+    pub service_accounts: Vec<ServiceAccountConfig>,
+    #[serde(default)]
+    pub folder_watches: Vec<FolderWatchConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FolderWatchConfig {
+    pub path: String,
+    pub label: String,
+    pub warn_above_gb: f64,
+    // Сколько уровней вложенных папок учитывается при подсчете размера
+    #[serde(default = "default_folder_watch_depth")]
+    pub max_depth: usize,
+}
+
+fn default_folder_watch_depth() -> usize {
+    16
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct IisPoolConfig {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServiceAccountConfig {
+    // DOMAIN\user или просто user
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LdapCheckConfig {
+    pub host: String,
+    #[serde(default = "default_ldap_port")]
+    pub port: u16,
+    pub label: String,
+}
+
+fn default_ldap_port() -> u16 {
+    389
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NetworkShareConfig {
+    pub path: String,
+    pub label: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ScheduledTaskConfig {
+    pub name: String,
+    pub label: String,
+    // Предупреждение, если задача не запускалась дольше; без значения не проверяется
+    #[serde(default)]
+    pub max_missed_interval_minutes: Option<u64>,
+}
+
+fn default_update_check_interval() -> u64 {
+    60
+}
+
+fn default_tree_depth() -> usize {
+    3
+}
+
+fn default_wmi_timeout_ms() -> u64 {
+    5000
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StorageConfig {
     pub max_records: usize,
+    #[serde(default = "default_mmap_threshold_mb")]
+    pub mmap_threshold_mb: usize,
+    #[serde(default = "default_backup_before_cleanup")]
+    pub backup_before_cleanup: bool,
+}
+
+fn default_mmap_threshold_mb() -> usize {
+    4
+}
+
+fn default_backup_before_cleanup() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct WebConfig {
     pub host: String,
     pub port: u16,
+    #[serde(default = "default_true")]
+    pub access_log_enabled: bool,
+    #[serde(default = "default_gzip_level")]
+    pub gzip_level: u32,
+    #[serde(default = "default_compression_threshold")]
+    pub compression_threshold_bytes: usize,
+    #[serde(default = "default_cors_origins")]
+    pub cors_origins: Vec<String>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub capacity: u64,
+    pub refill_per_sec: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { enabled: true, capacity: 60, refill_per_sec: 10.0 }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: "config/tls/cert.pem".to_string(),
+            key_path: "config/tls/key.pem".to_string(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_gzip_level() -> u32 {
+    6
+}
+
+fn default_compression_threshold() -> usize {
+    4096
+}
+
+fn default_cors_origins() -> Vec<String> {
+    vec!["http://localhost".to_string(), "http://127.0.0.1".to_string()]
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub cpu_usage: f32,
+    pub memory_usage: f32,
+    pub disk_warning: f32,
+    pub disk_critical: f32,
+    pub pending_updates: u32,
+    // Предупреждение, если /metrics отвечает дольше, мс
+    pub web_response_warning_ms: u64,
+    pub disk_overrides: Vec<DiskThreshold>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            cpu_usage: 90.0,
+            memory_usage: 90.0,
+            disk_warning: 80.0,
+            disk_critical: 90.0,
+            pending_updates: 10,
+            web_response_warning_ms: 1000,
+            disk_overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DiskThreshold {
+    pub drive: String,
+    pub warning: f32,
+    pub critical: f32,
+}
+
+impl AlertsConfig {
+    // Пороги (warning, critical) для диска; буква диска сравнивается без учета регистра
+    pub fn disk_thresholds(&self, drive: &str) -> (f32, f32) {
+        self.disk_overrides
+            .iter()
+            .find(|o| o.drive.eq_ignore_ascii_case(drive))
+            .map_or((self.disk_warning, self.disk_critical), |o| (o.warning, o.critical))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    EnvVar(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File => write!(f, "from config.toml"),
+            ConfigSource::EnvVar(name) => write!(f, "from env {}", name),
+        }
+    }
+}
+
+// Итоговая конфигурация и источник каждого поля; ключи вида "web.port"
+#[derive(Debug, Clone)]
+pub struct ConfigWithSources {
+    pub config: Config,
+    pub sources: HashMap<String, ConfigSource>,
+    values: toml::Table,
+}
+
+impl ConfigWithSources {
+    pub fn source(&self, key: &str) -> &ConfigSource {
+        self.sources.get(key).unwrap_or(&ConfigSource::Default)
+    }
+
+    // Пути полей, заданных в зашифрованном виде; элементы массивов пути не меняют
+    pub fn protected_fields(&self) -> HashSet<String> {
+        fn collect(path: &str, value: &toml::Value, fields: &mut HashSet<String>) {
+            match value {
+                toml::Value::Table(table) => {
+                    for (key, value) in table {
+                        match key.strip_suffix(PROTECTED_SUFFIX) {
+                            Some(name) => {
+                                fields.insert(format!("{}{}", path, name));
+                            }
+                            None => collect(&format!("{}{}.", path, key), value, fields),
+                        }
+                    }
+                }
+                toml::Value::Array(items) => items.iter().for_each(|item| collect(path, item, fields)),
+                _ => {}
+            }
+        }
+
+        let mut fields = HashSet::new();
+        collect("", &toml::Value::Table(self.values.clone()), &mut fields);
+        fields
+    }
+
+    // Изменение с замаскированными значениями, если оно затрагивает зашифрованное поле
+    pub fn mask_change(&self, mut change: ConfigChange) -> ConfigChange {
+        let prefix = format!("{}.", change.field);
+        if self.protected_fields().iter().any(|field| *field == change.field || field.starts_with(&prefix)) {
+            change.old_value = "***".to_string();
+            change.new_value = "***".to_string();
+        }
+        change
+    }
+
+    // TOML итоговой конфигурации с комментарием об источнике у каждого поля
+    pub fn to_annotated_toml(&self) -> String {
+        let mut output = String::new();
+        for (section, table) in &self.values {
+            let Some(table) = table.as_table() else {
+                continue;
+            };
+            output.push_str(&format!("[{}]\n", section));
+
+            let mut table_arrays = Vec::new();
+            for (key, value) in table {
+                let path = format!("{}.{}", section, key);
+                match value.as_array() {
+                    Some(items) if !items.is_empty() && items.iter().all(toml::Value::is_table) => {
+                        table_arrays.push((path, items));
+                    }
+                    _ => output.push_str(&format!("{} = {} # {}\n", key, value, self.source(&path))),
+                }
+            }
+
+            for (path, items) in table_arrays {
+                output.push_str(&format!("\n# {}\n", self.source(&path)));
+                for item in items.iter().filter_map(toml::Value::as_table) {
+                    output.push_str(&format!("[[{}]]\n", path));
+                    for (key, value) in item {
+                        output.push_str(&format!("{} = {}\n", key, value));
+                    }
+                }
+            }
+            output.push('\n');
+        }
+        output.trim_end().to_string()
+    }
+
+    // config.toml против итоговой конфигурации с умолчаниями и переменными окружения.
+    // Обе стороны заново сериализуются, поэтому комментарии и порядок полей не учитываются;
+    // зашифрованные поля сравниваются в зашифрованном виде
+    pub fn file_diff(&self) -> Result<Vec<(ChangeTag, String)>> {
+        let file: toml::Table = toml::from_str(&fs::read_to_string(CONFIG_PATH)?)?;
+        let old = toml::to_string(&sorted_toml(&toml::Value::Table(file)))?;
+        let new = toml::to_string(&sorted_toml(&toml::Value::Table(self.values.clone())))?;
+        Ok(toml_line_diff(&old, &new))
+    }
+}
+
+// Та же таблица с ключами по алфавиту на всех уровнях, чтобы порядок полей не давал различий
+fn sorted_toml(value: &toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Table(table) => {
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            toml::Value::Table(entries.into_iter().map(|(key, value)| (key.clone(), sorted_toml(value))).collect())
+        }
+        toml::Value::Array(items) => toml::Value::Array(items.iter().map(sorted_toml).collect()),
+        value => value.clone(),
+    }
+}
+
+// Построчное сравнение: Delete - строка есть только в old, Insert - только в new
+pub fn toml_line_diff(old: &str, new: &str) -> Vec<(ChangeTag, String)> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| (change.tag(), change.value().trim_end_matches(['\r', '\n']).to_string()))
+        .collect()
+}
+
+fn env_var_name(section: &str, key: &str) -> String {
+    format!("{}_{}_{}", ENV_PREFIX, section, key).to_uppercase()
+}
+
+// Значение переменной окружения разбирается как TOML, а если не вышло - берется строкой
+fn parse_env_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+// Поле "<имя>_protected" хранит секрет DPAPI в base64; расшифрованный текст
+// подставляется в поле <имя> той же таблицы
+fn unprotect_fields(table: &mut toml::Table) -> Result<()> {
+    use base64::Engine;
+    let mut secrets = Vec::new();
+    for (key, value) in table.iter_mut() {
+        match value {
+            toml::Value::Table(inner) => unprotect_fields(inner)?,
+            toml::Value::Array(items) => {
+                for inner in items.iter_mut().filter_map(toml::Value::as_table_mut) {
+                    unprotect_fields(inner)?;
+                }
+            }
+            toml::Value::String(encoded) => {
+                let Some(name) = key.strip_suffix(PROTECTED_SUFFIX) else {
+                    continue;
+                };
+                let secret = base64::engine::general_purpose::STANDARD
+                    .decode(encoded.trim())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|blob| SecurityManager::unprotect_secret(&blob))
+                    .and_then(|plain| Ok(String::from_utf8(plain)?))
+                    .map_err(|e| MonitorError::ConfigInvalid {
+                        field: key.clone(),
+                        reason: format!("не удалось расшифровать: {}", e),
+                    })?;
+                secrets.push((name.to_string(), secret));
+            }
+            _ => {}
+        }
+    }
+    for (name, secret) in secrets {
+        table.insert(name, toml::Value::String(secret));
+    }
+    Ok(())
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
-        let config_path = "config/config.toml";
-
-        if !std::path::Path::new(config_path).exists() {
+        if !std::path::Path::new(CONFIG_PATH).exists() {
             Self::generate_default()?;
-            println!("Создан файл конфигурации по умолчанию: {}", config_path);
+            println!("Создан файл конфигурации по умолчанию: {}", CONFIG_PATH);
         }
 
-        let config_content = fs::read_to_string(config_path)?;
-        let config: Config = toml::from_str(&config_content)?;
-        Ok(config)
+        Ok(Self::load_with_sources()?.config)
+    }
+
+    // Значения по умолчанию, поверх них config.toml, поверх него переменные MONITORSYS_<РАЗДЕЛ>_<ПОЛЕ>
+    pub fn load_with_sources() -> Result<ConfigWithSources> {
+        let mut values: toml::Table = toml::from_str(DEFAULT_CONFIG)?;
+        let mut sources = HashMap::new();
+
+        if std::path::Path::new(CONFIG_PATH).exists() {
+            let file: toml::Table = toml::from_str(&fs::read_to_string(CONFIG_PATH)?)?;
+            for (section, table) in file {
+                let toml::Value::Table(table) = table else {
+                    return Err(MonitorError::ConfigInvalid {
+                        field: section,
+                        reason: format!("раздел в {} должен быть таблицей", CONFIG_PATH),
+                    }
+                    .into());
+                };
+                let target = values
+                    .entry(section.clone())
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                if let Some(target) = target.as_table_mut() {
+                    for (key, value) in table {
+                        sources.insert(format!("{}.{}", section, key), ConfigSource::File);
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        for (section, table) in values.iter_mut() {
+            let Some(table) = table.as_table_mut() else {
+                continue;
+            };
+            for (key, value) in table.iter_mut() {
+                let name = env_var_name(section, key);
+                if let Ok(raw) = std::env::var(&name) {
+                    *value = parse_env_value(&raw);
+                    sources.insert(format!("{}.{}", section, key), ConfigSource::EnvVar(name));
+                }
+            }
+        }
+
+        // values остаются с зашифрованными полями, чтобы config show не выводил секреты
+        let mut resolved = values.clone();
+        unprotect_fields(&mut resolved)?;
+        let config: Config = resolved.try_into()?;
+        config.validate()?;
+        Ok(ConfigWithSources { config, sources, values })
+    }
+
+    // Проверки, которые нельзя выразить типами полей
+    fn validate(&self) -> Result<()> {
+        if self.web.tls.enabled && !cfg!(feature = "tls") {
+            return Err(MonitorError::ConfigInvalid {
+                field: "web.tls.enabled".to_string(),
+                reason: "программа собрана без поддержки TLS: пересоберите ее с --features tls \
+                         или установите enabled = false"
+                    .to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    // Время изменения config.toml; None, если файла нет
+    pub fn modified_time() -> Option<SystemTime> {
+        fs::metadata(CONFIG_PATH).and_then(|metadata| metadata.modified()).ok()
     }
 
     pub fn generate_default() -> Result<()> {
@@ -57,7 +695,163 @@ impl Config {
             fs::create_dir_all(config_dir)?;
         }
 
-        fs::write("config/config.toml", DEFAULT_CONFIG.trim())?;
+        fs::write(CONFIG_PATH, DEFAULT_CONFIG.trim())?;
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_config() -> Config {
+        toml::from_str(DEFAULT_CONFIG).unwrap()
+    }
+
+    // Поле, которого нет в старом config.toml, получает то же значение, что и в DEFAULT_CONFIG
+    #[test]
+    fn access_log_defaults_match_default_config() {
+        let web: WebConfig = toml::from_str("host = \"127.0.0.1\"\nport = 8080").unwrap();
+        assert_eq!(web.access_log_enabled, default_config().web.access_log_enabled);
+        assert!(web.access_log_enabled);
+    }
+
+    #[test]
+    fn default_config_passes_validation() {
+        assert!(default_config().validate().is_ok());
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[test]
+    fn tls_without_feature_fails_validation() {
+        let mut config = default_config();
+        config.web.tls.enabled = true;
+        let error = config.validate().unwrap_err();
+        assert!(error.to_string().contains("web.tls.enabled"), "{}", error);
+    }
+
+    #[test]
+    fn undecryptable_secret_is_reported_with_its_field() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            [[notifications.slack_webhooks]]
+            webhook_url_protected = "не base64"
+            "#,
+        )
+        .unwrap();
+        let error = unprotect_fields(&mut table).unwrap_err();
+        match error.downcast_ref::<MonitorError>() {
+            Some(MonitorError::ConfigInvalid { field, .. }) => assert_eq!(field, "webhook_url_protected"),
+            other => panic!("ожидалась ConfigInvalid, получено {:?}", other),
+        }
+    }
+
+    #[test]
+    fn env_var_overrides_port_with_its_source() {
+        std::env::set_var("MONITORSYS_WEB_PORT", "9090");
+        let loaded = Config::load_with_sources();
+        std::env::remove_var("MONITORSYS_WEB_PORT");
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.config.web.port, 9090);
+        assert_eq!(loaded.source("web.port"), &ConfigSource::EnvVar("MONITORSYS_WEB_PORT".to_string()));
+        assert!(loaded.to_annotated_toml().contains("port = 9090 # from env MONITORSYS_WEB_PORT"));
+    }
+
+    fn changed_fields(old: &Config, new: &Config) -> Vec<String> {
+        let mut fields: Vec<String> = diff_configs(old, new).into_iter().map(|change| change.field).collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn identical_configs_have_no_changes() {
+        assert!(diff_configs(&default_config(), &default_config()).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_changes_in_every_section() {
+        let old = default_config();
+        let mut new = default_config();
+        new.monitoring.interval_seconds += 5;
+        new.storage.max_records += 1;
+        new.web.port += 1;
+        new.web.tls.enabled = !old.web.tls.enabled;
+        new.alerts.cpu_usage += 1.0;
+        new.notifications.anomaly_cooldown_minutes += 1;
+        new.notifications.templates.start_body.body.push('!');
+        new.reload.hot_reload_preview = !old.reload.hot_reload_preview;
+
+        assert_eq!(
+            changed_fields(&old, &new),
+            [
+                "alerts.cpu_usage",
+                "config.hot_reload_preview",
+                "monitoring.interval_seconds",
+                "notifications.anomaly_cooldown_minutes",
+                "notifications.templates.start_body.body",
+                "storage.max_records",
+                "web.port",
+                "web.tls.enabled",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_change_is_printed_with_both_values() {
+        let old = default_config();
+        let mut new = default_config();
+        new.monitoring.interval_seconds = old.monitoring.interval_seconds + 5;
+        let changes = diff_configs(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].to_string(),
+            format!(
+                "[CONFIG CHANGE] monitoring.interval_seconds: {} → {}",
+                old.monitoring.interval_seconds,
+                new.monitoring.interval_seconds
+            )
+        );
+    }
+
+    #[test]
+    fn added_array_entry_changes_whole_array() {
+        let old = default_config();
+        let mut new = default_config();
+        new.alerts.disk_overrides.push(DiskThreshold { drive: "D:".to_string(), warning: 70.0, critical: 80.0 });
+        let changes = diff_configs(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "alerts.disk_overrides");
+    }
+
+    fn count_tags(diff: &[(ChangeTag, String)]) -> (usize, usize, usize) {
+        diff.iter().fold((0, 0, 0), |(added, removed, equal), (tag, _)| match tag {
+            ChangeTag::Insert => (added + 1, removed, equal),
+            ChangeTag::Delete => (added, removed + 1, equal),
+            ChangeTag::Equal => (added, removed, equal + 1),
+        })
+    }
+
+    #[test]
+    fn toml_line_diff_counts_added_and_removed_lines() {
+        let old = "[monitoring]\ninterval_seconds = 5\n\n[web]\nhost = \"127.0.0.1\"\nport = 8080\n";
+        let new = "[monitoring]\ninterval_seconds = 10\n\n[web]\nhost = \"127.0.0.1\"\nport = 8080\ngzip_level = 6\n";
+        let diff = toml_line_diff(old, new);
+        assert_eq!(count_tags(&diff), (2, 1, 5));
+        assert!(diff.contains(&(ChangeTag::Delete, "interval_seconds = 5".to_string())));
+        assert!(diff.contains(&(ChangeTag::Insert, "interval_seconds = 10".to_string())));
+        assert!(diff.contains(&(ChangeTag::Insert, "gzip_level = 6".to_string())));
+    }
+
+    #[test]
+    fn key_order_does_not_count_as_a_difference() {
+        let sorted = |source: &str| {
+            let table: toml::Table = toml::from_str(source).unwrap();
+            toml::to_string(&sorted_toml(&toml::Value::Table(table))).unwrap()
+        };
+        let old = sorted("[web]\nport = 8080\nhost = \"127.0.0.1\"\n");
+        let new = sorted("[web]\nhost = \"127.0.0.1\"\nport = 8080\n");
+        let (added, removed, _) = count_tags(&toml_line_diff(&old, &new));
+        assert_eq!((added, removed), (0, 0));
+    }
 }
\ No newline at end of file
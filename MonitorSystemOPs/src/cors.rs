@@ -0,0 +1,92 @@
+use warp::cors::Builder;
+use warp::http::Uri;
+
+// Origin должен состоять только из схемы и хоста, иначе warp паникует при разборе
+fn is_valid_origin(origin: &str) -> bool {
+    match origin.parse::<Uri>() {
+        Ok(uri) => {
+            uri.scheme().is_some()
+                && uri.authority().is_some()
+                && uri.path_and_query().is_none_or(|p| p.as_str() == "/" || p.as_str().is_empty())
+        }
+        Err(_) => false,
+    }
+}
+
+// CORS по списку разрешенных источников; "*" разрешает все, пустой список - ни одного
+pub fn cors_filter(origins: &[String]) -> Builder {
+    let cors = warp::cors().allow_methods(["GET", "POST", "DELETE"]);
+    if origins.iter().any(|o| o == "*") {
+        return cors.allow_any_origin();
+    }
+
+    // Без пустого набора warp пропускает любой источник
+    let mut cors = cors.allow_origins(Vec::<&str>::new());
+
+    for origin in origins {
+        let origin = origin.trim_end_matches('/');
+        if is_valid_origin(origin) {
+            cors = cors.allow_origin(origin);
+        } else {
+            eprintln!("⚠️  Пропущен некорректный CORS-источник: {}", origin);
+        }
+    }
+    cors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    async fn request(origins: &[&str], origin: Option<&str>) -> warp::http::Response<warp::hyper::body::Bytes> {
+        let origins: Vec<String> = origins.iter().map(|o| o.to_string()).collect();
+        let routes = warp::path("metrics").map(|| "{}").with(cors_filter(&origins));
+        let mut request = warp::test::request().path("/metrics");
+        if let Some(origin) = origin {
+            request = request.header("origin", origin);
+        }
+        request.reply(&routes).await
+    }
+
+    fn allowed_origin(response: &warp::http::Response<warp::hyper::body::Bytes>) -> Option<&str> {
+        response.headers().get("access-control-allow-origin").and_then(|v| v.to_str().ok())
+    }
+
+    #[tokio::test]
+    async fn listed_origin_is_allowed() {
+        let response = request(&["http://localhost:3000/", "https://ops.example.com"], Some("http://localhost:3000")).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(allowed_origin(&response), Some("http://localhost:3000"));
+    }
+
+    #[tokio::test]
+    async fn unlisted_origin_is_blocked() {
+        for origins in [&["https://ops.example.com"][..], &[]] {
+            let response = request(origins, Some("https://evil.example.com")).await;
+            assert_eq!(response.status(), 403, "{:?}", origins);
+            assert_eq!(allowed_origin(&response), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn wildcard_allows_any_origin() {
+        let response = request(&["*"], Some("https://anything.example.com")).await;
+        assert_eq!(response.status(), 200);
+        assert!(allowed_origin(&response).is_some());
+    }
+
+    #[tokio::test]
+    async fn request_without_origin_is_not_cors() {
+        let response = request(&["https://ops.example.com"], None).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(allowed_origin(&response), None);
+    }
+
+    #[test]
+    fn origins_with_a_path_are_invalid() {
+        assert!(is_valid_origin("https://ops.example.com:8443"));
+        assert!(!is_valid_origin("https://ops.example.com/dashboard"));
+        assert!(!is_valid_origin("ops.example.com"));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::monitor::SystemMetrics;
+use crate::utils::format_throughput;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Tsv,
+    Prometheus,
+}
+
+const COLUMNS: [&str; 12] = [
+    "timestamp_unix",
+    "timestamp",
+    "cpu_usage",
+    "memory_used",
+    "memory_total",
+    "memory_usage_percent",
+    "disk_used",
+    "disk_total",
+    "disk_usage_percent",
+    "network_rx",
+    "network_tx",
+    "processes_count",
+];
+
+// Принимает RFC 3339 или дату YYYY-MM-DD; для верхней границы дата означает конец дня
+pub fn parse_time(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Неверный формат времени '{}': ожидается RFC 3339 или YYYY-MM-DD", value))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    time.map(|t| t.and_utc())
+        .ok_or_else(|| anyhow!("Неверная дата '{}'", value))
+}
+
+pub fn format_metrics(metrics: &[SystemMetrics], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Csv => format_as_csv(metrics),
+        ExportFormat::Tsv => format_as_tsv(metrics),
+        ExportFormat::Json => format_as_json(metrics)?,
+        ExportFormat::Prometheus => format_as_prometheus(metrics),
+    })
+}
+
+fn row_values(metric: &SystemMetrics) -> [String; 12] {
+    [
+        metric.timestamp.timestamp().to_string(),
+        metric.timestamp.to_rfc3339(),
+        metric.cpu_usage.to_string(),
+        metric.memory_used.to_string(),
+        metric.memory_total.to_string(),
+        metric.memory_usage_percent.to_string(),
+        metric.disk_used.to_string(),
+        metric.disk_total.to_string(),
+        metric.disk_usage_percent.to_string(),
+        metric.network_rx.to_string(),
+        metric.network_tx.to_string(),
+        metric.processes_count.to_string(),
+    ]
+}
+
+fn format_delimited(metrics: &[SystemMetrics], separator: &str) -> String {
+    let mut output = COLUMNS.join(separator);
+    output.push('\n');
+    for metric in metrics {
+        output.push_str(&row_values(metric).join(separator));
+        output.push('\n');
+    }
+    output
+}
+
+pub fn format_as_csv(metrics: &[SystemMetrics]) -> String {
+    format_delimited(metrics, ",")
+}
+
+pub fn format_as_tsv(metrics: &[SystemMetrics]) -> String {
+    format_delimited(metrics, "\t")
+}
+
+pub fn format_as_json(metrics: &[SystemMetrics]) -> Result<String> {
+    // Помимо исходных полей добавляем время в секундах Unix
+    let records: Vec<serde_json::Value> = metrics
+        .iter()
+        .map(|metric| {
+            let mut value = serde_json::to_value(metric)?;
+            value["timestamp_unix"] = metric.timestamp.timestamp().into();
+            Ok(value)
+        })
+        .collect::<Result<_>>()?;
+    Ok(serde_json::to_string_pretty(&records)?)
+}
+
+type MetricValue = fn(&SystemMetrics) -> f64;
+type DiskIoValue = fn(&SystemMetrics) -> Option<f64>;
+
+// Текстовый формат Prometheus: по семейству на метрику, отметки времени в миллисекундах
+pub fn format_as_prometheus(metrics: &[SystemMetrics]) -> String {
+    let families: [(&str, &str, MetricValue); 12] = [
+        ("monitor_cpu_usage_percent", "Загрузка CPU, %", |m| m.cpu_usage as f64),
+        ("monitor_memory_used_bytes", "Использовано памяти, байт", |m| m.memory_used as f64),
+        ("monitor_memory_total_bytes", "Всего памяти, байт", |m| m.memory_total as f64),
+        ("monitor_memory_usage_percent", "Загрузка памяти, %", |m| m.memory_usage_percent as f64),
+        ("monitor_disk_used_bytes", "Занято на диске, байт", |m| m.disk_used as f64),
+        ("monitor_disk_total_bytes", "Объем диска, байт", |m| m.disk_total as f64),
+        ("monitor_disk_usage_percent", "Заполнение диска, %", |m| m.disk_usage_percent as f64),
+        ("monitor_network_rx_bytes", "Получено по сети за интервал, байт", |m| m.network_rx as f64),
+        ("monitor_network_tx_bytes", "Отправлено по сети за интервал, байт", |m| m.network_tx as f64),
+        ("monitor_processes_count", "Количество процессов", |m| m.processes_count as f64),
+        ("monitor_uptime_seconds", "Время с запуска службы, с", |m| m.uptime_seconds as f64),
+        ("monitor_restart_count", "Перезапусков службы", |m| m.restart_count as f64),
+    ];
+
+    let mut output = String::new();
+    for (name, help, value) in families {
+        output.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+        for metric in metrics {
+            output.push_str(&format!("{} {} {}\n", name, value(metric), metric.timestamp.timestamp_millis()));
+        }
+    }
+
+    // Скорость дисков есть только у записей, собранных через PDH; рядом с каждым
+    // значением - комментарий с ним же в читаемом виде
+    let disk_io: [(&str, &str, DiskIoValue); 2] = [
+        ("monitor_disk_read_bytes_per_second", "Скорость чтения дисков, байт/с", |m| m.disk_read_bytes_per_sec),
+        ("monitor_disk_write_bytes_per_second", "Скорость записи дисков, байт/с", |m| m.disk_write_bytes_per_sec),
+    ];
+    for (name, help, value) in disk_io {
+        output.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+        for metric in metrics {
+            if let Some(bps) = value(metric) {
+                output.push_str(&format!("# {}\n", format_throughput(bps as u64)));
+                output.push_str(&format!("{} {} {}\n", name, bps, metric.timestamp.timestamp_millis()));
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn two_records() -> Vec<SystemMetrics> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        vec![
+            SystemMetrics::sample(start, 12.5),
+            SystemMetrics::sample(start + chrono::Duration::minutes(1), 50.0),
+        ]
+    }
+
+    #[test]
+    fn csv_and_tsv_have_a_header_and_a_row_per_record() {
+        for (format, separator) in [(ExportFormat::Csv, ","), (ExportFormat::Tsv, "\t")] {
+            let output = format_metrics(&two_records(), format).unwrap();
+            let lines: Vec<&str> = output.lines().collect();
+            assert_eq!(lines.len(), 3, "{:?}", format);
+            assert_eq!(lines[0], COLUMNS.join(separator));
+            let first: Vec<&str> = lines[1].split(separator).collect();
+            assert_eq!(first.len(), COLUMNS.len());
+            assert_eq!(first[..3], ["1704067200", "2024-01-01T00:00:00+00:00", "12.5"]);
+            assert!(lines[2].starts_with(&["1704067260", "2024-01-01T00:01:00+00:00", "50"].join(separator)));
+        }
+    }
+
+    #[test]
+    fn json_adds_unix_time_to_every_record() {
+        let output = format_metrics(&two_records(), ExportFormat::Json).unwrap();
+        let records: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["timestamp_unix"], 1704067200);
+        assert_eq!(records[1]["timestamp_unix"], 1704067260);
+        assert_eq!(records[1]["cpu_usage"], 50.0);
+        assert_eq!(records[0]["processes_count"], 120);
+    }
+
+    #[test]
+    fn prometheus_has_a_sample_per_record_in_every_family() {
+        let output = format_metrics(&two_records(), ExportFormat::Prometheus).unwrap();
+        assert!(output.contains("# TYPE monitor_cpu_usage_percent gauge\n"));
+        assert!(output.contains("monitor_cpu_usage_percent 12.5 1704067200000\nmonitor_cpu_usage_percent 50 1704067260000\n"));
+        assert_eq!(output.lines().filter(|line| line.starts_with("monitor_processes_count ")).count(), 2);
+        // Без данных PDH семейства скорости дисков остаются пустыми
+        assert!(!output.lines().any(|line| line.starts_with("monitor_disk_read_bytes_per_second ")));
+    }
+
+    #[test]
+    fn dates_cover_the_whole_day_as_an_upper_bound() {
+        assert_eq!(parse_time("2024-01-01", false).unwrap(), Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(parse_time("2024-01-01", true).unwrap(), Utc.with_ymd_and_hms(2024, 1, 1, 23, 59, 59).unwrap());
+        assert_eq!(
+            parse_time("2024-01-01T12:00:00+03:00", false).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap()
+        );
+        assert!(parse_time("01.01.2024", false).is_err());
+    }
+}
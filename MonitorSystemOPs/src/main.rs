@@ -1,20 +1,40 @@
+mod access_log;
+mod aggregate;
+mod compression;
 mod config;
+mod cors;
 mod monitor;
+mod rate_limit;
 mod storage;
+mod tls;
 mod service_manager;
+mod export;
+mod compare;
 mod notification;
 mod security;
+mod snooze;
+mod utils;
 
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use clap::{Parser, Subcommand};
 
-use crate::config::Config;
-use crate::monitor::ResourceMonitor;
-use crate::storage::Storage;
-use crate::service_manager::WindowsServiceManager;
+use crate::aggregate::AggregationWindow;
+use crate::compression::CompressionSettings;
+use crate::config::{diff_configs, Config, NotificationsConfig, WebConfig};
+use crate::export::ExportFormat;
+use crate::compare::OutputFormat;
+use crate::monitor::{
+    check_ldap_hosts, measure_response_time, record_anomalies, AlertLevel, AnomalyRecord, ResourceMonitor,
+    ALERT_HISTORY_CAPACITY,
+};
 use crate::notification::NotificationSystem;
 use crate::security::SecurityManager;
+use crate::storage::Storage;
+use crate::service_manager::{ServiceStats, WindowsServiceManager};
+use crate::snooze::Snoozes;
 
 #[derive(Parser)]
 #[command(name = "MonitorSystemOPs")]
@@ -33,7 +53,137 @@ enum Commands {
     Restart,
     Status,
     Run,
-    Config,
+    /// Работа с конфигурацией; без подкоманды создает файл по умолчанию
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
+    },
+    /// Выгрузить сохраненные метрики за период
+    ExportMetrics {
+        /// Начало периода (RFC 3339 или YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<String>,
+        /// Конец периода (RFC 3339 или YYYY-MM-DD, включительно)
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+        /// Файл для записи; по умолчанию вывод в stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Сравнить средние показатели двух периодов по часовым агрегатам
+    Compare {
+        /// Начало периода A, с которым сравнивается B (RFC 3339 или YYYY-MM-DD)
+        #[arg(long)]
+        period_a_from: String,
+        /// Конец периода A (включительно)
+        #[arg(long)]
+        period_a_to: String,
+        #[arg(long)]
+        period_b_from: String,
+        #[arg(long)]
+        period_b_to: String,
+        #[arg(long, value_enum, default_value = "table")]
+        format: OutputFormat,
+    },
+    /// Восстановить историю метрик из data/metrics.json.bak после прерванной очистки
+    RecoverStorage,
+    /// Создать самоподписанный сертификат по путям из [web.tls]
+    GenerateTlsCert,
+    /// Замерить время сбора метрик
+    Benchmark {
+        /// Количество замеров
+        #[arg(long, short, default_value_t = 10)]
+        iterations: usize,
+        /// Вывести результат в формате JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Отправить тестовое уведомление во все настроенные каналы
+    TestNotification {
+        #[arg(long, value_enum, default_value = "warning")]
+        level: AlertLevel,
+        /// Текст уведомления
+        #[arg(long)]
+        message: Option<String>,
+        /// Только показать, что будет отправлено
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Показать итоговую конфигурацию с источником каждого поля
+    Show,
+    /// Создать config/config.toml со значениями по умолчанию
+    Generate,
+    /// Зашифровать секрет из stdin для поля вида webhook_url_protected
+    Protect,
+    /// Сравнить config.toml с итоговой конфигурацией; код выхода 1, если они различаются
+    Diff,
+}
+
+#[derive(serde::Deserialize)]
+struct AlertsQuery {
+    limit: Option<usize>,
+}
+
+#[derive(serde::Deserialize)]
+struct NetworkHistoryQuery {
+    minutes: Option<u32>,
+}
+
+// Границы для /history/hourly и /history/daily в формате RFC 3339 или YYYY-MM-DD
+#[derive(serde::Deserialize)]
+struct RangeQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+async fn aggregated_history(
+    window: AggregationWindow,
+    query: RangeQuery,
+    storage: Arc<Storage>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    use warp::Reply;
+
+    let parse = |value: Option<String>, end_of_day| value.map(|v| export::parse_time(&v, end_of_day)).transpose();
+    let (from, to) = match parse(query.from, false).and_then(|from| Ok((from, parse(query.to, true)?))) {
+        Ok(range) => range,
+        Err(e) => {
+            let error = warp::reply::json(&serde_json::json!({ "error": e.to_string() }));
+            return Ok(warp::reply::with_status(error, warp::http::StatusCode::BAD_REQUEST).into_response());
+        }
+    };
+    match storage.aggregate(window, from, to) {
+        Ok(history) => Ok(warp::reply::json(&history).into_response()),
+        Err(_) => Err(warp::reject::not_found()),
+    }
+}
+
+// GET /history/hourly и /history/daily с необязательными from и to
+fn aggregated_history_routes(
+    storage: Arc<Storage>,
+) -> impl warp::Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    use warp::Filter;
+
+    let storage_filter = warp::any().map(move || storage.clone());
+
+    let hourly = warp::path!("history" / "hourly")
+        .and(warp::get())
+        .and(warp::query::<RangeQuery>())
+        .and(storage_filter.clone())
+        .and_then(|query, storage| aggregated_history(AggregationWindow::Hourly, query, storage));
+
+    let daily = warp::path!("history" / "daily")
+        .and(warp::get())
+        .and(warp::query::<RangeQuery>())
+        .and(storage_filter)
+        .and_then(|query, storage| aggregated_history(AggregationWindow::Daily, query, storage));
+
+    hourly.or(daily).unify()
 }
 
 #[tokio::main]
@@ -61,9 +211,52 @@ async fn main() -> anyhow::Result<()> {
         Some(Commands::Status) => {
             WindowsServiceManager::status()?;
         }
-        Some(Commands::Config) => {
+        Some(Commands::Config { action: Some(ConfigCommand::Show) }) => {
+            println!("{}", Config::load_with_sources()?.to_annotated_toml());
+        }
+        Some(Commands::Config { action: Some(ConfigCommand::Generate) | None }) => {
             Config::generate_default()?;
         }
+        Some(Commands::Config { action: Some(ConfigCommand::Protect) }) => {
+            use base64::Engine;
+            let mut secret = String::new();
+            std::io::stdin().read_line(&mut secret)?;
+            let blob = security::SecurityManager::protect_secret(secret.trim_end_matches(['\r', '\n']).as_bytes())?;
+            println!("{}", base64::engine::general_purpose::STANDARD.encode(blob));
+        }
+        Some(Commands::Config { action: Some(ConfigCommand::Diff) }) => {
+            if print_config_diff()? {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::ExportMetrics { from, to, format, output }) => {
+            export_metrics(from, to, format, output)?;
+        }
+        Some(Commands::Compare { period_a_from, period_a_to, period_b_from, period_b_to, format }) => {
+            compare_metrics((&period_a_from, &period_a_to), (&period_b_from, &period_b_to), format)?;
+        }
+        Some(Commands::RecoverStorage) => {
+            let recovered = Storage::new().recover_from_backup()?;
+            println!("✅ История восстановлена из резервной копии, записей: {}", recovered);
+        }
+        Some(Commands::GenerateTlsCert) => {
+            let config = Config::load()?;
+            tls::generate_self_signed(&config.web.tls)?;
+            println!("🔐 Сертификат: {}", config.web.tls.cert_path);
+            println!("🔐 Ключ: {}", config.web.tls.key_path);
+        }
+        Some(Commands::Benchmark { iterations, json }) => {
+            let report = monitor::bench::run(&mut ResourceMonitor::new(), iterations);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                report.print_table();
+            }
+        }
+        Some(Commands::TestNotification { level, message, dry_run }) => {
+            let message = message.unwrap_or_else(|| "Test alert from MonitorSystemOPs".to_string());
+            send_test_notification(level, message, dry_run).await?;
+        }
         Some(Commands::Run) | None => {
             run_service().await?;
         }
@@ -72,52 +265,227 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn export_metrics(
+    from: Option<String>,
+    to: Option<String>,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let from = from.map(|v| export::parse_time(&v, false)).transpose()?;
+    let to = to.map(|v| export::parse_time(&v, true)).transpose()?;
+
+    let metrics = Storage::new().load_metrics_range(from, to)?;
+    let content = export::format_metrics(&metrics, format)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            eprintln!("✅ Выгружено записей: {} -> {}", metrics.len(), path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn compare_metrics(period_a: (&str, &str), period_b: (&str, &str), format: OutputFormat) -> anyhow::Result<()> {
+    let storage = Storage::new();
+    let load = |(from, to): (&str, &str)| -> anyhow::Result<Vec<monitor::SystemMetrics>> {
+        storage.load_metrics_range(Some(export::parse_time(from, false)?), Some(export::parse_time(to, true)?))
+    };
+    let report = compare::compare_periods(&load(period_a)?, &load(period_b)?)?;
+
+    match format {
+        OutputFormat::Table => report.print_table(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+// Печатает построчные отличия итоговой конфигурации от config.toml; true, если они есть
+fn print_config_diff() -> anyhow::Result<bool> {
+    use similar::ChangeTag;
+    use std::io::IsTerminal;
+
+    let colored = std::io::stdout().is_terminal();
+    let mut changed = false;
+    for (tag, line) in Config::load_with_sources()?.file_diff()? {
+        let (sign, color) = match tag {
+            ChangeTag::Insert => ('+', "\x1b[32m"),
+            ChangeTag::Delete => ('-', "\x1b[31m"),
+            ChangeTag::Equal => (' ', ""),
+        };
+        changed |= tag != ChangeTag::Equal;
+        if colored && !color.is_empty() {
+            println!("{}{}{}\x1b[0m", color, sign, line);
+        } else {
+            println!("{}{}", sign, line);
+        }
+    }
+    Ok(changed)
+}
+
+// Каналы отправляют запросы синхронно, поэтому проверка выполняется вне потоков tokio
+async fn send_test_notification(level: AlertLevel, message: String, dry_run: bool) -> anyhow::Result<()> {
+    let notifications = Config::load()?.notifications;
+    let level = match level {
+        AlertLevel::Warning => "WARNING",
+        AlertLevel::Critical => "CRITICAL",
+    };
+    let results = tokio::task::spawn_blocking(move || {
+        build_notifier(&notifications).send_test_notification(level, &message, dry_run)
+    })
+    .await?;
+
+    if results.is_empty() {
+        println!("Каналы уведомлений не настроены");
+        return Ok(());
+    }
+    let mut failed = 0;
+    for (channel, result) in &results {
+        match result {
+            Ok(()) => println!("✅ {}: отправлено", channel),
+            Err(e) => {
+                failed += 1;
+                println!("❌ {}: {}", channel, e);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("Не удалось отправить в {} из {} каналов", failed, results.len());
+    }
+    Ok(())
+}
+
+fn build_notifier(notifications: &NotificationsConfig) -> NotificationSystem {
+    NotificationSystem::new(notifications.templates.clone())
+        .with_slack_webhooks(notifications.slack_webhooks.clone())
+}
+
+// Каналы уведомлений работают синхронно, поэтому отправка уходит в отдельный поток
+fn notify(notifier: &Arc<NotificationSystem>, send: impl FnOnce(&NotificationSystem) + Send + 'static) {
+    let notifier = notifier.clone();
+    tokio::task::spawn_blocking(move || send(&notifier));
+}
+
 async fn run_service() -> anyhow::Result<()> {
     println!("🚀 Запуск MonitorSystemOPs...");
 
-    let config = Config::load().unwrap_or_else(|_| {
-        println!("Используется конфигурация по умолчанию");
-        Config::generate_default().unwrap();
-        Config::load().unwrap()
-    });
+    // Config::load сам создает файл по умолчанию, если его нет; остальные ошибки (в том
+    // числе нерасшифровываемые секреты) не должны приводить к перезаписи config.toml
+    let mut config = Config::load()?;
+
+    let security = SecurityManager::new();
+    if !security.is_running_as_admin() {
+        println!("⚠️  Запуск без прав администратора: часть метрик (SMART, обновления, IIS) может быть недоступна");
+    }
+    security.validate_config_permissions()?;
+
+    let mut service_stats = ServiceStats::load();
+    service_stats.record_start(chrono::Utc::now());
+    if let Err(e) = service_stats.save() {
+        eprintln!("❌ Ошибка сохранения статистики службы: {}", e);
+    }
+    if service_stats.restart_count > 0 {
+        println!("🔁 Перезапусков службы: {}", service_stats.restart_count);
+    }
+
+    let mut notifier = Arc::new(build_notifier(&config.notifications));
+    notify(&notifier, |n| n.send_start_notification());
+    let mut last_anomaly_notification: Option<std::time::Instant> = None;
 
-    let storage = Arc::new(Storage::new());
+    let storage = Arc::new(
+        Storage::new()
+            .with_mmap_threshold_mb(config.storage.mmap_threshold_mb)
+            .with_backup_before_cleanup(config.storage.backup_before_cleanup),
+    );
     let current_metrics = Arc::new(RwLock::new(None));
 
+    let alerts = Arc::new(RwLock::new(VecDeque::with_capacity(ALERT_HISTORY_CAPACITY)));
+    let snoozes: Snoozes = Arc::default();
+
     {
         let storage = storage.clone();
         let current_metrics = current_metrics.clone();
-        let host = config.web.host.clone();
-        let port = config.web.port;
+        let alerts = alerts.clone();
+        let snoozes = snoozes.clone();
+        let web = config.web.clone();
+        let notifier = notifier.clone();
 
         tokio::spawn(async move {
-            if let Err(e) = start_simple_web_server(storage, current_metrics, host, port).await {
+            if let Err(e) = start_simple_web_server(storage, current_metrics, alerts, snoozes, web).await {
                 eprintln!("Ошибка веб-сервера: {}", e);
+                let error = format!("веб-сервер остановлен: {}", e);
+                notify(&notifier, move |n| n.send_error_notification(&error));
             }
         });
     }
 
-    let mut monitor = ResourceMonitor::new();
+    let mut config_modified = Config::modified_time();
+    let mut monitor = build_monitor(&config);
     let mut interval = tokio::time::interval(
         std::time::Duration::from_secs(config.monitoring.interval_seconds)
     );
+    // Сертификат локального сервера обычно самоподписанный, поэтому при самопроверке не проверяется
+    let self_check_client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let self_check_url = self_check_url(&config.web);
 
     println!("📊 Мониторинг запущен. Интервал: {} сек.", config.monitoring.interval_seconds);
-    println!("🌐 Веб-интерфейс: http://{}:{}", config.web.host, config.web.port);
+    let scheme = if config.web.tls.enabled { "https" } else { "http" };
+    println!("🌐 Веб-интерфейс: {}://{}:{}", scheme, config.web.host, config.web.port);
 
     loop {
-        interval.tick().await;
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("🛑 Остановка мониторинга");
+                // Уведомление об остановке отправляется до выхода из процесса
+                let notifier = notifier.clone();
+                let _ = tokio::task::spawn_blocking(move || notifier.send_stop_notification()).await;
+                return Ok(());
+            }
+        }
+
+        let modified = Config::modified_time();
+        if modified != config_modified {
+            config_modified = modified;
+            if reload_config(&mut config) {
+                notifier = Arc::new(build_notifier(&config.notifications));
+                monitor = build_monitor(&config);
+                interval = tokio::time::interval(std::time::Duration::from_secs(config.monitoring.interval_seconds));
+                interval.tick().await;
+            }
+        }
 
-        let metrics = monitor.collect_metrics();
+        let mut metrics = monitor.collect_metrics();
+        metrics.uptime_seconds = service_stats.uptime_seconds(metrics.timestamp);
+        metrics.restart_count = service_stats.restart_count;
+        metrics.ldap_results = check_ldap_hosts(&config.monitoring.ldap_checks).await;
+        // В первом цикле сервер мог еще не запуститься, а /metrics нечего отдавать
+        if current_metrics.read().await.is_some() {
+            metrics.self_response_time_ms = measure_response_time(&self_check_client, &self_check_url).await;
+        }
         let metrics_log = metrics.clone();
 
-        let anomalies = monitor.check_anomalies(&metrics);
+        let anomalies = monitor.check_anomalies(&metrics, &config.alerts, &*snoozes.read().await);
         if !anomalies.is_empty() {
-            println!("⚠️  Предупреждение: {}", anomalies.join(", "));
+            let messages: Vec<String> = anomalies.iter().map(|a| a.message.clone()).collect();
+            println!("⚠️  Предупреждение: {}", messages.join(", "));
+            // Аномалия держится много циклов подряд; уведомления о ней не должны приходить каждый цикл
+            let cooldown = std::time::Duration::from_secs(config.notifications.anomaly_cooldown_minutes * 60);
+            if last_anomaly_notification.is_none_or(|sent| sent.elapsed() >= cooldown) {
+                last_anomaly_notification = Some(std::time::Instant::now());
+                notify(&notifier, move |n| n.send_anomaly_notification(&messages));
+            }
+            record_anomalies(&mut *alerts.write().await, anomalies);
         }
 
         if let Err(e) = storage.save_metrics(&metrics) {
             eprintln!("❌ Ошибка сохранения: {}", e);
+            let error = format!("не удалось сохранить метрики: {}", e);
+            notify(&notifier, move |n| n.send_error_notification(&error));
         }
 
         {
@@ -133,24 +501,118 @@ async fn run_service() -> anyhow::Result<()> {
                  metrics_log.cpu_usage,
                  metrics_log.memory_usage_percent,
                  metrics_log.disk_usage_percent);
+        if let (Some(read), Some(write)) = (metrics_log.disk_read_bytes_per_sec, metrics_log.disk_write_bytes_per_sec) {
+            println!("💽 Disk I/O: чтение {}, запись {}",
+                     utils::format_throughput(read as u64),
+                     utils::format_throughput(write as u64));
+        }
+    }
+}
+
+fn build_monitor(config: &Config) -> ResourceMonitor {
+    ResourceMonitor::new()
+        .with_update_check_interval(
+            std::time::Duration::from_secs(config.monitoring.update_check_interval_minutes * 60)
+        )
+        .with_scheduled_tasks(config.monitoring.scheduled_tasks.clone())
+        .with_iis_app_pools(config.monitoring.iis_app_pools.clone())
+        .with_network_shares(config.monitoring.network_shares.clone())
+        .with_service_accounts(config.monitoring.service_accounts.clone())
+        .with_process_tree_depth(config.monitoring.tree_depth)
+        .with_wmi_timeout(std::time::Duration::from_millis(config.monitoring.wmi_timeout_ms))
+        .with_folder_watches(config.monitoring.folder_watches.clone())
+}
+
+// Перечитывает измененный config.toml; возвращает true, если конфигурация изменилась.
+// Веб-сервер уже запущен, поэтому изменения [web] вступают в силу после перезапуска
+fn reload_config(config: &mut Config) -> bool {
+    let reloaded = match Config::load_with_sources() {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            eprintln!("❌ Ошибка перезагрузки конфигурации: {}", e);
+            return false;
+        }
+    };
+    let changes = diff_configs(config, &reloaded.config);
+    if changes.is_empty() {
+        return false;
+    }
+
+    if reloaded.config.reload.hot_reload_preview {
+        for change in &changes {
+            println!("{}", reloaded.mask_change(change.clone()));
+        }
+    }
+    if changes.iter().any(|change| change.field.starts_with("web.")) {
+        println!("⚠️  Изменения [web] вступят в силу после перезапуска службы");
     }
+    println!("🔄 Конфигурация перезагружена, изменено полей: {}", changes.len());
+    *config = reloaded.config;
+    true
+}
+
+// Адрес /metrics собственного сервера; при прослушивании всех интерфейсов запрос идет на localhost
+fn self_check_url(web: &WebConfig) -> String {
+    let scheme = if web.tls.enabled { "https" } else { "http" };
+    let host = match web.host.as_str() {
+        "0.0.0.0" | "::" | "[::]" => "localhost",
+        host => host,
+    };
+    format!("{}://{}:{}/metrics", scheme, host, web.port)
+}
+
+// GET /alerts?limit=N и DELETE /alerts/clear
+fn alert_routes(
+    alerts: Arc<RwLock<VecDeque<AnomalyRecord>>>,
+) -> impl warp::Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    use warp::{Filter, Reply};
+
+    let alerts_filter = warp::any().map(move || alerts.clone());
+
+    let list = warp::path!("alerts")
+        .and(warp::get())
+        .and(warp::query::<AlertsQuery>())
+        .and(alerts_filter.clone())
+        .and_then(|query: AlertsQuery, alerts: Arc<RwLock<VecDeque<AnomalyRecord>>>| async move {
+            let alerts = alerts.read().await;
+            let limit = query.limit.unwrap_or(100);
+            let recent: Vec<&AnomalyRecord> = alerts.iter().skip(alerts.len().saturating_sub(limit)).collect();
+            Ok::<_, warp::Rejection>(warp::reply::json(&recent).into_response())
+        });
+
+    let clear = warp::path!("alerts" / "clear")
+        .and(warp::delete())
+        .and(alerts_filter)
+        .and_then(|alerts: Arc<RwLock<VecDeque<AnomalyRecord>>>| async move {
+            alerts.write().await.clear();
+            Ok::<_, warp::Rejection>(warp::http::StatusCode::NO_CONTENT.into_response())
+        });
+
+    list.or(clear).unify()
 }
 
 async fn start_simple_web_server(
     storage: Arc<Storage>,
     current_metrics: Arc<RwLock<Option<crate::monitor::SystemMetrics>>>,
-    host: String,
-    port: u16,
+    alerts: Arc<RwLock<VecDeque<AnomalyRecord>>>,
+    snoozes: Snoozes,
+    web: WebConfig,
 ) -> anyhow::Result<()> {
     use warp::Filter;
     use std::net::SocketAddr;
 
+    let compression = CompressionSettings {
+        level: web.gzip_level,
+        threshold_bytes: web.compression_threshold_bytes,
+    };
+
+    let aggregation_storage = storage.clone();
     let storage_filter = warp::any().map(move || storage.clone());
     let metrics_filter = warp::any().map(move || current_metrics.clone());
 
     let metrics_route = warp::path("metrics")
         .and(warp::get())
-        .and(metrics_filter)
+        .and(metrics_filter.clone())
         .and_then(|metrics: Arc<RwLock<Option<crate::monitor::SystemMetrics>>>| async move {
             let metrics_guard = metrics.read().await;
             match &*metrics_guard {
@@ -159,9 +621,20 @@ async fn start_simple_web_server(
             }
         });
 
-    let history_route = warp::path("history")
+    let process_tree_route = warp::path!("process-tree")
         .and(warp::get())
-        .and(storage_filter)
+        .and(metrics_filter)
+        .and_then(|metrics: Arc<RwLock<Option<crate::monitor::SystemMetrics>>>| async move {
+            let metrics_guard = metrics.read().await;
+            match &*metrics_guard {
+                Some(m) => Ok(warp::reply::json(&m.process_tree)),
+                None => Err(warp::reject::not_found()),
+            }
+        });
+
+    let history_route = warp::path!("history")
+        .and(warp::get())
+        .and(storage_filter.clone())
         .and_then(|storage: Arc<Storage>| async move {
             match storage.load_metrics() {
                 Ok(metrics) => Ok(warp::reply::json(&metrics)),
@@ -169,20 +642,159 @@ async fn start_simple_web_server(
             }
         });
 
+    let network_history_route = warp::path!("history" / "network")
+        .and(warp::get())
+        .and(warp::query::<NetworkHistoryQuery>())
+        .and(storage_filter)
+        .and_then(|query: NetworkHistoryQuery, storage: Arc<Storage>| async move {
+            let minutes = query.minutes.unwrap_or(60);
+            let from = chrono::Utc::now() - chrono::TimeDelta::minutes(minutes as i64);
+            match storage.load_metrics_range(Some(from), None) {
+                Ok(metrics) => Ok(warp::reply::json(&aggregate::network_rates(&metrics))),
+                Err(_) => Err(warp::reject::not_found()),
+            }
+        });
+
     let index_route = warp::path::end()
         .and(warp::get())
         .map(|| {
-            warp::reply::html(include_str!("../static/simple_index.html"))
+            warp::reply::html(include_str!("../static/simple_index.html").to_string())
         });
 
-    let routes = index_route
-        .or(metrics_route)
-        .or(history_route)
-        .with(warp::cors().allow_any_origin());
+    let limiter = Arc::new(rate_limit::RateLimiter::new(web.rate_limit.clone()));
+    let routes = rate_limit::rate_limit_filter(limiter)
+        .and(warp::header::optional::<String>("accept-encoding"))
+        .and(
+            index_route
+                .or(metrics_route)
+                .or(process_tree_route)
+                .or(history_route)
+                .or(aggregated_history_routes(aggregation_storage))
+                .or(network_history_route)
+                .or(alert_routes(alerts))
+                .or(snooze::snooze_routes(snoozes)),
+        )
+        .and_then(move |encoding, reply| compression::compress_reply(encoding, reply, compression))
+        .recover(rate_limit::handle_rejection)
+        .with(cors::cors_filter(&web.cors_origins))
+        .with(access_log::access_log_filter(web.access_log_enabled));
 
-    let addr: SocketAddr = format!("{}:{}", host, port).parse()?;
-    println!("🌐 Веб-сервер запущен на http://{}", addr);
-    warp::serve(routes).run(addr).await;
+    let addr: SocketAddr = format!("{}:{}", web.host, web.port).parse()?;
+    if web.tls.enabled {
+        #[cfg(feature = "tls")]
+        {
+            let (cert, key) = tls::load_identity(&web.tls)?;
+            println!("🌐 Веб-сервер запущен на https://{}", addr);
+            warp::serve(routes).tls().cert(cert).key(key).run(addr).await;
+        }
+        // Config::validate уже отклоняет такую конфигурацию
+        #[cfg(not(feature = "tls"))]
+        return Err(anyhow::anyhow!("HTTPS включен в конфигурации, но программа собрана без --features tls"));
+    } else {
+        println!("🌐 Веб-сервер запущен на http://{}", addr);
+        warp::serve(routes).run(addr).await;
+    }
 
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anomaly(message: &str, level: AlertLevel) -> Vec<AnomalyRecord> {
+        vec![AnomalyRecord { timestamp: chrono::Utc::now(), message: message.to_string(), level }]
+    }
+
+    async fn get_alerts(alerts: &Arc<RwLock<VecDeque<AnomalyRecord>>>, path: &str) -> Vec<serde_json::Value> {
+        let response = warp::test::request().path(path).reply(&alert_routes(alerts.clone())).await;
+        assert_eq!(response.status(), 200);
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn alerts_returns_recorded_anomalies() {
+        let alerts = Arc::new(RwLock::new(VecDeque::new()));
+        record_anomalies(&mut *alerts.write().await, anomaly("Высокая загрузка CPU: 95.0%", AlertLevel::Warning));
+        record_anomalies(&mut *alerts.write().await, anomaly("Критическое заполнение диска C:: 91.0%", AlertLevel::Critical));
+
+        let recent = get_alerts(&alerts, "/alerts").await;
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0]["message"], "Высокая загрузка CPU: 95.0%");
+        assert_eq!(recent[1]["level"], "critical");
+
+        let last = get_alerts(&alerts, "/alerts?limit=1").await;
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0]["level"], "critical");
+    }
+
+    #[tokio::test]
+    async fn clear_empties_alert_history() {
+        let alerts = Arc::new(RwLock::new(VecDeque::new()));
+        record_anomalies(&mut *alerts.write().await, anomaly("Высокая загрузка CPU: 95.0%", AlertLevel::Warning));
+
+        let response = warp::test::request()
+            .method("DELETE")
+            .path("/alerts/clear")
+            .reply(&alert_routes(alerts.clone()))
+            .await;
+        assert_eq!(response.status(), 204);
+        assert!(get_alerts(&alerts, "/alerts").await.is_empty());
+    }
+
+    // Три записи 1 мая в 10:00, 10:30 и 11:00 и одна 2 мая
+    fn history_storage() -> (tempfile::TempDir, Arc<Storage>) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        for (time, cpu) in [
+            ("2024-05-01T10:00:00Z", 20.0),
+            ("2024-05-01T10:30:00Z", 40.0),
+            ("2024-05-01T11:00:00Z", 60.0),
+            ("2024-05-02T09:00:00Z", 80.0),
+        ] {
+            storage.save_metrics(&crate::monitor::SystemMetrics::sample(time.parse().unwrap(), cpu)).unwrap();
+        }
+        (dir, Arc::new(storage))
+    }
+
+    async fn get_history(storage: &Arc<Storage>, path: &str) -> (u16, serde_json::Value) {
+        let response = warp::test::request().path(path).reply(&aggregated_history_routes(storage.clone())).await;
+        (response.status().as_u16(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn hourly_history_without_range() {
+        let (_dir, storage) = history_storage();
+        let (status, body) = get_history(&storage, "/history/hourly").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["meta"], serde_json::json!({ "total_records": 4, "periods": 3 }));
+        let data = body["data"].as_array().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0]["samples"], 2);
+        assert_eq!(data[0]["cpu_avg"], 30.0);
+        assert!(data[0]["period_start"].is_string());
+    }
+
+    #[tokio::test]
+    async fn hourly_history_within_range() {
+        let (_dir, storage) = history_storage();
+        let (status, body) =
+            get_history(&storage, "/history/hourly?from=2024-05-01T10:15:00Z&to=2024-05-01").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["meta"], serde_json::json!({ "total_records": 2, "periods": 2 }));
+        assert_eq!(body["data"][0]["cpu_avg"], 40.0);
+    }
+
+    #[tokio::test]
+    async fn daily_history_and_invalid_range() {
+        let (_dir, storage) = history_storage();
+        let (status, body) = get_history(&storage, "/history/daily").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["meta"], serde_json::json!({ "total_records": 4, "periods": 2 }));
+        assert_eq!(body["data"][0]["samples"], 3);
+
+        let (status, body) = get_history(&storage, "/history/hourly?from=yesterday").await;
+        assert_eq!(status, 400);
+        assert!(body["error"].is_string());
+    }
 }
\ No newline at end of file
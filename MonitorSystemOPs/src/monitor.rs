@@ -1,6 +1,23 @@
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use std::process::Command;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+use crate::config::{
+    AlertsConfig, FolderWatchConfig, IisPoolConfig, LdapCheckConfig, NetworkShareConfig, ScheduledTaskConfig, ServiceAccountConfig,
+};
+use crate::snooze;
+
+pub mod bench;
+pub mod error;
+pub mod pdh;
+
+use error::MonitorError;
+use pdh::PdhCollector;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SystemMetrics {
@@ -12,30 +29,724 @@ pub struct SystemMetrics {
     pub disk_used: u64,
     pub disk_total: u64,
     pub disk_usage_percent: f32,
+    // Скорость чтения и записи дисков; доступна только при сборе через PDH
+    #[serde(default)]
+    pub disk_read_bytes_per_sec: Option<f64>,
+    #[serde(default)]
+    pub disk_write_bytes_per_sec: Option<f64>,
     pub network_rx: u64,
     pub network_tx: u64,
     pub processes_count: usize,
+    #[serde(default)]
+    pub disks: Vec<DiskMetrics>,
+    #[serde(default)]
+    pub pending_updates: Option<u32>,
+    #[serde(default)]
+    pub scheduled_task_results: Vec<ScheduledTaskStatus>,
+    #[serde(default)]
+    pub iis_pool_results: Vec<IisPoolStatus>,
+    #[serde(default)]
+    pub network_share_results: Vec<NetworkShareStatus>,
+    #[serde(default)]
+    pub smart_health: Vec<SmartHealthResult>,
+    #[serde(default)]
+    pub ldap_results: Vec<LdapCheckResult>,
+    #[serde(default)]
+    pub service_account_results: Vec<ServiceAccountStatus>,
+    #[serde(default)]
+    pub process_tree: Vec<ProcessNode>,
+    #[serde(default)]
+    pub folder_watch_results: Vec<FolderWatchResult>,
+    // Время ответа /metrics собственного веб-сервера
+    #[serde(default)]
+    pub self_response_time_ms: Option<u64>,
+    // Состояние брандмауэра и Defender; None, если запрос не удался
+    #[serde(default)]
+    pub firewall_rule_count: Option<u32>,
+    #[serde(default)]
+    pub defender_realtime_enabled: Option<bool>,
+    #[serde(default)]
+    pub defender_last_scan: Option<DateTime<Utc>>,
+    // Команды сбора, снятые по таймауту за последние TIMEOUT_WINDOW_CYCLES циклов
+    #[serde(default)]
+    pub collection_timeouts: u32,
+    // Время с последнего запуска службы и число перезапусков из data/service_stats.json
+    #[serde(default)]
+    pub uptime_seconds: u64,
+    #[serde(default)]
+    pub restart_count: u32,
+}
+
+#[cfg(test)]
+impl SystemMetrics {
+    // Запись для тестов: обязательные поля заполнены, остальные - значения по умолчанию
+    pub fn sample(timestamp: DateTime<Utc>, cpu_usage: f32) -> Self {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "cpu_usage": cpu_usage,
+            "memory_used": 4_u64 << 30,
+            "memory_total": 16_u64 << 30,
+            "memory_usage_percent": 25.0,
+            "disk_used": 100_u64 << 30,
+            "disk_total": 500_u64 << 30,
+            "disk_usage_percent": 20.0,
+            "network_rx": 1000,
+            "network_tx": 500,
+            "processes_count": 120,
+        }))
+        .expect("valid sample metrics")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmartStatus {
+    Healthy,
+    Degraded,
+    Failed,
+    Unknown,
+}
+
+impl SmartStatus {
+    // Значение HealthStatus из Get-PhysicalDisk
+    fn from_health_status(value: &str) -> Self {
+        match value.trim() {
+            "Healthy" => SmartStatus::Healthy,
+            "Warning" => SmartStatus::Degraded,
+            "Unhealthy" => SmartStatus::Failed,
+            _ => SmartStatus::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SmartHealthResult {
+    pub drive: String,
+    pub status: SmartStatus,
+    pub reallocated_sectors: Option<u32>,
+}
+
+// growth_bytes_since_last - изменение размера с прошлого цикла, 0 в первом цикле
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FolderWatchResult {
+    pub label: String,
+    pub size_bytes: u64,
+    pub file_count: u32,
+    pub growth_bytes_since_last: i64,
+}
+
+// parent_pid пустой у корневых процессов; children - PID дочерних процессов,
+// попавших в дерево с учетом ограничения глубины
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub name: String,
+    pub parent_pid: Option<u32>,
+    pub children: Vec<u32>,
+}
+
+// expires_at и days_remaining пустые, если срок действия пароля не ограничен
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServiceAccountStatus {
+    pub username: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub days_remaining: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LdapCheckResult {
+    pub label: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NetworkShareStatus {
+    pub label: String,
+    pub accessible: bool,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IisPoolStatus {
+    pub name: String,
+    // Started, Stopped, Starting или Stopping
+    pub state: String,
+    pub worker_process_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScheduledTaskStatus {
+    pub label: String,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_result: i32,
+    pub next_run: Option<DateTime<Utc>>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskMetrics {
+    pub drive: String,
+    pub used: u64,
+    pub total: u64,
+    pub usage_percent: f32,
+}
+
+// Сколько последних аномалий хранится для /alerts
+pub const ALERT_HISTORY_CAPACITY: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertLevel {
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnomalyRecord {
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    pub level: AlertLevel,
+}
+
+// Добавляет аномалии в историю, вытесняя самые старые при переполнении
+pub fn record_anomalies(history: &mut VecDeque<AnomalyRecord>, anomalies: Vec<AnomalyRecord>) {
+    for anomaly in anomalies {
+        if history.len() >= ALERT_HISTORY_CAPACITY {
+            history.pop_front();
+        }
+        history.push_back(anomaly);
+    }
+}
+
+// Коды LastTaskResult, не означающие сбой: выполняется сейчас (0x41301) и еще не запускалась (0x41303)
+const TASK_RESULTS_OK: [i32; 3] = [0, 0x41301, 0x41303];
+
+// Проверка, не завершившаяся за это время, считается недоступностью ресурса
+const NETWORK_SHARE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Обращение к недоступному серверу может висеть минутами, поэтому каждый ресурс
+// проверяется в отдельном потоке, а в метрики попадает результат предыдущей проверки
+struct NetworkShareProbe {
+    config: NetworkShareConfig,
+    last: Option<NetworkShareStatus>,
+    pending: Option<(Instant, Receiver<NetworkShareStatus>)>,
+}
+
+impl NetworkShareProbe {
+    fn new(config: NetworkShareConfig) -> Self {
+        Self { config, last: None, pending: None }
+    }
+
+    fn poll(&mut self) -> Option<NetworkShareStatus> {
+        if let Some((started, receiver)) = &self.pending {
+            match receiver.try_recv() {
+                Ok(status) => {
+                    self.last = Some(status);
+                    self.pending = None;
+                }
+                Err(TryRecvError::Empty) if started.elapsed() > NETWORK_SHARE_TIMEOUT => {
+                    return Some(NetworkShareStatus {
+                        label: self.config.label.clone(),
+                        accessible: false,
+                        latency_ms: None,
+                    });
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        if self.pending.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            let config = self.config.clone();
+            std::thread::spawn(move || {
+                let _ = sender.send(get_network_share_status(&config.label, &config.path));
+            });
+            self.pending = Some((Instant::now(), receiver));
+        }
+
+        self.last.clone()
+    }
+}
+
+// Размер и число файлов с учетом max_depth уровней вложенных папок; символические ссылки
+// не раскрываются, недоступные папки и файлы пропускаются
+pub fn get_folder_size(path: &Path, max_depth: usize) -> (u64, u32) {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return (0, 0);
+    };
+    let (mut size, mut count) = (0, 0);
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if max_depth > 0 {
+                let (dir_size, dir_count) = get_folder_size(&entry.path(), max_depth - 1);
+                size += dir_size;
+                count += dir_count;
+            }
+        } else if file_type.is_file() {
+            size += entry.metadata().map_or(0, |metadata| metadata.len());
+            count += 1;
+        }
+    }
+    (size, count)
+}
+
+pub fn get_network_share_status(label: &str, path: &str) -> NetworkShareStatus {
+    let started = Instant::now();
+    let accessible = std::fs::metadata(path).is_ok();
+    NetworkShareStatus {
+        label: label.to_string(),
+        accessible,
+        latency_ms: accessible.then(|| started.elapsed().as_millis() as u64),
+    }
+}
+
+// Срок действия паролей меняется редко, а запрос к AD небыстрый
+const SERVICE_ACCOUNT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Сколько дней до истечения пароля дают предупреждение и критическую аномалию
+const PASSWORD_EXPIRY_WARNING_DAYS: i64 = 14;
+const PASSWORD_EXPIRY_CRITICAL_DAYS: i64 = 3;
+
+// Опрос состояния дисков медленный, поэтому результат обновляется раз в 5 минут
+const SMART_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const SMART_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Атрибут SMART 5 (переназначенные сектора) берется из MSStorageDriver_FailurePredictData:
+// записи по 12 байт с 2-го, сырое значение - с 5-го байта записи. Диск сопоставляется
+// по PNPDeviceID из Win32_DiskDrive, у которого Index совпадает с DeviceId физического диска
+const SMART_SCRIPT: &str = r#"
+$smart = @{}
+Get-CimInstance -Namespace root\wmi -ClassName MSStorageDriver_FailurePredictData -ErrorAction SilentlyContinue | ForEach-Object {
+    $data = $_.VendorSpecific
+    for ($i = 2; $i + 11 -lt $data.Length; $i += 12) {
+        if ($data[$i] -eq 5) { $smart[($_.InstanceName -replace '_0$', '').ToUpper()] = [BitConverter]::ToUInt32($data, $i + 5) }
+    }
+}
+$pnp = @{}
+Get-CimInstance Win32_DiskDrive -ErrorAction SilentlyContinue | ForEach-Object { $pnp[[string]$_.Index] = ([string]$_.PNPDeviceID).ToUpper() }
+Get-PhysicalDisk | ForEach-Object {
+    Write-Output "$($_.FriendlyName)|$($_.HealthStatus)|$($_.OperationalStatus)|$($smart[$pnp[[string]$_.DeviceId]])"
+}
+"#;
+
+// Запросы к брандмауэру и Defender медленные, поэтому выполняются раз в столько циклов
+const SECURITY_CHECK_CYCLES: u64 = 10;
+
+const FIREWALL_RULE_SCRIPT: &str =
+    "(Get-NetFirewallRule | Where-Object Enabled -eq True | Measure-Object).Count";
+const DEFENDER_SCRIPT: &str = "$status = Get-MpComputerStatus -ErrorAction Stop; \
+     $scan = if ($status.LastQuickScanTime) { $status.LastQuickScanTime.ToUniversalTime().ToString('yyyy-MM-ddTHH:mm:ssZ') }; \
+     Write-Output \"$($status.RealTimeProtectionEnabled)|$scan\"";
+
+// Поиск обновлений через Windows Update может занимать десятки секунд
+const UPDATE_SEARCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Окно, за которое считаются таймауты команд сбора, и порог предупреждения
+const TIMEOUT_WINDOW_CYCLES: usize = 10;
+const TIMEOUT_WARNING_COUNT: u32 = 3;
+
 pub struct ResourceMonitor {
+    // Счетчики производительности; None, если PDH недоступен и метрики собираются PowerShell
+    pdh: Option<PdhCollector>,
+    // Объем памяти не меняется, поэтому при сборе через PDH запрашивается один раз
+    memory_total: Option<u64>,
     last_network_stats: Option<(u64, u64)>,
+    update_check_interval: Duration,
+    pending_updates: Option<u32>,
+    last_update_check: Option<Instant>,
+    scheduled_tasks: Vec<ScheduledTaskConfig>,
+    iis_app_pools: Vec<IisPoolConfig>,
+    network_shares: Vec<NetworkShareProbe>,
+    service_accounts: Vec<ServiceAccountConfig>,
+    // Сроки действия паролей из последнего запроса к AD
+    password_expiry: Vec<(String, Option<DateTime<Utc>>)>,
+    last_service_account_check: Option<Instant>,
+    smart_health: Vec<SmartHealthResult>,
+    last_smart_check: Option<Instant>,
+    process_tree_depth: usize,
+    folder_watches: Vec<FolderWatchConfig>,
+    // Размер каждой папки в прошлом цикле, по метке
+    last_folder_sizes: HashMap<String, u64>,
+    // Номер цикла сбора; по нему раз в SECURITY_CHECK_CYCLES обновляется состояние защиты
+    collection_cycle: u64,
+    firewall_rule_count: Option<u32>,
+    defender_status: Option<(bool, Option<DateTime<Utc>>)>,
+    // Предельное время одной команды PowerShell, кроме поиска обновлений и SMART
+    wmi_timeout: Duration,
+    // Таймауты текущего цикла; Cell, потому что команды запускаются из методов с &self
+    cycle_timeouts: Cell<u32>,
+    recent_timeouts: VecDeque<u32>,
+}
+
+// Количество (обновлений, правил брандмауэра) - последняя непустая строка вывода PowerShell
+fn parse_count(output: &str) -> Option<u32> {
+    output
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())?
+        .parse()
+        .ok()
+}
+
+// Строка вида "RealTimeProtectionEnabled|LastQuickScanTime"; время пустое, если
+// быстрая проверка ни разу не выполнялась
+fn parse_defender_status(output: &str) -> Option<(bool, Option<DateTime<Utc>>)> {
+    let line = output.lines().map(str::trim).rfind(|line| !line.is_empty())?;
+    let (enabled, last_scan) = line.split_once('|')?;
+    let enabled = match enabled.trim() {
+        value if value.eq_ignore_ascii_case("True") => true,
+        value if value.eq_ignore_ascii_case("False") => false,
+        _ => return None,
+    };
+    Some((enabled, parse_task_time(last_scan)))
+}
+
+// Дата из PowerShell в UTC; пустая строка или дата до 2000 года (так планировщик
+// обозначает отсутствие запуска) дают None
+fn parse_task_time(value: &str) -> Option<DateTime<Utc>> {
+    let time: DateTime<Utc> = value.trim().parse().ok()?;
+    (time.timestamp() >= 946_684_800).then_some(time)
+}
+
+// Строка вида "State|LastRunTime|LastTaskResult|NextRunTime"
+fn parse_task_info(label: &str, output: &str) -> Option<ScheduledTaskStatus> {
+    let line = output.lines().map(str::trim).rfind(|line| !line.is_empty())?;
+    let parts: Vec<&str> = line.split('|').collect();
+    let [state, last_run, last_result, next_run] = parts[..] else {
+        return None;
+    };
+    Some(ScheduledTaskStatus {
+        label: label.to_string(),
+        last_run: parse_task_time(last_run),
+        last_result: last_result.trim().parse::<i64>().ok()? as i32,
+        next_run: parse_task_time(next_run),
+        enabled: !state.trim().eq_ignore_ascii_case("Disabled"),
+    })
+}
+
+// Строки вида "Name|State|WorkerProcessCount"; нераспознанные строки пропускаются
+fn parse_iis_pools(output: &str) -> Vec<IisPoolStatus> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.trim().split('|').collect();
+            let [name, state, workers] = parts[..] else {
+                return None;
+            };
+            Some(IisPoolStatus {
+                name: name.to_string(),
+                state: state.to_string(),
+                worker_process_count: workers.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+// Последняя непустая строка: "never" для пароля без срока действия или дата в UTC;
+// None, если вывод не распознан
+fn parse_password_expiry(output: &str) -> Option<Option<DateTime<Utc>>> {
+    let line = output.lines().map(str::trim).rfind(|line| !line.is_empty())?;
+    if line.eq_ignore_ascii_case("never") {
+        return Some(None);
+    }
+    line.parse::<DateTime<Utc>>().ok().map(Some)
+}
+
+// Строки вида "ProcessId|Name|ParentProcessId"; нераспознанные строки пропускаются
+fn parse_process_list(output: &str) -> Vec<(u32, String, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.trim().split('|').collect();
+            let [pid, name, parent] = parts[..] else {
+                return None;
+            };
+            Some((pid.trim().parse().ok()?, name.trim().to_string(), parent.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// Корни - процессы, родителя которых нет в списке (он уже завершился) или которые
+// указаны родителями самих себя, как System Idle; обход в ширину до max_depth уровней
+fn build_process_tree(processes: Vec<(u32, String, u32)>, max_depth: usize) -> Vec<ProcessNode> {
+    let mut nodes: HashMap<u32, ProcessNode> = processes
+        .into_iter()
+        .map(|(pid, name, parent)| (pid, ProcessNode { pid, name, parent_pid: Some(parent), children: Vec::new() }))
+        .collect();
+    let mut pids: Vec<u32> = nodes.keys().copied().collect();
+    pids.sort_unstable();
+
+    let mut roots = Vec::new();
+    for &pid in &pids {
+        let parent = nodes[&pid].parent_pid.filter(|&parent| parent != pid && nodes.contains_key(&parent));
+        match parent {
+            Some(parent) => nodes.get_mut(&parent).unwrap().children.push(pid),
+            None => {
+                nodes.get_mut(&pid).unwrap().parent_pid = None;
+                roots.push(pid);
+            }
+        }
+    }
+
+    let mut tree = Vec::new();
+    let mut level = roots;
+    for depth in 1..=max_depth {
+        let mut next = Vec::new();
+        for pid in level {
+            let Some(mut node) = nodes.remove(&pid) else {
+                continue;
+            };
+            if depth == max_depth {
+                node.children.clear();
+            }
+            next.extend_from_slice(&node.children);
+            tree.push(node);
+        }
+        level = next;
+    }
+    tree
+}
+
+// Строки вида "FriendlyName|HealthStatus|OperationalStatus|ReallocatedSectors";
+// число секторов пустое, если SMART недоступен
+fn parse_smart_health(output: &str) -> Vec<SmartHealthResult> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.trim().split('|').collect();
+            let [drive, health, _operational, reallocated] = parts[..] else {
+                return None;
+            };
+            Some(SmartHealthResult {
+                drive: drive.trim().to_string(),
+                status: SmartStatus::from_health_status(health),
+                reallocated_sectors: reallocated.trim().parse().ok(),
+            })
+        })
+        .collect()
+}
+
+// Get-ADUser принимает имя без домена, домен передается как -Server.
+// msDS-UserPasswordExpiryTimeComputed - FILETIME; 0 и Int64.MaxValue означают "без срока"
+fn password_expiry_script(username: &str) -> String {
+    let (server, user) = match username.split_once('\\') {
+        Some((domain, user)) => (format!(" -Server '{}'", domain.replace('\'', "''")), user),
+        None => (String::new(), username),
+    };
+    format!(
+        "Import-Module ActiveDirectory -ErrorAction Stop; \
+         $user = Get-ADUser -Identity '{}'{} -Properties PasswordNeverExpires, 'msDS-UserPasswordExpiryTimeComputed' -ErrorAction Stop; \
+         $time = $user.'msDS-UserPasswordExpiryTimeComputed'; \
+         if ($user.PasswordNeverExpires -or -not $time -or $time -ge [long]::MaxValue) {{ 'never' }} \
+         else {{ [DateTime]::FromFileTimeUtc($time).ToString('yyyy-MM-ddTHH:mm:ssZ') }}",
+        user.replace('\'', "''"),
+        server
+    )
+}
+
+const LDAP_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Только TCP-подключение к порту, без LDAP bind
+pub async fn check_ldap_host(check: &LdapCheckConfig) -> LdapCheckResult {
+    let started = Instant::now();
+    let connect = tokio::net::TcpStream::connect((check.host.as_str(), check.port));
+    let reachable = matches!(tokio::time::timeout(LDAP_CONNECT_TIMEOUT, connect).await, Ok(Ok(_)));
+    LdapCheckResult {
+        label: check.label.clone(),
+        reachable,
+        latency_ms: reachable.then(|| started.elapsed().as_millis() as u64),
+    }
+}
+
+// Хосты проверяются параллельно, порядок результатов совпадает с конфигурацией
+pub async fn check_ldap_hosts(checks: &[LdapCheckConfig]) -> Vec<LdapCheckResult> {
+    let handles: Vec<_> = checks
+        .iter()
+        .cloned()
+        .map(|check| tokio::spawn(async move { check_ldap_host(&check).await }))
+        .collect();
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+    results
+}
+
+// Время полного ответа на GET, включая чтение тела; None, если запрос не удался
+pub async fn measure_response_time(client: &reqwest::Client, url: &str) -> Option<u64> {
+    let started = Instant::now();
+    let response = match client.get(url).send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response,
+        Err(e) => {
+            let error = MonitorError::CollectionFailed { collector: "self_response_time".to_string(), source: e.into() };
+            eprintln!("{}", error);
+            return None;
+        }
+    };
+    response.bytes().await.ok()?;
+    Some(started.elapsed().as_millis() as u64)
+}
+
+// Запускает команду и завершает ее, если она не уложилась в timeout; превышение времени -
+// ошибка с ErrorKind::TimedOut. Вывод читается в отдельном потоке, иначе команда с большим
+// выводом остановится на заполненном канале и будет ошибочно снята по таймауту
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> std::io::Result<Output> {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+    let mut stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("stdout недоступен"))?;
+    let reader = std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        std::io::Read::read_to_end(&mut stdout, &mut buffer).map(|_| buffer)
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait()? {
+            Some(status) => break status,
+            None if started.elapsed() < timeout => std::thread::sleep(Duration::from_millis(50)),
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "команда не уложилась в таймаут"));
+            }
+        }
+    };
+    let stdout = reader.join().map_err(|_| std::io::Error::other("поток чтения вывода завершился с ошибкой"))??;
+    Ok(Output { status, stdout, stderr: Vec::new() })
 }
 
 impl ResourceMonitor {
     pub fn new() -> Self {
+        Self::from_pdh(PdhCollector::new())
+    }
+
+    // Если счетчики производительности не открылись, метрики собираются PowerShell
+    fn from_pdh(pdh: Result<PdhCollector, MonitorError>) -> Self {
+        let pdh = pdh
+            .map_err(|e| eprintln!("Счетчики производительности недоступны, используется PowerShell: {}", e))
+            .ok();
+        Self::with_collector(pdh)
+    }
+
+    fn with_collector(pdh: Option<PdhCollector>) -> Self {
         Self {
+            pdh,
+            memory_total: None,
             last_network_stats: None,
+            update_check_interval: Duration::from_secs(60 * 60),
+            pending_updates: None,
+            last_update_check: None,
+            scheduled_tasks: Vec::new(),
+            iis_app_pools: Vec::new(),
+            network_shares: Vec::new(),
+            service_accounts: Vec::new(),
+            password_expiry: Vec::new(),
+            last_service_account_check: None,
+            smart_health: Vec::new(),
+            last_smart_check: None,
+            process_tree_depth: 3,
+            folder_watches: Vec::new(),
+            last_folder_sizes: HashMap::new(),
+            collection_cycle: 0,
+            firewall_rule_count: None,
+            defender_status: None,
+            wmi_timeout: Duration::from_millis(5000),
+            cycle_timeouts: Cell::new(0),
+            recent_timeouts: VecDeque::with_capacity(TIMEOUT_WINDOW_CYCLES),
         }
     }
 
+    pub fn with_wmi_timeout(mut self, timeout: Duration) -> Self {
+        self.wmi_timeout = timeout;
+        self
+    }
+
+    fn command_output(&self, command: &mut Command) -> std::io::Result<Output> {
+        self.command_output_within(command, self.wmi_timeout)
+    }
+
+    // Снятая по таймауту команда учитывается в cycle_timeouts; вызывающий код
+    // обрабатывает ошибку так же, как неудачный запуск, и берет значение по умолчанию
+    fn command_output_within(&self, command: &mut Command, timeout: Duration) -> std::io::Result<Output> {
+        let result = output_with_timeout(command, timeout);
+        if matches!(&result, Err(e) if e.kind() == std::io::ErrorKind::TimedOut) {
+            eprintln!("⚠️ Команда сбора метрик не завершилась за {} мс: {:?}", timeout.as_millis(), command);
+            self.cycle_timeouts.set(self.cycle_timeouts.get() + 1);
+        }
+        result
+    }
+
+    pub fn with_folder_watches(mut self, watches: Vec<FolderWatchConfig>) -> Self {
+        self.folder_watches = watches;
+        self
+    }
+
+    pub fn with_process_tree_depth(mut self, depth: usize) -> Self {
+        self.process_tree_depth = depth;
+        self
+    }
+
+    pub fn with_service_accounts(mut self, accounts: Vec<ServiceAccountConfig>) -> Self {
+        self.service_accounts = accounts;
+        self
+    }
+
+    pub fn with_network_shares(mut self, shares: Vec<NetworkShareConfig>) -> Self {
+        self.network_shares = shares.into_iter().map(NetworkShareProbe::new).collect();
+        self
+    }
+
+    pub fn with_iis_app_pools(mut self, pools: Vec<IisPoolConfig>) -> Self {
+        self.iis_app_pools = pools;
+        self
+    }
+
+    pub fn with_scheduled_tasks(mut self, tasks: Vec<ScheduledTaskConfig>) -> Self {
+        self.scheduled_tasks = tasks;
+        self
+    }
+
+    pub fn with_update_check_interval(mut self, interval: Duration) -> Self {
+        self.update_check_interval = interval;
+        self
+    }
+
     pub fn collect_metrics(&mut self) -> SystemMetrics {
         let timestamp = Utc::now();
+        self.cycle_timeouts.set(0);
 
         let cpu_usage = self.get_cpu_usage();
         let (memory_used, memory_total, memory_usage_percent) = self.get_memory_info();
-        let (disk_used, disk_total, disk_usage_percent) = self.get_disk_info();
+        let disks = self.get_disk_info();
+        // Общие поля disk_* описывают системный диск C:
+        let (disk_used, disk_total, disk_usage_percent) = disks
+            .iter()
+            .find(|d| d.drive.eq_ignore_ascii_case("C:"))
+            .or(disks.first())
+            .map_or((0, 0, 0.0), |d| (d.used, d.total, d.usage_percent));
+        let (disk_read_bytes_per_sec, disk_write_bytes_per_sec) = self.get_disk_io().unzip();
         let (network_rx, network_tx) = self.get_network_stats();
         let processes_count = self.get_process_count();
+        let pending_updates = self.get_pending_updates();
+        let scheduled_task_results = self.get_scheduled_tasks();
+        let iis_pool_results = self.get_iis_pools();
+        let network_share_results = self.network_shares.iter_mut().filter_map(NetworkShareProbe::poll).collect();
+        let smart_health = self.get_smart_health();
+        let service_account_results = self.get_service_accounts(timestamp);
+        let process_tree = self.get_process_tree();
+        let folder_watch_results = self.get_folder_watches();
+        self.refresh_security_status();
+        let (defender_realtime_enabled, defender_last_scan) = self.defender_status.unzip();
+
+        if self.recent_timeouts.len() == TIMEOUT_WINDOW_CYCLES {
+            self.recent_timeouts.pop_front();
+        }
+        self.recent_timeouts.push_back(self.cycle_timeouts.get());
 
         SystemMetrics {
             timestamp,
@@ -46,18 +757,44 @@ impl ResourceMonitor {
             disk_used,
             disk_total,
             disk_usage_percent,
+            disk_read_bytes_per_sec,
+            disk_write_bytes_per_sec,
             network_rx,
             network_tx,
             processes_count,
+            disks,
+            pending_updates,
+            scheduled_task_results,
+            iis_pool_results,
+            network_share_results,
+            smart_health,
+            ldap_results: Vec::new(),
+            service_account_results,
+            process_tree,
+            folder_watch_results,
+            self_response_time_ms: None,
+            firewall_rule_count: self.firewall_rule_count,
+            defender_realtime_enabled,
+            defender_last_scan: defender_last_scan.flatten(),
+            collection_timeouts: self.recent_timeouts.iter().sum(),
+            uptime_seconds: 0,
+            restart_count: 0,
         }
     }
 
     fn get_cpu_usage(&self) -> f32 {
-        let output = Command::new("powershell")
-            .args(&[
+        if let Some(pdh) = &self.pdh {
+            match pdh.cpu_usage() {
+                Ok(usage) => return usage,
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        let output = self.command_output(
+            Command::new("powershell").args([
                 "Get-WmiObject Win32_Processor | Measure-Object -Property LoadPercentage -Average | Select-Object -ExpandProperty Average"
-            ])
-            .output();
+            ]),
+        );
 
         match output {
             Ok(output) if output.status.success() => {
@@ -71,26 +808,39 @@ impl ResourceMonitor {
         }
     }
 
-    fn get_memory_info(&self) -> (u64, u64, f32) {
-        let output = Command::new("powershell")
-            .args(&[
+    fn get_memory_info(&mut self) -> (u64, u64, f32) {
+        if let (Some(pdh), Some(total)) = (&self.pdh, self.memory_total) {
+            match pdh.available_memory_bytes() {
+                Ok(available) => {
+                    let used = total.saturating_sub(available);
+                    return (used, total, used as f32 / total as f32 * 100.0);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        let output = self.command_output(
+            Command::new("powershell").args([
                 "$mem = Get-WmiObject Win32_OperatingSystem;",
                 "$total = $mem.TotalVisibleMemorySize * 1KB;",
                 "$free = $mem.FreePhysicalMemory * 1KB;",
                 "$used = $total - $free;",
                 "$usage = ($used / $total) * 100;",
                 "Write-Output \"$total $used $usage\""
-            ])
-            .output();
+            ]),
+        );
 
         match output {
             Ok(output) if output.status.success() => {
                 let output_str = String::from_utf8_lossy(&output.stdout);
-                let parts: Vec<&str> = output_str.trim().split_whitespace().collect();
+                let parts: Vec<&str> = output_str.split_whitespace().collect();
                 if parts.len() == 3 {
                     let total = parts[0].parse().unwrap_or(0);
                     let used = parts[1].parse().unwrap_or(0);
                     let usage = parts[2].parse().unwrap_or(0.0);
+                    if total > 0 {
+                        self.memory_total = Some(total);
+                    }
                     return (used, total, usage);
                 }
             }
@@ -99,37 +849,51 @@ impl ResourceMonitor {
         (0, 0, 0.0)
     }
 
-    fn get_disk_info(&self) -> (u64, u64, f32) {
-        let output = Command::new("powershell")
-            .args(&[
-                "$disk = Get-WmiObject Win32_LogicalDisk -Filter \"DeviceID='C:'\";",
-                "$total = $disk.Size;",
-                "$free = $disk.FreeSpace;",
-                "$used = $total - $free;",
-                "$usage = ($used / $total) * 100;",
-                "Write-Output \"$total $used $usage\""
-            ])
-            .output();
+    fn get_disk_io(&self) -> Option<(f64, f64)> {
+        self.pdh.as_ref()?.disk_io().map_err(|e| eprintln!("{}", e)).ok()
+    }
+
+    fn get_disk_info(&self) -> Vec<DiskMetrics> {
+        let output = self.command_output(
+            Command::new("powershell").args([
+                "Get-WmiObject Win32_LogicalDisk -Filter \"DriveType=3\" | ForEach-Object {",
+                "    Write-Output \"$($_.DeviceID) $($_.Size) $($_.FreeSpace)\"",
+                "}"
+            ]),
+        );
 
         match output {
             Ok(output) if output.status.success() => {
                 let output_str = String::from_utf8_lossy(&output.stdout);
-                let parts: Vec<&str> = output_str.trim().split_whitespace().collect();
-                if parts.len() == 3 {
-                    let total = parts[0].parse().unwrap_or(0);
-                    let used = parts[1].parse().unwrap_or(0);
-                    let usage = parts[2].parse().unwrap_or(0.0);
-                    return (used, total, usage);
-                }
+                output_str
+                    .lines()
+                    .filter_map(|line| {
+                        let parts: Vec<&str> = line.split_whitespace().collect();
+                        if parts.len() != 3 {
+                            return None;
+                        }
+                        let total: u64 = parts[1].parse().ok()?;
+                        let free: u64 = parts[2].parse().ok()?;
+                        let used = total.saturating_sub(free);
+                        let usage_percent = if total > 0 {
+                            used as f32 / total as f32 * 100.0
+                        } else {
+                            0.0
+                        };
+                        Some(DiskMetrics { drive: parts[0].to_string(), used, total, usage_percent })
+                    })
+                    .collect()
+            }
+            _ => {
+                eprintln!("Ошибка получения disk info");
+                Vec::new()
             }
-            _ => eprintln!("Ошибка получения disk info"),
         }
-        (0, 0, 0.0)
     }
 
     fn get_network_stats(&mut self) -> (u64, u64) {
-        let output = Command::new("powershell")
-            .args(&[
+        let output = self.command_output(
+            Command::new("powershell").args([
                 "$adapters = Get-NetAdapter -Physical | Where-Object {$_.Status -eq 'Up'};",
                 "$totalRx = 0; $totalTx = 0;",
                 "foreach ($adapter in $adapters) {",
@@ -138,13 +902,13 @@ impl ResourceMonitor {
                 "    $totalTx += $stats.SentBytes;",
                 "}",
                 "Write-Output \"$totalRx $totalTx\""
-            ])
-            .output();
+            ]),
+        );
 
         match output {
             Ok(output) if output.status.success() => {
                 let output_str = String::from_utf8_lossy(&output.stdout);
-                let parts: Vec<&str> = output_str.trim().split_whitespace().collect();
+                let parts: Vec<&str> = output_str.split_whitespace().collect();
                 if parts.len() == 2 {
                     let rx: u64 = parts[0].parse().unwrap_or(0);
                     let tx: u64 = parts[1].parse().unwrap_or(0);
@@ -165,9 +929,9 @@ impl ResourceMonitor {
     }
 
     fn get_process_count(&self) -> usize {
-        let output = Command::new("powershell")
-            .args(&["Get-Process | Measure-Object | Select-Object -ExpandProperty Count"])
-            .output();
+        let output = self.command_output(
+            Command::new("powershell").args(["Get-Process | Measure-Object | Select-Object -ExpandProperty Count"]),
+        );
 
         match output {
             Ok(output) if output.status.success() => {
@@ -181,21 +945,724 @@ impl ResourceMonitor {
         }
     }
 
-    pub fn check_anomalies(&self, metrics: &SystemMetrics) -> Vec<String> {
+    fn get_folder_watches(&mut self) -> Vec<FolderWatchResult> {
+        self.folder_watches
+            .iter()
+            .map(|watch| {
+                let (size_bytes, file_count) = get_folder_size(Path::new(&watch.path), watch.max_depth);
+                let previous = self.last_folder_sizes.insert(watch.label.clone(), size_bytes);
+                FolderWatchResult {
+                    label: watch.label.clone(),
+                    size_bytes,
+                    file_count,
+                    growth_bytes_since_last: previous.map_or(0, |previous| size_bytes as i64 - previous as i64),
+                }
+            })
+            .collect()
+    }
+
+    // Один запрос Win32_Process вместо обращения к WMI для каждого процесса Get-Process
+    fn get_process_tree(&self) -> Vec<ProcessNode> {
+        if self.process_tree_depth == 0 {
+            return Vec::new();
+        }
+
+        let output = self.command_output(
+            Command::new("powershell").args([
+                "Get-CimInstance Win32_Process | ForEach-Object { \"$($_.ProcessId)|$($_.Name)|$($_.ParentProcessId)\" }",
+            ]),
+        );
+        match output {
+            Ok(output) if output.status.success() => build_process_tree(
+                parse_process_list(&String::from_utf8_lossy(&output.stdout)),
+                self.process_tree_depth,
+            ),
+            _ => {
+                eprintln!("Ошибка получения дерева процессов");
+                Vec::new()
+            }
+        }
+    }
+
+    // Результат кэшируется: запрос к Windows Update выполняется не чаще update_check_interval
+    fn get_pending_updates(&mut self) -> Option<u32> {
+        let fresh = self
+            .last_update_check
+            .is_some_and(|checked| checked.elapsed() < self.update_check_interval);
+        if fresh {
+            return self.pending_updates;
+        }
+
+        let output = self.command_output_within(
+            Command::new("powershell").args([
+                "(New-Object -ComObject Microsoft.Update.Session).CreateUpdateSearcher().Search(\"IsInstalled=0\").Updates.Count"
+            ]),
+            UPDATE_SEARCH_TIMEOUT,
+        );
+
+        self.pending_updates = match output {
+            Ok(output) if output.status.success() => {
+                parse_count(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => {
+                eprintln!("Ошибка получения pending updates");
+                None
+            }
+        };
+        self.last_update_check = Some(Instant::now());
+        self.pending_updates
+    }
+
+    // Первый цикл тоже выполняет запрос, между запросами отдаются прошлые значения
+    fn refresh_security_status(&mut self) {
+        let cycle = self.collection_cycle;
+        self.collection_cycle += 1;
+        if !cycle.is_multiple_of(SECURITY_CHECK_CYCLES) {
+            return;
+        }
+
+        self.firewall_rule_count = match self.command_output(Command::new("powershell").args([FIREWALL_RULE_SCRIPT])) {
+            Ok(output) if output.status.success() => parse_count(&String::from_utf8_lossy(&output.stdout)),
+            _ => None,
+        };
+        if self.firewall_rule_count.is_none() {
+            eprintln!("Ошибка получения числа правил брандмауэра");
+        }
+
+        self.defender_status = match self.command_output(Command::new("powershell").args([DEFENDER_SCRIPT])) {
+            Ok(output) if output.status.success() => parse_defender_status(&String::from_utf8_lossy(&output.stdout)),
+            _ => None,
+        };
+        if self.defender_status.is_none() {
+            eprintln!("Ошибка получения состояния Defender");
+        }
+    }
+
+    // Учетные записи, срок пароля которых получить не удалось, в результат не попадают;
+    // число оставшихся дней пересчитывается в каждом цикле
+    fn get_service_accounts(&mut self, now: DateTime<Utc>) -> Vec<ServiceAccountStatus> {
+        let fresh = self
+            .last_service_account_check
+            .is_some_and(|checked| checked.elapsed() < SERVICE_ACCOUNT_REFRESH_INTERVAL);
+        if !fresh && !self.service_accounts.is_empty() {
+            self.password_expiry = self
+                .service_accounts
+                .iter()
+                .filter_map(|account| {
+                    let script = password_expiry_script(&account.username);
+                    let expiry = match self.command_output(Command::new("powershell").args([script])) {
+                        Ok(output) if output.status.success() => {
+                            parse_password_expiry(&String::from_utf8_lossy(&output.stdout))
+                        }
+                        _ => None,
+                    };
+                    if expiry.is_none() {
+                        eprintln!("Ошибка получения срока пароля {}", account.username);
+                    }
+                    Some((account.username.clone(), expiry?))
+                })
+                .collect();
+            self.last_service_account_check = Some(Instant::now());
+        }
+
+        self.password_expiry
+            .iter()
+            .map(|(username, expires_at)| ServiceAccountStatus {
+                username: username.clone(),
+                expires_at: *expires_at,
+                days_remaining: expires_at.map(|expires_at| (expires_at - now).num_days()),
+            })
+            .collect()
+    }
+
+    fn get_smart_health(&mut self) -> Vec<SmartHealthResult> {
+        let fresh = self
+            .last_smart_check
+            .is_some_and(|checked| checked.elapsed() < SMART_REFRESH_INTERVAL);
+        if fresh {
+            return self.smart_health.clone();
+        }
+
+        let output = self.command_output_within(Command::new("powershell").args([SMART_SCRIPT]), SMART_QUERY_TIMEOUT);
+        self.smart_health = match output {
+            Ok(output) if output.status.success() => parse_smart_health(&String::from_utf8_lossy(&output.stdout)),
+            _ => {
+                eprintln!("Ошибка получения состояния дисков");
+                Vec::new()
+            }
+        };
+        self.last_smart_check = Some(Instant::now());
+        self.smart_health.clone()
+    }
+
+    // Задачи, состояние которых получить не удалось, в результат не попадают
+    fn get_scheduled_tasks(&self) -> Vec<ScheduledTaskStatus> {
+        self.scheduled_tasks
+            .iter()
+            .filter_map(|task| {
+                let script = format!(
+                    "$task = Get-ScheduledTask -TaskName '{}' -ErrorAction Stop; \
+                     $info = $task | Get-ScheduledTaskInfo; \
+                     $utc = {{ param($t) if ($t) {{ $t.ToUniversalTime().ToString('yyyy-MM-ddTHH:mm:ssZ') }} }}; \
+                     Write-Output \"$($task.State)|$(& $utc $info.LastRunTime)|$($info.LastTaskResult)|$(& $utc $info.NextRunTime)\"",
+                    task.name.replace('\'', "''")
+                );
+                let output = self.command_output(Command::new("powershell").args([script]));
+                match output {
+                    Ok(output) if output.status.success() => {
+                        parse_task_info(&task.label, &String::from_utf8_lossy(&output.stdout))
+                    }
+                    _ => {
+                        eprintln!("Ошибка получения состояния задачи {}", task.name);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Без IIS модуль WebAdministration не загружается; это не ошибка, пулов просто нет
+    fn get_iis_pools(&self) -> Vec<IisPoolStatus> {
+        if self.iis_app_pools.is_empty() {
+            return Vec::new();
+        }
+
+        let names: Vec<String> = self
+            .iis_app_pools
+            .iter()
+            .map(|pool| format!("'{}'", pool.name.replace('\'', "''")))
+            .collect();
+        let script = format!(
+            "Import-Module WebAdministration -ErrorAction Stop; \
+             foreach ($name in @({})) {{ \
+                 $state = (Get-WebAppPoolState -Name $name -ErrorAction SilentlyContinue).Value; \
+                 if (-not $state) {{ continue }}; \
+                 $workers = @(Get-ChildItem \"IIS:\\AppPools\\$name\\WorkerProcesses\" -ErrorAction SilentlyContinue).Count; \
+                 Write-Output \"$name|$state|$workers\" \
+             }}",
+            names.join(", ")
+        );
+
+        match self.command_output(Command::new("powershell").args([script])) {
+            Ok(output) if output.status.success() => parse_iis_pools(&String::from_utf8_lossy(&output.stdout)),
+            _ => Vec::new(),
+        }
+    }
+
+    // Аномалии отложенных через /alerts/snooze правил не попадают в результат
+    pub fn check_anomalies(
+        &self,
+        metrics: &SystemMetrics,
+        alerts: &AlertsConfig,
+        snoozes: &HashMap<String, Instant>,
+    ) -> Vec<AnomalyRecord> {
         let mut anomalies = Vec::new();
+        let now = Instant::now();
+        let mut push = |level, message: String| {
+            if !snooze::is_snoozed(snoozes, &message, now) {
+                anomalies.push(AnomalyRecord { timestamp: metrics.timestamp, message, level });
+            }
+        };
+
+        if metrics.cpu_usage > alerts.cpu_usage {
+            push(AlertLevel::Warning, format!("Высокая загрузка CPU: {:.1}%", metrics.cpu_usage));
+        }
+
+        if metrics.memory_usage_percent > alerts.memory_usage {
+            push(AlertLevel::Warning, format!("Высокая загрузка памяти: {:.1}%", metrics.memory_usage_percent));
+        }
+
+        if let Some(ms) = metrics.self_response_time_ms.filter(|ms| *ms > alerts.web_response_warning_ms) {
+            push(AlertLevel::Warning, format!("Медленный ответ веб-сервера: {} мс", ms));
+        }
+
+        if let Some(count) = metrics.pending_updates.filter(|count| *count > alerts.pending_updates) {
+            push(AlertLevel::Critical, format!("Ожидают установки обновлений: {}", count));
+        }
+
+        // Старые записи без списка дисков проверяются по общим порогам
+        if metrics.disks.is_empty() {
+            if let Some((level, message)) = disk_anomaly(
+                "C:",
+                metrics.disk_usage_percent,
+                (alerts.disk_warning, alerts.disk_critical),
+            ) {
+                push(level, message);
+            }
+        }
+
+        for disk in &metrics.disks {
+            let thresholds = alerts.disk_thresholds(&disk.drive);
+            if let Some((level, message)) = disk_anomaly(&disk.drive, disk.usage_percent, thresholds) {
+                push(level, message);
+            }
+        }
+
+        if metrics.collection_timeouts > TIMEOUT_WARNING_COUNT {
+            push(
+                AlertLevel::Warning,
+                format!(
+                    "Команды сбора метрик не укладываются в таймаут: {} за {} циклов",
+                    metrics.collection_timeouts, TIMEOUT_WINDOW_CYCLES
+                ),
+            );
+        }
+
+        if metrics.defender_realtime_enabled == Some(false) {
+            push(AlertLevel::Critical, "Защита Defender в реальном времени отключена".to_string());
+        }
+
+        for pool in metrics.iis_pool_results.iter().filter(|pool| pool.state.eq_ignore_ascii_case("Stopped")) {
+            push(AlertLevel::Critical, format!("Пул приложений IIS {} остановлен", pool.name));
+        }
 
-        if metrics.cpu_usage > 90.0 {
-            anomalies.push(format!("Высокая загрузка CPU: {:.1}%", metrics.cpu_usage));
+        for disk in &metrics.smart_health {
+            match disk.status {
+                SmartStatus::Failed => push(AlertLevel::Critical, format!("Диск {} неисправен по данным SMART", disk.drive)),
+                SmartStatus::Degraded => push(AlertLevel::Warning, format!("Диск {}: SMART сообщает о деградации", disk.drive)),
+                SmartStatus::Healthy | SmartStatus::Unknown => {}
+            }
         }
 
-        if metrics.memory_usage_percent > 90.0 {
-            anomalies.push(format!("Высокая загрузка памяти: {:.1}%", metrics.memory_usage_percent));
+        for account in &metrics.service_account_results {
+            let Some(days) = account.days_remaining else {
+                continue;
+            };
+            if days < PASSWORD_EXPIRY_CRITICAL_DAYS {
+                push(AlertLevel::Critical, format!("Пароль {} истекает через {} дн.", account.username, days.max(0)));
+            } else if days < PASSWORD_EXPIRY_WARNING_DAYS {
+                push(AlertLevel::Warning, format!("Пароль {} истекает через {} дн.", account.username, days));
+            }
         }
 
-        if metrics.disk_usage_percent > 90.0 {
-            anomalies.push(format!("Высокая загрузка диска: {:.1}%", metrics.disk_usage_percent));
+        for ldap in metrics.ldap_results.iter().filter(|ldap| !ldap.reachable) {
+            push(AlertLevel::Critical, format!("Контроллер домена {} недоступен", ldap.label));
+        }
+
+        for share in metrics.network_share_results.iter().filter(|share| !share.accessible) {
+            push(AlertLevel::Critical, format!("Сетевой ресурс {} недоступен", share.label));
+        }
+
+        for folder in &metrics.folder_watch_results {
+            let Some(watch) = self.folder_watches.iter().find(|watch| watch.label == folder.label) else {
+                continue;
+            };
+            let size_gb = folder.size_bytes as f64 / (1024.0 * 1024.0 * 1024.0);
+            if size_gb > watch.warn_above_gb {
+                push(AlertLevel::Warning, format!("Папка {} занимает {:.1} ГБ", folder.label, size_gb));
+            }
+        }
+
+        for task in &self.scheduled_tasks {
+            let Some(status) = metrics.scheduled_task_results.iter().find(|s| s.label == task.label) else {
+                push(AlertLevel::Warning, format!("Не удалось получить состояние задачи {}", task.label));
+                continue;
+            };
+            if !TASK_RESULTS_OK.contains(&status.last_result) {
+                push(AlertLevel::Critical, format!(
+                    "Задача {} завершилась с кодом 0x{:X}", task.label, status.last_result
+                ));
+            }
+            if let Some(max_minutes) = task.max_missed_interval_minutes {
+                let missed = status.last_run.is_none_or(|last_run| {
+                    metrics.timestamp - last_run > chrono::Duration::minutes(max_minutes as i64)
+                });
+                if missed {
+                    push(AlertLevel::Warning, format!(
+                        "Задача {} не запускалась больше {} мин", task.label, max_minutes
+                    ));
+                }
+            }
         }
 
         anomalies
     }
-}
\ No newline at end of file
+}
+
+fn disk_anomaly(drive: &str, usage: f32, (warning, critical): (f32, f32)) -> Option<(AlertLevel, String)> {
+    if usage > critical {
+        Some((AlertLevel::Critical, format!("Критическое заполнение диска {}: {:.1}%", drive, usage)))
+    } else if usage > warning {
+        Some((AlertLevel::Warning, format!("Высокая загрузка диска {}: {:.1}%", drive, usage)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DiskThreshold;
+    use chrono::TimeZone;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, minute, 0).unwrap()
+    }
+
+    // Без PDH: конструктор не обращается к счетчикам производительности
+    fn monitor() -> ResourceMonitor {
+        ResourceMonitor::with_collector(None)
+    }
+
+    fn disk(drive: &str, usage_percent: f32) -> DiskMetrics {
+        DiskMetrics { drive: drive.to_string(), used: 0, total: 0, usage_percent }
+    }
+
+    fn anomalies(metrics: &SystemMetrics, alerts: &AlertsConfig) -> Vec<(AlertLevel, String)> {
+        monitor()
+            .check_anomalies(metrics, alerts, &HashMap::new())
+            .into_iter()
+            .map(|a| (a.level, a.message))
+            .collect()
+    }
+
+    #[test]
+    fn drive_override_replaces_default_disk_thresholds() {
+        let alerts = AlertsConfig {
+            disk_overrides: vec![DiskThreshold { drive: "d:".to_string(), warning: 95.0, critical: 98.0 }],
+            ..Default::default()
+        };
+        let mut metrics = SystemMetrics::sample(at(0), 10.0);
+        metrics.disks = vec![disk("C:", 91.0), disk("D:", 95.0)];
+        let fired = anomalies(&metrics, &alerts);
+        assert_eq!(fired, [(AlertLevel::Critical, "Критическое заполнение диска C:: 91.0%".to_string())]);
+
+        metrics.disks = vec![disk("C:", 50.0), disk("D:", 99.0)];
+        let fired = anomalies(&metrics, &alerts);
+        assert_eq!(fired, [(AlertLevel::Critical, "Критическое заполнение диска D:: 99.0%".to_string())]);
+        assert_eq!(alerts.disk_thresholds("E:"), (80.0, 90.0));
+    }
+
+    #[test]
+    fn count_is_read_from_last_non_empty_line() {
+        assert_eq!(parse_count("17"), Some(17));
+        assert_eq!(parse_count("  3\r\n\r\n"), Some(3));
+        assert_eq!(parse_count("WARNING: поиск занял 25 с\n0\n"), Some(0));
+        assert_eq!(parse_count(""), None);
+        assert_eq!(parse_count("-1"), None);
+        assert_eq!(parse_count("Ошибка доступа"), None);
+    }
+
+    #[test]
+    fn failed_pdh_initialization_falls_back_to_powershell() {
+        let error = MonitorError::CollectionFailed { collector: "pdh".to_string(), source: "PDH_CSTATUS_NO_OBJECT".into() };
+        let monitor = ResourceMonitor::from_pdh(Err(error));
+        assert!(monitor.pdh.is_none());
+        // Скорость дисков есть только в PDH, остальные метрики берутся из PowerShell
+        assert_eq!(monitor.get_disk_io(), None);
+        assert!((0.0..=100.0).contains(&monitor.get_cpu_usage()));
+    }
+
+    #[test]
+    fn firewall_rule_count_is_parsed_from_powershell_output() {
+        assert_eq!(parse_count("\r\n412\r\n"), Some(412));
+        assert_eq!(parse_count("0\r\n"), Some(0));
+        assert_eq!(parse_count("Get-NetFirewallRule : Отказано в доступе\r\n"), None);
+    }
+
+    #[test]
+    fn defender_status_is_parsed_from_powershell_line() {
+        let scan = "2024-03-01T02:00:05Z".parse().unwrap();
+        assert_eq!(parse_defender_status("True|2024-03-01T02:00:05Z\r\n"), Some((true, Some(scan))));
+        assert_eq!(parse_defender_status("False|2024-03-01T02:00:05Z"), Some((false, Some(scan))));
+        assert_eq!(parse_defender_status("false|2024-03-01T02:00:05Z"), Some((false, Some(scan))));
+    }
+
+    #[test]
+    fn defender_without_quick_scan_has_no_scan_time() {
+        assert_eq!(parse_defender_status("True|\r\n"), Some((true, None)));
+        assert_eq!(parse_defender_status("False|"), Some((false, None)));
+    }
+
+    #[test]
+    fn garbage_defender_output_is_rejected() {
+        assert_eq!(parse_defender_status(""), None);
+        assert_eq!(parse_defender_status("True"), None);
+        assert_eq!(parse_defender_status("Enabled|2024-03-01T02:00:05Z"), None);
+        assert_eq!(parse_defender_status("Get-MpComputerStatus : служба недоступна"), None);
+    }
+
+    #[test]
+    fn task_info_is_parsed_from_powershell_line() {
+        let task = parse_task_info("Бэкап", "Ready|2024-03-01T02:00:05Z|267011|2024-03-02T02:00:00Z\r\n").unwrap();
+        assert_eq!(task.label, "Бэкап");
+        assert_eq!(task.last_run, Some("2024-03-01T02:00:05Z".parse().unwrap()));
+        assert_eq!(task.last_result, 267011);
+        assert_eq!(task.next_run, Some("2024-03-02T02:00:00Z".parse().unwrap()));
+        assert!(task.enabled);
+    }
+
+    #[test]
+    fn task_without_runs_and_negative_result() {
+        // Коды HRESULT выводятся как UInt32 и превышают i32::MAX
+        let task = parse_task_info("Бэкап", "Disabled||2147942402|").unwrap();
+        assert_eq!(task.last_run, None);
+        assert_eq!(task.next_run, None);
+        assert_eq!(task.last_result, 0x80070002_u32 as i32);
+        assert!(!task.enabled);
+
+        let never_run = parse_task_info("Бэкап", "Ready|1999-11-30T00:00:00Z|0|").unwrap();
+        assert_eq!(never_run.last_run, None);
+
+        assert!(parse_task_info("Бэкап", "Ready|2024-03-01T02:00:05Z|0").is_none());
+        assert!(parse_task_info("Бэкап", "Ready||ошибка|").is_none());
+    }
+
+    #[test]
+    fn iis_pools_are_parsed_line_by_line() {
+        let pools = parse_iis_pools("DefaultAppPool|Started|2\r\nApi|Stopped|0\r\n\r\n");
+        assert_eq!(pools.len(), 2);
+        assert_eq!(pools[0].name, "DefaultAppPool");
+        assert_eq!(pools[0].state, "Started");
+        assert_eq!(pools[0].worker_process_count, 2);
+        assert_eq!(pools[1].name, "Api");
+        assert_eq!(pools[1].state, "Stopped");
+        assert_eq!(pools[1].worker_process_count, 0);
+    }
+
+    #[test]
+    fn unrecognized_iis_lines_are_skipped() {
+        let output = "Import-Module : The specified module 'WebAdministration' was not loaded\r\n\
+                      Api|Started|много\r\nReports|Starting|1";
+        let pools = parse_iis_pools(output);
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].name, "Reports");
+        assert!(parse_iis_pools("").is_empty());
+    }
+
+    #[test]
+    fn missing_share_is_inaccessible() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("нет такой папки");
+        let status = get_network_share_status("Архив", missing.to_str().unwrap());
+        assert_eq!(status.label, "Архив");
+        assert!(!status.accessible);
+        assert_eq!(status.latency_ms, None);
+    }
+
+    #[test]
+    fn existing_share_reports_latency() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = get_network_share_status("Архив", dir.path().to_str().unwrap());
+        assert!(status.accessible);
+        assert!(status.latency_ms.is_some());
+    }
+
+    #[test]
+    fn smart_health_is_parsed_per_disk() {
+        let output = "Samsung SSD 870|Healthy|OK|0\r\nWDC WD40EFRX|Warning|Degraded|12\r\n\
+                      ST2000DM008|Unhealthy|Lost Communication|\r\nVirtual Disk|Unknown|OK|\r\n";
+        let disks = parse_smart_health(output);
+        let summary: Vec<(&str, SmartStatus, Option<u32>)> = disks
+            .iter()
+            .map(|d| (d.drive.as_str(), d.status, d.reallocated_sectors))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Samsung SSD 870", SmartStatus::Healthy, Some(0)),
+                ("WDC WD40EFRX", SmartStatus::Degraded, Some(12)),
+                ("ST2000DM008", SmartStatus::Failed, None),
+                ("Virtual Disk", SmartStatus::Unknown, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_smart_lines_are_skipped() {
+        assert!(parse_smart_health("Samsung SSD 870|Healthy|OK\r\n").is_empty());
+        assert!(parse_smart_health("").is_empty());
+    }
+
+    fn ldap_check(port: u16) -> LdapCheckConfig {
+        LdapCheckConfig { host: "127.0.0.1".to_string(), port, label: "DC01".to_string() }
+    }
+
+    #[tokio::test]
+    async fn listening_ldap_host_is_reachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let result = check_ldap_host(&ldap_check(listener.local_addr().unwrap().port())).await;
+        assert_eq!(result.label, "DC01");
+        assert!(result.reachable);
+        assert!(result.latency_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn stalled_ldap_host_times_out() {
+        // Сокет без accept с заполненной очередью не отвечает на новые подключения
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(1).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut backlog = Vec::new();
+        while let Ok(Ok(stream)) =
+            tokio::time::timeout(Duration::from_millis(200), tokio::net::TcpStream::connect(addr)).await
+        {
+            backlog.push(stream);
+        }
+
+        let started = Instant::now();
+        let result = check_ldap_host(&ldap_check(addr.port())).await;
+        assert!(!result.reachable);
+        assert_eq!(result.latency_ms, None);
+        assert!(started.elapsed() < LDAP_CONNECT_TIMEOUT + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn response_time_matches_handler_delay() {
+        use warp::Filter;
+
+        const DELAY_MS: u64 = 150;
+        let route = warp::path("metrics").and_then(|| async {
+            tokio::time::sleep(Duration::from_millis(DELAY_MS)).await;
+            Ok::<_, warp::Rejection>("{}")
+        });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let url = format!("http://{}/metrics", addr);
+        let elapsed = measure_response_time(&reqwest::Client::new(), &url).await.unwrap();
+        assert!(elapsed.abs_diff(DELAY_MS) <= 10, "{} мс вместо {}", elapsed, DELAY_MS);
+    }
+
+    #[tokio::test]
+    async fn failed_request_has_no_response_time() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+        drop(listener);
+        assert_eq!(measure_response_time(&reqwest::Client::new(), &url).await, None);
+    }
+
+    #[tokio::test]
+    async fn ldap_results_keep_config_order() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let closed = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let checks = [LdapCheckConfig { label: "DC02".to_string(), ..ldap_check(closed) }, ldap_check(port)];
+        let results = check_ldap_hosts(&checks).await;
+        let summary: Vec<(&str, bool)> = results.iter().map(|r| (r.label.as_str(), r.reachable)).collect();
+        assert_eq!(summary, [("DC02", false), ("DC01", true)]);
+    }
+
+    #[test]
+    fn password_expiry_is_parsed_from_ad_output() {
+        assert_eq!(
+            parse_password_expiry("2024-04-15T08:30:00Z\r\n"),
+            Some(Some(Utc.with_ymd_and_hms(2024, 4, 15, 8, 30, 0).unwrap()))
+        );
+        assert_eq!(parse_password_expiry("WARNING: модуль загружен\r\nnever\r\n"), Some(None));
+        assert_eq!(parse_password_expiry("15.04.2024 8:30:00"), None);
+        assert_eq!(parse_password_expiry(""), None);
+    }
+
+    #[test]
+    fn password_expiry_script_splits_domain() {
+        let script = password_expiry_script("CORP\\svc_monitor");
+        assert!(script.contains("-Identity 'svc_monitor' -Server 'CORP'"), "{}", script);
+
+        let script = password_expiry_script("o'brien");
+        assert!(script.contains("-Identity 'o''brien' -Properties"), "{}", script);
+    }
+
+    const PROCESS_LIST: &str = "0|System Idle Process|0\r\n4|System|0\r\n600|wininit.exe|500\r\n\
+                                700|services.exe|600\r\n800|svchost.exe|700\r\n900|svchost.exe|700\r\n\
+                                1000|child.exe|800\r\nне процесс\r\n";
+
+    fn children(tree: &[ProcessNode]) -> Vec<(u32, Option<u32>, Vec<u32>)> {
+        tree.iter().map(|node| (node.pid, node.parent_pid, node.children.clone())).collect()
+    }
+
+    #[test]
+    fn process_list_skips_unrecognized_lines() {
+        let processes = parse_process_list(PROCESS_LIST);
+        assert_eq!(processes.len(), 7);
+        assert_eq!(processes[2], (600, "wininit.exe".to_string(), 500));
+    }
+
+    #[test]
+    fn process_tree_links_children_to_parents() {
+        let tree = build_process_tree(parse_process_list(PROCESS_LIST), 10);
+        assert_eq!(
+            children(&tree),
+            [
+                (0, None, vec![4]),
+                (600, None, vec![700]),
+                (4, Some(0), vec![]),
+                (700, Some(600), vec![800, 900]),
+                (800, Some(700), vec![1000]),
+                (900, Some(700), vec![]),
+                (1000, Some(800), vec![]),
+            ]
+        );
+        assert_eq!(tree[3].name, "services.exe");
+    }
+
+    #[test]
+    fn process_tree_stops_at_max_depth() {
+        let tree = build_process_tree(parse_process_list(PROCESS_LIST), 3);
+        let pids: Vec<u32> = tree.iter().map(|node| node.pid).collect();
+        assert_eq!(pids, [0, 600, 4, 700, 800, 900]);
+        assert!(tree.iter().find(|node| node.pid == 800).unwrap().children.is_empty());
+    }
+
+    // 10 байт в корне, 200 во вложенной папке, 3000 на втором уровне вложенности
+    fn folder_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("logs").join("archive");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join("app.db"), [0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("logs").join("today.log"), [0u8; 120]).unwrap();
+        std::fs::write(dir.path().join("logs").join("errors.log"), [0u8; 80]).unwrap();
+        std::fs::write(nested.join("old.log"), [0u8; 3000]).unwrap();
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn folder_size_counts_nested_files() {
+        let dir = folder_tree();
+        assert_eq!(get_folder_size(dir.path(), 5), (3210, 4));
+    }
+
+    #[test]
+    fn folder_size_respects_depth_limit() {
+        let dir = folder_tree();
+        assert_eq!(get_folder_size(dir.path(), 1), (210, 3));
+        assert_eq!(get_folder_size(dir.path(), 0), (10, 1));
+        assert_eq!(get_folder_size(&dir.path().join("нет"), 5), (0, 0));
+    }
+
+    fn shell(script: &str) -> Command {
+        let mut command = if cfg!(windows) { Command::new("powershell") } else { Command::new("sh") };
+        command.args([if cfg!(windows) { "-Command" } else { "-c" }, script]);
+        command
+    }
+
+    #[test]
+    fn hanging_command_times_out_to_default() {
+        let monitor = monitor();
+        let script = if cfg!(windows) { "Start-Sleep -Seconds 2; 42" } else { "sleep 2; echo 42" };
+        let started = Instant::now();
+        let result = monitor.command_output_within(&mut shell(script), Duration::from_millis(300));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.as_ref().unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(monitor.cycle_timeouts.get(), 1);
+
+        // Так вызывающий код берет значение по умолчанию
+        let count = result.ok().and_then(|output| parse_count(&String::from_utf8_lossy(&output.stdout)));
+        assert_eq!(count.unwrap_or_default(), 0);
+    }
+
+    #[test]
+    fn fast_command_output_is_returned() {
+        let monitor = monitor();
+        let script = if cfg!(windows) { "Write-Output 42" } else { "echo 42" };
+        let output = monitor.command_output_within(&mut shell(script), Duration::from_secs(10)).unwrap();
+        assert!(output.status.success());
+        assert_eq!(parse_count(&String::from_utf8_lossy(&output.stdout)), Some(42));
+        assert_eq!(monitor.cycle_timeouts.get(), 0);
+    }
+}
@@ -0,0 +1,180 @@
+use super::ResourceMonitor;
+use serde::Serialize;
+use std::time::Instant;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+    pub p95_ms: f64,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        Self {
+            min_ms: min(samples),
+            max_ms: max(samples),
+            mean_ms: mean(samples),
+            p95_ms: percentile(samples, 95.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectorTiming {
+    pub name: &'static str,
+    pub stats: LatencyStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub iterations: usize,
+    pub collect_metrics: LatencyStats,
+    pub throughput_per_sec: f64,
+    pub collectors: Vec<CollectorTiming>,
+    pub slowest_collector: &'static str,
+}
+
+pub fn min(values: &[f64]) -> f64 {
+    values.iter().copied().reduce(f64::min).unwrap_or(0.0)
+}
+
+pub fn max(values: &[f64]) -> f64 {
+    values.iter().copied().reduce(f64::max).unwrap_or(0.0)
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Перцентиль методом ближайшего ранга: p95 из 10 значений - 10-е по величине
+pub fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+type Collector = fn(&mut ResourceMonitor);
+
+const COLLECTORS: [(&str, Collector); 5] = [
+    ("cpu", |m| {
+        m.get_cpu_usage();
+    }),
+    ("memory", |m| {
+        m.get_memory_info();
+    }),
+    ("disk", |m| {
+        m.get_disk_info();
+    }),
+    ("network", |m| {
+        m.get_network_stats();
+    }),
+    ("processes", |m| {
+        m.get_process_count();
+    }),
+];
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+pub fn run(monitor: &mut ResourceMonitor, iterations: usize) -> BenchmarkReport {
+    let iterations = iterations.max(1);
+    let mut totals = Vec::with_capacity(iterations);
+    let mut samples: Vec<(&'static str, Vec<f64>)> =
+        COLLECTORS.iter().map(|(name, _)| (*name, Vec::with_capacity(iterations))).collect();
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        monitor.collect_metrics();
+        totals.push(elapsed_ms(start));
+
+        // Каждый сборщик замеряется отдельно, чтобы найти самый медленный
+        for ((_, collect), (_, samples)) in COLLECTORS.iter().zip(samples.iter_mut()) {
+            let start = Instant::now();
+            collect(monitor);
+            samples.push(elapsed_ms(start));
+        }
+    }
+
+    let collectors: Vec<CollectorTiming> = samples
+        .iter()
+        .map(|(name, samples)| CollectorTiming { name, stats: LatencyStats::from_samples(samples) })
+        .collect();
+    let slowest_collector = collectors
+        .iter()
+        .max_by(|a, b| a.stats.mean_ms.total_cmp(&b.stats.mean_ms))
+        .map_or("", |c| c.name);
+
+    let collect_metrics = LatencyStats::from_samples(&totals);
+    let throughput_per_sec = if collect_metrics.mean_ms > 0.0 {
+        1000.0 / collect_metrics.mean_ms
+    } else {
+        0.0
+    };
+
+    BenchmarkReport {
+        iterations,
+        collect_metrics,
+        throughput_per_sec,
+        collectors,
+        slowest_collector,
+    }
+}
+
+impl BenchmarkReport {
+    pub fn print_table(&self) {
+        println!("⏱️  Замер сбора метрик, итераций: {}", self.iterations);
+        println!();
+        println!("{:<16} {:>10} {:>10} {:>10} {:>10}", "Этап", "min, мс", "max, мс", "mean, мс", "p95, мс");
+        print_row("collect_metrics", &self.collect_metrics);
+        for collector in &self.collectors {
+            print_row(collector.name, &collector.stats);
+        }
+        println!();
+        println!("Пропускная способность: {:.2} сборов/сек", self.throughput_per_sec);
+        println!("Самый медленный сборщик: {}", self.slowest_collector);
+        println!(
+            "Минимально разумный interval_seconds: {}",
+            (self.collect_metrics.max_ms / 1000.0).ceil().max(1.0)
+        );
+    }
+}
+
+fn print_row(name: &str, stats: &LatencyStats) {
+    println!(
+        "{:<16} {:>10.1} {:>10.1} {:>10.1} {:>10.1}",
+        name, stats.min_ms, stats.max_ms, stats.mean_ms, stats.p95_ms
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_of_known_samples() {
+        let samples = [5.0, 1.0, 4.0, 2.0, 3.0, 10.0, 9.0, 8.0, 7.0, 6.0];
+        assert_eq!(min(&samples), 1.0);
+        assert_eq!(max(&samples), 10.0);
+        assert_eq!(mean(&samples), 5.5);
+        assert_eq!(percentile(&samples, 95.0), 10.0);
+        assert_eq!(percentile(&samples, 50.0), 5.0);
+        assert_eq!(percentile(&samples, 0.0), 1.0);
+    }
+
+    #[test]
+    fn statistics_of_no_samples_are_zero() {
+        let stats = LatencyStats::from_samples(&[]);
+        assert_eq!(stats, LatencyStats { min_ms: 0.0, max_ms: 0.0, mean_ms: 0.0, p95_ms: 0.0 });
+        assert_eq!(LatencyStats::from_samples(&[2.5]).p95_ms, 2.5);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::path::PathBuf;
+
+// Ошибки службы с полями, по которым их можно различить; наружу передаются как anyhow::Error
+#[derive(Debug)]
+pub enum MonitorError {
+    CollectionFailed {
+        collector: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    StorageCorrupted {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    StorageNotFound {
+        path: PathBuf,
+    },
+    NoMetrics {
+        period: String,
+    },
+    ConfigInvalid {
+        field: String,
+        reason: String,
+    },
+    ServiceNotInstalled {
+        service: String,
+    },
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorError::CollectionFailed { collector, source } => {
+                write!(f, "Ошибка сбора метрики {}: {}", collector, source)
+            }
+            MonitorError::StorageCorrupted { path, line, reason } => {
+                write!(f, "Повреждена запись {} в {}: {}", line, path.display(), reason)
+            }
+            MonitorError::StorageNotFound { path } => {
+                write!(f, "Файл {} не найден", path.display())
+            }
+            MonitorError::NoMetrics { period } => {
+                write!(f, "Нет сохраненных метрик за период {}", period)
+            }
+            MonitorError::ConfigInvalid { field, reason } => {
+                write!(f, "Неверное значение {} в конфигурации: {}", field, reason)
+            }
+            MonitorError::ServiceNotInstalled { service } => {
+                write!(f, "Служба '{}' не установлена", service)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MonitorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MonitorError::CollectionFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_names_the_failing_field() {
+        let cases = [
+            (
+                MonitorError::CollectionFailed { collector: "pdh_cpu".into(), source: "нет счетчика".into() },
+                vec!["pdh_cpu", "нет счетчика"],
+            ),
+            (
+                MonitorError::StorageCorrupted { path: "data/metrics.json".into(), line: 42, reason: "EOF".into() },
+                vec!["data/metrics.json", "42", "EOF"],
+            ),
+            (MonitorError::StorageNotFound { path: "data/metrics.json.bak".into() }, vec!["data/metrics.json.bak"]),
+            (MonitorError::NoMetrics { period: "A".into() }, vec!["период A"]),
+            (
+                MonitorError::ConfigInvalid { field: "web.port".into(), reason: "не число".into() },
+                vec!["web.port", "не число"],
+            ),
+            (MonitorError::ServiceNotInstalled { service: "MonitorSystemOPs".into() }, vec!["MonitorSystemOPs"]),
+        ];
+        for (error, expected) in cases {
+            let text = error.to_string();
+            for part in expected {
+                assert!(text.contains(part), "{:?} не содержит {:?}", text, part);
+            }
+        }
+    }
+
+    #[test]
+    fn only_collection_failures_have_a_source() {
+        use std::error::Error;
+        let failed = MonitorError::CollectionFailed { collector: "wmi".into(), source: "timeout".into() };
+        assert_eq!(failed.source().unwrap().to_string(), "timeout");
+        assert!(MonitorError::NoMetrics { period: "B".into() }.source().is_none());
+    }
+}
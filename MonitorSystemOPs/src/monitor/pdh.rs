@@ -0,0 +1,131 @@
+use windows::core::{HRESULT, HSTRING};
+use windows::Win32::System::Performance::{
+    PdhAddEnglishCounterW, PdhCloseQuery, PdhCollectQueryData, PdhGetFormattedCounterValue, PdhOpenQueryW,
+    PDH_FMT_COUNTERVALUE, PDH_FMT_DOUBLE,
+};
+
+use super::error::MonitorError;
+
+const ERROR_SUCCESS: u32 = 0;
+
+// Путь счетчика вида \Объект(Экземпляр)\Счетчик; экземпляр указывается не у всех объектов
+pub fn counter_path(object: &str, instance: Option<&str>, counter: &str) -> String {
+    match instance {
+        Some(instance) => format!("\\{}({})\\{}", object, instance, counter),
+        None => format!("\\{}\\{}", object, counter),
+    }
+}
+
+fn pdh_error(status: u32) -> MonitorError {
+    MonitorError::CollectionFailed {
+        collector: "pdh".to_string(),
+        source: Box::new(windows::core::Error::from(HRESULT(status as i32))),
+    }
+}
+
+fn check(status: u32) -> Result<(), MonitorError> {
+    if status == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(pdh_error(status))
+    }
+}
+
+// Запрос PDH со своим набором счетчиков. Счетчики скоростей считаются между двумя
+// последними сборами, поэтому каждый запрос собирается один раз за цикл
+struct PdhQuery {
+    query: isize,
+    counters: Vec<isize>,
+}
+
+impl PdhQuery {
+    // Английские имена счетчиков не зависят от языка системы
+    fn open(paths: &[String]) -> Result<Self, MonitorError> {
+        let mut query = 0;
+        check(unsafe { PdhOpenQueryW(None, 0, &mut query) })?;
+        let mut pdh_query = Self { query, counters: Vec::new() };
+        for path in paths {
+            let mut counter = 0;
+            check(unsafe { PdhAddEnglishCounterW(query, &HSTRING::from(path.as_str()), 0, &mut counter) })?;
+            pdh_query.counters.push(counter);
+        }
+        // Первый сбор задает начальную точку для счетчиков скоростей
+        check(unsafe { PdhCollectQueryData(query) })?;
+        Ok(pdh_query)
+    }
+
+    fn collect(&self) -> Result<Vec<f64>, MonitorError> {
+        check(unsafe { PdhCollectQueryData(self.query) })?;
+        self.counters
+            .iter()
+            .map(|&counter| {
+                let mut value = PDH_FMT_COUNTERVALUE::default();
+                check(unsafe { PdhGetFormattedCounterValue(counter, PDH_FMT_DOUBLE, None, &mut value) })?;
+                check(value.CStatus)?;
+                Ok(unsafe { value.Anonymous.doubleValue })
+            })
+            .collect()
+    }
+}
+
+impl Drop for PdhQuery {
+    fn drop(&mut self) {
+        unsafe {
+            PdhCloseQuery(self.query);
+        }
+    }
+}
+
+// Сбор процессора, памяти и дисков через счетчики производительности в процессе службы,
+// без запуска PowerShell
+pub struct PdhCollector {
+    cpu: PdhQuery,
+    memory: PdhQuery,
+    disk_io: PdhQuery,
+}
+
+impl PdhCollector {
+    pub fn new() -> Result<Self, MonitorError> {
+        Ok(Self {
+            cpu: PdhQuery::open(&[counter_path("Processor", Some("_Total"), "% Processor Time")])?,
+            memory: PdhQuery::open(&[counter_path("Memory", None, "Available MBytes")])?,
+            disk_io: PdhQuery::open(&[
+                counter_path("PhysicalDisk", Some("_Total"), "Disk Read Bytes/sec"),
+                counter_path("PhysicalDisk", Some("_Total"), "Disk Write Bytes/sec"),
+            ])?,
+        })
+    }
+
+    pub fn cpu_usage(&self) -> Result<f32, MonitorError> {
+        Ok(self.cpu.collect()?[0] as f32)
+    }
+
+    pub fn available_memory_bytes(&self) -> Result<u64, MonitorError> {
+        Ok((self.memory.collect()?[0] * 1024.0 * 1024.0) as u64)
+    }
+
+    // Чтение и запись всех физических дисков, байт в секунду
+    pub fn disk_io(&self) -> Result<(f64, f64), MonitorError> {
+        let values = self.disk_io.collect()?;
+        Ok((values[0], values[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_path_includes_instance() {
+        assert_eq!(counter_path("Processor", Some("_Total"), "% Processor Time"), r"\Processor(_Total)\% Processor Time");
+        assert_eq!(
+            counter_path("PhysicalDisk", Some("_Total"), "Disk Read Bytes/sec"),
+            r"\PhysicalDisk(_Total)\Disk Read Bytes/sec"
+        );
+    }
+
+    #[test]
+    fn counter_path_without_instance() {
+        assert_eq!(counter_path("Memory", None, "Available MBytes"), r"\Memory\Available MBytes");
+    }
+}
@@ -1,6 +1,12 @@
+use std::collections::HashMap;
 use std::process::Command;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
 use chrono::Utc;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::NotificationTemplates;
 
 #[derive(Debug, Serialize)]
 pub struct Notification {
@@ -8,46 +14,191 @@ pub struct Notification {
     pub level: String,
     pub message: String,
     pub service: String,
+    pub hostname: String,
+}
+
+// Текст уведомления с подстановками {level}, {message}, {service}, {timestamp}, {hostname}
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotificationTemplate {
+    pub body: String,
 }
 
-pub struct NotificationSystem;
+impl NotificationTemplate {
+    pub fn new(body: &str) -> Self {
+        Self { body: body.to_string() }
+    }
+
+    // Неизвестные подстановки остаются как есть; подставленные значения повторно не разбираются
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> String {
+        let mut output = String::with_capacity(self.body.len());
+        let mut rest = self.body.as_str();
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find(['{', '}']) {
+                Some(end) if after.as_bytes()[end] == b'}' => {
+                    let name = &after[..end];
+                    match vars.get(name) {
+                        Some(value) => output.push_str(value),
+                        None => output.push_str(&rest[start..start + end + 2]),
+                    }
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    output.push('{');
+                    rest = after;
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+const SERVICE_NAME: &str = "MonitorSystemOPs";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Входящий вебхук Slack; channel переопределяет канал, заданный при создании вебхука
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SlackWebhookChannel {
+    pub webhook_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+impl SlackWebhookChannel {
+    fn color(level: &str) -> &'static str {
+        match level {
+            "ERROR" | "CRITICAL" => "danger",
+            "WARNING" => "warning",
+            _ => "good",
+        }
+    }
+
+    pub fn payload(&self, notification: &Notification) -> Value {
+        let subject = format!("[{}] {}: {}", notification.hostname, notification.service, notification.level);
+        let mut payload = json!({
+            "text": subject,
+            "attachments": [{
+                "color": Self::color(&notification.level),
+                "fallback": format!("{} - {}", subject, notification.message),
+                "blocks": [
+                    {
+                        "type": "section",
+                        "text": { "type": "mrkdwn", "text": notification.message },
+                    },
+                    {
+                        "type": "context",
+                        "elements": [{ "type": "mrkdwn", "text": notification.timestamp }],
+                    },
+                ],
+            }],
+        });
+        if let Some(channel) = &self.channel {
+            payload["channel"] = json!(channel);
+        }
+        payload
+    }
+
+    pub fn send(&self, notification: &Notification) -> Result<()> {
+        let response = reqwest::blocking::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()?
+            .post(&self.webhook_url)
+            .json(&self.payload(notification))
+            .send()?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Slack вернул {}: {}", response.status(), response.text().unwrap_or_default()));
+        }
+        Ok(())
+    }
+}
+
+pub struct NotificationSystem {
+    templates: NotificationTemplates,
+    slack_webhooks: Vec<SlackWebhookChannel>,
+}
 
 impl NotificationSystem {
-    pub fn new() -> Self {
-        Self
+    pub fn new(templates: NotificationTemplates) -> Self {
+        Self { templates, slack_webhooks: Vec::new() }
+    }
+
+    pub fn with_slack_webhooks(mut self, webhooks: Vec<SlackWebhookChannel>) -> Self {
+        self.slack_webhooks = webhooks;
+        self
+    }
+
+    fn build(&self, template: &NotificationTemplate, level: &str, message: &str) -> Notification {
+        let timestamp = Utc::now().to_rfc3339();
+        let hostname = std::env::var("COMPUTERNAME").unwrap_or_default();
+        let vars = HashMap::from([
+            ("level", level),
+            ("message", message),
+            ("service", SERVICE_NAME),
+            ("timestamp", timestamp.as_str()),
+            ("hostname", hostname.as_str()),
+        ]);
+        Notification {
+            message: template.render(&vars),
+            timestamp,
+            level: level.to_string(),
+            service: SERVICE_NAME.to_string(),
+            hostname,
+        }
+    }
+
+    // Запрос выполняется синхронно, поэтому уведомления нельзя отправлять из асинхронного кода
+    fn send_to_channels(&self, notification: &Notification) {
+        for webhook in &self.slack_webhooks {
+            if let Err(e) = webhook.send(notification) {
+                eprintln!("❌ Ошибка отправки в Slack: {}", e);
+            }
+        }
+    }
+
+    // Проверка каналов: сообщение без шаблона уходит в каждый канал по очереди,
+    // результат возвращается для каждого канала отдельно. При dry_run только выводится
+    // то, что было бы отправлено
+    pub fn send_test_notification(&self, level: &str, message: &str, dry_run: bool) -> Vec<(String, Result<()>)> {
+        let notification = self.build(&NotificationTemplate::new("{message}"), level, message);
+        self.slack_webhooks
+            .iter()
+            .map(|webhook| {
+                let name = match &webhook.channel {
+                    Some(channel) => format!("Slack ({})", channel),
+                    None => "Slack".to_string(),
+                };
+                let result = if dry_run {
+                    println!("{} <- {}", name, webhook.payload(&notification));
+                    Ok(())
+                } else {
+                    webhook.send(&notification)
+                };
+                (name, result)
+            })
+            .collect()
     }
 
     pub fn send_start_notification(&self) {
-        let notification = Notification {
-            timestamp: Utc::now().to_rfc3339(),
-            level: "INFO".to_string(),
-            message: "Служба мониторинга запущена".to_string(),
-            service: "MonitorSystemOPs".to_string(),
-        };
+        let notification = self.build(&self.templates.start_body, "INFO", "");
         self.log_notification(&notification);
-        self.show_system_notification("MonitorSystemOPs", "Служба мониторинга запущена");
+        self.send_to_channels(&notification);
+        self.show_system_notification(SERVICE_NAME, &notification.message);
     }
 
     pub fn send_stop_notification(&self) {
-        let notification = Notification {
-            timestamp: Utc::now().to_rfc3339(),
-            level: "INFO".to_string(),
-            message: "Служба мониторинга остановлена".to_string(),
-            service: "MonitorSystemOPs".to_string(),
-        };
+        let notification = self.build(&self.templates.stop_body, "INFO", "");
         self.log_notification(&notification);
-        self.show_system_notification("MonitorSystemOPs", "Служба мониторинга остановлена");
+        self.send_to_channels(&notification);
+        self.show_system_notification(SERVICE_NAME, &notification.message);
     }
 
     pub fn send_error_notification(&self, error: &str) {
-        let notification = Notification {
-            timestamp: Utc::now().to_rfc3339(),
-            level: "ERROR".to_string(),
-            message: format!("Ошибка: {}", error),
-            service: "MonitorSystemOPs".to_string(),
-        };
+        let notification = self.build(&self.templates.error_body, "ERROR", error);
         self.log_notification(&notification);
-        self.show_system_notification("MonitorSystemOPs - Ошибка", error);
+        self.send_to_channels(&notification);
+        self.show_system_notification("MonitorSystemOPs - Ошибка", &notification.message);
     }
 
     pub fn send_anomaly_notification(&self, anomalies: &[String]) {
@@ -55,15 +206,10 @@ impl NotificationSystem {
             return;
         }
 
-        let message = anomalies.join("; ");
-        let notification = Notification {
-            timestamp: Utc::now().to_rfc3339(),
-            level: "WARNING".to_string(),
-            message: format!("Обнаружены аномалии: {}", message),
-            service: "MonitorSystemOPs".to_string(),
-        };
+        let notification = self.build(&self.templates.anomaly_body, "WARNING", &anomalies.join("; "));
         self.log_notification(&notification);
-        self.show_system_notification("MonitorSystemOPs - Предупреждение", &message);
+        self.send_to_channels(&notification);
+        self.show_system_notification("MonitorSystemOPs - Предупреждение", &notification.message);
     }
 
     fn log_notification(&self, notification: &Notification) {
@@ -85,12 +231,164 @@ impl NotificationSystem {
     }
 
     fn show_system_notification(&self, title: &str, message: &str) {
-        // Используем PowerShell для показа системных уведомлений
+        // Используем PowerShell для показа системных уведомлений; закрытия окна не ждем,
+        // иначе цикл уведомлений встанет до реакции пользователя
         let _ = Command::new("powershell")
-            .args(&[
+            .args([
                 "-Command",
                 &format!("Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.MessageBox]::Show('{}', '{}')", message, title)
             ])
-            .output();
+            .spawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // HTTP-сервер на один запрос: отвечает status и возвращает тело запроса
+    fn mock_server(status: &'static str) -> (String, JoinHandle<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            serde_json::from_slice(&body).unwrap()
+        });
+        (url, handle)
+    }
+
+    fn notification(level: &str) -> Notification {
+        Notification {
+            timestamp: "2024-03-01T12:00:00+00:00".to_string(),
+            level: level.to_string(),
+            message: "Высокая загрузка CPU: 95.0%".to_string(),
+            service: SERVICE_NAME.to_string(),
+            hostname: "SRV01".to_string(),
+        }
+    }
+
+    fn vars() -> HashMap<&'static str, &'static str> {
+        HashMap::from([
+            ("level", "WARNING"),
+            ("message", "Высокая загрузка CPU: 95.0%"),
+            ("service", SERVICE_NAME),
+            ("timestamp", "2024-03-01T12:00:00+00:00"),
+            ("hostname", "SRV01"),
+        ])
+    }
+
+    #[test]
+    fn all_placeholders_are_replaced() {
+        let template = NotificationTemplate::new("[{hostname}] {service} {level} {timestamp}: {message}");
+        assert_eq!(
+            template.render(&vars()),
+            "[SRV01] MonitorSystemOPs WARNING 2024-03-01T12:00:00+00:00: Высокая загрузка CPU: 95.0%"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_stay_verbatim() {
+        let template = NotificationTemplate::new("{unknown} {level} {message");
+        assert_eq!(template.render(&vars()), "{unknown} WARNING {message");
+        assert_eq!(NotificationTemplate::new("{{level}}").render(&vars()), "{WARNING}");
+    }
+
+    #[test]
+    fn substituted_values_are_not_rendered_again() {
+        let vars = HashMap::from([("message", "{level}"), ("level", "ERROR")]);
+        assert_eq!(NotificationTemplate::new("{message}").render(&vars), "{level}");
+    }
+
+    #[test]
+    fn slack_payload_is_colored_by_level() {
+        for (level, color) in [("INFO", "good"), ("WARNING", "warning"), ("ERROR", "danger"), ("CRITICAL", "danger")] {
+            let (url, server) = mock_server("200 OK");
+            let webhook = SlackWebhookChannel { webhook_url: url, channel: None };
+            webhook.send(&notification(level)).unwrap();
+
+            let payload = server.join().unwrap();
+            assert_eq!(payload["text"], format!("[SRV01] MonitorSystemOPs: {}", level));
+            assert!(payload.get("channel").is_none());
+            let attachment = &payload["attachments"][0];
+            assert_eq!(attachment["color"], color, "{}", level);
+            assert_eq!(
+                attachment["fallback"],
+                format!("[SRV01] MonitorSystemOPs: {} - Высокая загрузка CPU: 95.0%", level)
+            );
+            assert_eq!(attachment["blocks"][0]["text"]["text"], "Высокая загрузка CPU: 95.0%");
+            assert_eq!(attachment["blocks"][1]["elements"][0]["text"], "2024-03-01T12:00:00+00:00");
+        }
+    }
+
+    #[test]
+    fn slack_channel_overrides_webhook_channel() {
+        let (url, server) = mock_server("200 OK");
+        let webhook = SlackWebhookChannel { webhook_url: url, channel: Some("#ops".to_string()) };
+        webhook.send(&notification("WARNING")).unwrap();
+        assert_eq!(server.join().unwrap()["channel"], "#ops");
+    }
+
+    #[test]
+    fn slack_error_status_is_reported() {
+        let (url, server) = mock_server("404 Not Found");
+        let webhook = SlackWebhookChannel { webhook_url: url, channel: None };
+        let error = webhook.send(&notification("ERROR")).unwrap_err();
+        assert!(error.to_string().contains("404"), "{}", error);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_notification_reaches_every_channel() {
+        let (ops_url, ops) = mock_server("200 OK");
+        let (dev_url, dev) = mock_server("500 Internal Server Error");
+        let system = NotificationSystem::new(NotificationTemplates::default()).with_slack_webhooks(vec![
+            SlackWebhookChannel { webhook_url: ops_url, channel: Some("#ops".to_string()) },
+            SlackWebhookChannel { webhook_url: dev_url, channel: None },
+        ]);
+
+        let results = system.send_test_notification("WARNING", "Test alert from MonitorSystemOPs", false);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "Slack (#ops)");
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0, "Slack");
+        assert!(results[1].1.as_ref().unwrap_err().to_string().contains("500"));
+
+        for payload in [ops.join().unwrap(), dev.join().unwrap()] {
+            assert_eq!(payload["attachments"][0]["color"], "warning");
+            assert_eq!(payload["attachments"][0]["blocks"][0]["text"]["text"], "Test alert from MonitorSystemOPs");
+        }
+    }
+
+    #[test]
+    fn dry_run_test_notification_sends_nothing() {
+        // На закрытый порт отправка завершилась бы ошибкой
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let system = NotificationSystem::new(NotificationTemplates::default()).with_slack_webhooks(vec![
+            SlackWebhookChannel { webhook_url: format!("http://{}/hook", closed), channel: None },
+        ]);
+        let results = system.send_test_notification("CRITICAL", "{level}", true);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok());
     }
 }
\ No newline at end of file
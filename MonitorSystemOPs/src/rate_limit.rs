@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+use crate::config::RateLimitConfig;
+
+// Сверх этого числа клиентов корзины, успевшие снова наполниться, удаляются
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+// Запрос тратит один токен; токены восстанавливаются непрерывно со скоростью refill_per_sec
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub last_refill: Instant,
+    pub capacity: u64,
+    pub refill_per_sec: f64,
+}
+
+impl TokenBucket {
+    fn new(capacity: u64, refill_per_sec: f64, now: Instant) -> Self {
+        Self { tokens: capacity as f64, last_refill: now, capacity, refill_per_sec }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.capacity as f64
+    }
+
+    // Ok - токен потрачен, Err - через сколько появится следующий
+    fn consume(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.refill_per_sec <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+    }
+}
+
+// Отдельная корзина на каждый IP-адрес клиента
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    // Общий Mutex вместо DashMap: крейта нет среди зависимостей, а сборка идет без доступа
+    // к реестру. Блокировка держится на несколько арифметических операций над корзиной,
+    // и при емкости в 60 запросов на клиента конкуренции за нее практически нет; полный
+    // проход по карте бывает только при очистке сверх MAX_TRACKED_CLIENTS
+    buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn consume(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.consume_at(ip, Instant::now())
+    }
+
+    pub fn consume_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&ip) {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                !bucket.is_full()
+            });
+        }
+        buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::new(self.config.capacity, self.config.refill_per_sec, now))
+            .consume(now)
+    }
+}
+
+#[derive(Debug)]
+struct RateLimited {
+    retry_after: Duration,
+}
+
+impl warp::reject::Reject for RateLimited {}
+
+// Пропускает запрос, если у клиента остались токены; без адреса клиента ограничение не действует
+pub fn rate_limit_filter(limiter: Arc<RateLimiter>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |addr: Option<std::net::SocketAddr>| {
+            let limiter = limiter.clone();
+            async move {
+                match addr.map(|addr| limiter.consume(addr.ip())) {
+                    Some(Err(retry_after)) => Err(warp::reject::custom(RateLimited { retry_after })),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// 429 с Retry-After в целых секундах, округленных вверх; остальные отказы передаются дальше
+pub async fn handle_rejection(rejection: Rejection) -> Result<warp::reply::Response, Rejection> {
+    let Some(limited) = rejection.find::<RateLimited>() else {
+        return Err(rejection);
+    };
+    let retry_after = limited.retry_after.as_secs_f64().ceil().clamp(1.0, u32::MAX as f64) as u64;
+    let body = warp::reply::json(&serde_json::json!({ "error": "Слишком много запросов" }));
+    let reply = warp::reply::with_status(body, StatusCode::TOO_MANY_REQUESTS);
+    Ok(warp::reply::with_header(reply, "Retry-After", retry_after.to_string()).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7));
+
+    #[tokio::test]
+    async fn request_over_capacity_gets_429() {
+        // Восстановление замедлено, чтобы токен не успел появиться за время теста
+        let config = RateLimitConfig { refill_per_sec: 0.1, ..RateLimitConfig::default() };
+        let limiter = Arc::new(RateLimiter::new(config));
+        let route = rate_limit_filter(limiter)
+            .and(warp::path("metrics"))
+            .map(|| "{}")
+            .recover(handle_rejection);
+        let request = |ip: IpAddr| warp::test::request().path("/metrics").remote_addr(SocketAddr::new(ip, 50000));
+
+        for i in 0..60 {
+            assert_eq!(request(CLIENT).reply(&route).await.status(), StatusCode::OK, "запрос {}", i + 1);
+        }
+        let response = request(CLIENT).reply(&route).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["Retry-After"], "10");
+
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 8));
+        assert_eq!(request(other).reply(&route).await.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+        let now = Instant::now();
+        for _ in 0..60 {
+            assert!(limiter.consume_at(CLIENT, now).is_ok());
+        }
+        assert_eq!(limiter.consume_at(CLIENT, now), Err(Duration::from_millis(100)));
+        assert!(limiter.consume_at(CLIENT, now + Duration::from_millis(100)).is_ok());
+        assert!(limiter.consume_at(CLIENT, now + Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn disabled_limiter_lets_everything_through() {
+        let limiter = RateLimiter::new(RateLimitConfig { enabled: false, ..RateLimitConfig::default() });
+        let now = Instant::now();
+        assert!((0..100).all(|_| limiter.consume_at(CLIENT, now).is_ok()));
+    }
+}
@@ -1,62 +1,86 @@
 use std::fs;
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{LocalFree, HLOCAL};
+use windows::Win32::Security::Cryptography::{
+    CryptProtectData, CryptUnprotectData, CRYPTPROTECT_LOCAL_MACHINE, CRYPTPROTECT_UI_FORBIDDEN,
+    CRYPT_INTEGER_BLOB,
+};
 
 pub struct SecurityManager;
 
+// Копирует результат DPAPI и освобождает буфер, выделенный системой
+fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    if blob.pbData.is_null() {
+        return Vec::new();
+    }
+    // SAFETY: DPAPI вернул буфер длиной cbData, выделенный через LocalAlloc
+    unsafe {
+        let data = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(blob.pbData.cast()));
+        data
+    }
+}
+
+fn input_blob(data: &[u8]) -> Result<CRYPT_INTEGER_BLOB> {
+    Ok(CRYPT_INTEGER_BLOB {
+        cbData: u32::try_from(data.len()).map_err(|_| anyhow!("Слишком большой секрет"))?,
+        pbData: data.as_ptr() as *mut u8,
+    })
+}
+
 impl SecurityManager {
     pub fn new() -> Self {
         Self
     }
 
-    pub fn validate_config_permissions(&self) -> Result<()> {
-        let config_path = "config/config.toml";
-
-        if !Path::new(config_path).exists() {
-            return Ok(());
+    // Шифрует секрет DPAPI в области машины: расшифровать его может любой процесс
+    // на этом компьютере (в том числе служба), но не на другом
+    pub fn protect_secret(plaintext: &[u8]) -> Result<Vec<u8>> {
+        let input = input_blob(plaintext)?;
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        // SAFETY: input ссылается на plaintext, который живет до конца вызова
+        unsafe {
+            CryptProtectData(
+                &input,
+                PCWSTR::null(),
+                None,
+                None,
+                None,
+                CRYPTPROTECT_LOCAL_MACHINE | CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )?;
         }
-
-        let _metadata = fs::metadata(config_path)?;
-        println!("Конфигурационный файл защищен");
-
-        Ok(())
+        Ok(take_blob(output))
     }
 
-    pub fn encrypt_config(&self) -> Result<()> {
-        use base64::Engine;
-        let config_path = "config/config.toml";
-        let backup_path = "config/config.toml.backup";
-
-        if Path::new(config_path).exists() {
-            let content = fs::read_to_string(config_path)?;
-            let encoded = base64::engine::general_purpose::STANDARD.encode(&content);
-            fs::write(backup_path, encoded)?;
-            println!("Конфигурация зашифрована и сохранена в backup");
+    pub fn unprotect_secret(blob: &[u8]) -> Result<Vec<u8>> {
+        let input = input_blob(blob)?;
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        // SAFETY: input ссылается на blob, который живет до конца вызова
+        unsafe {
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)?;
         }
-
-        Ok(())
+        Ok(take_blob(output))
     }
 
-    pub fn decrypt_config(&self) -> Result<()> {
-        // Используем новое API base64
-        use base64::Engine;
+    pub fn validate_config_permissions(&self) -> Result<()> {
         let config_path = "config/config.toml";
-        let backup_path = "config/config.toml.backup";
 
-        if Path::new(backup_path).exists() {
-            let encoded = fs::read_to_string(backup_path)?;
-            let decoded = base64::engine::general_purpose::STANDARD.decode(encoded)?;
-            let content = String::from_utf8(decoded)?;
-            fs::write(config_path, content)?;
-            println!("Конфигурация восстановлена из backup");
+        if !Path::new(config_path).exists() {
+            return Ok(());
         }
 
+        let _metadata = fs::metadata(config_path)?;
+        println!("Конфигурационный файл защищен");
+
         Ok(())
     }
 
     pub fn is_running_as_admin(&self) -> bool {
         let output = std::process::Command::new("powershell")
-            .args(&[
+            .args([
                 "-Command",
                 "([Security.Principal.WindowsPrincipal] [Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole([Security.Principal.WindowsBuiltInRole] 'Administrator')"
             ])
@@ -70,4 +94,24 @@ impl SecurityManager {
             _ => false
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DPAPI есть только в Windows
+    #[cfg(windows)]
+    #[test]
+    fn protected_secret_round_trips() {
+        let plaintext = "xoxb-секрет 123".as_bytes();
+        let blob = SecurityManager::protect_secret(plaintext).unwrap();
+        assert_ne!(blob, plaintext);
+        assert_eq!(SecurityManager::unprotect_secret(&blob).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn empty_blob_copies_nothing() {
+        assert!(take_blob(CRYPT_INTEGER_BLOB::default()).is_empty());
+    }
 }
\ No newline at end of file
@@ -1,12 +1,17 @@
 use windows_service::{
     service::{
-        ServiceAccess, ServiceErrorControl, ServiceInfo, ServiceStartType, ServiceState,
+        Service, ServiceAccess, ServiceErrorControl, ServiceInfo, ServiceStartType, ServiceState,
         ServiceType,
     },
     service_manager::{ServiceManager as WinServiceManager, ServiceManagerAccess},
 };
 use std::ffi::OsString;
+use std::path::Path;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::error::MonitorError;
 
 const SERVICE_NAME: &str = "MonitorSystemOPs";
 const SERVICE_DISPLAY_NAME: &str = "System Operations Monitor";
@@ -14,6 +19,78 @@ const SERVICE_DESCRIPTION: &str = "Monitors system resources and provides operat
 
 pub struct WindowsServiceManager;
 
+const SERVICE_STATS_FILE: &str = "data/service_stats.json";
+
+// Время последнего запуска и число перезапусков; переживает перезапуск службы,
+// поэтому время хранится в UTC, а не в Instant
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceStats {
+    pub started_at: Option<DateTime<Utc>>,
+    pub restart_count: u32,
+}
+
+impl ServiceStats {
+    // Отсутствующий или поврежденный файл - служба еще не запускалась
+    pub fn load() -> Self {
+        Self::load_from(Path::new(SERVICE_STATS_FILE))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Path::new(SERVICE_STATS_FILE))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Первый запуск перезапуском не считается
+    pub fn record_start(&mut self, now: DateTime<Utc>) {
+        if self.started_at.is_some() {
+            self.restart_count += 1;
+        }
+        self.started_at = Some(now);
+    }
+
+    pub fn uptime_seconds(&self, now: DateTime<Utc>) -> u64 {
+        self.started_at.map_or(0, |started_at| (now - started_at).num_seconds().max(0) as u64)
+    }
+}
+
+// Длительность вида "2 д 3 ч 15 мин"; меньше минуты - в секундах
+fn format_uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86_400, seconds % 86_400 / 3600, seconds % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{} с", seconds),
+        (0, 0, _) => format!("{} мин", minutes),
+        (0, _, _) => format!("{} ч {} мин", hours, minutes),
+        _ => format!("{} д {} ч {} мин", days, hours, minutes),
+    }
+}
+
+// Код ошибки Windows ERROR_SERVICE_DOES_NOT_EXIST
+const ERROR_SERVICE_DOES_NOT_EXIST: i32 = 1060;
+
+// Отсутствие службы выделяется в MonitorError::ServiceNotInstalled
+fn open_service(manager: &WinServiceManager, access: ServiceAccess) -> Result<Service> {
+    manager.open_service(SERVICE_NAME, access).map_err(|e| match &e {
+        windows_service::Error::Winapi(io) if io.raw_os_error() == Some(ERROR_SERVICE_DOES_NOT_EXIST) => {
+            MonitorError::ServiceNotInstalled { service: SERVICE_NAME.to_string() }.into()
+        }
+        _ => anyhow::Error::from(e),
+    })
+}
+
 impl WindowsServiceManager {
     pub fn install() -> Result<()> {
         let manager_access = ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE;
@@ -48,7 +125,7 @@ impl WindowsServiceManager {
         let service_manager = WinServiceManager::local_computer(None::<&str>, manager_access)?;
 
         let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
-        let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+        let service = open_service(&service_manager, service_access)?;
 
         // Останавливаем службу если запущена
         if let Ok(status) = service.query_status() {
@@ -69,7 +146,7 @@ impl WindowsServiceManager {
         let service_manager = WinServiceManager::local_computer(None::<&str>, manager_access)?;
 
         let service_access = ServiceAccess::START;
-        let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+        let service = open_service(&service_manager, service_access)?;
 
         service.start(&[] as &[OsString])?;
         println!("Служба '{}' запущена", SERVICE_NAME);
@@ -81,7 +158,7 @@ impl WindowsServiceManager {
         let service_manager = WinServiceManager::local_computer(None::<&str>, manager_access)?;
 
         let service_access = ServiceAccess::STOP;
-        let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+        let service = open_service(&service_manager, service_access)?;
 
         service.stop()?;
         println!("Служба '{}' остановлена", SERVICE_NAME);
@@ -93,7 +170,7 @@ impl WindowsServiceManager {
         let service_manager = WinServiceManager::local_computer(None::<&str>, manager_access)?;
 
         let service_access = ServiceAccess::QUERY_STATUS;
-        match service_manager.open_service(SERVICE_NAME, service_access) {
+        match open_service(&service_manager, service_access) {
             Ok(service) => {
                 let status = service.query_status()?;
                 println!("Служба: {}", SERVICE_NAME);
@@ -106,10 +183,14 @@ impl WindowsServiceManager {
                     println!("PID: не доступен");
                 }
                 println!("Тип: {:?}", status.service_type);
+                let stats = ServiceStats::load();
+                if status.current_state == ServiceState::Running {
+                    println!("Время работы: {}", format_uptime(stats.uptime_seconds(Utc::now())));
+                }
+                println!("Перезапусков: {}", stats.restart_count);
             }
-            Err(_) => {
-                println!("Служба '{}' не установлена", SERVICE_NAME);
-            }
+            Err(e) if e.downcast_ref::<MonitorError>().is_some() => println!("{}", e),
+            Err(e) => return Err(e),
         }
 
         Ok(())
@@ -122,4 +203,43 @@ impl WindowsServiceManager {
         println!("Служба '{}' перезапущена", SERVICE_NAME);
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn restart_count_accumulates_across_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data").join("service_stats.json");
+        for run in 0..4 {
+            let mut stats = ServiceStats::load_from(&path);
+            stats.record_start(Utc.with_ymd_and_hms(2024, 5, 1, 10, run, 0).unwrap());
+            stats.save_to(&path).unwrap();
+        }
+
+        let stats = ServiceStats::load_from(&path);
+        assert_eq!(stats.restart_count, 3);
+        assert_eq!(stats.started_at, Some(Utc.with_ymd_and_hms(2024, 5, 1, 10, 3, 0).unwrap()));
+    }
+
+    #[test]
+    fn corrupted_stats_file_starts_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("service_stats.json");
+        std::fs::write(&path, "{ не json").unwrap();
+        assert_eq!(ServiceStats::load_from(&path), ServiceStats::default());
+    }
+
+    #[test]
+    fn uptime_is_counted_from_last_start() {
+        let started = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let stats = ServiceStats { started_at: Some(started), restart_count: 0 };
+        assert_eq!(stats.uptime_seconds(started + chrono::TimeDelta::seconds(3725)), 3725);
+        assert_eq!(stats.uptime_seconds(started - chrono::TimeDelta::seconds(5)), 0);
+        assert_eq!(ServiceStats::default().uptime_seconds(started), 0);
+        assert_eq!(format_uptime(3725), "1 ч 2 мин");
+    }
 }
\ No newline at end of file
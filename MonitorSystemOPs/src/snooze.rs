@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+// Отложенные правила: имя правила и момент, когда откладывание истекает.
+// Хранятся только в памяти, поэтому после перезапуска службы не действуют
+pub type Snoozes = Arc<RwLock<HashMap<String, Instant>>>;
+
+// Дольше недели откладывать не дает, для этого есть правка порогов в конфигурации
+const MAX_SNOOZE_MINUTES: u64 = 7 * 24 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct SnoozeRequest {
+    pub rule_name: String,
+    pub minutes: u64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SnoozeStatus {
+    pub rule_name: String,
+    pub remaining_minutes: u64,
+}
+
+// Правило аномалии - начало ее сообщения, например "Высокая загрузка CPU"
+pub fn is_snoozed(snoozes: &HashMap<String, Instant>, message: &str, now: Instant) -> bool {
+    snoozes
+        .iter()
+        .any(|(rule_name, expires_at)| *expires_at > now && message.starts_with(rule_name.as_str()))
+}
+
+// Действующие откладывания по имени правила; остаток округляется вверх до минуты
+pub fn active_snoozes(snoozes: &HashMap<String, Instant>, now: Instant) -> Vec<SnoozeStatus> {
+    let mut active: Vec<SnoozeStatus> = snoozes
+        .iter()
+        .filter(|(_, expires_at)| **expires_at > now)
+        .map(|(rule_name, expires_at)| SnoozeStatus {
+            rule_name: rule_name.clone(),
+            remaining_minutes: (*expires_at - now).as_secs().div_ceil(60),
+        })
+        .collect();
+    active.sort_by(|a, b| a.rule_name.cmp(&b.rule_name));
+    active
+}
+
+// Сегмент пути с %XX-последовательностями; None, если после декодирования не UTF-8
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn error_reply(status: StatusCode, message: &str) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&serde_json::json!({ "error": message })), status).into_response()
+}
+
+async fn add_snooze(request: SnoozeRequest, snoozes: Snoozes) -> Result<warp::reply::Response, Rejection> {
+    let rule_name = request.rule_name.trim();
+    if rule_name.is_empty() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, "rule_name не может быть пустым"));
+    }
+    if request.minutes == 0 || request.minutes > MAX_SNOOZE_MINUTES {
+        return Ok(error_reply(
+            StatusCode::BAD_REQUEST,
+            &format!("minutes должно быть от 1 до {}", MAX_SNOOZE_MINUTES),
+        ));
+    }
+
+    let now = Instant::now();
+    let mut snoozes = snoozes.write().await;
+    snoozes.retain(|_, expires_at| *expires_at > now);
+    snoozes.insert(rule_name.to_string(), now + Duration::from_secs(request.minutes * 60));
+    let status = SnoozeStatus { rule_name: rule_name.to_string(), remaining_minutes: request.minutes };
+    Ok(warp::reply::with_status(warp::reply::json(&status), StatusCode::CREATED).into_response())
+}
+
+async fn list_snoozes(snoozes: Snoozes) -> Result<warp::reply::Json, Rejection> {
+    Ok(warp::reply::json(&active_snoozes(&*snoozes.read().await, Instant::now())))
+}
+
+async fn cancel_snooze(rule_name: String, snoozes: Snoozes) -> Result<warp::reply::Response, Rejection> {
+    let Some(rule_name) = percent_decode(&rule_name) else {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, "Некорректное имя правила"));
+    };
+    match snoozes.write().await.remove(&rule_name) {
+        Some(expires_at) if expires_at > Instant::now() => Ok(StatusCode::NO_CONTENT.into_response()),
+        _ => Ok(error_reply(StatusCode::NOT_FOUND, "Правило не отложено")),
+    }
+}
+
+// POST, GET /alerts/snooze и DELETE /alerts/snooze/<rule_name>
+pub fn snooze_routes(snoozes: Snoozes) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone {
+    let snoozes_filter = warp::any().map(move || snoozes.clone());
+
+    let add = warp::path!("alerts" / "snooze")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(4 * 1024))
+        .and(warp::body::json())
+        .and(snoozes_filter.clone())
+        .and_then(add_snooze);
+
+    let list = warp::path!("alerts" / "snooze")
+        .and(warp::get())
+        .and(snoozes_filter.clone())
+        .and_then(list_snoozes)
+        .map(Reply::into_response);
+
+    let cancel = warp::path!("alerts" / "snooze" / String)
+        .and(warp::delete())
+        .and(snoozes_filter)
+        .and_then(cancel_snooze);
+
+    add.or(list).unify().or(cancel).unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    async fn post(snoozes: &Snoozes, body: Value) -> (StatusCode, Value) {
+        let response = warp::test::request()
+            .method("POST")
+            .path("/alerts/snooze")
+            .json(&body)
+            .reply(&snooze_routes(snoozes.clone()))
+            .await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    async fn list(snoozes: &Snoozes) -> Value {
+        let response = warp::test::request().path("/alerts/snooze").reply(&snooze_routes(snoozes.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    async fn delete(snoozes: &Snoozes, path: &str) -> StatusCode {
+        warp::test::request()
+            .method("DELETE")
+            .path(path)
+            .reply(&snooze_routes(snoozes.clone()))
+            .await
+            .status()
+    }
+
+    #[tokio::test]
+    async fn snooze_is_added_listed_and_cancelled() {
+        let snoozes = Snoozes::default();
+        let (status, body) = post(&snoozes, json!({ "rule_name": " Высокая загрузка CPU ", "minutes": 30 })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, json!({ "rule_name": "Высокая загрузка CPU", "remaining_minutes": 30 }));
+        assert_eq!(list(&snoozes).await, json!([{ "rule_name": "Высокая загрузка CPU", "remaining_minutes": 30 }]));
+
+        let path = "/alerts/snooze/%D0%92%D1%8B%D1%81%D0%BE%D0%BA%D0%B0%D1%8F%20%D0%B7%D0%B0%D0%B3%D1%80%D1%83%D0%B7%D0%BA%D0%B0%20CPU";
+        assert_eq!(delete(&snoozes, path).await, StatusCode::NO_CONTENT);
+        assert_eq!(list(&snoozes).await, json!([]));
+        assert_eq!(delete(&snoozes, path).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn invalid_snooze_requests_are_rejected() {
+        let snoozes = Snoozes::default();
+        for body in [
+            json!({ "rule_name": "  ", "minutes": 30 }),
+            json!({ "rule_name": "Высокая загрузка CPU", "minutes": 0 }),
+            json!({ "rule_name": "Высокая загрузка CPU", "minutes": MAX_SNOOZE_MINUTES + 1 }),
+        ] {
+            let (status, response) = post(&snoozes, body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(response["error"].is_string());
+        }
+        assert!(snoozes.read().await.is_empty());
+        assert_eq!(delete(&snoozes, "/alerts/snooze/%FF").await, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn expired_snooze_does_not_match() {
+        let now = Instant::now();
+        let snoozes = HashMap::from([
+            ("Высокая загрузка CPU".to_string(), now + Duration::from_secs(90)),
+            ("Критическое заполнение".to_string(), now),
+        ]);
+        assert!(is_snoozed(&snoozes, "Высокая загрузка CPU: 95.0%", now));
+        assert!(!is_snoozed(&snoozes, "Высокая загрузка памяти: 95.0%", now));
+        assert!(!is_snoozed(&snoozes, "Критическое заполнение диска C:: 91.0%", now));
+        assert_eq!(
+            active_snoozes(&snoozes, now),
+            [SnoozeStatus { rule_name: "Высокая загрузка CPU".to_string(), remaining_minutes: 2 }]
+        );
+    }
+}
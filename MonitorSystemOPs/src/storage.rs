@@ -1,25 +1,106 @@
+use crate::aggregate::{aggregate, AggregatedHistory, AggregationMeta, AggregationWindow};
+use crate::monitor::error::MonitorError;
 use crate::monitor::SystemMetrics;
-use serde_json;
+use memmap2::Mmap;
 use std::fs::{self, File};
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 
-const DATA_FILE: &str = "data/metrics.json";
+const DATA_FILE: &str = "metrics.json";
+// Копия истории на время перезаписи в cleanup_old_records; остается на диске, только
+// если перезапись не завершилась
+const BACKUP_FILE: &str = "metrics.json.bak";
+// Новая версия истории, которая после записи переименовывается в DATA_FILE
+const TEMP_FILE: &str = "metrics.json.tmp";
 
-pub struct Storage;
+pub struct Storage {
+    data_file: PathBuf,
+    backup_file: PathBuf,
+    temp_file: PathBuf,
+    // Файлы от этого размера читаются через отображение в память, чтобы не держать
+    // в памяти еще и копию всего файла
+    mmap_threshold_bytes: u64,
+    // Последний результат aggregate; пересчитывается, только если изменились запрос или история
+    aggregation_cache: Mutex<Option<(AggregationKey, AggregatedHistory)>>,
+    backup_before_cleanup: bool,
+    // На чтение - пока файл истории открыт или отображен, на запись - пока он подменяется
+    file_lock: RwLock<()>,
+}
+
+// Размер и время изменения файла истории: любая запись или очистка меняет хотя бы одно
+// из них, поэтому кэш можно проверить, не читая сам файл
+#[derive(Debug, Clone, PartialEq)]
+struct AggregationKey {
+    window: AggregationWindow,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    file_len: u64,
+    modified: Option<SystemTime>,
+}
+
+// Строки NDJSON разбираются прямо из буфера; поврежденные записи пропускаются,
+// остальная история остается доступной
+fn parse_metrics(path: &Path, data: &[u8]) -> Vec<SystemMetrics> {
+    let mut metrics = Vec::new();
+    for (index, line) in data.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_slice::<SystemMetrics>(line) {
+            Ok(metric) => metrics.push(metric),
+            Err(e) => eprintln!("{}", MonitorError::StorageCorrupted {
+                path: path.into(),
+                line: index + 1,
+                reason: e.to_string(),
+            }),
+        }
+    }
+    metrics
+}
 
 impl Storage {
     pub fn new() -> Self {
+        Self::in_dir("data")
+    }
+
+    pub fn in_dir(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
         // Создаем директорию, если не существует
-        let _ = fs::create_dir_all("data");
-        Self
+        let _ = fs::create_dir_all(dir);
+        let storage = Self {
+            data_file: dir.join(DATA_FILE),
+            backup_file: dir.join(BACKUP_FILE),
+            temp_file: dir.join(TEMP_FILE),
+            mmap_threshold_bytes: 4 * 1024 * 1024,
+            aggregation_cache: Mutex::new(None),
+            backup_before_cleanup: true,
+            file_lock: RwLock::new(()),
+        };
+        storage.warn_about_backup();
+        storage
+    }
+
+    pub fn with_backup_before_cleanup(mut self, enabled: bool) -> Self {
+        self.backup_before_cleanup = enabled;
+        self
+    }
+
+    pub fn with_mmap_threshold_mb(mut self, mb: usize) -> Self {
+        self.mmap_threshold_bytes = mb as u64 * 1024 * 1024;
+        self
     }
 
     pub fn save_metrics(&self, metrics: &SystemMetrics) -> Result<()> {
+        let _guard = self.file_lock.read().unwrap_or_else(|e| e.into_inner());
         let file = File::options()
             .create(true)
             .append(true)
-            .open(DATA_FILE)?;
+            .open(&self.data_file)?;
 
         let mut writer = BufWriter::new(file);
         serde_json::to_writer(&mut writer, metrics)?;
@@ -28,35 +109,274 @@ impl Storage {
     }
 
     pub fn load_metrics(&self) -> Result<Vec<SystemMetrics>> {
-        if !std::path::Path::new(DATA_FILE).exists() {
+        let _guard = self.file_lock.read().unwrap_or_else(|e| e.into_inner());
+        self.read_metrics()
+    }
+
+    // Вызывающий держит file_lock
+    fn read_metrics(&self) -> Result<Vec<SystemMetrics>> {
+        if !self.data_file.exists() {
             return Ok(Vec::new());
         }
 
-        let content = fs::read_to_string(DATA_FILE)?;
-        let mut metrics = Vec::new();
-        for line in content.lines() {
-            if line.is_empty() {
-                continue;
-            }
-            match serde_json::from_str::<SystemMetrics>(line) {
-                Ok(metric) => metrics.push(metric),
-                Err(e) => eprintln!("Ошибка парсинга метрики: {}", e),
-            }
+        let file = File::open(&self.data_file)?;
+        if file.metadata()?.len() < self.mmap_threshold_bytes {
+            return Ok(parse_metrics(&self.data_file, &fs::read(&self.data_file)?));
         }
+
+        // SAFETY: файл истории никогда не усекается и не перезаписывается на месте:
+        // save_metrics только дописывает в конец (дописанное после отображения в mmap не
+        // попадает), а cleanup_old_records и recover_from_backup подменяют файл целиком
+        // переименованием. В этом процессе подмена ждет file_lock, который держится до конца
+        // разбора; в Windows переименование поверх файла, отображенного другим процессом,
+        // завершается ошибкой, в Unix отображение продолжает ссылаться на прежний файл
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(parse_metrics(&self.data_file, &mmap))
+    }
+
+    // Записи с отметкой времени в диапазоне [from, to]; None - граница не задана
+    pub fn load_metrics_range(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<SystemMetrics>> {
+        let mut metrics = self.load_metrics()?;
+        metrics.retain(|m| {
+            from.is_none_or(|from| m.timestamp >= from) && to.is_none_or(|to| m.timestamp <= to)
+        });
         Ok(metrics)
     }
 
+    // Часовые или суточные сводки записей из диапазона [from, to]
+    pub fn aggregate(
+        &self,
+        window: AggregationWindow,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<AggregatedHistory> {
+        let metadata = fs::metadata(&self.data_file).ok();
+        let key = AggregationKey {
+            window,
+            from,
+            to,
+            file_len: metadata.as_ref().map_or(0, |m| m.len()),
+            modified: metadata.and_then(|m| m.modified().ok()),
+        };
+        let mut cache = self.aggregation_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached_key, history)) = &*cache {
+            if *cached_key == key {
+                return Ok(history.clone());
+            }
+        }
+
+        let metrics = self.load_metrics_range(from, to)?;
+        let data = aggregate(&metrics, window);
+        let history = AggregatedHistory {
+            meta: AggregationMeta { total_records: metrics.len(), periods: data.len() },
+            data,
+        };
+        *cache = Some((key, history.clone()));
+        Ok(history)
+    }
+
+    // Оставшиеся записи пишутся во временный файл, который затем заменяет историю:
+    // файл, который может быть отображен в память, не усекается на месте
     pub fn cleanup_old_records(&self, max_records: usize) -> Result<()> {
-        let mut metrics = self.load_metrics()?;
+        let _guard = self.file_lock.write().unwrap_or_else(|e| e.into_inner());
+        let mut metrics = self.read_metrics()?;
         if metrics.len() > max_records {
             metrics.drain(0..metrics.len() - max_records);
-            let file = File::create(DATA_FILE)?;
+            if self.backup_before_cleanup {
+                fs::copy(&self.data_file, &self.backup_file)?;
+            }
+            let file = File::create(&self.temp_file)?;
             let mut writer = BufWriter::new(file);
             for metric in metrics {
                 serde_json::to_writer(&mut writer, &metric)?;
                 writeln!(writer)?;
             }
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&self.temp_file, &self.data_file)?;
+            if self.backup_before_cleanup {
+                fs::remove_file(&self.backup_file)?;
+            }
         }
         Ok(())
     }
+
+    // Заменяет файл истории резервной копией; возвращает число восстановленных записей
+    pub fn recover_from_backup(&self) -> Result<usize> {
+        let _guard = self.file_lock.write().unwrap_or_else(|e| e.into_inner());
+        if !self.backup_file.exists() {
+            return Err(MonitorError::StorageNotFound { path: self.backup_file.clone() }.into());
+        }
+        let recovered = parse_metrics(&self.backup_file, &fs::read(&self.backup_file)?).len();
+        fs::rename(&self.backup_file, &self.data_file)?;
+        *self.aggregation_cache.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        Ok(recovered)
+    }
+
+    // Оставшаяся резервная копия означает, что очистка истории прервалась; если копия
+    // больше основного файла, часть записей, скорее всего, потеряна при перезаписи
+    fn warn_about_backup(&self) {
+        let (Ok(data), Ok(backup)) = (fs::metadata(&self.data_file), fs::metadata(&self.backup_file)) else {
+            return;
+        };
+        if backup.len() > data.len() {
+            eprintln!(
+                "⚠️ Найдена резервная копия {} ({} байт) больше файла истории {} ({} байт): очистка истории \
+                 прервалась. Проверьте файлы и при необходимости выполните recover-storage",
+                self.backup_file.display(),
+                backup.len(),
+                self.data_file.display(),
+                data.len()
+            );
+        } else {
+            eprintln!(
+                "⚠️ Найдена резервная копия {} после прерванной очистки истории. Проверьте файлы и удалите \
+                 копию или восстановите ее командой recover-storage",
+                self.backup_file.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use std::time::Instant;
+
+    fn history(count: usize) -> Vec<SystemMetrics> {
+        let start = DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        (0..count)
+            .map(|i| SystemMetrics::sample(start + TimeDelta::minutes(i as i64), (i % 100) as f32))
+            .collect()
+    }
+
+    fn write_history(storage: &Storage, metrics: &[SystemMetrics]) {
+        let mut writer = BufWriter::new(File::create(&storage.data_file).unwrap());
+        for metric in metrics {
+            serde_json::to_writer(&mut writer, metric).unwrap();
+            writeln!(writer).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    fn load_with_threshold(storage: &mut Storage, threshold: u64) -> String {
+        storage.mmap_threshold_bytes = threshold;
+        serde_json::to_string(&storage.load_metrics().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn mmap_and_read_paths_return_identical_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::in_dir(dir.path());
+        write_history(&storage, &history(200));
+        fs::OpenOptions::new().append(true).open(&storage.data_file).unwrap().write_all(b"{broken\n").unwrap();
+
+        let read = load_with_threshold(&mut storage, u64::MAX);
+        let mapped = load_with_threshold(&mut storage, 0);
+        assert_eq!(read, mapped);
+        assert_eq!(storage.load_metrics().unwrap().len(), 200);
+    }
+
+    #[test]
+    fn missing_history_loads_empty_on_both_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::in_dir(dir.path());
+        assert_eq!(load_with_threshold(&mut storage, u64::MAX), "[]");
+        assert_eq!(load_with_threshold(&mut storage, 0), "[]");
+    }
+
+    // Очистка подменяет файл, а не усекает его: уже отображенные данные остаются целыми
+    #[cfg(unix)]
+    #[test]
+    fn cleanup_keeps_mapped_history_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        write_history(&storage, &history(50));
+        let file = File::open(&storage.data_file).unwrap();
+        // SAFETY: файл меняется только через Storage, который не усекает его на месте
+        let mapped = unsafe { Mmap::map(&file).unwrap() };
+
+        storage.cleanup_old_records(10).unwrap();
+
+        assert_eq!(parse_metrics(&storage.data_file, &mapped).len(), 50);
+        assert_eq!(storage.load_metrics().unwrap().len(), 10);
+        assert!(!storage.temp_file.exists());
+    }
+
+    #[test]
+    fn aggregation_cache_is_checked_before_reading_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        write_history(&storage, &history(120));
+        let first = storage.aggregate(AggregationWindow::Hourly, None, None).unwrap();
+        assert_eq!(first.meta.total_records, 120);
+        assert_eq!(first.meta.periods, 2);
+
+        // Тот же размер и время изменения: файл не читается, иначе записи не разобрались бы
+        let modified = fs::metadata(&storage.data_file).unwrap().modified().unwrap();
+        let len = fs::metadata(&storage.data_file).unwrap().len() as usize;
+        fs::write(&storage.data_file, vec![b'x'; len]).unwrap();
+        File::options().write(true).open(&storage.data_file).unwrap().set_modified(modified).unwrap();
+        assert_eq!(storage.aggregate(AggregationWindow::Hourly, None, None).unwrap(), first);
+
+        write_history(&storage, &history(30));
+        let changed = storage.aggregate(AggregationWindow::Hourly, None, None).unwrap();
+        assert_eq!(changed.meta.total_records, 30);
+    }
+
+    #[test]
+    fn recovery_replaces_history_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        // Очистка прервалась после копирования: копия полная, основной файл уже урезан
+        write_history(&storage, &history(5));
+        fs::rename(&storage.data_file, &storage.backup_file).unwrap();
+        write_history(&storage, &history(2));
+
+        assert_eq!(storage.recover_from_backup().unwrap(), 5);
+        assert_eq!(storage.load_metrics().unwrap().len(), 5);
+        assert!(!storage.backup_file.exists());
+    }
+
+    #[test]
+    fn recovery_without_backup_is_storage_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        let error = storage.recover_from_backup().unwrap_err();
+        assert!(matches!(error.downcast_ref::<MonitorError>(), Some(MonitorError::StorageNotFound { .. })));
+    }
+
+    #[test]
+    fn cleanup_leaves_no_backup_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path());
+        write_history(&storage, &history(5));
+        storage.cleanup_old_records(3).unwrap();
+        assert_eq!(storage.load_metrics().unwrap().len(), 3);
+        assert!(!storage.backup_file.exists());
+    }
+
+    // cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_mmap_vs_read_on_50_mb_history() {
+        let sample_len = serde_json::to_vec(&history(1)[0]).unwrap().len() + 1;
+        let count = 50 * 1024 * 1024 / sample_len;
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = Storage::in_dir(dir.path());
+        write_history(&storage, &history(count));
+        println!("{} записей, {} байт", count, fs::metadata(&storage.data_file).unwrap().len());
+
+        for (label, threshold) in [("чтение", u64::MAX), ("mmap", 0)] {
+            storage.mmap_threshold_bytes = threshold;
+            let started = Instant::now();
+            let loaded = storage.load_metrics().unwrap();
+            println!("{}: {:?}", label, started.elapsed());
+            assert_eq!(loaded.len(), count);
+        }
+    }
 }
\ No newline at end of file
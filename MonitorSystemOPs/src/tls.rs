@@ -0,0 +1,98 @@
+use crate::config::TlsConfig;
+use anyhow::Result;
+
+// Сертификат и ключ в PEM; файлы проверяются заранее, чтобы warp не упал при запуске
+#[cfg(feature = "tls")]
+pub fn load_identity(tls: &TlsConfig) -> Result<(Vec<u8>, Vec<u8>)> {
+    use anyhow::{anyhow, Context};
+
+    let cert = std::fs::read(&tls.cert_path)
+        .with_context(|| format!("Не удалось прочитать сертификат {}", tls.cert_path))?;
+    let key = std::fs::read(&tls.key_path)
+        .with_context(|| format!("Не удалось прочитать ключ {}", tls.key_path))?;
+
+    let certs = rustls_pemfile::certs(&mut cert.as_slice()).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(anyhow!("В {} нет сертификатов PEM", tls.cert_path));
+    }
+    if rustls_pemfile::private_key(&mut key.as_slice())?.is_none() {
+        return Err(anyhow!("В {} нет закрытого ключа PEM", tls.key_path));
+    }
+
+    Ok((cert, key))
+}
+
+// Самоподписанный сертификат для localhost и 127.0.0.1
+#[cfg(feature = "tls")]
+pub fn generate_self_signed(tls: &TlsConfig) -> Result<()> {
+    let names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    let rcgen::CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(names)?;
+
+    for path in [&tls.cert_path, &tls.key_path] {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+    }
+    std::fs::write(&tls.cert_path, cert.pem())?;
+    std::fs::write(&tls.key_path, key_pair.serialize_pem())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "tls"))]
+pub fn generate_self_signed(_tls: &TlsConfig) -> Result<()> {
+    Err(anyhow::anyhow!("Программа собрана без поддержки TLS (cargo build --features tls)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tls_config(dir: &std::path::Path) -> TlsConfig {
+        TlsConfig {
+            enabled: true,
+            cert_path: dir.join("tls/cert.pem").display().to_string(),
+            key_path: dir.join("tls/key.pem").display().to_string(),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn serves_https_trusted_through_custom_ca() {
+        use warp::Filter;
+
+        let dir = tempfile::tempdir().unwrap();
+        let tls = tls_config(dir.path());
+        generate_self_signed(&tls).unwrap();
+        let (cert, key) = load_identity(&tls).unwrap();
+
+        let routes = warp::path("metrics").map(|| "ok");
+        let (addr, server) = warp::serve(routes).tls().cert(&cert).key(&key).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(&cert).unwrap())
+            .build()
+            .unwrap();
+        let response = client.get(format!("https://localhost:{}/metrics", addr.port())).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn missing_key_is_reported_before_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let tls = tls_config(dir.path());
+        generate_self_signed(&tls).unwrap();
+        std::fs::write(&tls.key_path, "").unwrap();
+        let error = load_identity(&tls).unwrap_err().to_string();
+        assert!(error.contains("нет закрытого ключа"), "{}", error);
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[test]
+    fn certificate_generation_requires_tls_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(generate_self_signed(&tls_config(dir.path())).is_err());
+    }
+}
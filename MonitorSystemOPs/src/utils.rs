@@ -0,0 +1,45 @@
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+// Размер с приставкой по основанию 1024 и тремя значащими цифрами: "999 B",
+// "1.00 KB", "512 KB", "3.14 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[0]),
+        _ if value < 10.0 => format!("{:.2} {}", value, UNITS[unit]),
+        _ if value < 100.0 => format!("{:.1} {}", value, UNITS[unit]),
+        _ => format!("{:.0} {}", value, UNITS[unit]),
+    }
+}
+
+pub fn format_throughput(bps: u64) -> String {
+    format!("{}/s", format_bytes(bps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throughput_by_magnitude() {
+        assert_eq!(format_throughput(0), "0 B/s");
+        assert_eq!(format_throughput(1023), "1023 B/s");
+        assert_eq!(format_throughput(1024), "1.00 KB/s");
+        assert_eq!(format_throughput(512 * 1024), "512 KB/s");
+        assert_eq!(format_throughput(1024 * 1024), "1.00 MB/s");
+        assert_eq!(format_throughput(3 * 1024 * 1024 + 147 * 1024), "3.14 MB/s");
+        assert_eq!(format_throughput(3 << 29), "1.50 GB/s");
+    }
+
+    #[test]
+    fn bytes_keep_three_significant_digits() {
+        assert_eq!(format_bytes(50 * 1024 + 512), "50.5 KB");
+        assert_eq!(format_bytes(5 << 40), "5.00 TB");
+        assert_eq!(format_bytes(2048 << 40), "2048 TB");
+    }
+}
@@ -2,234 +2,170 @@ use eframe::egui::{
     self, menu, Color32, Context, FontId,
     Key, Modifiers, RichText, ViewportCommand
 };
-use std::path::{Path, PathBuf};
+use eframe::egui::text::{CCursor, CCursorRange};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-#[derive(Clone)]
-struct Document {
-    title: String,
-    content: String,
-    path: Option<PathBuf>,
-    modified: bool,
-    undo_stack: Vec<String>,
-    redo_stack: Vec<String>,
-    last_content: String, // Перемещаем last_content в Document
-}
-
-impl Document {
-    fn new(title: &str) -> Self {
-        let content = String::new();
-        Self {
-            title: title.to_string(),
-            content: content.clone(),
-            path: None,
-            modified: false,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            last_content: content,
-        }
-    }
-
-    fn load(path: &Path) -> Result<Self, std::io::Error> {
-        let content = std::fs::read_to_string(path)?;
-        let title = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Безымянный")
-            .to_string();
-
-        Ok(Self {
-            title,
-            content: content.clone(),
-            path: Some(path.to_path_buf()),
-            modified: false,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            last_content: content,
-        })
-    }
-
-    fn save(&mut self, path: &Path) -> Result<(), std::io::Error> {
-        std::fs::write(path, &self.content)?;
-        self.path = Some(path.to_path_buf());
-        self.modified = false;
-        self.title = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Безымянный")
-            .to_string();
-        Ok(())
-    }
-
-    fn save_as(&mut self, path: &Path) -> Result<(), std::io::Error> {
-        self.save(path)
-    }
+use crate::autocomplete::AutocompleteEngine;
+use crate::clipboard::{self, ClipboardProvider, SystemClipboard, CLIPBOARD_HISTORY_CAPACITY};
+use crate::diff::{self, DiffRow, LineDiff};
+use crate::document::{format_duration, is_binary, line_window, scroll_fraction, visible_line_count, CaseMode, BINARY_SNIFF_LEN, WRAP_DELIMITERS, Document, DocumentStats, IndentMode, LineEnding};
+use crate::macros::{self, MacroAction};
+use crate::markdown::{self, Block, OutlineEntry};
+use crate::minimap::{self, SyncScroll};
+use crate::print;
+use crate::recovery;
+use crate::search::{self, SearchOptions, FIND_HISTORY_CAPACITY};
+use crate::search_panel::{PanelAction, SearchResultsPanel};
+use crate::session::{Session, SessionDocument};
+use crate::settings::{default_shortcuts, filter_templates, format_column_list, parse_column_list, AppSettings, CustomTheme, DocumentTemplate, FontFamily, KeyCombo, ShortcutAction, Theme, WindowGeometry, CUSTOM_FONT_FAMILY};
+use crate::tail::{TailUpdate, TailWatcher};
 
-    fn title(&self) -> &str {
-        &self.title
-    }
+// Два документа, сравниваемые построчно вместо обычного редактора
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CompareMode {
+    left: usize,
+    right: usize,
+}
 
-    fn path(&self) -> Option<&Path> {
-        self.path.as_deref()
-    }
+// Как часто несохраненные документы копируются во временную папку
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(10);
+const WINDOW_GEOMETRY_DEBOUNCE: Duration = Duration::from_millis(500);
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+const AUTO_SAVE_TOAST_DURATION: Duration = Duration::from_secs(3);
 
-    fn is_modified(&self) -> bool {
-        self.modified
-    }
+// Какие панели окна показываются в текущем кадре
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PanelVisibility {
+    menu_bar: bool,
+    toolbar: bool,
+    tabs: bool,
+    status_bar: bool,
+}
 
-    fn set_modified(&mut self, modified: bool) {
-        self.modified = modified;
-    }
+// Выбор шаблона для нового документа: фильтр по расширению и выбранный шаблон
+struct TemplateChooser {
+    extension: Option<String>,
+    selected: Option<usize>,
+}
 
-    fn save_state_before_change(&mut self) {
-        self.undo_stack.push(self.content.clone());
-        if self.undo_stack.len() > 50 {
-            self.undo_stack.remove(0);
-        }
-        self.redo_stack.clear();
-    }
+pub struct TextEditorApp {
+    documents: Vec<Document>,
+    active_document: usize,
+    settings: AppSettings,
+    // Настройки читаются с диска в фоне; визуальные параметры применяются в начале кадра
+    settings_loader: Option<JoinHandle<AppSettings>>,
+    settings_dirty: bool,
 
-    fn undo(&mut self) -> bool {
-        if let Some(previous_state) = self.undo_stack.pop() {
-            self.redo_stack.push(self.content.clone());
-            self.content = previous_state;
-            self.modified = true;
-            self.last_content = self.content.clone();
-            true
-        } else {
-            false
-        }
-    }
+    show_settings: bool,
+    show_stats: bool,
+    show_checkpoints: bool,
+    checkpoint_label: String,
+    show_bookmarks: bool,
+    show_clipboard_history: bool,
+    // Панель изменений с последнего сохранения и ее кэш: документ, версия, признак изменений
+    show_saved_diff: bool,
+    saved_diff_cache: Option<(usize, u64, bool, Vec<LineDiff>)>,
+    bookmark_label: String,
+    show_find_replace: bool,
+    error_message: Option<String>,
+    last_save_time: Instant,
+    // Аварийное восстановление: поколение последней записанной копии каждого документа
+    // и найденные при запуске копии, о которых еще не спросили пользователя
+    last_recovery_write: Instant,
+    // Папка файлов восстановления, обычно %TEMP%
+    recovery_dir: PathBuf,
+    recovery_generations: HashMap<usize, u64>,
+    pending_recovery: Vec<(usize, PathBuf)>,
+    // Файл, похожий на двоичный, который ждет подтверждения открытия
+    pending_binary_open: Option<PathBuf>,
+    // Всплывающее сообщение, время его появления и сколько его показывать
+    toast: Option<(String, Instant, Duration)>,
 
-    fn redo(&mut self) -> bool {
-        if let Some(next_state) = self.redo_stack.pop() {
-            self.undo_stack.push(self.content.clone());
-            self.content = next_state;
-            self.modified = true;
-            self.last_content = self.content.clone();
-            true
-        } else {
-            false
-        }
-    }
+    // Автодополнение по словарю документа
+    autocomplete: AutocompleteEngine,
+    autocomplete_document: Option<usize>,
+    suggestions: Vec<String>,
+    selected_suggestion: usize,
 
-    fn calculate_stats(&self) -> DocumentStats {
-        let characters = self.content.chars().count();
-        let characters_no_spaces = self.content.chars().filter(|c| !c.is_whitespace()).count();
-        let words = self.content.split_whitespace().count();
-        let lines = self.content.lines().count();
-        let paragraphs = self.content.split("\n\n").count();
+    clipboard: Box<dyn ClipboardProvider>,
+    // Скопированные и вырезанные фрагменты, последний первым
+    clipboard_history: VecDeque<String>,
+    // Запросы диалога "Найти и заменить"; хранятся в настройках
+    find_history: VecDeque<String>,
 
-        let pages = (words as f32 / 500.0).ceil() as usize;
+    // Предпросмотр Markdown: включен вручную для не-.md файлов и кэш разобранных блоков
+    preview_forced: bool,
+    preview_cache: Option<(usize, u64, Vec<Block>)>,
+    // Заголовки активного документа для панели структуры, по номеру версии документа
+    outline_cache: Option<(usize, u64, Vec<OutlineEntry>)>,
 
-        DocumentStats {
-            pages,
-            words,
-            characters,
-            characters_no_spaces,
-            lines,
-            paragraphs,
-        }
-    }
+    // Документ, открытый в правой панели разделенного вида
+    split_view: Option<usize>,
 
-    fn cursor_line(&self) -> usize {
-        self.content[..].matches('\n').count() + 1
-    }
+    // Сравнение документов и кэш строк сравнения по поколениям обоих документов
+    compare_mode: Option<CompareMode>,
+    compare_cache: Option<(CompareMode, u64, u64, Vec<DiffRow>)>,
 
-    fn cursor_column(&self) -> usize {
-        self.content.len().saturating_sub(
-            self.content.rfind('\n').map(|pos| pos + 1).unwrap_or(0)
-        )
-    }
+    // Режим без отвлечений: только текст на весь экран, меню появляется у верхнего края
+    distraction_free: bool,
+    menu_strip_rect: Option<egui::Rect>,
 
-    fn update_last_content(&mut self) {
-        // Сохраняем предыдущее состояние в стек отмены, если содержимое изменилось
-        if self.content != self.last_content {
-            if !self.undo_stack.last().map_or(false, |last| last == &self.last_content) {
-                self.undo_stack.push(self.last_content.clone());
-                if self.undo_stack.len() > 50 {
-                    self.undo_stack.remove(0);
-                }
-                self.redo_stack.clear();
-            }
-            self.last_content = self.content.clone();
-            self.modified = true;
-        }
-    }
-}
+    // Темы из папки themes и редактируемая новая тема
+    custom_themes: Vec<CustomTheme>,
+    theme_editor: Option<CustomTheme>,
 
-struct DocumentStats {
-    pages: usize,
-    words: usize,
-    characters: usize,
-    characters_no_spaces: usize,
-    lines: usize,
-    paragraphs: usize,
-}
+    // Доля прокрутки основного редактора, по которой прокручивается миникарта
+    minimap_scroll: SyncScroll,
 
-#[derive(Clone, Copy, PartialEq, Debug)]
-enum Theme {
-    Light,
-    Dark,
-}
+    // Первая строка фрагмента, показанного в редакторе большого документа
+    viewport_line_offset: usize,
+    split_viewport_line_offset: usize,
 
-impl Theme {
-    fn all() -> [Theme; 2] {
-        [Theme::Light, Theme::Dark]
-    }
+    // Шаблоны документов из папки templates
+    templates: Vec<DocumentTemplate>,
+    template_chooser: Option<TemplateChooser>,
+    show_template_manager: bool,
+    // Редактируемый шаблон и имя, под которым он был сохранен раньше
+    template_editor: Option<(Option<String>, DocumentTemplate)>,
 
-    fn egui_visuals(&self) -> egui::Visuals {
-        match self {
-            Theme::Light => egui::Visuals::light(),
-            Theme::Dark => egui::Visuals::dark(),
-        }
-    }
-}
+    // Строка поиска в прошлом кадре, для поиска по мере ввода
+    last_find_text: String,
 
-#[derive(Clone)]
-struct AppSettings {
-    theme: Theme,
-    font_size: f32,
-    auto_save_enabled: bool,
-    auto_save_interval: Duration,
-}
+    // Последние размер и положение окна и время их изменения; в настройки
+    // записываются, когда окно не меняется WINDOW_GEOMETRY_DEBOUNCE
+    observed_window_geometry: Option<(WindowGeometry, Instant)>,
 
-impl Default for AppSettings {
-    fn default() -> Self {
-        Self {
-            theme: Theme::Light,
-            font_size: 16.0,
-            auto_save_enabled: true,
-            auto_save_interval: Duration::from_secs(30),
-        }
-    }
-}
+    // Путь к шрифту, установленному в egui через set_fonts
+    installed_font: Option<String>,
 
-impl AppSettings {
-    fn load() -> Self {
-        Self::default()
-    }
+    // Действие, для которого в настройках ожидается новое сочетание клавиш
+    capturing_shortcut: Option<ShortcutAction>,
+    // Редактируемый в настройках список линеек; заполняется при открытии окна
+    column_rulers_input: Option<String>,
 
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        Ok(())
-    }
-}
+    // Поиск по всем открытым документам
+    show_global_search: bool,
+    global_search_query: String,
+    global_search_options: SearchOptions,
+    global_search_error: Option<String>,
+    search_results: Option<SearchResultsPanel>,
+    // Строка, к которой нужно перейти в активном документе в начале следующего кадра
+    jump_to_line: Option<usize>,
 
-pub struct TextEditorApp {
-    documents: Vec<Document>,
-    active_document: usize,
-    settings: AppSettings,
+    // Фоновые наблюдатели за файлами документов в режиме просмотра хвоста
+    tail_watchers: Vec<TailWatcher>,
 
-    show_settings: bool,
-    show_stats: bool,
-    show_find_replace: bool,
-    error_message: Option<String>,
-    last_save_time: Instant,
+    // Запись и воспроизведение макросов
+    macro_recording: bool,
+    recorded_macro: Vec<MacroAction>,
+    macro_repeat_count: usize,
 
-    find_text: String,
-    replace_text: String,
-    match_case: bool,
-    whole_word: bool,
+    // Окно, запущенное с --open из другого окна: сеанс и файлы восстановления
+    // принадлежат первому окну, поэтому здесь не читаются и не записываются
+    secondary_window: bool,
 }
 
 impl Default for TextEditorApp {
@@ -238,225 +174,1787 @@ impl Default for TextEditorApp {
             documents: Vec::new(),
             active_document: 0,
             settings: AppSettings::default(),
+            settings_loader: None,
+            settings_dirty: false,
             show_settings: false,
             show_stats: false,
+            show_checkpoints: false,
+            checkpoint_label: String::new(),
+            show_bookmarks: false,
+            show_clipboard_history: false,
+            show_saved_diff: false,
+            saved_diff_cache: None,
+            bookmark_label: String::new(),
             show_find_replace: false,
             error_message: None,
             last_save_time: Instant::now(),
-            find_text: String::new(),
-            replace_text: String::new(),
-            match_case: false,
-            whole_word: false,
+            last_recovery_write: Instant::now(),
+            recovery_dir: std::env::temp_dir(),
+            recovery_generations: HashMap::new(),
+            pending_recovery: Vec::new(),
+            pending_binary_open: None,
+            toast: None,
+            autocomplete: AutocompleteEngine::new(),
+            autocomplete_document: None,
+            suggestions: Vec::new(),
+            selected_suggestion: 0,
+            clipboard: Box::new(SystemClipboard::default()),
+            clipboard_history: VecDeque::with_capacity(CLIPBOARD_HISTORY_CAPACITY),
+            find_history: VecDeque::with_capacity(FIND_HISTORY_CAPACITY),
+            preview_forced: false,
+            preview_cache: None,
+            outline_cache: None,
+            split_view: None,
+            compare_mode: None,
+            compare_cache: None,
+            distraction_free: false,
+            menu_strip_rect: None,
+            custom_themes: Vec::new(),
+            theme_editor: None,
+            minimap_scroll: SyncScroll::default(),
+            viewport_line_offset: 0,
+            split_viewport_line_offset: 0,
+            templates: Vec::new(),
+            template_chooser: None,
+            show_template_manager: false,
+            template_editor: None,
+            last_find_text: String::new(),
+            observed_window_geometry: None,
+            installed_font: None,
+            capturing_shortcut: None,
+            column_rulers_input: None,
+            show_global_search: false,
+            global_search_query: String::new(),
+            global_search_options: SearchOptions::default(),
+            global_search_error: None,
+            search_results: None,
+            jump_to_line: None,
+            tail_watchers: Vec::new(),
+            macro_recording: false,
+            recorded_macro: Vec::new(),
+            macro_repeat_count: 1,
+            secondary_window: false,
         }
     }
 }
 
 impl TextEditorApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut app = Self::default();
-        app.settings = AppSettings::load();
-        app.apply_settings(&cc.egui_ctx);
+    pub fn new(_cc: &eframe::CreationContext<'_>, open: Option<PathBuf>) -> Self {
+        let mut app = Self {
+            settings_loader: Some(std::thread::spawn(|| AppSettings::load().unwrap_or_default())),
+            custom_themes: CustomTheme::load_all(),
+            templates: DocumentTemplate::load_all(),
+            secondary_window: open.is_some(),
+            ..Self::default()
+        };
 
-        app.documents.push(Document::new("Безымянный 1"));
+        match open {
+            Some(path) => app.open_path(&path),
+            None => {
+                app.pending_recovery = recovery::find_all(&app.recovery_dir);
+                app.restore_session();
+            }
+        }
+        if app.documents.is_empty() {
+            app.documents.push(Document::new("Безымянный 1"));
+        }
 
         app
     }
 
-    fn apply_settings(&self, ctx: &Context) {
-        ctx.set_visuals(self.settings.theme.egui_visuals());
-    }
-
-    fn ensure_active_document(&mut self) {
-        if self.documents.is_empty() {
-            self.documents.push(Document::new("Безымянный 1"));
+    // Открывает документы прошлого сеанса; исчезнувшие с диска файлы пропускаются
+    fn restore_session(&mut self) {
+        let Ok(session) = Session::load() else {
+            return;
+        };
+        for entry in session.documents {
+            if let Ok(mut doc) = Document::load(&entry.path) {
+                doc.set_pinned(entry.pinned);
+                doc.set_bookmarks(entry.bookmarks);
+                self.documents.push(doc);
+            }
         }
-        if self.active_document >= self.documents.len() {
-            self.active_document = self.documents.len().saturating_sub(1);
+        self.active_document = session.active_document.min(self.documents.len().saturating_sub(1));
+        for text in session.clipboard_history.iter().rev() {
+            clipboard::push_history(&mut self.clipboard_history, text);
         }
     }
 
-    fn current_document_mut(&mut self) -> &mut Document {
-        &mut self.documents[self.active_document]
+    fn save_session(&self) {
+        let documents = self
+            .documents
+            .iter()
+            .filter_map(|doc| {
+                doc.path().map(|path| SessionDocument {
+                    path: path.to_path_buf(),
+                    pinned: doc.is_pinned(),
+                    bookmarks: doc.bookmarks().to_vec(),
+                })
+            })
+            .collect();
+        let session = Session {
+            documents,
+            active_document: self.active_document,
+            clipboard_history: self.clipboard_history.iter().cloned().collect(),
+        };
+        let _ = session.save();
     }
 
-    fn current_document(&self) -> &Document {
-        &self.documents[self.active_document]
-    }
+    fn apply_settings(&mut self, ctx: &Context) {
+        ctx.set_visuals(self.settings.theme.egui_visuals());
 
-    fn new_document(&mut self) {
-        let count = self.documents.len() + 1;
-        self.documents.push(Document::new(&format!("Безымянный {}", count)));
-        self.active_document = self.documents.len() - 1;
-    }
+        // Набор шрифтов перестраивается только при смене файла шрифта
+        let custom_font = match &self.settings.font_family {
+            FontFamily::Custom(path) => Some(path.clone()),
+            _ => None,
+        };
+        if custom_font == self.installed_font {
+            return;
+        }
 
-    fn open_document(&mut self) {
-        if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Текстовые файлы", &["txt", "md", "rs", "json", "xml", "html", "css"])
-            .add_filter("Все файлы", &["*"])
-            .pick_file()
-        {
-            match Document::load(&path) {
-                Ok(doc) => {
-                    self.documents.push(doc);
-                    self.active_document = self.documents.len() - 1;
+        let mut fonts = egui::FontDefinitions::default();
+        if let Some(path) = &custom_font {
+            match std::fs::read(path) {
+                Ok(data) => {
+                    fonts.font_data.insert(CUSTOM_FONT_FAMILY.to_owned(), egui::FontData::from_owned(data));
+                    // Символы, которых нет в файле, берутся из встроенного моноширинного шрифта
+                    let mut family = vec![CUSTOM_FONT_FAMILY.to_owned()];
+                    family.extend(fonts.families[&egui::FontFamily::Monospace].iter().cloned());
+                    fonts.families.insert(FontFamily::Custom(path.clone()).egui_family(), family);
                 }
                 Err(e) => {
-                    self.error_message = Some(format!("Не удалось открыть файл: {}", e));
+                    self.error_message = Some(format!("Не удалось загрузить шрифт {}: {}", path, e));
+                    self.settings.font_family = FontFamily::Monospace;
+                    if self.installed_font.is_none() {
+                        return;
+                    }
                 }
             }
         }
+        ctx.set_fonts(fonts);
+        self.installed_font = match self.settings.font_family {
+            FontFamily::Custom(_) => custom_font,
+            _ => None,
+        };
     }
 
-    fn save_document(&mut self) {
-        let path = {
-            let doc = self.current_document();
-            doc.path().map(|p| p.to_path_buf())
+    // Первый кадр рисуется без ожидания диска, а со второго кадра чтение дожидается,
+    // чтобы окно не работало дольше одного кадра с настройками по умолчанию
+    fn load_deferred_settings(&mut self, ctx: &Context) {
+        self.finish_settings_load(ctx.frame_nr() > 0);
+        if self.settings_loader.is_some() {
+            ctx.request_repaint();
+        }
+        if self.settings_dirty {
+            self.apply_settings(ctx);
+            self.settings_dirty = false;
+        }
+    }
+
+    // Забирает настройки из фонового потока; wait - дождаться окончания чтения
+    fn finish_settings_load(&mut self, wait: bool) {
+        let Some(loader) = self.settings_loader.take() else {
+            return;
         };
+        if !wait && !loader.is_finished() {
+            self.settings_loader = Some(loader);
+            return;
+        }
 
-        if let Some(path) = path {
-            let doc = self.current_document_mut();
-            if let Err(e) = doc.save(&path) {
-                self.error_message = Some(format!("Не удалось сохранить файл: {}", e));
-            } else {
-                self.last_save_time = Instant::now();
-                println!("Файл сохранен: {:?}", path);
-            }
-        } else {
-            self.save_document_as();
+        self.settings = loader.join().unwrap_or_default();
+        self.recorded_macro = self.settings.last_macro.clone();
+        // Запросы, найденные до окончания загрузки, остаются наверху истории
+        let recent = std::mem::replace(&mut self.find_history, self.settings.find_history.clone());
+        for query in recent.iter().rev() {
+            search::push_find_history(&mut self.find_history, query);
         }
+        self.settings_dirty = true;
     }
 
-    fn save_document_as(&mut self) {
-        if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Текстовые файлы", &["txt"])
-            .add_filter("Все файлы", &["*"])
-            .save_file()
-        {
-            let path = if path.extension().is_none() {
-                path.with_extension("txt")
-            } else {
-                path
-            };
+    fn track_window_geometry(&mut self, ctx: &Context) {
+        // До загрузки настроек сохранение затерло бы их значениями по умолчанию
+        if self.settings_loader.is_some() {
+            return;
+        }
+        let geometry = ctx.input(|i| {
+            let viewport = i.viewport();
+            if viewport.minimized == Some(true) || viewport.maximized == Some(true) || viewport.fullscreen == Some(true) {
+                return None;
+            }
+            let size = viewport.inner_rect?.size();
+            let position = viewport.outer_rect.map(|rect| rect.min);
+            Some(WindowGeometry {
+                window_width: size.x,
+                window_height: size.y,
+                window_x: position.map(|p| p.x.round() as i32),
+                window_y: position.map(|p| p.y.round() as i32),
+            })
+        });
+        let Some(geometry) = geometry else {
+            return;
+        };
+        if self.update_window_geometry(geometry, Instant::now()) {
+            let _ = self.settings.save();
+        } else if self.observed_window_geometry.is_some() {
+            ctx.request_repaint_after(WINDOW_GEOMETRY_DEBOUNCE);
+        }
+    }
 
-            let doc = self.current_document_mut();
-            if let Err(e) = doc.save_as(&path) {
-                self.error_message = Some(format!("Не удалось сохранить файл: {}", e));
-            } else {
-                self.last_save_time = Instant::now();
-                println!("Файл сохранен как: {:?}", path);
+    // Запоминает геометрию окна; возвращает true, когда она перенесена в настройки
+    fn update_window_geometry(&mut self, geometry: WindowGeometry, now: Instant) -> bool {
+        match self.observed_window_geometry {
+            Some((observed, since)) if observed == geometry => {
+                if now.duration_since(since) < WINDOW_GEOMETRY_DEBOUNCE {
+                    return false;
+                }
+                self.observed_window_geometry = None;
+                self.settings.set_window_geometry(geometry);
+                true
+            }
+            _ if geometry == self.settings.window_geometry() => {
+                self.observed_window_geometry = None;
+                false
+            }
+            _ => {
+                self.observed_window_geometry = Some((geometry, now));
+                false
             }
         }
     }
 
-    fn close_current_document(&mut self) {
-        if self.documents.len() > 1 {
-            self.documents.remove(self.active_document);
-            self.active_document = self.active_document.saturating_sub(1);
+    fn ensure_active_document(&mut self) {
+        if self.documents.is_empty() {
+            self.documents.push(Document::new("Безымянный 1"));
+        }
+        if self.active_document >= self.documents.len() {
+            self.active_document = self.documents.len().saturating_sub(1);
+        }
+        if self.split_view.is_some_and(|split| split >= self.documents.len() || split == self.active_document) {
+            self.split_view = None;
+        }
+        let len = self.documents.len();
+        if self.compare_mode.is_some_and(|mode| mode.left >= len || mode.right >= len) {
+            self.compare_mode = None;
         }
     }
 
-    fn auto_save(&mut self) {
-        if self.settings.auto_save_enabled && self.last_save_time.elapsed() > self.settings.auto_save_interval {
-            let paths_to_save: Vec<PathBuf> = self.documents
-                .iter()
-                .filter(|doc| doc.is_modified())
-                .filter_map(|doc| doc.path().map(|p| p.to_path_buf()))
-                .collect();
+    fn compare_with(&mut self, other: usize) {
+        if other != self.active_document {
+            self.compare_mode = Some(CompareMode { left: self.active_document, right: other });
+        }
+    }
 
-            for path in paths_to_save {
-                for doc in &mut self.documents {
-                    if let Some(doc_path) = doc.path() {
-                        if doc_path == path.as_path() && doc.is_modified() {
-                            let _ = doc.save(&path);
-                            break;
-                        }
-                    }
-                }
-            }
-            self.last_save_time = Instant::now();
+    fn show_compare_view(&mut self, ui: &mut egui::Ui) {
+        let Some(mode) = self.compare_mode else {
+            return;
+        };
+        let left_generation = self.documents[mode.left].generation();
+        let right_generation = self.documents[mode.right].generation();
+        let cache_valid = matches!(&self.compare_cache,
+            Some((cached, left, right, _)) if *cached == mode && *left == left_generation && *right == right_generation);
+        if !cache_valid {
+            let rows = diff::diff_rows(&self.documents[mode.left].content, &self.documents[mode.right].content);
+            self.compare_cache = Some((mode, left_generation, right_generation, rows));
+        }
+        let Some((_, _, _, rows)) = &self.compare_cache else {
+            return;
+        };
+
+        let summary = diff::summarize(rows);
+        let mut exit = false;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!(
+                "{} ↔ {}",
+                self.documents[mode.left].title(),
+                self.documents[mode.right].title()
+            )).strong());
+            ui.label(format!(
+                "Удалено: {}, добавлено: {}, изменено: {}",
+                summary.deleted, summary.inserted, summary.changed
+            ));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                exit = ui.button("Выйти из сравнения").clicked();
+            });
+        });
+        ui.separator();
+        diff::render_diff_view(ui, rows, self.settings.font_size);
+
+        if exit {
+            self.compare_mode = None;
+            self.compare_cache = None;
         }
     }
 
-    fn copy_text(&self) {
-        let doc = self.current_document();
-        println!("Текст скопирован: {}", doc.content);
+    fn toggle_split_view(&mut self) {
+        self.split_view = match self.split_view {
+            Some(_) => None,
+            None if self.documents.len() > 1 => Some((self.active_document + 1) % self.documents.len()),
+            None => None,
+        };
     }
 
-    fn cut_text(&mut self) {
-        let doc = self.current_document_mut();
-        if !doc.content.is_empty() {
-            doc.save_state_before_change();
-            let old_content = std::mem::take(&mut doc.content);
-            println!("Текст вырезан: {}", old_content);
-            doc.set_modified(true);
+    fn toggle_distraction_free(&mut self, ctx: &Context) {
+        self.distraction_free = !self.distraction_free;
+        self.menu_strip_rect = None;
+        ctx.send_viewport_cmd(ViewportCommand::Fullscreen(self.distraction_free));
+    }
+
+    // В режиме без отвлечений панели скрыты, а меню появляется только при наведении на полосу
+    fn visible_panels(&self, menu_strip_hovered: bool) -> PanelVisibility {
+        let shown = !self.distraction_free;
+        PanelVisibility {
+            menu_bar: shown || menu_strip_hovered,
+            toolbar: shown,
+            tabs: shown,
+            status_bar: shown,
         }
     }
 
-    fn paste_text(&mut self) {
-        let doc = self.current_document_mut();
-        doc.save_state_before_change();
-        doc.content.push_str("[ВСТАВЛЕННЫЙ ТЕКСТ]");
-        doc.set_modified(true);
+    // Меню видно, пока курсор у верхнего края, над самим меню или над открытым списком
+    fn menu_strip_visible(&self, ctx: &Context) -> bool {
+        let pointer = ctx.input(|i| i.pointer.hover_pos());
+        let over_strip = pointer.is_some_and(|pos| {
+            pos.y <= 4.0 || self.menu_strip_rect.is_some_and(|rect| rect.contains(pos))
+        });
+        over_strip || (self.menu_strip_rect.is_some() && ctx.is_pointer_over_area())
     }
 
-    fn select_all(&mut self) {
-        println!("Выделить всё");
+    // Текст по центру экрана с полями по бокам
+    fn show_distraction_free_editor(&mut self, ui: &mut egui::Ui) {
+        let available = ui.available_rect_before_wrap();
+        let text_width = (available.width() * 0.7).min(800.0);
+        let margin = ((available.width() - text_width) / 2.0).max(0.0);
+        ui.add_space(24.0);
+        let rect = ui.available_rect_before_wrap().shrink2(egui::vec2(margin, 0.0));
+        ui.allocate_ui_at_rect(rect, |ui| self.show_editor(ui));
     }
 
-    fn show_menu_bar(&mut self, ctx: &Context) {
-        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
-            menu::bar(ui, |ui| {
-                ui.menu_button("Файл", |ui| {
-                    if ui.button("Создать").clicked() {
-                        self.new_document();
-                        ui.close_menu();
-                    }
-                    if ui.button("Открыть...").clicked() {
-                        self.open_document();
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Сохранить").clicked() {
-                        self.save_document();
-                        ui.close_menu();
-                    }
-                    if ui.button("Сохранить как...").clicked() {
-                        self.save_document_as();
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Закрыть").clicked() {
-                        self.close_current_document();
-                        ui.close_menu();
-                    }
-                    if ui.button("Выход").clicked() {
-                        ctx.send_viewport_cmd(ViewportCommand::Close);
-                        ui.close_menu();
-                    }
-                });
+    // Активирует вкладку; если она была в правой панели, панели меняются местами
+    fn activate_document(&mut self, index: usize) {
+        if self.split_view == Some(index) {
+            self.split_view = Some(self.active_document);
+        }
+        self.active_document = index;
+    }
 
-                ui.menu_button("Правка", |ui| {
-                    let can_undo = !self.current_document().undo_stack.is_empty();
-                    let can_redo = !self.current_document().redo_stack.is_empty();
+    fn current_document_mut(&mut self) -> &mut Document {
+        &mut self.documents[self.active_document]
+    }
 
-                    if ui.add_enabled(can_undo, egui::Button::new("Отменить")).clicked() {
-                        self.current_document_mut().undo();
-                        ui.close_menu();
-                    }
-                    if ui.add_enabled(can_redo, egui::Button::new("Повторить")).clicked() {
-                        self.current_document_mut().redo();
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Вырезать").clicked() {
-                        self.cut_text();
-                        ui.close_menu();
-                    }
-                    if ui.button("Копировать").clicked() {
+    fn current_document(&self) -> &Document {
+        &self.documents[self.active_document]
+    }
+
+    // При наличии шаблонов сначала предлагается выбрать один из них
+    fn new_document(&mut self) {
+        if self.templates.is_empty() {
+            self.create_document(None);
+        } else {
+            self.template_chooser = Some(TemplateChooser { extension: None, selected: self.last_template(None) });
+        }
+    }
+
+    fn create_document(&mut self, template: Option<usize>) {
+        let count = self.documents.len() + 1;
+        let doc = match template.and_then(|i| self.templates.get(i)) {
+            Some(template) if template.extension.is_empty() => {
+                Document::with_content(&format!("Безымянный {}", count), template.content.clone())
+            }
+            Some(template) => Document::with_content(
+                &format!("Безымянный {}.{}", count, template.extension),
+                template.content.clone(),
+            ),
+            None => Document::new(&format!("Безымянный {}", count)),
+        };
+        self.documents.push(doc);
+        self.active_document = self.documents.len() - 1;
+    }
+
+    // Последний выбранный шаблон для фильтра, если он еще существует и подходит под фильтр
+    fn last_template(&self, extension: Option<&str>) -> Option<usize> {
+        let name = self.settings.last_templates.get(extension.unwrap_or(""))?;
+        filter_templates(&self.templates, extension)
+            .into_iter()
+            .find(|&i| self.templates[i].name == *name)
+    }
+
+    fn show_template_chooser(&mut self, ctx: &Context) {
+        let Some(chooser) = &mut self.template_chooser else {
+            return;
+        };
+
+        let mut extensions: Vec<String> = self
+            .templates
+            .iter()
+            .map(|t| t.extension.to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+
+        let mut open = true;
+        let mut filter_changed = false;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("Новый документ")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Тип файла")
+                    .selected_text(chooser.extension.as_deref().map_or("Все".to_string(), |ext| format!(".{}", ext)))
+                    .show_ui(ui, |ui| {
+                        filter_changed |= ui.selectable_value(&mut chooser.extension, None, "Все").changed();
+                        for ext in &extensions {
+                            filter_changed |= ui
+                                .selectable_value(&mut chooser.extension, Some(ext.clone()), format!(".{}", ext))
+                                .changed();
+                        }
+                    });
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    let blank = ui.selectable_label(chooser.selected.is_none(), "Пустой документ");
+                    if blank.clicked() {
+                        chooser.selected = None;
+                    }
+                    create |= blank.double_clicked();
+                    for i in filter_templates(&self.templates, chooser.extension.as_deref()) {
+                        let template = &self.templates[i];
+                        let label = if template.extension.is_empty() {
+                            template.name.clone()
+                        } else {
+                            format!("{} (.{})", template.name, template.extension)
+                        };
+                        let response = ui.selectable_label(chooser.selected == Some(i), label);
+                        if response.clicked() {
+                            chooser.selected = Some(i);
+                        }
+                        create |= response.double_clicked();
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    create |= ui.button("Создать").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        let extension = chooser.extension.clone();
+        let selected = chooser.selected;
+        if filter_changed {
+            let last = self.last_template(extension.as_deref());
+            if let Some(chooser) = &mut self.template_chooser {
+                chooser.selected = last;
+            }
+        }
+        if create {
+            let key = extension.unwrap_or_default();
+            match selected {
+                Some(i) => self.settings.last_templates.insert(key, self.templates[i].name.clone()),
+                None => self.settings.last_templates.remove(&key),
+            };
+            self.create_document(selected);
+        }
+        if create || cancel || !open {
+            self.template_chooser = None;
+        }
+    }
+
+    fn show_template_manager(&mut self, ctx: &Context) {
+        if !self.show_template_manager {
+            return;
+        }
+
+        let mut open = true;
+        let mut edit = None;
+        let mut delete = None;
+        let mut save = false;
+        let mut cancel_edit = false;
+        egui::Window::new("Шаблоны документов")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if let Some((_, template)) = &mut self.template_editor {
+                    egui::Grid::new("template_editor_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Название:");
+                        ui.text_edit_singleline(&mut template.name);
+                        ui.end_row();
+                        ui.label("Расширение:");
+                        ui.add(egui::TextEdit::singleline(&mut template.extension).hint_text("md, rs, txt..."));
+                        ui.end_row();
+                    });
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut template.content)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_rows(12)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        save = ui.button("Сохранить").clicked();
+                        cancel_edit = ui.button("Отмена").clicked();
+                    });
+                    return;
+                }
+
+                if self.templates.is_empty() {
+                    ui.label("Шаблонов пока нет");
+                }
+                egui::Grid::new("templates_grid").num_columns(3).striped(true).show(ui, |ui| {
+                    for (i, template) in self.templates.iter().enumerate() {
+                        ui.label(&template.name);
+                        ui.label(if template.extension.is_empty() { "—".to_string() } else { format!(".{}", template.extension) });
+                        ui.horizontal(|ui| {
+                            if ui.small_button("Изменить").clicked() {
+                                edit = Some(i);
+                            }
+                            if ui.small_button("Удалить").clicked() {
+                                delete = Some(i);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Создать шаблон").clicked() {
+                        let template = DocumentTemplate {
+                            name: "Новый шаблон".to_string(),
+                            extension: String::new(),
+                            content: String::new(),
+                        };
+                        self.template_editor = Some((None, template));
+                    }
+                    if ui.button("Из текущего документа").clicked() {
+                        let doc = &self.documents[self.active_document];
+                        let template = DocumentTemplate {
+                            name: doc.title().to_string(),
+                            extension: doc.extension().unwrap_or_default(),
+                            content: doc.content.clone(),
+                        };
+                        self.template_editor = Some((None, template));
+                    }
+                });
+            });
+
+        if let Some(i) = edit {
+            let template = self.templates[i].clone();
+            self.template_editor = Some((Some(template.name.clone()), template));
+        }
+        if let Some(i) = delete {
+            if let Err(e) = self.templates[i].delete() {
+                self.error_message = Some(format!("Не удалось удалить шаблон: {}", e));
+            }
+            self.templates = DocumentTemplate::load_all();
+        }
+        if save {
+            self.save_edited_template();
+        }
+        if cancel_edit {
+            self.template_editor = None;
+        }
+        if !open {
+            self.show_template_manager = false;
+            self.template_editor = None;
+        }
+    }
+
+    fn save_edited_template(&mut self) {
+        let Some((original, mut template)) = self.template_editor.take() else {
+            return;
+        };
+        template.name = template.name.trim().to_string();
+        template.extension = template.extension.trim().trim_start_matches('.').to_lowercase();
+        if template.name.is_empty() {
+            self.error_message = Some("Укажите название шаблона".to_string());
+            self.template_editor = Some((original, template));
+            return;
+        }
+
+        // При переименовании файл со старым именем удаляется
+        if let Some(old) = original.filter(|old| *old != template.name) {
+            if let Some(previous) = self.templates.iter().find(|t| t.name == old) {
+                let _ = previous.delete();
+            }
+        }
+        if let Err(e) = template.save() {
+            self.error_message = Some(format!("Не удалось сохранить шаблон: {}", e));
+        }
+        self.templates = DocumentTemplate::load_all();
+    }
+
+    fn open_document(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Текстовые файлы", &["txt", "md", "rs", "json", "xml", "html", "css"])
+            .add_filter("Все файлы", &["*"])
+            .pick_file()
+        {
+            self.open_path(&path);
+        }
+    }
+
+    // Активный документ открывается в отдельном процессе редактора; общего
+    // состояния у окон нет, несохраненные изменения в новое окно не попадают
+    fn open_new_window(&mut self) {
+        let Some(path) = self.current_document().path().map(std::path::Path::to_path_buf) else {
+            self.error_message = Some("Сохраните документ, чтобы открыть его в новом окне".to_string());
+            return;
+        };
+        let spawned = std::env::current_exe().and_then(|exe| std::process::Command::new(exe).arg("--open").arg(&path).spawn());
+        if let Err(e) = spawned {
+            self.error_message = Some(format!("Не удалось открыть новое окно: {}", e));
+        }
+    }
+
+    // Похожие на двоичные файлы открываются только после подтверждения
+    fn open_path(&mut self, path: &std::path::Path) {
+        if file_looks_binary(path) {
+            self.pending_binary_open = Some(path.to_path_buf());
+            return;
+        }
+        self.push_loaded(Document::load(path));
+    }
+
+    fn push_loaded(&mut self, loaded: std::io::Result<Document>) {
+        match loaded {
+            Ok(doc) => {
+                self.documents.push(doc);
+                self.active_document = self.documents.len() - 1;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Не удалось открыть файл: {}", e));
+            }
+        }
+    }
+
+    // Файлы, перетащенные в окно из проводника, открываются в новых вкладках
+    fn handle_dropped_files(&mut self, ctx: &Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            if !path.as_os_str().is_empty() {
+                self.open_path(&path);
+            }
+        }
+    }
+
+    fn show_drop_overlay(&self, ctx: &Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+        let rect = ctx.screen_rect();
+        painter.rect_filled(rect, 0.0, ctx.style().visuals.selection.bg_fill.gamma_multiply(0.3));
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Перетащите файлы сюда",
+            FontId::proportional(24.0),
+            ctx.style().visuals.strong_text_color(),
+        );
+    }
+
+    fn save_document(&mut self) {
+        if self.current_document().read_only {
+            self.error_message = Some("Документ открыт только для чтения. Используйте \"Сохранить как\"".to_string());
+            return;
+        }
+        let path = {
+            let doc = self.current_document();
+            doc.path().map(|p| p.to_path_buf())
+        };
+
+        if let Some(path) = path {
+            let doc = self.current_document_mut();
+            if let Err(e) = doc.save(&path) {
+                self.error_message = Some(format!("Не удалось сохранить файл: {}", e));
+            } else {
+                self.last_save_time = Instant::now();
+                recovery::remove(&self.recovery_dir, self.active_document);
+                self.recovery_generations.remove(&self.active_document);
+                println!("Файл сохранен: {:?}", path);
+            }
+        } else {
+            self.save_document_as();
+        }
+    }
+
+    fn save_document_as(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Текстовые файлы", &["txt"])
+            .add_filter("Все файлы", &["*"])
+            .save_file()
+        {
+            let path = if path.extension().is_none() {
+                path.with_extension("txt")
+            } else {
+                path
+            };
+
+            let doc = self.current_document_mut();
+            if let Err(e) = doc.save_as(&path) {
+                self.error_message = Some(format!("Не удалось сохранить файл: {}", e));
+            } else {
+                self.last_save_time = Instant::now();
+                println!("Файл сохранен как: {:?}", path);
+            }
+        }
+    }
+
+    fn export_theme(&mut self) {
+        let file_name = format!("{}.json", self.settings.theme.name());
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Тема", &["json"])
+            .set_file_name(file_name)
+            .save_file()
+        {
+            if let Err(e) = self.settings.export_theme(&path) {
+                self.error_message = Some(format!("Не удалось экспортировать тему: {}", e));
+            }
+        }
+    }
+
+    // Импортированная тема сразу применяется и сохраняется в папку тем
+    fn import_theme(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("Тема", &["json"]).pick_file() else {
+            return;
+        };
+        if let Err(e) = self.settings.import_theme(&path) {
+            self.error_message = Some(format!("Не удалось импортировать тему: {}", e));
+            return;
+        }
+        let Theme::Custom(theme) = &self.settings.theme else {
+            return;
+        };
+        let theme = theme.clone();
+        if let Err(e) = theme.save() {
+            self.error_message = Some(format!("Не удалось сохранить тему: {}", e));
+            return;
+        }
+        self.toast = Some((format!("Тема \"{}\" импортирована", theme.name), Instant::now(), TOAST_DURATION));
+        self.custom_themes.retain(|t| t.name != theme.name);
+        self.custom_themes.push(theme);
+        self.custom_themes.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    fn export_as_html(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("HTML", &["html", "htm"])
+            .set_file_name(format!("{}.html", self.current_document().title()))
+            .save_file()
+        {
+            let path = if path.extension().is_none() {
+                path.with_extension("html")
+            } else {
+                path
+            };
+
+            if let Err(e) = std::fs::write(&path, self.current_document().to_html()) {
+                self.error_message = Some(format!("Не удалось экспортировать файл: {}", e));
+            }
+        }
+    }
+
+    // Измененный или не сохраненный документ печатается из временного файла,
+    // чтобы на бумагу попал текст из редактора, а не старая версия с диска
+    fn print_document(&mut self) {
+        let doc = self.current_document();
+        let path = doc.path().filter(|_| !doc.is_modified());
+        match print::print_document(path, doc.title(), &doc.content) {
+            Ok(()) => self.toast = Some(("Документ отправлен на печать".to_string(), Instant::now(), TOAST_DURATION)),
+            Err(e) => self.error_message = Some(format!("Не удалось напечатать документ: {}", e)),
+        }
+    }
+
+    // Сохраняет измененные документы, у которых уже есть файл; возвращает их число
+    fn save_modified_with_paths(&mut self) -> usize {
+        let mut saved = 0;
+        for doc in &mut self.documents {
+            let Some(path) = doc.path().map(|p| p.to_path_buf()) else {
+                continue;
+            };
+            if !doc.is_modified() {
+                continue;
+            }
+            match doc.save(&path) {
+                Ok(()) => saved += 1,
+                Err(e) => self.error_message = Some(format!("Не удалось сохранить файл: {}", e)),
+            }
+        }
+        saved
+    }
+
+    fn save_all_documents(&mut self) {
+        let mut saved = self.save_modified_with_paths();
+
+        // Для новых документов спрашиваем имя файла
+        let active = self.active_document;
+        for i in 0..self.documents.len() {
+            if self.documents[i].is_modified() && self.documents[i].path().is_none() {
+                self.active_document = i;
+                self.save_document_as();
+                if !self.documents[i].is_modified() {
+                    saved += 1;
+                }
+            }
+        }
+        self.active_document = active;
+
+        self.last_save_time = Instant::now();
+        self.toast = Some((format!("Сохранено файлов: {}", saved), Instant::now(), TOAST_DURATION));
+    }
+
+    // Закрепленные документы и единственный открытый документ не закрываются
+    fn close_document(&mut self, index: usize) -> bool {
+        if self.documents.len() <= 1 || self.documents[index].is_pinned() {
+            return false;
+        }
+        self.documents.remove(index);
+        if self.active_document >= index {
+            self.active_document = self.active_document.saturating_sub(1);
+        }
+        self.split_view = match self.split_view {
+            Some(split) if split == index => None,
+            Some(split) if split > index => Some(split - 1),
+            split => split,
+        };
+        if self.split_view == Some(self.active_document) {
+            self.split_view = None;
+        }
+        self.compare_mode = None;
+        // Номера документов сдвинулись, копии для восстановления переписываются заново
+        self.recovery_generations.clear();
+        true
+    }
+
+    fn close_current_document(&mut self) {
+        self.close_document(self.active_document);
+    }
+
+    // Закрывает все незакрепленные документы, кроме keep, который становится активным
+    fn close_unpinned_documents(&mut self, keep: Option<usize>) {
+        self.active_document = keep.map_or(0, |keep| {
+            self.documents[..keep].iter().filter(|doc| doc.is_pinned()).count()
+        });
+        let mut index = 0;
+        self.documents.retain(|doc| {
+            let retain = doc.is_pinned() || Some(index) == keep;
+            index += 1;
+            retain
+        });
+        self.split_view = None;
+        self.compare_mode = None;
+        self.recovery_generations.clear();
+        self.ensure_active_document();
+    }
+
+    fn close_all_documents(&mut self) {
+        self.close_unpinned_documents(None);
+    }
+
+    fn close_other_documents(&mut self, index: usize) {
+        self.close_unpinned_documents(Some(index));
+    }
+
+    // Пока пользователь не ответил на вопрос о восстановлении, старые копии не перезаписываются
+    fn write_recovery_files(&mut self) {
+        if self.secondary_window || !self.pending_recovery.is_empty() || self.last_recovery_write.elapsed() < RECOVERY_INTERVAL {
+            return;
+        }
+        self.last_recovery_write = Instant::now();
+
+        for (index, doc) in self.documents.iter().enumerate() {
+            if !doc.is_modified() {
+                self.recovery_generations.remove(&index);
+                recovery::remove(&self.recovery_dir, index);
+            } else if self.recovery_generations.get(&index) != Some(&doc.generation())
+                && recovery::write(&self.recovery_dir, index, &doc.content).is_ok()
+            {
+                self.recovery_generations.insert(index, doc.generation());
+            }
+        }
+        // Копии закрытых документов
+        for (index, _) in recovery::find_all(&self.recovery_dir).into_iter().filter(|(index, _)| *index >= self.documents.len()) {
+            recovery::remove(&self.recovery_dir, index);
+        }
+    }
+
+    fn restore_recovery_files(&mut self) {
+        for (_, path) in std::mem::take(&mut self.pending_recovery) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                let title = format!("Восстановленный {}", self.documents.len() + 1);
+                self.documents.push(Document::recovered(&title, content));
+            }
+            let _ = std::fs::remove_file(path);
+        }
+        self.recovery_generations.clear();
+    }
+
+    fn discard_recovery_files(&mut self) {
+        for (_, path) in std::mem::take(&mut self.pending_recovery) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn show_binary_file_dialog(&mut self, ctx: &Context) {
+        let Some(path) = self.pending_binary_open.clone() else {
+            return;
+        };
+
+        let mut open = false;
+        let mut cancel = false;
+        egui::Window::new("Двоичный файл")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Этот файл может быть двоичным. Всё равно открыть?");
+                ui.label(RichText::new(path.display().to_string()).weak());
+                ui.separator();
+                ui.horizontal(|ui| {
+                    open = ui.button("Открыть как текст").clicked();
+                    cancel = ui.button("Отмена").clicked();
+                });
+            });
+
+        if open {
+            self.pending_binary_open = None;
+            self.push_loaded(Document::load_lossy(&path));
+        } else if cancel {
+            self.pending_binary_open = None;
+        }
+    }
+
+    fn show_recovery_dialog(&mut self, ctx: &Context) {
+        if self.pending_recovery.is_empty() {
+            return;
+        }
+
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Восстановление")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Восстановить несохраненные документы?");
+                ui.label(format!("Найдено копий: {}", self.pending_recovery.len()));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    restore = ui.button("Восстановить").clicked();
+                    discard = ui.button("Удалить").clicked();
+                });
+            });
+
+        if restore {
+            self.restore_recovery_files();
+        } else if discard {
+            self.discard_recovery_files();
+        }
+    }
+
+    fn auto_save(&mut self) {
+        if self.settings.auto_save_enabled && self.last_save_time.elapsed() > self.settings.auto_save_interval {
+            let paths_to_save: Vec<PathBuf> = self.documents
+                .iter()
+                .filter(|doc| doc.is_modified())
+                .filter_map(|doc| doc.path().map(|p| p.to_path_buf()))
+                .collect();
+
+            for path in paths_to_save {
+                for doc in &mut self.documents {
+                    if let Some(doc_path) = doc.path() {
+                        if doc_path == path.as_path() && doc.is_modified() {
+                            let _ = doc.save(&path);
+                            break;
+                        }
+                    }
+                }
+            }
+            self.auto_save_untitled();
+            self.last_save_time = Instant::now();
+        }
+    }
+
+    // Новые документы без пути сохраняются в папку из настроек под своим заголовком;
+    // после сохранения путь остается у документа, и следующие автосохранения идут в тот же файл
+    fn auto_save_untitled(&mut self) {
+        let Some(directory) = self.settings.default_save_directory.clone() else {
+            return;
+        };
+        let mut saved = Vec::new();
+        for doc in self.documents.iter_mut().filter(|doc| doc.is_modified() && doc.path().is_none()) {
+            let path = auto_save_path(&directory, doc.title());
+            if std::fs::create_dir_all(&directory).and_then(|_| doc.save(&path)).is_ok() {
+                saved.push(path);
+            }
+        }
+        let message = match saved.as_slice() {
+            [] => return,
+            [path] => format!("Автосохранено в {}", path.display()),
+            paths => format!("Автосохранено документов: {} в {}", paths.len(), directory.display()),
+        };
+        self.toast = Some((message, Instant::now(), AUTO_SAVE_TOAST_DURATION));
+    }
+
+    fn copy_text(&mut self) {
+        if let Some(text) = self.current_document().copy() {
+            match self.clipboard.set_text(&text) {
+                Ok(()) => clipboard::push_history(&mut self.clipboard_history, &text),
+                Err(e) => {
+                    self.error_message = Some(format!("Не удалось скопировать в буфер обмена: {}", e));
+                }
+            }
+        }
+    }
+
+    fn cut_text(&mut self) {
+        let Some(text) = self.current_document().copy() else {
+            return;
+        };
+        match self.clipboard.set_text(&text) {
+            Ok(()) => {
+                clipboard::push_history(&mut self.clipboard_history, &text);
+                self.current_document_mut().cut();
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Не удалось вырезать в буфер обмена: {}", e));
+            }
+        }
+    }
+
+    fn paste_text(&mut self) {
+        match self.clipboard.get_text() {
+            Ok(text) => self.current_document_mut().paste(&text.replace("\r\n", "\n")),
+            Err(e) => {
+                self.error_message = Some(format!("Не удалось вставить из буфера обмена: {}", e));
+            }
+        }
+    }
+
+    fn markdown_preview_visible(&self) -> bool {
+        if self.current_document().is_markdown() {
+            self.settings.markdown_preview
+        } else {
+            self.preview_forced
+        }
+    }
+
+    fn toggle_markdown_preview(&mut self) {
+        if self.current_document().is_markdown() {
+            self.settings.markdown_preview = !self.settings.markdown_preview;
+            let _ = self.settings.save();
+        } else {
+            self.preview_forced = !self.preview_forced;
+        }
+    }
+
+    fn show_markdown_preview(&mut self, ctx: &Context) {
+        let active = self.active_document;
+        let generation = self.current_document().generation();
+
+        // Разбираем документ заново только если он изменился
+        let cache_valid = matches!(&self.preview_cache, Some((doc, gen, _)) if *doc == active && *gen == generation);
+        if !cache_valid {
+            let blocks = markdown::parse_blocks(&self.current_document().content);
+            self.preview_cache = Some((active, generation, blocks));
+        }
+
+        let font_size = self.settings.font_size;
+        let mut copy_html = false;
+        egui::SidePanel::right("markdown_preview")
+            .resizable(true)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Предпросмотр").strong());
+                    if ui.small_button("Копировать HTML").clicked() {
+                        copy_html = true;
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_source("markdown_preview_scroll")
+                    .show(ui, |ui| {
+                        if let Some((_, _, blocks)) = &self.preview_cache {
+                            markdown::show_blocks(ui, blocks, font_size);
+                        }
+                    });
+            });
+
+        if copy_html {
+            let html = markdown::to_html(&self.current_document().content);
+            if let Err(e) = self.clipboard.set_text(&html) {
+                self.error_message = Some(format!("Не удалось скопировать в буфер обмена: {}", e));
+            }
+        }
+    }
+
+    fn show_outline(&mut self, ctx: &Context) {
+        let active = self.active_document;
+        let generation = self.current_document().generation();
+        let cache_valid = matches!(&self.outline_cache, Some((doc, gen, _)) if *doc == active && *gen == generation);
+        if !cache_valid {
+            let entries = markdown::parse_outline(&self.current_document().content);
+            self.outline_cache = Some((active, generation, entries));
+        }
+
+        let mut jump = None;
+        egui::SidePanel::left("outline")
+            .resizable(true)
+            .default_width(200.0)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Структура").strong());
+                ui.separator();
+                let Some((_, _, entries)) = &self.outline_cache else {
+                    return;
+                };
+                if entries.is_empty() {
+                    ui.label(RichText::new("Нет заголовков").weak());
+                }
+                egui::ScrollArea::vertical()
+                    .id_source("outline_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for entry in entries {
+                            ui.horizontal(|ui| {
+                                ui.add_space((entry.level - 1) as f32 * 12.0);
+                                let label = egui::Label::new(&entry.title).truncate(true).sense(egui::Sense::click());
+                                if ui.add(label).on_hover_text(format!("Строка {}", entry.line)).clicked() {
+                                    jump = Some(entry.line);
+                                }
+                            });
+                        }
+                    });
+            });
+
+        if jump.is_some() {
+            self.jump_to_line = jump;
+        }
+    }
+
+    // Сохранение не меняет версию документа, поэтому кэш сверяется и с признаком изменений
+    fn show_saved_diff(&mut self, ctx: &Context) {
+        let active = self.active_document;
+        let doc = self.current_document();
+        let key = (active, doc.generation(), doc.is_modified());
+        let cache_valid = matches!(&self.saved_diff_cache, Some((a, g, m, _)) if (*a, *g, *m) == key);
+        if !cache_valid {
+            let lines = doc.diff_from_saved();
+            self.saved_diff_cache = Some((key.0, key.1, key.2, lines));
+        }
+
+        let font_size = self.settings.font_size;
+        let mut open = true;
+        egui::SidePanel::right("saved_diff")
+            .resizable(true)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let Some((_, _, _, lines)) = &self.saved_diff_cache else {
+                    return;
+                };
+                let (added, removed) = diff::count_changes(lines);
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Изменения с последнего сохранения").strong());
+                    if ui.small_button("✖").on_hover_text("Закрыть").clicked() {
+                        open = false;
+                    }
+                });
+                ui.label(format!("+{} / -{} строк", added, removed));
+                ui.separator();
+                diff::render_line_diff(ui, lines, font_size);
+            });
+
+        if !open {
+            self.show_saved_diff = false;
+            self.saved_diff_cache = None;
+        }
+    }
+
+    fn show_clipboard_history(&mut self, ctx: &Context) {
+        let mut insert = None;
+        let mut clear = false;
+        egui::SidePanel::right("clipboard_history")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("История буфера обмена").strong());
+                    clear = ui.small_button("Очистить").clicked();
+                });
+                ui.separator();
+                if self.clipboard_history.is_empty() {
+                    ui.label(RichText::new("Пусто").weak());
+                }
+                egui::ScrollArea::vertical()
+                    .id_source("clipboard_history_scroll")
+                    .show(ui, |ui| {
+                        for (i, text) in self.clipboard_history.iter().enumerate() {
+                            if i > 0 {
+                                ui.separator();
+                            }
+                            // Первая строка фрагмента; полный текст во всплывающей подсказке
+                            let preview = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+                            let response = ui
+                                .add(egui::Label::new(preview).truncate(true).sense(egui::Sense::click()))
+                                .on_hover_text(text.as_str());
+                            if response.clicked() {
+                                insert = Some(text.clone());
+                            }
+                        }
+                    });
+            });
+
+        if clear {
+            self.clipboard_history.clear();
+        }
+        if let Some(text) = insert {
+            self.current_document_mut().insert_at_cursor(&text);
+        }
+    }
+
+    fn show_minimap(&mut self, ctx: &Context) {
+        let editor_id = self.editor_id();
+        let viewport = ctx.data(|d| d.get_temp(editor_id.with("minimap_viewport"))).unwrap_or_default();
+        egui::SidePanel::right("minimap")
+            .resizable(false)
+            .exact_width(80.0)
+            .show(ctx, |ui| {
+                let text = &self.documents[self.active_document].content;
+                if let Some(offset) = minimap::show_minimap(ui, text, viewport, &self.minimap_scroll) {
+                    ui.data_mut(|d| d.insert_temp(editor_id.with("minimap_scroll"), offset));
+                }
+            });
+    }
+
+    fn editor_id(&self) -> egui::Id {
+        egui::Id::new("text_editor").with(self.active_document)
+    }
+
+    // Правая панель разделенного вида хранит прокрутку и курсор отдельно от основной
+    fn split_editor_id(split: usize) -> egui::Id {
+        egui::Id::new("split_editor").with(split)
+    }
+
+    fn select_all(&mut self) {
+        self.current_document_mut().select_all();
+    }
+
+    fn accept_suggestion(&mut self) {
+        let Some(word) = self.suggestions.get(self.selected_suggestion).cloned() else {
+            return;
+        };
+        let doc = self.current_document_mut();
+        let prefix_len = doc.word_before_cursor().len();
+        if let Some(suffix) = word.get(prefix_len..) {
+            doc.insert_at_cursor(suffix);
+        }
+        self.suggestions.clear();
+    }
+
+    // Перехватывает клавиши списка подсказок до того, как их получит TextEdit
+    fn handle_autocomplete_keys(&mut self, ctx: &Context) {
+        if self.suggestions.is_empty() {
+            return;
+        }
+
+        let accept_key = self.settings.autocomplete_accept_key;
+        let count = self.suggestions.len();
+        let (accept, close, down, up) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, accept_key),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            )
+        });
+
+        if down {
+            self.selected_suggestion = (self.selected_suggestion + 1) % count;
+        }
+        if up {
+            self.selected_suggestion = (self.selected_suggestion + count - 1) % count;
+        }
+        if close {
+            self.suggestions.clear();
+        } else if accept {
+            self.accept_suggestion();
+        }
+    }
+
+    // Tab и Shift+Tab перехватываются до TextEdit, чтобы отступ учитывал настройки
+    fn handle_indent_keys(&mut self, ctx: &Context) {
+        if !ctx.memory(|m| m.has_focus(self.editor_id())) {
+            return;
+        }
+
+        let (outdent, indent) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::SHIFT, Key::Tab),
+                i.consume_key(Modifiers::NONE, Key::Tab),
+            )
+        });
+        let tab_size = self.settings.tab_size;
+        let indent_with_spaces = self.settings.indent_with_spaces;
+        let unit = self.settings.indent_unit();
+        let doc = self.current_document_mut();
+        let selection = doc.selection().unwrap_or((doc.cursor_position(), doc.cursor_position()));
+
+        // Tab с выделением в пределах одной строки заменяет его отступом
+        if outdent {
+            doc.indent_selection(selection, IndentMode::Outdent, indent_with_spaces, tab_size);
+        } else if indent {
+            if doc.content[selection.0..selection.1].contains('\n') {
+                doc.indent_selection(selection, IndentMode::Indent, indent_with_spaces, tab_size);
+            } else {
+                doc.insert_at_cursor(&unit);
+            }
+        }
+    }
+
+    // Enter перехватывается до TextEdit, чтобы новая строка получила отступ текущей
+    fn handle_auto_indent(&mut self, ctx: &Context) {
+        if !self.settings.auto_indent
+            || self.current_document().column_selection().is_some()
+            || !ctx.memory(|m| m.has_focus(self.editor_id()))
+        {
+            return;
+        }
+
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter)) {
+            let indent_with_spaces = self.settings.indent_with_spaces;
+            let tab_size = self.settings.tab_size;
+            self.current_document_mut().insert_newline_with_indent(indent_with_spaces, tab_size);
+        }
+    }
+
+    fn record_macro_action(&mut self, action: MacroAction) {
+        if self.macro_recording {
+            macros::push_action(&mut self.recorded_macro, action);
+        }
+    }
+
+    fn toggle_macro_recording(&mut self) {
+        if self.macro_recording {
+            self.macro_recording = false;
+            self.settings.last_macro = self.recorded_macro.clone();
+            if let Err(e) = self.settings.save() {
+                self.error_message = Some(format!("Не удалось сохранить макрос: {}", e));
+            }
+        } else {
+            self.recorded_macro.clear();
+            self.macro_recording = true;
+        }
+    }
+
+    fn play_macro(&mut self) {
+        let actions = self.recorded_macro.clone();
+        let times = self.macro_repeat_count;
+        let auto_close_pairs = self.settings.auto_close_pairs;
+        macros::play(&actions, self.current_document_mut(), times, auto_close_pairs);
+    }
+
+    fn format_json(&mut self, minify: bool) {
+        let doc = self.current_document_mut();
+        let result = if minify { doc.minify_json() } else { doc.format_as_json() };
+        if let Err(e) = result {
+            self.error_message = Some(format!("Текст не является корректным JSON: {}", e));
+        }
+    }
+
+    fn convert_case(&mut self, mode: CaseMode) {
+        self.current_document_mut().convert_case(mode);
+        self.record_macro_action(MacroAction::ConvertCase(mode));
+    }
+
+    // Во время записи макроса запоминаем ввод в редакторе до того, как его разберут обработчики
+    fn record_macro_input(&mut self, ctx: &Context) {
+        if !self.macro_recording || !ctx.memory(|m| m.has_focus(self.editor_id())) {
+            return;
+        }
+
+        let indent = self.settings.indent_unit();
+        let actions: Vec<MacroAction> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Text(text) => Some(MacroAction::InsertText(text.clone())),
+                    egui::Event::Key { key, pressed: true, modifiers, .. } if modifiers.is_none() => {
+                        match key {
+                            Key::Enter => Some(MacroAction::InsertText("\n".to_string())),
+                            Key::Tab => Some(MacroAction::InsertText(indent.clone())),
+                            Key::Backspace => Some(MacroAction::DeleteChars(1)),
+                            Key::ArrowLeft => Some(MacroAction::MoveCursor(-1)),
+                            Key::ArrowRight => Some(MacroAction::MoveCursor(1)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                })
+                .collect()
+        });
+
+        for action in actions {
+            self.record_macro_action(action);
+        }
+    }
+
+    // Прямоугольное выделение: Alt+Shift+стрелки, ввод и удаление сразу во всех строках
+    fn handle_column_selection_keys(&mut self, ctx: &Context) {
+        if !ctx.memory(|m| m.has_focus(self.editor_id())) {
+            return;
+        }
+
+        let alt_shift = Modifiers::ALT | Modifiers::SHIFT;
+        let active = self.current_document().column_selection().is_some();
+        let mut typed = String::new();
+        let (moves, backspace, delete, escape) = ctx.input_mut(|i| {
+            let moves = [
+                (Key::ArrowUp, -1, 0),
+                (Key::ArrowDown, 1, 0),
+                (Key::ArrowLeft, 0, -1),
+                (Key::ArrowRight, 0, 1),
+            ]
+            .into_iter()
+            .filter(|(key, _, _)| i.consume_key(alt_shift, *key))
+            .map(|(_, rows, cols)| (rows, cols))
+            .collect::<Vec<(isize, isize)>>();
+
+            if !active && moves.is_empty() {
+                return (moves, false, false, false);
+            }
+
+            i.events.retain(|event| match event {
+                egui::Event::Text(text) => {
+                    typed.push_str(text);
+                    false
+                }
+                _ => true,
+            });
+            (
+                moves,
+                i.consume_key(Modifiers::NONE, Key::Backspace),
+                i.consume_key(Modifiers::NONE, Key::Delete),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+
+        let doc = self.current_document_mut();
+        for (rows, cols) in moves {
+            doc.extend_column_selection(rows, cols);
+        }
+        if doc.column_selection().is_none() {
+            return;
+        }
+
+        if escape {
+            doc.clear_column_selection();
+        } else if !typed.is_empty() {
+            doc.replace_column_selection(&typed);
+        } else if backspace {
+            doc.delete_column_backward();
+        } else if delete {
+            doc.replace_column_selection("");
+        }
+    }
+
+    // Скобки и кавычки вводятся парами; событие ввода забирается у TextEdit
+    fn handle_auto_close(&mut self, ctx: &Context) {
+        if !self.settings.auto_close_pairs || !ctx.memory(|m| m.has_focus(self.editor_id())) {
+            return;
+        }
+
+        let mut typed = Vec::new();
+        ctx.input_mut(|i| {
+            i.events.retain(|event| {
+                let egui::Event::Text(text) = event else {
+                    return true;
+                };
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if "([{<\"')]}".contains(c) => {
+                        typed.push(c);
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        });
+
+        let doc = self.current_document_mut();
+        for c in typed {
+            doc.type_pair_char(c);
+        }
+    }
+
+    fn show_editor(&mut self, ui: &mut egui::Ui) {
+        let active = self.active_document;
+        let editor_id = self.editor_id();
+
+        if self.autocomplete_document != Some(active) {
+            self.autocomplete_document = Some(active);
+            self.autocomplete.request_reindex(self.documents[active].content.clone());
+            self.suggestions.clear();
+        }
+        self.autocomplete.poll();
+
+        let doc = &mut self.documents[active];
+        let output = render_editor_pane(
+            doc,
+            ui,
+            editor_id,
+            &self.settings,
+            &mut self.viewport_line_offset,
+            Some(&self.minimap_scroll),
+        );
+
+        if output.response.changed() {
+            self.autocomplete.request_reindex(doc.content.clone());
+
+            let typed_letter = doc.content[..doc.cursor_position()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric());
+            self.suggestions = if self.settings.autocomplete_enabled && typed_letter && doc.selection().is_none() {
+                self.autocomplete.suggest(doc.word_before_cursor(), 5)
+            } else {
+                Vec::new()
+            };
+            self.selected_suggestion = 0;
+        } else if output.response.clicked() || output.cursor_range.is_none() {
+            self.suggestions.clear();
+        }
+
+        if self.suggestions.is_empty() {
+            return;
+        }
+
+        let Some(range) = output.cursor_range else {
+            return;
+        };
+        let cursor_rect = output
+            .galley
+            .pos_from_cursor(&range.primary)
+            .translate(output.galley_pos.to_vec2());
+
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("autocomplete_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(cursor_rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, word) in self.suggestions.iter().enumerate() {
+                        if ui.selectable_label(i == self.selected_suggestion, word).clicked() {
+                            clicked = Some(i);
+                        }
+                    }
+                });
+            });
+
+        if let Some(i) = clicked {
+            self.selected_suggestion = i;
+            self.accept_suggestion();
+        }
+    }
+
+    fn run_shortcut_action(&mut self, ctx: &Context, action: ShortcutAction) {
+        if action.modifies_text() && self.current_document().read_only {
+            return;
+        }
+        match action {
+            ShortcutAction::New => self.new_document(),
+            ShortcutAction::Open => self.open_document(),
+            ShortcutAction::Save => self.save_document(),
+            ShortcutAction::SaveAs => self.save_document_as(),
+            ShortcutAction::SaveAll => self.save_all_documents(),
+            ShortcutAction::Print => self.print_document(),
+            ShortcutAction::Find => self.show_find_replace = true,
+            ShortcutAction::FindPrevious => self.find_previous(),
+            ShortcutAction::GlobalSearch => self.show_global_search = !self.show_global_search,
+            ShortcutAction::SelectAll => self.select_all(),
+            ShortcutAction::Copy => self.copy_text(),
+            ShortcutAction::Cut => self.cut_text(),
+            ShortcutAction::Paste => self.paste_text(),
+            ShortcutAction::Undo => {
+                self.current_document_mut().undo();
+            }
+            ShortcutAction::Redo => {
+                self.current_document_mut().redo();
+            }
+            ShortcutAction::DuplicateLine => {
+                let doc = self.current_document_mut();
+                let cursor = doc.duplicate_line(doc.cursor_position());
+                doc.set_cursor_position(cursor);
+            }
+            ShortcutAction::DeleteLine => {
+                let doc = self.current_document_mut();
+                let cursor = doc.delete_line(doc.cursor_position());
+                doc.set_cursor_position(cursor);
+            }
+            ShortcutAction::MoveLineUp => {
+                let doc = self.current_document_mut();
+                let cursor = doc.move_line_up(doc.cursor_position());
+                doc.set_cursor_position(cursor);
+            }
+            ShortcutAction::MoveLineDown => {
+                let doc = self.current_document_mut();
+                let cursor = doc.move_line_down(doc.cursor_position());
+                doc.set_cursor_position(cursor);
+            }
+            ShortcutAction::FormatJson => self.format_json(false),
+            ShortcutAction::DistractionFree => self.toggle_distraction_free(ctx),
+            ShortcutAction::ToggleComment => {
+                let doc = self.current_document_mut();
+                doc.toggle_line_comment(doc.selection());
+            }
+        }
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        // Захват нового сочетания для выбранного в настройках действия
+        if let Some(action) = self.capturing_shortcut {
+            let captured = ctx.input_mut(|i| {
+                let pressed = i.events.iter().find_map(|event| match event {
+                    egui::Event::Key { key, modifiers, pressed: true, .. } => Some((*key, *modifiers)),
+                    _ => None,
+                });
+                if pressed.is_some() {
+                    i.events.clear();
+                }
+                pressed
+            });
+
+            if let Some((key, modifiers)) = captured {
+                if key != Key::Escape {
+                    self.settings.shortcuts.insert(action, KeyCombo::new(modifiers, key));
+                    if let Err(e) = self.settings.save() {
+                        self.error_message = Some(format!("Не удалось сохранить настройки: {}", e));
+                    }
+                }
+                self.capturing_shortcut = None;
+            }
+            return;
+        }
+
+        // Системные Ctrl+C/X/V приходят событиями буфера обмена, а не нажатиями клавиш
+        let editor_focused = ctx.memory(|m| m.has_focus(self.editor_id()));
+        let shortcuts = self.settings.sorted_shortcuts();
+        let triggered: Vec<ShortcutAction> = ctx.input_mut(|i| {
+            let mut triggered = Vec::new();
+            if editor_focused {
+                i.events.retain(|event| {
+                    let action = match event {
+                        egui::Event::Copy => ShortcutAction::Copy,
+                        egui::Event::Cut => ShortcutAction::Cut,
+                        egui::Event::Paste(_) => ShortcutAction::Paste,
+                        _ => return true,
+                    };
+                    triggered.push(action);
+                    false
+                });
+            }
+            triggered.extend(
+                shortcuts
+                    .iter()
+                    .filter(|(_, combo)| i.consume_shortcut(&combo.to_shortcut()))
+                    .map(|(action, _)| *action),
+            );
+            triggered
+        });
+
+        for action in triggered {
+            self.run_shortcut_action(ctx, action);
+        }
+    }
+
+    fn show_menu_bar(&mut self, ctx: &Context) -> egui::Rect {
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            menu::bar(ui, |ui| {
+                ui.menu_button("Файл", |ui| {
+                    if ui.button("Создать").clicked() {
+                        self.new_document();
+                        ui.close_menu();
+                    }
+                    if ui.button("Открыть...").clicked() {
+                        self.open_document();
+                        ui.close_menu();
+                    }
+                    if ui.button("Открыть в новом окне").clicked() {
+                        self.open_new_window();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Сохранить").clicked() {
+                        self.save_document();
+                        ui.close_menu();
+                    }
+                    if ui.button("Сохранить как...").clicked() {
+                        self.save_document_as();
+                        ui.close_menu();
+                    }
+                    if ui.button("Сохранить все").clicked() {
+                        self.save_all_documents();
+                        ui.close_menu();
+                    }
+                    if ui.button("Управление шаблонами...").clicked() {
+                        self.show_template_manager = true;
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Экспорт", |ui| {
+                        if ui.button("HTML...").clicked() {
+                            self.export_as_html();
+                            ui.close_menu();
+                        }
+                    });
+                    if ui.button("Печать...").clicked() {
+                        self.print_document();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Закрыть").clicked() {
+                        self.close_current_document();
+                        ui.close_menu();
+                    }
+                    if ui.button("Закрыть прочие").clicked() {
+                        self.close_other_documents(self.active_document);
+                        ui.close_menu();
+                    }
+                    if ui.button("Закрыть всё").clicked() {
+                        self.close_all_documents();
+                        ui.close_menu();
+                    }
+                    if ui.button("Выход").clicked() {
+                        ctx.send_viewport_cmd(ViewportCommand::Close);
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Правка", |ui| {
+                    let editable = !self.current_document().read_only;
+                    let can_undo = editable && self.current_document().can_undo();
+                    let can_redo = editable && self.current_document().can_redo();
+
+                    if ui.add_enabled(can_undo, egui::Button::new("Отменить")).clicked() {
+                        self.current_document_mut().undo();
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(can_redo, egui::Button::new("Повторить")).clicked() {
+                        self.current_document_mut().redo();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.add_enabled(editable, egui::Button::new("Вырезать")).clicked() {
+                        self.cut_text();
+                        ui.close_menu();
+                    }
+                    if ui.button("Копировать").clicked() {
                         self.copy_text();
                         ui.close_menu();
                     }
-                    if ui.button("Вставить").clicked() {
+                    if ui.add_enabled(editable, egui::Button::new("Вставить")).clicked() {
                         self.paste_text();
                         ui.close_menu();
                     }
@@ -464,11 +1962,131 @@ impl TextEditorApp {
                         self.select_all();
                         ui.close_menu();
                     }
+                    ui.menu_button("Регистр", |ui| {
+                        for mode in CaseMode::all() {
+                            if ui.button(mode.label()).clicked() {
+                                self.convert_case(mode);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    let selection = self.current_document().selection();
+                    ui.add_enabled_ui(selection.is_some(), |ui| {
+                        ui.menu_button("Обернуть выделение", |ui| {
+                            for (open, close) in WRAP_DELIMITERS {
+                                if ui.button(format!("{}...{}", open, close)).clicked() {
+                                    if let Some(selection) = selection {
+                                        self.current_document_mut().wrap_selection(selection, open, close);
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                    if ui.add_enabled(selection.is_some(), egui::Button::new("Кодировать URL")).clicked() {
+                        if let Some(selection) = selection {
+                            self.current_document_mut().url_encode_selection(selection);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(selection.is_some(), egui::Button::new("Декодировать URL")).clicked() {
+                        if let Some(selection) = selection {
+                            if let Err(e) = self.current_document_mut().url_decode_selection(selection) {
+                                self.error_message = Some(format!("Не удалось декодировать URL: {}", e));
+                            }
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(selection.is_some(), egui::Button::new("Кодировать Base64")).clicked() {
+                        if let Some(selection) = selection {
+                            self.current_document_mut().base64_encode_selection(selection);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(selection.is_some(), egui::Button::new("Декодировать Base64")).clicked() {
+                        if let Some(selection) = selection {
+                            let doc = self.current_document_mut();
+                            doc.base64_decode_selection(selection);
+                            if let Some(e) = doc.take_last_error() {
+                                self.error_message = Some(format!("Не удалось декодировать Base64: {}", e));
+                            }
+                        }
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Строки", |ui| {
+                        let sorts = [
+                            ("Сортировать", false, false),
+                            ("Сортировать по убыванию", true, false),
+                            ("Сортировать без учета регистра", false, true),
+                        ];
+                        for (label, reverse, case_insensitive) in sorts {
+                            if ui.add_enabled(selection.is_some(), egui::Button::new(label)).clicked() {
+                                if let Some(selection) = selection {
+                                    self.current_document_mut().sort_lines_in_range(selection, reverse, case_insensitive);
+                                }
+                                ui.close_menu();
+                            }
+                        }
+                        if ui.add_enabled(selection.is_some(), egui::Button::new("Удалить дубликаты")).clicked() {
+                            if let Some(selection) = selection {
+                                self.current_document_mut().deduplicate_lines_in_range(selection);
+                            }
+                            ui.close_menu();
+                        }
+                    });
+                    if ui.add_enabled(selection.is_some(), egui::Button::new("Объединить строки")).clicked() {
+                        if let Some(selection) = selection {
+                            self.current_document_mut().join_lines(selection);
+                        }
+                        ui.close_menu();
+                    }
+                    let wrap_column = self.settings.wrap_at_column.unwrap_or(80);
+                    let wrap_button = ui
+                        .button("Перенос по ширине")
+                        .on_hover_text(format!("По {} символов; без выделения - весь документ", wrap_column));
+                    if wrap_button.clicked() {
+                        let doc = self.current_document_mut();
+                        let selection = doc.selection().unwrap_or((0, doc.content.len()));
+                        doc.wrap_to_width(selection, wrap_column);
+                        ui.close_menu();
+                    }
+                    let can_comment = self.current_document().comment_prefix().is_some();
+                    if ui.add_enabled(can_comment, egui::Button::new("Закомментировать строки")).clicked() {
+                        let doc = self.current_document_mut();
+                        doc.toggle_line_comment(doc.selection());
+                        ui.close_menu();
+                    }
+                    if ui.button("Форматировать JSON").clicked() {
+                        self.format_json(false);
+                        ui.close_menu();
+                    }
+                    if ui.button("Сжать JSON").clicked() {
+                        self.format_json(true);
+                        ui.close_menu();
+                    }
                     ui.separator();
                     if ui.button("Найти/Заменить").clicked() {
                         self.show_find_replace = true;
                         ui.close_menu();
                     }
+                    if ui.button("Поиск в проекте").clicked() {
+                        self.show_global_search = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let mut recording = self.macro_recording;
+                    if ui.checkbox(&mut recording, "Запись макроса").clicked() {
+                        self.toggle_macro_recording();
+                        ui.close_menu();
+                    }
+                    ui.horizontal(|ui| {
+                        let can_play = !self.macro_recording && !self.recorded_macro.is_empty();
+                        if ui.add_enabled(can_play, egui::Button::new("Выполнить макрос")).clicked() {
+                            self.play_macro();
+                            ui.close_menu();
+                        }
+                        ui.add(egui::DragValue::new(&mut self.macro_repeat_count).clamp_range(1..=1000).suffix(" раз"));
+                    });
                 });
 
                 ui.menu_button("Вид", |ui| {
@@ -476,6 +2094,56 @@ impl TextEditorApp {
                         self.show_stats = true;
                         ui.close_menu();
                     }
+                    if ui.button("Контрольные точки...").clicked() {
+                        self.show_checkpoints = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Закладки...").clicked() {
+                        self.show_bookmarks = true;
+                        ui.close_menu();
+                    }
+                    let mut preview = self.markdown_preview_visible();
+                    if ui.checkbox(&mut preview, "Предпросмотр Markdown").clicked() {
+                        self.toggle_markdown_preview();
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.settings.show_minimap, "Миникарта");
+                    ui.checkbox(&mut self.settings.show_outline, "Структура Markdown");
+                    ui.checkbox(&mut self.show_clipboard_history, "История буфера обмена");
+                    ui.checkbox(&mut self.show_saved_diff, "Изменения с последнего сохранения");
+                    // Несохраненные правки потерялись бы при перечитывании файла
+                    let doc = self.current_document();
+                    let mut tail = doc.tail_mode;
+                    let can_tail = doc.tail_mode || (doc.path().is_some() && !doc.is_modified());
+                    if ui.add_enabled(can_tail, egui::Checkbox::new(&mut tail, "Режим просмотра хвоста")).clicked() {
+                        self.toggle_tail_mode();
+                        ui.close_menu();
+                    }
+                    let mut split = self.split_view.is_some();
+                    let can_split = self.documents.len() > 1;
+                    if ui.add_enabled(can_split, egui::Checkbox::new(&mut split, "Разделить вид")).clicked() {
+                        self.toggle_split_view();
+                        ui.close_menu();
+                    }
+                    ui.add_enabled_ui(self.documents.len() > 1, |ui| {
+                        ui.menu_button("Сравнить с", |ui| {
+                            let mut compare = None;
+                            for (i, doc) in self.documents.iter().enumerate() {
+                                if i != self.active_document && ui.button(doc.title()).clicked() {
+                                    compare = Some(i);
+                                    ui.close_menu();
+                                }
+                            }
+                            if let Some(i) = compare {
+                                self.compare_with(i);
+                            }
+                        });
+                    });
+                    let mut distraction_free = self.distraction_free;
+                    if ui.checkbox(&mut distraction_free, "Режим без отвлечений").clicked() {
+                        self.toggle_distraction_free(ui.ctx());
+                        ui.close_menu();
+                    }
                     ui.separator();
                     if ui.button("Увеличить").clicked() {
                         self.settings.font_size = (self.settings.font_size + 1.0).min(72.0);
@@ -496,9 +2164,21 @@ impl TextEditorApp {
                         self.show_settings = true;
                         ui.close_menu();
                     }
+                    ui.separator();
+                    let is_custom = matches!(self.settings.theme, Theme::Custom(_));
+                    if ui.add_enabled(is_custom, egui::Button::new("Экспортировать тему...")).clicked() {
+                        self.export_theme();
+                        ui.close_menu();
+                    }
+                    if ui.button("Импортировать тему...").clicked() {
+                        self.import_theme();
+                        ui.close_menu();
+                    }
                 });
             });
-        });
+        })
+        .response
+        .rect
     }
 
     fn show_toolbar(&mut self, ui: &mut egui::Ui) {
@@ -515,54 +2195,279 @@ impl TextEditorApp {
             if ui.button("💾 Сохранить как").clicked() {
                 self.save_document_as();
             }
-            ui.separator();
+            if ui.button("💾 Все").clicked() {
+                self.save_all_documents();
+            }
+            ui.separator();
+
+            let editable = !self.current_document().read_only;
+            let can_undo = editable && self.current_document().can_undo();
+            let can_redo = editable && self.current_document().can_redo();
+
+            if ui.add_enabled(can_undo, egui::Button::new("↶ Отменить")).clicked() {
+                self.current_document_mut().undo();
+            }
+            if ui.add_enabled(can_redo, egui::Button::new("↷ Повторить")).clicked() {
+                self.current_document_mut().redo();
+            }
+            ui.separator();
+            if ui.button("🔍 Найти").clicked() {
+                self.show_find_replace = true;
+            }
+        });
+    }
+
+    fn show_document_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut close_index = None;
+        let mut close_others = None;
+        let mut close_all = false;
+        let mut toggle_pin = None;
+        let mut toggle_read_only = None;
+        let mut activate = None;
+        let mut split = None;
+        let mut compare = None;
+
+        ui.horizontal(|ui| {
+            for (i, doc) in self.documents.iter().enumerate() {
+                let is_active = i == self.active_document;
+                let label = if doc.is_modified() {
+                    format!("{} ●", doc.title())
+                } else {
+                    doc.title().to_string()
+                };
+
+                let label = if self.split_view == Some(i) { format!("{} ◨", label) } else { label };
+                let label = if doc.read_only { format!("{} [Только чтение]", label) } else { label };
+                let response = ui.selectable_label(is_active, label);
+
+                if response.clicked() && !is_active {
+                    activate = Some(i);
+                }
+
+                response.context_menu(|ui| {
+                    let pin_label = if doc.is_pinned() { "Открепить" } else { "Закрепить" };
+                    if ui.button(pin_label).clicked() {
+                        toggle_pin = Some(i);
+                        ui.close_menu();
+                    }
+                    if ui.button("Переключить режим только для чтения").clicked() {
+                        toggle_read_only = Some(i);
+                        ui.close_menu();
+                    }
+                    if self.split_view == Some(i) {
+                        if ui.button("Убрать из правой панели").clicked() {
+                            split = Some(None);
+                            ui.close_menu();
+                        }
+                    } else if ui.add_enabled(!is_active, egui::Button::new("Открыть справа")).clicked() {
+                        split = Some(Some(i));
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(!is_active, egui::Button::new("Сравнить с текущим")).clicked() {
+                        compare = Some(i);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(!doc.is_pinned(), egui::Button::new("Закрыть")).clicked() {
+                        close_index = Some(i);
+                        ui.close_menu();
+                    }
+                    if ui.button("Закрыть прочие").clicked() {
+                        close_others = Some(i);
+                        ui.close_menu();
+                    }
+                    if ui.button("Закрыть всё").clicked() {
+                        close_all = true;
+                        ui.close_menu();
+                    }
+                });
+
+                // Вместо кнопки закрытия у закрепленной вкладки значок булавки
+                if doc.is_pinned() {
+                    ui.label("📌");
+                } else if self.documents.len() > 1 && ui.small_button("✕").clicked() {
+                    close_index = Some(i);
+                }
+            }
+
+            if ui.button("+").clicked() {
+                self.new_document();
+            }
+        });
 
-            let can_undo = !self.current_document().undo_stack.is_empty();
-            let can_redo = !self.current_document().redo_stack.is_empty();
+        if let Some(i) = activate {
+            self.activate_document(i);
+        }
+        if let Some(split) = split {
+            self.split_view = split;
+        }
+        if let Some(i) = compare {
+            self.compare_with(i);
+        }
+        if let Some(i) = toggle_pin {
+            let doc = &mut self.documents[i];
+            doc.set_pinned(!doc.is_pinned());
+        }
+        if let Some(i) = toggle_read_only {
+            let doc = &mut self.documents[i];
+            doc.read_only = !doc.read_only;
+        }
+        if let Some(i) = close_index {
+            self.close_document(i);
+        }
+        if let Some(i) = close_others {
+            self.close_other_documents(i);
+        }
+        if close_all {
+            self.close_all_documents();
+        }
+    }
 
-            if ui.add_enabled(can_undo, egui::Button::new("↶ Отменить")).clicked() {
-                self.current_document_mut().undo();
-            }
-            if ui.add_enabled(can_redo, egui::Button::new("↷ Повторить")).clicked() {
-                self.current_document_mut().redo();
+    fn run_global_search(&mut self) {
+        match search::search_documents(&self.documents, &self.global_search_query, &self.global_search_options) {
+            Ok(results) => {
+                self.search_results = Some(SearchResultsPanel::new(&self.global_search_query, results));
+                self.global_search_error = None;
             }
-            ui.separator();
-            if ui.button("🔍 Найти").clicked() {
-                self.show_find_replace = true;
+            Err(e) => {
+                self.search_results = None;
+                self.global_search_error = Some(format!("Неверное регулярное выражение: {}", e));
             }
-        });
+        }
     }
 
-    fn show_document_tabs(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            for (i, doc) in self.documents.iter().enumerate() {
-                let is_active = i == self.active_document;
-                let label = if doc.is_modified() {
-                    format!("{} ●", doc.title())
-                } else {
-                    doc.title().to_string()
-                };
+    fn show_global_search_panel(&mut self, ctx: &Context) {
+        let mut run_search = false;
 
-                let response = ui.selectable_label(is_active, label);
+        egui::SidePanel::left("global_search")
+            .resizable(true)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.heading("Поиск в проекте");
 
-                if response.clicked() && !is_active {
-                    self.active_document = i;
+                let response = ui.text_edit_singleline(&mut self.global_search_query);
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    run_search = true;
+                }
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.global_search_options.match_case, "Регистр");
+                    ui.checkbox(&mut self.global_search_options.whole_word, "Слово");
+                    ui.checkbox(&mut self.global_search_options.use_regex, "Regex");
+                });
+                if ui.button("Найти").clicked() {
+                    run_search = true;
                 }
 
-                if self.documents.len() > 1 {
-                    let close_response = ui.small_button("✕");
-                    if close_response.clicked() {
-                        self.documents.remove(i);
-                        self.active_document = self.active_document.saturating_sub(1);
-                        break;
-                    }
+                if let Some(error) = &self.global_search_error {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+
+        if run_search {
+            self.run_global_search();
+        }
+    }
+
+    // Панель результатов остается открытой и после закрытия панели поиска
+    fn show_search_results_panel(&mut self, ctx: &Context) {
+        let Some(panel) = &mut self.search_results else {
+            return;
+        };
+        let action = panel.show(ctx);
+        self.apply_search_panel_action(action);
+    }
+
+    // Переход выполняется в начале следующего кадра, см. apply_jump_to_line
+    fn apply_search_panel_action(&mut self, action: PanelAction) {
+        let Some(panel) = &self.search_results else {
+            return;
+        };
+        match action {
+            PanelAction::None => {}
+            PanelAction::Close => self.search_results = None,
+            PanelAction::Jump(index) => {
+                let result = &panel.results[index];
+                if result.doc_index < self.documents.len() {
+                    self.active_document = result.doc_index;
+                    self.jump_to_line = Some(result.line_number);
                 }
             }
+        }
+    }
 
-            if ui.button("+").clicked() {
-                self.new_document();
+    // Включение перечитывает файл целиком, чтобы дальше дописывать только новые байты
+    fn toggle_tail_mode(&mut self) {
+        let doc = self.current_document_mut();
+        if doc.tail_mode {
+            doc.tail_mode = false;
+            return;
+        }
+        let Some(path) = doc.path().map(|path| path.to_path_buf()) else {
+            return;
+        };
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                doc.reload_tail(&String::from_utf8_lossy(&bytes), bytes.len() as u64);
+                doc.tail_mode = true;
             }
+            Err(e) => self.error_message = Some(format!("Не удалось открыть файл: {}", e)),
+        }
+    }
+
+    // Наблюдатели запускаются и останавливаются вслед за флагом tail_mode документов
+    fn poll_tail_watchers(&mut self, ctx: &Context) {
+        let documents = &self.documents;
+        self.tail_watchers.retain(|watcher| {
+            documents.iter().any(|doc| doc.tail_mode && doc.path() == Some(watcher.path()))
         });
+        let interval = Duration::from_millis(self.settings.tail_poll_interval_ms.max(50));
+        for doc in self.documents.iter().filter(|doc| doc.tail_mode) {
+            let Some(path) = doc.path() else {
+                continue;
+            };
+            if !self.tail_watchers.iter().any(|watcher| watcher.path() == path) {
+                self.tail_watchers.push(TailWatcher::start(path.to_path_buf(), doc.tail_file_pos(), interval));
+            }
+        }
+
+        let mut stopped = Vec::new();
+        for (index, watcher) in self.tail_watchers.iter().enumerate() {
+            let Some(updates) = watcher.poll() else {
+                stopped.push(index);
+                continue;
+            };
+            let Some(doc) = self.documents.iter_mut().find(|doc| doc.path() == Some(watcher.path())) else {
+                continue;
+            };
+            for update in updates {
+                match update {
+                    TailUpdate::Appended { text, position } => doc.append_tail(&text, position),
+                    TailUpdate::Reloaded { text, position } => doc.reload_tail(&text, position),
+                }
+            }
+        }
+        for index in stopped.into_iter().rev() {
+            self.tail_watchers.remove(index);
+        }
+
+        if !self.tail_watchers.is_empty() {
+            ctx.request_repaint_after(interval);
+        }
+    }
+
+    fn apply_jump_to_line(&mut self) {
+        if let Some(line) = self.jump_to_line.take() {
+            let doc = self.current_document_mut();
+            let offset = doc.line_start_offset(line);
+            doc.set_cursor_position(offset);
+        }
+    }
+
+    fn find_previous(&mut self) {
+        let doc = self.current_document_mut();
+        if !doc.find_previous() {
+            // Поиск дошел до начала документа: продолжаем с конца
+            doc.find_previous();
+        }
     }
 
     fn show_find_replace_dialog(&mut self, ctx: &Context) {
@@ -570,12 +2475,14 @@ impl TextEditorApp {
             return;
         }
 
-        let mut find_text = self.find_text.clone();
-        let mut replace_text = self.replace_text.clone();
-        let mut match_case = self.match_case;
-        let mut whole_word = self.whole_word;
+        let doc = self.current_document();
+        let mut find_text = doc.find_text.clone();
+        let mut replace_text = doc.replace_text.clone();
+        let mut match_case = doc.match_case;
+        let mut whole_word = doc.whole_word;
 
         let mut find_next_clicked = false;
+        let mut find_previous_clicked = false;
         let mut replace_clicked = false;
         let mut replace_all_clicked = false;
 
@@ -585,6 +2492,20 @@ impl TextEditorApp {
                 ui.horizontal(|ui| {
                     ui.label("Найти:");
                     ui.text_edit_singleline(&mut find_text);
+                    ui.add_enabled_ui(!self.find_history.is_empty(), |ui| {
+                        egui::ComboBox::from_id_source("find_history")
+                            .selected_text("")
+                            .width(24.0)
+                            .show_ui(ui, |ui| {
+                                for query in &self.find_history {
+                                    if ui.selectable_label(false, query).clicked() {
+                                        find_text = query.clone();
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("История поиска");
+                    });
                 });
 
                 ui.horizontal(|ui| {
@@ -593,6 +2514,9 @@ impl TextEditorApp {
                 });
 
                 ui.horizontal(|ui| {
+                    if ui.button("Найти предыдущее").clicked() {
+                        find_previous_clicked = true;
+                    }
                     if ui.button("Найти далее").clicked() {
                         find_next_clicked = true;
                     }
@@ -608,40 +2532,45 @@ impl TextEditorApp {
                 ui.checkbox(&mut whole_word, "Целое слово");
             });
 
-        self.find_text = find_text;
-        self.replace_text = replace_text;
-        self.match_case = match_case;
-        self.whole_word = whole_word;
+        if find_next_clicked || find_previous_clicked || replace_clicked || replace_all_clicked {
+            search::push_find_history(&mut self.find_history, &find_text);
+        }
 
-        if find_next_clicked {
-            let doc = self.current_document();
-            if !self.find_text.is_empty() {
-                if let Some(pos) = doc.content.find(&self.find_text) {
-                    println!("Найдено в позиции: {}", pos);
-                }
-            }
+        let incremental_search = self.settings.incremental_search;
+        let previous_find_text = std::mem::replace(&mut self.last_find_text, find_text.clone());
+        let doc = self.current_document_mut();
+        doc.find_text = find_text;
+        doc.replace_text = replace_text;
+        doc.match_case = match_case;
+        doc.whole_word = whole_word;
+
+        if incremental_search && doc.find_text != previous_find_text {
+            doc.find_incremental(&previous_find_text);
+        }
+
+        if find_next_clicked && !doc.find_next() {
+            // Поиск дошел до конца документа: начинаем сначала
+            doc.find_next();
+        }
+
+        if find_previous_clicked {
+            self.find_previous();
         }
 
+        let doc = self.current_document_mut();
         if replace_clicked {
-            let find_text_clone = self.find_text.clone();
-            let replace_text_clone = self.replace_text.clone();
-            let doc = self.current_document_mut();
-            if !find_text_clone.is_empty() && doc.content.contains(&find_text_clone) {
-                doc.save_state_before_change();
-                doc.content = doc.content.replacen(&find_text_clone, &replace_text_clone, 1);
-                doc.set_modified(true);
-            }
+            doc.replace_next();
         }
 
         if replace_all_clicked {
-            let find_text_clone = self.find_text.clone();
-            let replace_text_clone = self.replace_text.clone();
-            let doc = self.current_document_mut();
-            if !find_text_clone.is_empty() && doc.content.contains(&find_text_clone) {
-                doc.save_state_before_change();
-                doc.content = doc.content.replace(&find_text_clone, &replace_text_clone);
-                doc.set_modified(true);
-            }
+            doc.replace_all();
+        }
+
+        if find_next_clicked {
+            self.record_macro_action(MacroAction::FindNext);
+        }
+        if replace_clicked {
+            self.record_macro_action(MacroAction::ReplaceOne);
         }
     }
 
@@ -651,12 +2580,33 @@ impl TextEditorApp {
         }
 
         let mut font_size = self.settings.font_size;
-        let mut theme = self.settings.theme;
+        let mut font_family = self.settings.font_family.clone();
+        let mut theme = self.settings.theme.clone();
+        let mut create_theme_clicked = false;
         let mut auto_save_enabled = self.settings.auto_save_enabled;
+        let mut default_save_directory = self.settings.default_save_directory.clone();
+        let mut autocomplete_enabled = self.settings.autocomplete_enabled;
+        let mut autocomplete_accept_key = self.settings.autocomplete_accept_key;
+        let mut word_count_target = self.settings.word_count_target;
+        let mut word_wrap = self.settings.word_wrap;
+        let mut wrap_at_column = self.settings.wrap_at_column;
+        let mut tab_size = self.settings.tab_size;
+        let mut indent_with_spaces = self.settings.indent_with_spaces;
+        let mut auto_close_pairs = self.settings.auto_close_pairs;
+        let mut auto_indent = self.settings.auto_indent;
+        let mut incremental_search = self.settings.incremental_search;
+        let mut ruler_color = self.settings.ruler_color;
+        let mut large_file_threshold_mb = self.settings.large_file_threshold_bytes / (1024 * 1024);
+        let column_rulers_input = self.column_rulers_input
+            .get_or_insert_with(|| format_column_list(&self.settings.column_rulers));
         let mut show_settings = self.show_settings;
 
         let mut apply_clicked = false;
         let mut cancel_clicked = false;
+        let mut capture_action = None;
+        let mut reset_shortcuts_clicked = false;
+        let capturing = self.capturing_shortcut;
+        let shortcuts = self.settings.shortcuts.clone();
 
         egui::Window::new("Настройки")
             .open(&mut show_settings)
@@ -669,20 +2619,178 @@ impl TextEditorApp {
                         ui.add(egui::Slider::new(&mut font_size, 8.0..=72.0));
                         ui.end_row();
 
-                        ui.label("Тема:");
-                        egui::ComboBox::from_id_source("theme_combo")
-                            .selected_text(format!("{:?}", theme))
-                            .show_ui(ui, |ui| {
-                                for t in Theme::all() {
-                                    ui.selectable_value(&mut theme, t, format!("{:?}", t));
+                        ui.label("Шрифт:");
+                        ui.horizontal(|ui| {
+                            let custom_path = match &font_family {
+                                FontFamily::Custom(path) => path.clone(),
+                                _ => String::new(),
+                            };
+                            egui::ComboBox::from_id_source("font_family_combo")
+                                .selected_text(font_family.label())
+                                .show_ui(ui, |ui| {
+                                    for option in [
+                                        FontFamily::Monospace,
+                                        FontFamily::Proportional,
+                                        FontFamily::Custom(custom_path.clone()),
+                                    ] {
+                                        let selected = std::mem::discriminant(&font_family) == std::mem::discriminant(&option);
+                                        if ui.selectable_label(selected, option.label()).clicked() {
+                                            font_family = option;
+                                        }
+                                    }
+                                });
+                            if let FontFamily::Custom(path) = &mut font_family {
+                                ui.text_edit_singleline(path);
+                                if ui.button("Обзор...").clicked() {
+                                    if let Some(file) = rfd::FileDialog::new()
+                                        .add_filter("Шрифты", &["ttf", "otf"])
+                                        .pick_file()
+                                    {
+                                        *path = file.display().to_string();
+                                    }
                                 }
-                            });
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Тема:");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("theme_combo")
+                                .selected_text(theme.name())
+                                .show_ui(ui, |ui| {
+                                    let custom = self.custom_themes.iter().cloned().map(Theme::Custom);
+                                    for t in Theme::all().into_iter().chain(custom) {
+                                        let name = t.name().to_string();
+                                        ui.selectable_value(&mut theme, t, name);
+                                    }
+                                });
+                            if ui.button("Создать тему").clicked() {
+                                create_theme_clicked = true;
+                            }
+                        });
                         ui.end_row();
 
                         ui.label("Автосохранение:");
                         ui.checkbox(&mut auto_save_enabled, "Включено");
                         ui.end_row();
+
+                        ui.label("Папка для новых документов:");
+                        ui.horizontal(|ui| {
+                            match &default_save_directory {
+                                Some(directory) => ui.label(directory.display().to_string()),
+                                None => ui.weak("не задана, новые документы не автосохраняются"),
+                            };
+                            if ui.button("Обзор...").clicked() {
+                                if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+                                    default_save_directory = Some(directory);
+                                }
+                            }
+                            if default_save_directory.is_some() && ui.button("Сбросить").clicked() {
+                                default_save_directory = None;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Автодополнение:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut autocomplete_enabled, "Включено");
+                            egui::ComboBox::from_id_source("autocomplete_key_combo")
+                                .selected_text(autocomplete_accept_key.name())
+                                .show_ui(ui, |ui| {
+                                    for key in [Key::Tab, Key::Enter] {
+                                        ui.selectable_value(&mut autocomplete_accept_key, key, key.name());
+                                    }
+                                });
+                        });
+                        ui.end_row();
+
+                        ui.label("Цель по словам:");
+                        ui.horizontal(|ui| match &mut word_count_target {
+                            Some(target) => {
+                                ui.add(egui::DragValue::new(target).clamp_range(1..=1_000_000).speed(10));
+                                if ui.button("Очистить").clicked() {
+                                    word_count_target = None;
+                                }
+                            }
+                            None => {
+                                if ui.button("Задать").clicked() {
+                                    word_count_target = Some(1000);
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Перенос строк:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut word_wrap, "Включен");
+                            ui.add_enabled_ui(word_wrap, |ui| {
+                                let mut limited = wrap_at_column.is_some();
+                                if ui.checkbox(&mut limited, "По колонке").changed() {
+                                    wrap_at_column = limited.then_some(80);
+                                }
+                                if let Some(column) = &mut wrap_at_column {
+                                    ui.add(egui::Slider::new(column, 40..=200));
+                                }
+                            });
+                        });
+                        ui.end_row();
+
+                        ui.label("Линейки (колонки):");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(column_rulers_input).hint_text("80, 100"));
+                            ui.color_edit_button_srgba(&mut ruler_color);
+                        });
+                        ui.end_row();
+
+                        ui.label("Размер табуляции:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut tab_size).clamp_range(1..=8));
+                            ui.checkbox(&mut indent_with_spaces, "Отступ пробелами");
+                            ui.checkbox(&mut auto_indent, "Автоотступ");
+                        });
+                        ui.end_row();
+
+                        ui.label("Парные скобки и кавычки:");
+                        ui.checkbox(&mut auto_close_pairs, "Закрывать автоматически");
+                        ui.end_row();
+
+                        ui.label("Поиск:");
+                        ui.checkbox(&mut incremental_search, "Искать по мере ввода");
+                        ui.end_row();
+
+                        ui.label("Большие файлы от:")
+                            .on_hover_text("Показываются только видимые строки, без переноса");
+                        ui.add(egui::DragValue::new(&mut large_file_threshold_mb).clamp_range(1..=1024).suffix(" МБ"));
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                ui.label(RichText::new("Горячие клавиши").strong());
+                egui::Grid::new("shortcuts_grid")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in ShortcutAction::all() {
+                            ui.label(action.label());
+                            let text = if capturing == Some(*action) {
+                                "Нажмите сочетание...".to_string()
+                            } else {
+                                shortcuts
+                                    .get(action)
+                                    .map(|combo| ctx.format_shortcut(&combo.to_shortcut()))
+                                    .unwrap_or_else(|| "—".to_string())
+                            };
+                            if ui.button(text).clicked() {
+                                capture_action = Some(*action);
+                            }
+                            ui.end_row();
+                        }
                     });
+                if ui.button("Сбросить сочетания").clicked() {
+                    reset_shortcuts_clicked = true;
+                }
 
                 ui.separator();
 
@@ -696,49 +2804,362 @@ impl TextEditorApp {
                 });
             });
 
+        if capture_action.is_some() {
+            self.capturing_shortcut = capture_action;
+        }
+
+        if create_theme_clicked {
+            self.theme_editor = Some(CustomTheme::from_visuals("Моя тема", &theme.egui_visuals()));
+        }
+
+        if reset_shortcuts_clicked {
+            self.settings.shortcuts = default_shortcuts();
+            self.capturing_shortcut = None;
+            let _ = self.settings.save();
+        }
+
         if cancel_clicked {
             show_settings = false;
         }
 
         if apply_clicked {
             self.settings.font_size = font_size;
+            self.settings.font_family = font_family;
             self.settings.theme = theme;
             self.settings.auto_save_enabled = auto_save_enabled;
-            self.apply_settings(ctx);
+            self.settings.default_save_directory = default_save_directory;
+            self.settings.autocomplete_enabled = autocomplete_enabled;
+            self.settings.autocomplete_accept_key = autocomplete_accept_key;
+            self.settings.word_count_target = word_count_target;
+            self.settings.word_wrap = word_wrap;
+            self.settings.wrap_at_column = wrap_at_column;
+            self.settings.tab_size = tab_size;
+            self.settings.indent_with_spaces = indent_with_spaces;
+            self.settings.auto_indent = auto_indent;
+            self.settings.auto_close_pairs = auto_close_pairs;
+            self.settings.incremental_search = incremental_search;
+            self.settings.column_rulers = parse_column_list(column_rulers_input);
+            self.settings.ruler_color = ruler_color;
+            self.settings.large_file_threshold_bytes = large_file_threshold_mb * 1024 * 1024;
+            self.settings_dirty = true;
             let _ = self.settings.save();
             show_settings = false;
         }
 
-        self.show_settings = show_settings;
+        if !show_settings {
+            self.capturing_shortcut = None;
+            self.column_rulers_input = None;
+        }
+        self.show_settings = show_settings;
+    }
+
+    fn show_stats_dialog(&mut self, ctx: &Context) {
+        if !self.show_stats {
+            return;
+        }
+
+        let doc = self.current_document();
+        let stats = doc.calculate_stats();
+        let selection_stats = doc.calculate_stats_for_selection();
+        let mut show_stats = self.show_stats;
+
+        egui::Window::new("Статистика документа")
+            .open(&mut show_stats)
+            .show(ctx, |ui| {
+                let columns = if selection_stats.is_some() { 3 } else { 2 };
+                egui::Grid::new("stats_grid")
+                    .num_columns(columns)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        if selection_stats.is_some() {
+                            ui.label("");
+                            ui.strong("Выделение");
+                            ui.strong("Документ");
+                            ui.end_row();
+                        }
+
+                        let labels = [
+                            "Страницы:",
+                            "Слова:",
+                            "Символы:",
+                            "Символы без пробелов:",
+                            "Строки:",
+                            "Абзацы:",
+                            "Предложения:",
+                            "Уникальные слова:",
+                        ];
+                        let values = |s: &DocumentStats| {
+                            [
+                                s.pages,
+                                s.words,
+                                s.characters,
+                                s.characters_no_spaces,
+                                s.lines,
+                                s.paragraphs,
+                                s.sentences,
+                                s.unique_words,
+                            ]
+                        };
+                        let document_values = values(&stats);
+                        let selection_values = selection_stats.as_ref().map(values);
+                        for (i, label) in labels.iter().enumerate() {
+                            ui.label(*label);
+                            if let Some(selection_values) = &selection_values {
+                                ui.label(format!("{}", selection_values[i]));
+                            }
+                            ui.label(format!("{}", document_values[i]));
+                            ui.end_row();
+                        }
+
+                        ui.label("Удобочитаемость (Флеш):");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format!("{:.1}", selection.flesch_reading_ease));
+                        }
+                        ui.label(format!("{:.1}", stats.flesch_reading_ease));
+                        ui.end_row();
+
+                        ui.label("Уровень (Флеш-Кинкейд):");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format!("{:.1}", selection.flesch_kincaid_grade));
+                        }
+                        ui.label(format!("{:.1}", stats.flesch_kincaid_grade));
+                        ui.end_row();
+
+                        ui.label("Средняя длина предложения:");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format!("{:.1} слов", selection.avg_sentence_length));
+                        }
+                        ui.label(format!("{:.1} слов", stats.avg_sentence_length));
+                        ui.end_row();
+
+                        ui.label("Пассивный залог:");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format!("{:.0}%", selection.passive_voice_ratio * 100.0));
+                        }
+                        ui.label(format!("{:.0}%", stats.passive_voice_ratio * 100.0));
+                        ui.end_row();
+
+                        ui.label("Время чтения:");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format_duration(selection.reading_time_seconds));
+                        }
+                        ui.label(format_duration(stats.reading_time_seconds));
+                        ui.end_row();
+
+                        ui.label("Время чтения вслух:");
+                        if let Some(selection) = &selection_stats {
+                            ui.label(format_duration(selection.speaking_time_seconds));
+                        }
+                        ui.label(format_duration(stats.speaking_time_seconds));
+                        ui.end_row();
+                    });
+            });
+
+        self.show_stats = show_stats;
+    }
+
+    fn show_checkpoints_dialog(&mut self, ctx: &Context) {
+        if !self.show_checkpoints {
+            return;
+        }
+
+        let mut show_checkpoints = self.show_checkpoints;
+        let mut label = std::mem::take(&mut self.checkpoint_label);
+        let mut create_clicked = false;
+        let mut restore_index = None;
+        let doc = self.current_document();
+        let current_chars = doc.content.chars().count() as i64;
+
+        egui::Window::new("Контрольные точки")
+            .open(&mut show_checkpoints)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut label);
+                    if ui.add_enabled(!label.trim().is_empty(), egui::Button::new("Создать")).clicked() {
+                        create_clicked = true;
+                    }
+                });
+
+                ui.separator();
+
+                if doc.checkpoints().is_empty() {
+                    ui.label("Контрольных точек пока нет");
+                    return;
+                }
+
+                egui::Grid::new("checkpoints_grid")
+                    .num_columns(3)
+                    .spacing([20.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, (name, snapshot)) in doc.checkpoints().iter().enumerate() {
+                            ui.label(name);
+                            // Разница в символах между точкой и текущим текстом
+                            let diff = snapshot.chars().count() as i64 - current_chars;
+                            ui.label(format!("{:+} симв.", diff));
+                            if ui.button("Восстановить").clicked() {
+                                restore_index = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if create_clicked {
+            self.current_document_mut().create_checkpoint(label.trim());
+            label.clear();
+        }
+        if let Some(index) = restore_index {
+            self.current_document_mut().restore_checkpoint(index);
+        }
+
+        self.checkpoint_label = label;
+        self.show_checkpoints = show_checkpoints;
+    }
+
+    fn show_toast(&mut self, ctx: &Context) {
+        let Some((message, shown_at, duration)) = &self.toast else {
+            return;
+        };
+        let elapsed = shown_at.elapsed();
+        if elapsed > *duration {
+            self.toast = None;
+            return;
+        }
+
+        egui::Window::new("toast")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-16.0, -40.0])
+            .show(ctx, |ui| {
+                ui.label(message);
+            });
+        ctx.request_repaint_after(*duration - elapsed);
+    }
+
+    fn show_bookmarks_dialog(&mut self, ctx: &Context) {
+        if !self.show_bookmarks {
+            return;
+        }
+
+        let mut show_bookmarks = self.show_bookmarks;
+        let mut label = std::mem::take(&mut self.bookmark_label);
+        let mut add_clicked = false;
+        let mut goto_index = None;
+        let mut remove_index = None;
+        let doc = self.current_document();
+
+        egui::Window::new("Закладки")
+            .open(&mut show_bookmarks)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut label);
+                    if ui.button(format!("Добавить (строка {})", doc.cursor_line())).clicked() {
+                        add_clicked = true;
+                    }
+                });
+
+                ui.separator();
+
+                if doc.bookmarks().is_empty() {
+                    ui.label("Закладок пока нет");
+                    return;
+                }
+
+                egui::Grid::new("bookmarks_grid")
+                    .num_columns(4)
+                    .spacing([20.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, bookmark) in doc.bookmarks().iter().enumerate() {
+                            ui.label(format!("{}", bookmark.line));
+                            ui.label(&bookmark.label);
+                            if ui.button("Перейти").clicked() {
+                                goto_index = Some(i);
+                            }
+                            if ui.button("Удалить").clicked() {
+                                remove_index = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let doc = self.current_document_mut();
+        if add_clicked {
+            doc.add_bookmark(label.trim());
+            label.clear();
+        }
+        if let Some(index) = goto_index {
+            doc.goto_bookmark(index);
+        }
+        if let Some(index) = remove_index {
+            doc.remove_bookmark(index);
+        }
+
+        self.bookmark_label = label;
+        self.show_bookmarks = show_bookmarks;
     }
 
-    fn show_stats_dialog(&mut self, ctx: &Context) {
-        if !self.show_stats {
+    fn show_theme_editor(&mut self, ctx: &Context) {
+        let Some(theme) = &mut self.theme_editor else {
             return;
-        }
-
-        let stats = self.current_document().calculate_stats();
-        let mut show_stats = self.show_stats;
+        };
 
-        egui::Window::new("Статистика документа")
-            .open(&mut show_stats)
+        let mut open = true;
+        let mut save_clicked = false;
+        let mut cancel_clicked = false;
+        egui::Window::new("Создать тему")
+            .open(&mut open)
+            .resizable(false)
             .show(ctx, |ui| {
-                egui::Grid::new("stats_grid")
+                egui::Grid::new("theme_editor_grid")
                     .num_columns(2)
-                    .spacing([20.0, 4.0])
+                    .spacing([40.0, 4.0])
                     .show(ui, |ui| {
-                        ui.label("Страницы:"); ui.label(format!("{}", stats.pages));
-                        ui.end_row();
-                        ui.label("Слова:"); ui.label(format!("{}", stats.words));
-                        ui.end_row();
-                        ui.label("Символы:"); ui.label(format!("{}", stats.characters));
-                        ui.end_row();
-                        ui.label("Строки:"); ui.label(format!("{}", stats.lines));
+                        ui.label("Название:");
+                        ui.text_edit_singleline(&mut theme.name);
                         ui.end_row();
+
+                        for (label, color) in [
+                            ("Фон текста:", &mut theme.background),
+                            ("Текст:", &mut theme.text),
+                            ("Выделение:", &mut theme.selection),
+                            ("Фон элементов:", &mut theme.widget_bg),
+                            ("Фон панелей:", &mut theme.panel_bg),
+                        ] {
+                            ui.label(label);
+                            ui.color_edit_button_srgba_unmultiplied(color);
+                            ui.end_row();
+                        }
                     });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    save_clicked = ui.button("Сохранить").clicked();
+                    cancel_clicked = ui.button("Отмена").clicked();
+                });
             });
 
-        self.show_stats = show_stats;
+        if save_clicked {
+            theme.name = theme.name.trim().to_string();
+            if theme.name.is_empty() {
+                self.error_message = Some("Укажите название темы".to_string());
+                return;
+            }
+            if let Err(e) = theme.save() {
+                self.error_message = Some(format!("Не удалось сохранить тему: {}", e));
+                return;
+            }
+            let theme = theme.clone();
+            self.toast = Some((format!("Тема \"{}\" сохранена", theme.name), Instant::now(), TOAST_DURATION));
+            self.custom_themes.retain(|t| t.name != theme.name);
+            self.custom_themes.push(theme);
+            self.custom_themes.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        if !open || save_clicked || cancel_clicked {
+            self.theme_editor = None;
+        }
     }
 
     fn show_error_dialog(&mut self, ctx: &Context) {
@@ -760,111 +3181,996 @@ impl TextEditorApp {
         }
     }
 
-    fn show_status_bar(&self, ui: &mut egui::Ui) {
+    fn show_status_bar(&mut self, ui: &mut egui::Ui) {
+        let mut convert_to = None;
         let doc = self.current_document();
-        let stats = doc.calculate_stats();
 
         ui.horizontal(|ui| {
-            ui.label(format!(
-                "Строка {}, Колонка {} | Слова: {} | Символы: {}",
-                doc.cursor_line(), doc.cursor_column(), stats.words, stats.characters
+            // При активном выделении показываем статистику только по нему
+            let position = format!("Строка {}, Колонка {}", doc.cursor_line(), doc.cursor_column());
+            let (text, stats) = match doc.calculate_stats_for_selection() {
+                Some(stats) => (
+                    format!(
+                        "{} | Выделено слов: {} | Выделено символов: {}",
+                        position, stats.words, stats.characters
+                    ),
+                    stats,
+                ),
+                None => {
+                    let stats = doc.calculate_stats();
+                    (
+                        format!("{} | Слова: {} | Символы: {}", position, stats.words, stats.characters),
+                        stats,
+                    )
+                }
+            };
+            ui.label(text).on_hover_text(format!(
+                "Время чтения: {}\nВремя чтения вслух: {}",
+                format_duration(stats.reading_time_seconds),
+                format_duration(stats.speaking_time_seconds)
             ));
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if doc.is_modified() {
                     ui.label(RichText::new("Изменен").color(Color32::YELLOW));
                 }
+                if doc.read_only {
+                    ui.label("🔒").on_hover_text("Только чтение");
+                }
                 ui.label("UTF-8");
+                ui.menu_button(doc.detected_line_ending.label(), |ui| {
+                    ui.label("Преобразовать концы строк:");
+                    for ending in LineEnding::all() {
+                        let selected = doc.detected_line_ending == ending;
+                        if ui.selectable_label(selected, ending.label()).clicked() {
+                            convert_to = Some(ending);
+                            ui.close_menu();
+                        }
+                    }
+                });
             });
         });
+
+        if let Some(target) = self.settings.word_count_target {
+            let stats = doc.calculate_stats();
+            let progress = stats.target_progress(target);
+            // Зеленый до 80 %, желтый на финишной прямой, снова зеленый при достижении цели
+            let color = if (0.8..1.0).contains(&progress) {
+                Color32::from_rgb(220, 180, 40)
+            } else {
+                Color32::from_rgb(60, 170, 80)
+            };
+            ui.add(
+                egui::ProgressBar::new(progress.min(1.0))
+                    .fill(color)
+                    .text(format!("{} / {} слов ({:.1} %)", stats.words, target, progress * 100.0)),
+            );
+        }
+
+        if let Some(ending) = convert_to {
+            self.current_document_mut().normalize_line_endings(ending);
+        }
     }
 }
 
 impl eframe::App for TextEditorApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.load_deferred_settings(ctx);
+
+        self.track_window_geometry(ctx);
+        self.handle_dropped_files(ctx);
         self.ensure_active_document();
+        self.apply_jump_to_line();
+        self.poll_tail_watchers(ctx);
         self.auto_save();
+        self.write_recovery_files();
 
         // Обработка горячих клавиш
-        ctx.input_mut(|i| {
-            if i.consume_key(Modifiers::CTRL, Key::N) {
-                self.new_document();
+        self.record_macro_input(ctx);
+        self.handle_autocomplete_keys(ctx);
+        self.handle_column_selection_keys(ctx);
+        self.handle_indent_keys(ctx);
+        self.handle_auto_indent(ctx);
+        self.handle_auto_close(ctx);
+        self.handle_shortcuts(ctx);
+
+        let panels = self.visible_panels(self.distraction_free && self.menu_strip_visible(ctx));
+        self.menu_strip_rect = None;
+        if panels.menu_bar {
+            let rect = self.show_menu_bar(ctx);
+            if self.distraction_free {
+                self.menu_strip_rect = Some(rect);
             }
-            if i.consume_key(Modifiers::CTRL, Key::O) {
-                self.open_document();
+        }
+
+        if panels.toolbar {
+            egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+                self.show_toolbar(ui);
+            });
+        }
+
+        if panels.tabs {
+            egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+                self.show_document_tabs(ui);
+            });
+        }
+
+        if self.distraction_free {
+            egui::CentralPanel::default().show(ctx, |ui| self.show_distraction_free_editor(ui));
+        } else {
+            if self.show_global_search {
+                self.show_global_search_panel(ctx);
             }
-            if i.consume_key(Modifiers::CTRL, Key::S) {
-                self.save_document();
+
+            self.show_search_results_panel(ctx);
+
+            if self.markdown_preview_visible() {
+                self.show_markdown_preview(ctx);
             }
-            if i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::S) {
-                self.save_document_as();
+
+            if self.settings.show_minimap && self.compare_mode.is_none() {
+                self.show_minimap(ctx);
             }
-            if i.consume_key(Modifiers::CTRL, Key::F) {
-                self.show_find_replace = true;
+
+            if self.show_clipboard_history {
+                self.show_clipboard_history(ctx);
             }
-            if i.consume_key(Modifiers::CTRL, Key::A) {
-                self.select_all();
+
+            if self.show_saved_diff && self.compare_mode.is_none() {
+                self.show_saved_diff(ctx);
             }
-            if i.consume_key(Modifiers::CTRL, Key::C) {
-                self.copy_text();
+
+            if self.settings.show_outline && self.current_document().is_markdown() && self.compare_mode.is_none() {
+                self.show_outline(ctx);
             }
-            if i.consume_key(Modifiers::CTRL, Key::X) {
-                self.cut_text();
+
+            egui::CentralPanel::default().show(ctx, |ui| match self.split_view {
+                _ if self.compare_mode.is_some() => self.show_compare_view(ui),
+                Some(split) => {
+                    ui.columns(2, |columns| {
+                        self.show_editor(&mut columns[0]);
+                        let editor_id = Self::split_editor_id(split);
+                        render_editor_pane(
+                            &mut self.documents[split],
+                            &mut columns[1],
+                            editor_id,
+                            &self.settings,
+                            &mut self.split_viewport_line_offset,
+                            None,
+                        );
+                    });
+                }
+                None => self.show_editor(ui),
+            });
+        }
+
+        if panels.status_bar {
+            egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+                self.show_status_bar(ui);
+            });
+        }
+
+        self.show_find_replace_dialog(ctx);
+        self.show_settings_dialog(ctx);
+        self.show_theme_editor(ctx);
+        self.show_template_chooser(ctx);
+        self.show_template_manager(ctx);
+        self.show_stats_dialog(ctx);
+        self.show_checkpoints_dialog(ctx);
+        self.show_bookmarks_dialog(ctx);
+        self.show_error_dialog(ctx);
+        self.show_recovery_dialog(ctx);
+        self.show_binary_file_dialog(ctx);
+        self.show_toast(ctx);
+        self.show_drop_overlay(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Иначе несчитанные настройки были бы перезаписаны значениями по умолчанию
+        self.finish_settings_load(true);
+        self.settings.find_history = self.find_history.clone();
+        let _ = self.settings.save();
+        if self.secondary_window {
+            return;
+        }
+        self.save_session();
+        // Копии нужны только после аварийного завершения
+        if self.pending_recovery.is_empty() {
+            for index in 0..self.documents.len() {
+                recovery::remove(&self.recovery_dir, index);
             }
-            if i.consume_key(Modifiers::CTRL, Key::V) {
-                self.paste_text();
+        }
+    }
+}
+
+// "<папка>/<заголовок>.txt"; недопустимые в имени файла символы заменяются на "_",
+// а к имени уже существующего файла добавляется номер
+fn auto_save_path(directory: &std::path::Path, title: &str) -> PathBuf {
+    let name: String = title
+        .trim()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let name = if name.is_empty() { "Безымянный".to_string() } else { name };
+    let mut path = directory.join(format!("{}.txt", name));
+    let mut number = 2;
+    while path.exists() {
+        path = directory.join(format!("{} ({}).txt", name, number));
+        number += 1;
+    }
+    path
+}
+
+// Проверяются только первые BINARY_SNIFF_LEN байт; нечитаемый файл двоичным не считается,
+// ошибку покажет обычное открытие
+fn file_looks_binary(path: &std::path::Path) -> bool {
+    use std::io::Read;
+
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(BINARY_SNIFF_LEN as u64).read_to_end(&mut head))
+        .is_ok_and(|_| is_binary(&head))
+}
+
+// Экранные x вертикальных линеек: колонка, умноженная на ширину символа, от левого края текста
+fn ruler_positions(text_left: f32, columns: &[u8], char_width: f32) -> Vec<f32> {
+    columns.iter().map(|&column| text_left + column as f32 * char_width).collect()
+}
+
+// Шрифт из файла доступен только с кадра, следующего за set_fonts
+fn editor_font(ui: &egui::Ui, settings: &AppSettings) -> FontId {
+    editor_font_id(settings, &ui.fonts(|f| f.families()))
+}
+
+// Шрифт из настроек; незарегистрированное семейство заменяется моноширинным
+fn editor_font_id(settings: &AppSettings, families: &[egui::FontFamily]) -> FontId {
+    let family = settings.font_family.egui_family();
+    let family = if families.contains(&family) {
+        family
+    } else {
+        egui::FontFamily::Monospace
+    };
+    FontId::new(settings.font_size, family)
+}
+
+// Редактор одного документа: переносит курсор, рисует прямоугольное выделение и линейку.
+// Положение прокрутки хранится по editor_id, поэтому у каждой панели оно свое.
+fn render_editor_pane(
+    doc: &mut Document,
+    ui: &mut egui::Ui,
+    editor_id: egui::Id,
+    settings: &AppSettings,
+    viewport_line_offset: &mut usize,
+    scroll_sync: Option<&SyncScroll>,
+) -> egui::text_edit::TextEditOutput {
+    if doc.content.len() > settings.large_file_threshold_bytes {
+        return render_large_editor_pane(doc, ui, editor_id, settings, viewport_line_offset, scroll_sync);
+    }
+
+    // Переносим в виджет курсор, установленный операциями документа
+    let moved_cursor = doc.take_cursor_update();
+    if let Some((anchor, cursor)) = moved_cursor {
+        let mut state = egui::text_edit::TextEditState::load(ui.ctx(), editor_id).unwrap_or_default();
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(doc.byte_to_char(anchor)),
+            CCursor::new(doc.byte_to_char(cursor)),
+        )));
+        state.store(ui.ctx(), editor_id);
+        ui.memory_mut(|m| m.request_focus(editor_id));
+    }
+
+    let font_id = editor_font(ui, settings);
+    let char_width = ui.fonts(|f| f.glyph_width(&font_id, 'M'));
+    let wrap_width = settings.wrap_width(char_width);
+    let word_wrap = settings.word_wrap;
+
+    // Без переноса строки раскладываются в одну линию и прокручиваются по горизонтали
+    let mut no_wrap_layouter = |ui: &egui::Ui, text: &str, _wrap_width: f32| {
+        let color = ui.visuals().override_text_color
+            .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+        let job = egui::text::LayoutJob::simple(text.to_owned(), font_id.clone(), color, f32::INFINITY);
+        ui.fonts(|f| f.layout_job(job))
+    };
+
+    let mut scroll_area = if word_wrap {
+        egui::ScrollArea::vertical()
+    } else {
+        egui::ScrollArea::both()
+    };
+    // Прокрутка, запрошенная щелчком по миникарте; в режиме хвоста текст всегда прокручен вниз
+    if let Some(offset) = ui.data_mut(|d| d.remove_temp::<f32>(editor_id.with("minimap_scroll"))) {
+        scroll_area = scroll_area.vertical_scroll_offset(offset);
+    } else if doc.tail_mode {
+        scroll_area = scroll_area.vertical_scroll_offset(f32::MAX);
+    }
+    let scroll_output = scroll_area
+        .id_source(editor_id)
+        .show(ui, |ui| {
+            let mut editor = egui::TextEdit::multiline(&mut doc.content)
+                .id(editor_id)
+                .font(font_id.clone())
+                .desired_width(wrap_width.unwrap_or(f32::INFINITY))
+                .desired_rows(30)
+                .interactive(!doc.read_only)
+                .lock_focus(true);
+            if !word_wrap {
+                editor = editor.layouter(&mut no_wrap_layouter);
             }
-            if i.consume_key(Modifiers::CTRL, Key::Z) {
-                self.current_document_mut().undo();
+            let output = editor.show(ui);
+            let to_galley = |pos: egui::Pos2| pos - output.galley_pos.to_vec2();
+
+            // Перетаскивание с зажатым Alt задает прямоугольное выделение
+            if output.response.dragged() && ui.input(|i| i.modifiers.alt) {
+                let (origin, current) = ui.input(|i| (i.pointer.press_origin(), i.pointer.interact_pos()));
+                if let (Some(origin), Some(current)) = (origin, current) {
+                    let row_col = |pos: egui::Pos2| {
+                        let index = output.galley.cursor_from_pos(to_galley(pos).to_vec2()).ccursor.index;
+                        doc.offset_to_row_col(doc.char_to_byte(index))
+                    };
+                    let (anchor, active) = (row_col(origin), row_col(current));
+                    doc.set_column_selection(anchor, active);
+                }
+            } else if output.response.clicked() || output.response.drag_started() {
+                doc.clear_column_selection();
             }
-            if i.consume_key(Modifiers::CTRL, Key::Y) {
-                self.current_document_mut().redo();
+
+            // TextEdit сам прокручивает только к курсору, сдвинутому в нем самом
+            if let Some((_, cursor)) = moved_cursor {
+                let rect = output.galley.pos_from_ccursor(CCursor::new(doc.byte_to_char(cursor)));
+                ui.scroll_to_rect(rect.translate(output.galley_pos.to_vec2()), Some(egui::Align::Center));
             }
-        });
 
-        self.show_menu_bar(ctx);
+            let selection_color = ui.visuals().selection.bg_fill.gamma_multiply(0.6);
+            for (start, end) in doc.column_ranges() {
+                let from = output.galley.pos_from_ccursor(CCursor::new(doc.byte_to_char(start)));
+                let to = output.galley.pos_from_ccursor(CCursor::new(doc.byte_to_char(end)));
+                let rect = egui::Rect::from_min_max(from.min, egui::pos2(to.max.x.max(from.min.x + 1.5), from.max.y))
+                    .translate(output.galley_pos.to_vec2());
+                ui.painter().rect_filled(rect, 0.0, selection_color);
+            }
 
-        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
-            self.show_toolbar(ui);
+            // Линейка на колонке переноса и настроенные пользователем линейки
+            if let Some(width) = wrap_width {
+                let x = output.galley_pos.x + width;
+                let stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color().gamma_multiply(0.3));
+                ui.painter().vline(x, output.response.rect.y_range(), stroke);
+            }
+            let ruler_stroke = egui::Stroke::new(1.0, settings.ruler_color);
+            for x in ruler_positions(output.galley_pos.x, &settings.column_rulers, char_width) {
+                ui.painter().vline(x, output.response.rect.y_range(), ruler_stroke);
+            }
+            output
         });
+    let viewport = minimap::Viewport {
+        offset: scroll_output.state.offset.y,
+        visible: scroll_output.inner_rect.height(),
+        content: scroll_output.content_size.y,
+    };
+    ui.data_mut(|d| d.insert_temp(editor_id.with("minimap_viewport"), viewport));
+    if let Some(sync) = scroll_sync {
+        sync.update_from(&scroll_output);
+    }
+    let output = scroll_output.inner;
 
-        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
-            self.show_document_tabs(ui);
-        });
+    if let Some(range) = output.cursor_range {
+        doc.sync_cursor_from_editor(range.primary.ccursor.index, range.secondary.ccursor.index);
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let font_size = self.settings.font_size;
-            let doc = self.current_document_mut();
+    // Обновляем состояние undo/redo после изменений
+    if output.response.changed() {
+        doc.update_last_content();
+    }
+    output
+}
 
-            let response = egui::ScrollArea::vertical()
-                .id_source("text_editor")
-                .show(ui, |ui| {
-                    let text_edit = egui::TextEdit::multiline(&mut doc.content)
-                        .font(FontId::monospace(font_size))
-                        .desired_width(f32::INFINITY)
-                        .desired_rows(30)
-                        .lock_focus(true);
+// Большой документ: в TextEdit попадают только видимые строки с запасом, остальная высота
+// области прокрутки пустая. viewport_line_offset - первая строка показанного фрагмента;
+// при его сдвиге курсор виджета пересчитывается от нового начала фрагмента.
+fn render_large_editor_pane(
+    doc: &mut Document,
+    ui: &mut egui::Ui,
+    editor_id: egui::Id,
+    settings: &AppSettings,
+    viewport_line_offset: &mut usize,
+    scroll_sync: Option<&SyncScroll>,
+) -> egui::text_edit::TextEditOutput {
+    let moved_cursor = doc.take_cursor_update();
+    let font_id = editor_font(ui, settings);
+    let char_width = ui.fonts(|f| f.glyph_width(&font_id, 'M'));
+    let row_height = ui.fonts(|f| f.row_height(&font_id));
+    let total_lines = doc.line_count();
+    let read_only = doc.read_only;
 
-                    ui.add(text_edit)
-                });
+    let mut scroll_area = egui::ScrollArea::both().id_source(editor_id).auto_shrink([false, false]);
+    if let Some(offset) = ui.data_mut(|d| d.remove_temp::<f32>(editor_id.with("minimap_scroll"))) {
+        scroll_area = scroll_area.vertical_scroll_offset(offset);
+    } else if doc.tail_mode {
+        scroll_area = scroll_area.vertical_scroll_offset(f32::MAX);
+    } else if let Some((_, cursor)) = moved_cursor {
+        // Курсор за пределами фрагмента сдвигает фрагмент: прокручиваем к его строке
+        let line = doc.content[..cursor].matches('\n').count();
+        let offset = line as f32 * row_height - ui.available_height() / 2.0;
+        scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+        ui.memory_mut(|m| m.request_focus(editor_id));
+    }
+
+    let mut no_wrap_layouter = |ui: &egui::Ui, text: &str, _wrap_width: f32| {
+        let color = ui.visuals().override_text_color
+            .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+        let job = egui::text::LayoutJob::simple(text.to_owned(), font_id.clone(), color, f32::INFINITY);
+        ui.fonts(|f| f.layout_job(job))
+    };
 
-            // Обновляем состояние undo/redo после изменений
-            if response.inner.changed() {
-                doc.update_last_content();
+    let scroll_output = scroll_area.show_viewport(ui, |ui, viewport| {
+        let origin = ui.min_rect().min;
+        ui.set_min_height(total_lines as f32 * row_height);
+
+        let visible_lines = visible_line_count(viewport.height(), row_height);
+        let fraction = scroll_fraction(viewport.min.y, total_lines as f32 * row_height, viewport.height());
+        let window = line_window(total_lines, fraction, visible_lines);
+        let bytes = doc.line_byte_range(window.clone());
+        let mut text = doc.content[bytes.clone()].to_string();
+
+        // Курсор документа в символах фрагмента; вне фрагмента прижимается к его краю
+        let to_window = |pos: usize| doc.content[bytes.start..pos.clamp(bytes.start, bytes.end)].chars().count();
+        let (anchor, cursor) = doc.anchor_and_cursor();
+        let expected = (to_window(anchor), to_window(cursor));
+        if window.start != *viewport_line_offset || moved_cursor.is_some() {
+            *viewport_line_offset = window.start;
+            let mut state = egui::text_edit::TextEditState::load(ui.ctx(), editor_id).unwrap_or_default();
+            state.cursor.set_char_range(Some(CCursorRange::two(
+                CCursor::new(expected.0),
+                CCursor::new(expected.1),
+            )));
+            state.store(ui.ctx(), editor_id);
+        }
+
+        let rect = egui::Rect::from_min_size(
+            origin + egui::vec2(0.0, window.start as f32 * row_height),
+            egui::vec2(ui.available_width(), window.len() as f32 * row_height),
+        );
+        let output = ui
+            .allocate_ui_at_rect(rect, |ui| {
+                egui::TextEdit::multiline(&mut text)
+                    .id(editor_id)
+                    .font(font_id.clone())
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(window.len())
+                    .margin(egui::Vec2::ZERO)
+                    .frame(false)
+                    .interactive(!read_only)
+                    .lock_focus(true)
+                    .layouter(&mut no_wrap_layouter)
+                    .show(ui)
+            })
+            .inner;
+
+        if output.response.changed() {
+            doc.content.replace_range(bytes.clone(), &text);
+            doc.update_last_content();
+        }
+        if let Some(range) = output.cursor_range {
+            let (primary, secondary) = (range.primary.ccursor.index, range.secondary.ccursor.index);
+            if (secondary, primary) != expected || output.response.changed() {
+                let to_document = |index: usize| {
+                    bytes.start + text.char_indices().nth(index).map_or(text.len(), |(i, _)| i)
+                };
+                doc.sync_cursor_bytes(to_document(primary), to_document(secondary));
+            }
+        }
+
+        let ruler_stroke = egui::Stroke::new(1.0, settings.ruler_color);
+        for x in ruler_positions(output.galley_pos.x, &settings.column_rulers, char_width) {
+            ui.painter().vline(x, ui.clip_rect().y_range(), ruler_stroke);
+        }
+        output
+    });
+
+    let viewport = minimap::Viewport {
+        offset: scroll_output.state.offset.y,
+        visible: scroll_output.inner_rect.height(),
+        content: scroll_output.content_size.y,
+    };
+    ui.data_mut(|d| d.insert_temp(editor_id.with("minimap_viewport"), viewport));
+    if let Some(sync) = scroll_sync {
+        sync.update_from(&scroll_output);
+    }
+    scroll_output.inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Буфер обмена в памяти вместо системного
+    #[derive(Default)]
+    struct MemoryClipboard(String);
+
+    impl ClipboardProvider for MemoryClipboard {
+        fn get_text(&mut self) -> Result<String, arboard::Error> {
+            Ok(self.0.clone())
+        }
+
+        fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+            self.0 = text.to_string();
+            Ok(())
+        }
+    }
+
+    struct UnavailableClipboard;
+
+    impl ClipboardProvider for UnavailableClipboard {
+        fn get_text(&mut self) -> Result<String, arboard::Error> {
+            Err(arboard::Error::ClipboardNotSupported)
+        }
+
+        fn set_text(&mut self, _text: &str) -> Result<(), arboard::Error> {
+            Err(arboard::Error::ClipboardNotSupported)
+        }
+    }
+
+    fn app_with(text: &str) -> TextEditorApp {
+        TextEditorApp {
+            documents: vec![Document::with_content("Тест", text.to_string())],
+            clipboard: Box::new(MemoryClipboard::default()),
+            ..TextEditorApp::default()
+        }
+    }
+
+    #[test]
+    fn copy_then_paste_restores_the_text() {
+        let mut app = app_with("один два");
+        app.current_document_mut().sync_cursor_bytes(0, "один".len());
+        app.copy_text();
+        let end = "один два".len();
+        app.current_document_mut().sync_cursor_bytes(end, end);
+        app.paste_text();
+        assert_eq!(app.current_document().content, "один дваодин");
+        assert_eq!(app.clipboard_history, ["один"]);
+    }
+
+    #[test]
+    fn cut_then_paste_moves_the_text() {
+        let mut app = app_with("один два");
+        app.current_document_mut().sync_cursor_bytes("один".len(), "один два".len());
+        app.cut_text();
+        assert_eq!(app.current_document().content, "один");
+        app.current_document_mut().set_cursor_position(0);
+        app.paste_text();
+        assert_eq!(app.current_document().content, " дваодин");
+    }
+
+    #[test]
+    fn unavailable_clipboard_is_reported_and_keeps_the_text() {
+        let mut app = TextEditorApp { clipboard: Box::new(UnavailableClipboard), ..app_with("текст") };
+        app.current_document_mut().select_all();
+        app.cut_text();
+        assert_eq!(app.current_document().content, "текст");
+        assert!(app.error_message.as_deref().is_some_and(|e| e.starts_with("Не удалось вырезать")));
+        app.paste_text();
+        assert!(app.error_message.as_deref().is_some_and(|e| e.starts_with("Не удалось вставить")));
+    }
+
+    // Кадр, в котором редактор в фокусе и пользователь набрал text
+    fn type_in_editor(app: &mut TextEditorApp, text: &str) -> Vec<egui::Event> {
+        let ctx = egui::Context::default();
+        ctx.begin_frame(egui::RawInput { events: vec![egui::Event::Text(text.to_string())], ..Default::default() });
+        ctx.memory_mut(|m| m.request_focus(app.editor_id()));
+        app.handle_auto_close(&ctx);
+        ctx.input(|i| i.events.clone())
+    }
+
+    #[test]
+    fn auto_close_takes_the_typed_bracket_from_the_editor() {
+        let mut app = app_with("");
+        assert!(type_in_editor(&mut app, "(").is_empty());
+        assert_eq!(app.current_document().content, "()");
+    }
+
+    #[test]
+    fn auto_close_off_leaves_typing_to_the_editor() {
+        let mut app = app_with("");
+        app.settings.auto_close_pairs = false;
+        assert_eq!(type_in_editor(&mut app, "("), [egui::Event::Text("(".to_string())]);
+        assert_eq!(app.current_document().content, "");
+    }
+
+    fn app_with_documents(texts: &[&str]) -> TextEditorApp {
+        TextEditorApp {
+            documents: texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| Document::with_content(&format!("Документ {}", i + 1), text.to_string()))
+                .collect(),
+            clipboard: Box::new(MemoryClipboard::default()),
+            ..TextEditorApp::default()
+        }
+    }
+
+    #[test]
+    fn pinned_document_is_not_closed() {
+        let mut app = app_with_documents(&["один", "два"]);
+        app.documents[1].set_pinned(true);
+        app.active_document = 1;
+        app.close_current_document();
+        assert_eq!(app.documents.len(), 2);
+        assert_eq!(app.active_document, 1);
+
+        app.documents[1].set_pinned(false);
+        app.close_current_document();
+        assert_eq!(app.documents.len(), 1);
+        assert_eq!(app.current_document().content, "один");
+    }
+
+    #[test]
+    fn save_all_writes_only_modified_documents_with_a_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let (edited, untouched) = (dir.path().join("edited.txt"), dir.path().join("untouched.txt"));
+        std::fs::write(&edited, "old").unwrap();
+        std::fs::write(&untouched, "old").unwrap();
+
+        let mut app = app_with_documents(&["без файла"]);
+        app.documents[0].insert_at_cursor("новый ");
+        app.documents.push(Document::load(&edited).unwrap());
+        app.documents.push(Document::load(&untouched).unwrap());
+        app.documents[1].insert_at_cursor("new ");
+        // Изменение на диске покажет, перезаписывался ли нетронутый документ
+        std::fs::write(&untouched, "changed on disk").unwrap();
+
+        assert_eq!(app.save_modified_with_paths(), 1);
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "new old");
+        assert_eq!(std::fs::read_to_string(&untouched).unwrap(), "changed on disk");
+        assert!(!app.documents[1].is_modified());
+        assert!(app.documents[0].is_modified());
+    }
+
+    #[test]
+    fn rulers_are_placed_by_char_width() {
+        assert_eq!(ruler_positions(0.0, &[80, 100], 9.5), [760.0, 950.0]);
+        // Отступ текста от края панели сдвигает все линейки
+        assert_eq!(ruler_positions(12.0, &[80, 100], 9.5), [772.0, 962.0]);
+        assert!(ruler_positions(0.0, &[], 9.5).is_empty());
+    }
+
+    #[test]
+    fn search_result_jump_moves_cursor_to_line_start() {
+        let lines: Vec<String> = (1..=10).map(|n| format!("строка {} {}", n, if n == 1 || n == 10 { "цель" } else { "-" })).collect();
+        let text = lines.join("\n");
+        let mut app = app_with_documents(&["без совпадений", &text]);
+        let results = search::search_documents(&app.documents, "цель", &SearchOptions::default()).unwrap();
+        assert_eq!(results.iter().map(|r| (r.doc_index, r.line_number)).collect::<Vec<_>>(), [(1, 1), (1, 10)]);
+        app.search_results = Some(SearchResultsPanel::new("цель", results));
+
+        app.apply_search_panel_action(PanelAction::Jump(1));
+        assert_eq!(app.active_document, 1);
+        app.apply_jump_to_line();
+        let last_line_start = text.rfind('\n').unwrap() + 1;
+        assert_eq!(app.current_document().cursor_position(), last_line_start);
+        assert!(app.current_document().content[last_line_start..].starts_with("строка 10"));
+
+        app.apply_search_panel_action(PanelAction::Jump(0));
+        app.apply_jump_to_line();
+        assert_eq!(app.current_document().cursor_position(), 0);
+        assert_eq!(app.jump_to_line, None);
+    }
+
+    #[test]
+    fn copying_twice_leaves_one_history_entry() {
+        let mut app = app_with("один два");
+        app.current_document_mut().sync_cursor_bytes(0, "один".len());
+        app.copy_text();
+        app.copy_text();
+        assert_eq!(app.clipboard_history, ["один"]);
+    }
+
+    // Приложение с файлами восстановления в отдельной папке, готовое записать их сразу
+    fn app_with_recovery(dir: &std::path::Path, document: Document) -> TextEditorApp {
+        TextEditorApp {
+            documents: vec![document],
+            recovery_dir: dir.to_path_buf(),
+            last_recovery_write: Instant::now() - RECOVERY_INTERVAL,
+            clipboard: Box::new(MemoryClipboard::default()),
+            ..TextEditorApp::default()
+        }
+    }
+
+    #[test]
+    fn edit_writes_a_recovery_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_recovery(dir.path(), Document::with_content("Тест", String::new()));
+        app.write_recovery_files();
+        assert!(recovery::find_all(dir.path()).is_empty());
+
+        app.current_document_mut().insert_at_cursor("несохраненный текст");
+        app.last_recovery_write = Instant::now() - RECOVERY_INTERVAL;
+        app.write_recovery_files();
+        let path = recovery::recovery_path(dir.path(), 0);
+        assert_eq!(recovery::find_all(dir.path()), [(0, path.clone())]);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "несохраненный текст");
+    }
+
+    #[test]
+    fn recovery_file_is_not_rewritten_within_the_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_recovery(dir.path(), Document::with_content("Тест", String::new()));
+        app.current_document_mut().insert_at_cursor("раз");
+        app.write_recovery_files();
+        app.current_document_mut().insert_at_cursor("два ");
+        app.write_recovery_files();
+        assert_eq!(std::fs::read_to_string(recovery::recovery_path(dir.path(), 0)).unwrap(), "раз");
+    }
+
+    #[test]
+    fn save_deletes_the_recovery_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("документ.txt");
+        std::fs::write(&file, "old").unwrap();
+        let mut app = app_with_recovery(dir.path(), Document::load(&file).unwrap());
+        app.current_document_mut().insert_at_cursor("new ");
+        app.write_recovery_files();
+        assert!(recovery::recovery_path(dir.path(), 0).exists());
+
+        app.save_document();
+        assert!(!recovery::recovery_path(dir.path(), 0).exists());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "new old");
+    }
+
+    #[test]
+    fn tail_mode_picks_up_every_appended_byte() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("service.log");
+        std::fs::write(&path, "").unwrap();
+        let mut app = TextEditorApp { documents: vec![Document::load(&path).unwrap()], ..app_with("") };
+        app.settings.tail_poll_interval_ms = 50;
+        app.toggle_tail_mode();
+        assert!(app.current_document().tail_mode);
+
+        let ctx = egui::Context::default();
+        let mut expected = String::new();
+        let mut log = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        for i in 0..10 {
+            // 100 байт: номер порции, заполнитель и перевод строки
+            let chunk = format!("{:02}{}\n", i, "x".repeat(97));
+            assert_eq!(chunk.len(), 100);
+            log.write_all(chunk.as_bytes()).unwrap();
+            log.flush().unwrap();
+            expected.push_str(&chunk);
+
+            let started = Instant::now();
+            while app.current_document().content.len() < expected.len() && started.elapsed() < Duration::from_secs(5) {
+                app.poll_tail_watchers(&ctx);
+                std::thread::sleep(Duration::from_millis(10));
             }
+            assert_eq!(app.current_document().content, expected);
+        }
+        assert_eq!(app.current_document().tail_file_pos(), 1000);
+        assert!(!app.current_document().is_modified());
+    }
+
+    #[test]
+    fn auto_save_path_is_inside_the_configured_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = auto_save_path(dir.path(), "Заметки: план?");
+        assert_eq!(path, dir.path().join("Заметки_ план_.txt"));
+        assert_eq!(auto_save_path(dir.path(), "  "), dir.path().join("Безымянный.txt"));
+
+        // Существующий файл не перезаписывается
+        std::fs::write(dir.path().join("План.txt"), "").unwrap();
+        assert_eq!(auto_save_path(dir.path(), "План"), dir.path().join("План (2).txt"));
+    }
+
+    #[test]
+    fn auto_save_gives_untitled_documents_a_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Автосохранение");
+        let mut app = app_with_documents(&[""]);
+        app.settings.default_save_directory = Some(folder.clone());
+        app.current_document_mut().insert_at_cursor("черновик");
+        assert_eq!(app.current_document().path(), None);
+
+        app.auto_save_untitled();
+        let path = folder.join("Документ 1.txt");
+        assert_eq!(app.current_document().path(), Some(path.as_path()));
+        assert!(!app.current_document().is_modified());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "черновик");
+        assert!(app.toast.as_ref().is_some_and(|(message, ..)| message.contains("Документ 1.txt")));
+
+        // Следующие правки сохраняются в тот же файл обычным автосохранением
+        app.current_document_mut().insert_at_cursor(" 2");
+        app.auto_save_untitled();
+        assert_eq!(std::fs::read_dir(&folder).unwrap().count(), 1);
+    }
+
+    fn template(name: &str, extension: &str, content: &str) -> DocumentTemplate {
+        DocumentTemplate { name: name.to_string(), extension: extension.to_string(), content: content.to_string() }
+    }
+
+    #[test]
+    fn template_fills_the_new_document() {
+        let mut app = app_with("");
+        app.templates = vec![template("Заметки", "", "# Встреча\n"), template("Класс", "rs", "struct Name;\n")];
+
+        app.create_document(Some(1));
+        assert_eq!(app.active_document, 1);
+        assert_eq!(app.current_document().title(), "Безымянный 2.rs");
+        assert_eq!(app.current_document().content, "struct Name;\n");
+
+        app.create_document(Some(0));
+        assert_eq!(app.current_document().title(), "Безымянный 3");
+        assert_eq!(app.current_document().content, "# Встреча\n");
+
+        app.create_document(None);
+        assert_eq!(app.current_document().content, "");
+    }
+
+    #[test]
+    fn last_template_is_remembered_per_extension() {
+        let mut app = app_with("");
+        app.templates = vec![template("Заметки", "", ""), template("Класс", "rs", ""), template("Тест", "rs", "")];
+        app.settings.last_templates.insert("rs".to_string(), "Тест".to_string());
+        app.settings.last_templates.insert(String::new(), "Класс".to_string());
+        assert_eq!(app.last_template(Some("rs")), Some(2));
+        assert_eq!(app.last_template(None), Some(1));
+        // Запомненный шаблон не подходит под фильтр
+        app.settings.last_templates.insert("py".to_string(), "Тест".to_string());
+        assert_eq!(app.last_template(Some("py")), None);
+    }
+
+    fn geometry(width: f32) -> WindowGeometry {
+        WindowGeometry { window_width: width, window_height: 700.0, window_x: Some(10), window_y: Some(20) }
+    }
+
+    #[test]
+    fn window_size_is_saved_after_the_debounce_period() {
+        let mut app = app_with("");
+        let start = Instant::now();
+        assert!(!app.update_window_geometry(geometry(1024.0), start));
+        assert!(!app.update_window_geometry(geometry(1024.0), start + WINDOW_GEOMETRY_DEBOUNCE / 2));
+        assert_ne!(app.settings.window_width, 1024.0);
+
+        assert!(app.update_window_geometry(geometry(1024.0), start + WINDOW_GEOMETRY_DEBOUNCE));
+        assert_eq!(app.settings.window_width, 1024.0);
+        assert_eq!(app.settings.window_geometry(), geometry(1024.0));
+    }
+
+    #[test]
+    fn resizing_again_restarts_the_debounce_period() {
+        let mut app = app_with("");
+        let start = Instant::now();
+        app.update_window_geometry(geometry(1024.0), start);
+        assert!(!app.update_window_geometry(geometry(1100.0), start + WINDOW_GEOMETRY_DEBOUNCE));
+        assert_ne!(app.settings.window_width, 1100.0);
+        assert!(app.update_window_geometry(geometry(1100.0), start + WINDOW_GEOMETRY_DEBOUNCE * 2));
+        assert_eq!(app.settings.window_width, 1100.0);
+        // Та же геометрия, что в настройках, не запускает ожидание
+        assert!(!app.update_window_geometry(geometry(1100.0), start + WINDOW_GEOMETRY_DEBOUNCE * 3));
+        assert!(app.observed_window_geometry.is_none());
+    }
+
+    #[test]
+    fn deferred_settings_reach_the_app_before_the_second_frame() {
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let loader = std::thread::spawn(move || {
+            // Медленный диск: чтение заканчивается только после первого кадра
+            let _ = wait.recv();
+            AppSettings { font_size: 22.0, ..AppSettings::default() }
         });
+        let mut app = TextEditorApp { settings_loader: Some(loader), ..app_with("") };
+        let ctx = egui::Context::default();
+
+        ctx.begin_frame(egui::RawInput::default());
+        app.load_deferred_settings(&ctx);
+        assert!(app.settings_loader.is_some());
+        assert_eq!(app.settings.font_size, AppSettings::default().font_size);
+        let _ = ctx.end_frame();
+
+        release.send(()).unwrap();
+        ctx.begin_frame(egui::RawInput::default());
+        app.load_deferred_settings(&ctx);
+        assert!(app.settings_loader.is_none());
+        assert_eq!(app.settings.font_size, 22.0);
+        assert!(!app.settings_dirty);
+        let _ = ctx.end_frame();
+    }
+
+    #[test]
+    fn font_family_setting_changes_the_editor_font() {
+        let families = [egui::FontFamily::Proportional, egui::FontFamily::Monospace];
+        let mut settings = AppSettings { font_size: 18.0, ..AppSettings::default() };
+        settings.font_family = FontFamily::Monospace;
+        assert_eq!(editor_font_id(&settings, &families), FontId::new(18.0, egui::FontFamily::Monospace));
+        settings.font_family = FontFamily::Proportional;
+        assert_eq!(editor_font_id(&settings, &families), FontId::new(18.0, egui::FontFamily::Proportional));
+    }
+
+    #[test]
+    fn font_from_file_falls_back_until_registered() {
+        let settings = AppSettings { font_family: FontFamily::Custom("шрифт.ttf".into()), ..AppSettings::default() };
+        let families = [egui::FontFamily::Proportional, egui::FontFamily::Monospace];
+        assert_eq!(editor_font_id(&settings, &families).family, egui::FontFamily::Monospace);
+        let registered = [egui::FontFamily::Monospace, egui::FontFamily::Name(CUSTOM_FONT_FAMILY.into())];
+        assert_eq!(editor_font_id(&settings, &registered).family, egui::FontFamily::Name(CUSTOM_FONT_FAMILY.into()));
+    }
 
-        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            self.show_status_bar(ui);
+    // Кадр разделенного вида с заданной прокруткой панелей; возвращает id их областей прокрутки
+    fn render_split_frame(app: &mut TextEditorApp, ctx: &egui::Context, left: Option<f32>, right: Option<f32>) -> (egui::Id, egui::Id) {
+        let split = app.split_view.unwrap();
+        let (left_id, right_id) = (app.editor_id(), TextEditorApp::split_editor_id(split));
+        for (id, offset) in [(left_id, left), (right_id, right)] {
+            if let Some(offset) = offset {
+                ctx.data_mut(|d| d.insert_temp(id.with("minimap_scroll"), offset));
+            }
+        }
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+        ctx.begin_frame(egui::RawInput { screen_rect: Some(screen), ..Default::default() });
+        let mut scroll_ids = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.columns(2, |columns| {
+                // ScrollArea::id_source оборачивает источник в Id::new
+                let scroll_id = |ui: &egui::Ui, id| ui.make_persistent_id(egui::Id::new(id));
+                scroll_ids = Some((scroll_id(&columns[0], left_id), scroll_id(&columns[1], right_id)));
+                let active = app.active_document;
+                render_editor_pane(&mut app.documents[active], &mut columns[0], left_id, &app.settings, &mut app.viewport_line_offset, None);
+                render_editor_pane(&mut app.documents[split], &mut columns[1], right_id, &app.settings, &mut app.split_viewport_line_offset, None);
+            });
         });
+        let _ = ctx.end_frame();
+        scroll_ids.unwrap()
+    }
 
-        self.show_find_replace_dialog(ctx);
-        self.show_settings_dialog(ctx);
-        self.show_stats_dialog(ctx);
-        self.show_error_dialog(ctx);
+    #[test]
+    fn split_panes_keep_independent_scroll_positions() {
+        let text = (1..=300).map(|n| format!("строка {}", n)).collect::<Vec<_>>().join("\n");
+        let mut app = app_with_documents(&[&text, &text]);
+        app.toggle_split_view();
+        assert_eq!(app.split_view, Some(1));
+        let ctx = egui::Context::default();
+        let offset = |id| egui::scroll_area::State::load(&ctx, id).unwrap().offset.y;
+
+        let (left, right) = render_split_frame(&mut app, &ctx, Some(100.0), Some(400.0));
+        assert_eq!((offset(left), offset(right)), (100.0, 400.0));
+
+        // Прокрутка одной панели не сдвигает другую
+        render_split_frame(&mut app, &ctx, Some(250.0), None);
+        assert_eq!((offset(left), offset(right)), (250.0, 400.0));
+        render_split_frame(&mut app, &ctx, None, Some(0.0));
+        assert_eq!((offset(left), offset(right)), (250.0, 0.0));
     }
 
-    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
-        let _ = self.settings.save();
+    #[test]
+    fn distraction_free_hides_all_four_panels() {
+        let mut app = app_with("");
+        let all = PanelVisibility { menu_bar: true, toolbar: true, tabs: true, status_bar: true };
+        assert_eq!(app.visible_panels(false), all);
+
+        app.distraction_free = true;
+        let none = PanelVisibility { menu_bar: false, toolbar: false, tabs: false, status_bar: false };
+        assert_eq!(app.visible_panels(false), none);
+        // Наведение на полосу у верхнего края открывает только меню
+        assert_eq!(app.visible_panels(true), PanelVisibility { menu_bar: true, ..none });
+    }
+
+    #[test]
+    fn dropped_files_open_in_new_tabs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first, second) = (dir.path().join("один.txt"), dir.path().join("два.txt"));
+        std::fs::write(&first, "первый").unwrap();
+        std::fs::write(&second, "второй").unwrap();
+        let mut app = app_with("");
+
+        let ctx = egui::Context::default();
+        let dropped = [&first, &second].map(|path| egui::DroppedFile { path: Some(path.to_path_buf()), ..Default::default() });
+        // Файл без пути (например, из браузера) пропускается
+        let input = egui::RawInput { dropped_files: [dropped.to_vec(), vec![egui::DroppedFile::default()]].concat(), ..Default::default() };
+        ctx.begin_frame(input);
+        app.handle_dropped_files(&ctx);
+        let _ = ctx.end_frame();
+
+        assert_eq!(app.documents.len(), 3);
+        assert_eq!(app.active_document, 2);
+        assert_eq!(app.documents[1].content, "первый");
+        assert_eq!(app.current_document().content, "второй");
     }
-}
\ No newline at end of file
+}
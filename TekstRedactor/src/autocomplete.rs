@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// Слова короче этого порога не предлагаются: их быстрее допечатать
+const MIN_WORD_LEN: usize = 4;
+const MIN_PREFIX_LEN: usize = 2;
+
+pub struct AutocompleteEngine {
+    words: BTreeSet<String>,
+    pending: Option<Receiver<BTreeSet<String>>>,
+}
+
+impl Default for AutocompleteEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutocompleteEngine {
+    pub fn new() -> Self {
+        Self {
+            words: BTreeSet::new(),
+            pending: None,
+        }
+    }
+
+    pub fn index_words(content: &str) -> BTreeSet<String> {
+        content
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|word| word.chars().count() >= MIN_WORD_LEN)
+            .map(|word| word.to_string())
+            .collect()
+    }
+
+    // Переиндексация выполняется в фоновом потоке, чтобы не блокировать интерфейс
+    pub fn request_reindex(&mut self, content: String) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(Self::index_words(&content));
+        });
+        // Результат предыдущего запроса больше не нужен
+        self.pending = Some(receiver);
+    }
+
+    // Забирает готовый индекс, если фоновый поток уже закончил
+    pub fn poll(&mut self) {
+        if let Some(receiver) = &self.pending {
+            match receiver.try_recv() {
+                Ok(words) => {
+                    self.words = words;
+                    self.pending = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+    }
+
+    pub fn suggest(&self, prefix: &str, max: usize) -> Vec<String> {
+        if prefix.chars().count() < MIN_PREFIX_LEN {
+            return Vec::new();
+        }
+
+        self.words
+            .range(prefix.to_string()..)
+            .take_while(|word| word.starts_with(prefix))
+            .filter(|word| word.as_str() != prefix)
+            .take(max)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(content: &str) -> AutocompleteEngine {
+        let mut engine = AutocompleteEngine::new();
+        engine.words = AutocompleteEngine::index_words(content);
+        engine
+    }
+
+    #[test]
+    fn suggests_words_with_the_prefix_in_order() {
+        let engine = engine("configure config_path, confirm; cat conference");
+        assert_eq!(engine.suggest("conf", 10), ["conference", "config_path", "configure", "confirm"]);
+        assert_eq!(engine.suggest("config", 1), ["config_path"]);
+    }
+
+    #[test]
+    fn short_prefixes_and_exact_words_are_not_suggested() {
+        let engine = engine("word words wordy");
+        assert!(engine.suggest("w", 10).is_empty());
+        assert_eq!(engine.suggest("word", 10), ["words", "wordy"]);
+        assert!(engine.suggest("xyz", 10).is_empty());
+    }
+
+    #[test]
+    fn short_words_are_not_indexed() {
+        let engine = engine("the cat sat on матрасе");
+        assert!(engine.suggest("ca", 10).is_empty());
+        assert_eq!(engine.suggest("ма", 10), ["матрасе"]);
+    }
+
+    #[test]
+    fn background_reindex_replaces_the_words() {
+        let mut engine = engine("oldword");
+        engine.request_reindex("newword".to_string());
+        while engine.pending.is_some() {
+            engine.poll();
+        }
+        assert!(engine.suggest("ol", 10).is_empty());
+        assert_eq!(engine.suggest("ne", 10), ["newword"]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+
+// Сколько скопированных фрагментов хранит история буфера обмена
+pub const CLIPBOARD_HISTORY_CAPACITY: usize = 20;
+
+// Добавляет фрагмент в начало истории; повтор переносится наверх, а не дублируется
+pub fn push_history(history: &mut VecDeque<String>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    history.retain(|entry| entry != text);
+    history.push_front(text.to_string());
+    history.truncate(CLIPBOARD_HISTORY_CAPACITY);
+}
+
+// Доступ к системному буферу обмена через трейт, чтобы его можно было подменить
+pub trait ClipboardProvider {
+    fn get_text(&mut self) -> Result<String, arboard::Error>;
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error>;
+}
+
+// Системный буфер обмена; подключение создается при первом обращении
+#[derive(Default)]
+pub struct SystemClipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    fn clipboard(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        let clipboard = match self.inner.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new()?,
+        };
+        Ok(self.inner.insert(clipboard))
+    }
+}
+
+impl ClipboardProvider for SystemClipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        self.clipboard()?.get_text()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        self.clipboard()?.set_text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copying_the_same_text_twice_keeps_one_entry() {
+        let mut history = VecDeque::new();
+        push_history(&mut history, "один");
+        push_history(&mut history, "один");
+        assert_eq!(history, ["один"]);
+    }
+
+    #[test]
+    fn repeated_text_moves_to_the_top() {
+        let mut history = VecDeque::new();
+        for text in ["один", "два", "один"] {
+            push_history(&mut history, text);
+        }
+        assert_eq!(history, ["один", "два"]);
+    }
+
+    #[test]
+    fn empty_text_is_not_recorded() {
+        let mut history = VecDeque::new();
+        push_history(&mut history, "");
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn history_keeps_the_newest_entries() {
+        let mut history = VecDeque::new();
+        for i in 0..CLIPBOARD_HISTORY_CAPACITY + 5 {
+            push_history(&mut history, &i.to_string());
+        }
+        assert_eq!(history.len(), CLIPBOARD_HISTORY_CAPACITY);
+        assert_eq!(history.front(), Some(&(CLIPBOARD_HISTORY_CAPACITY + 4).to_string()));
+    }
+}
@@ -0,0 +1,220 @@
+use eframe::egui::{self, Color32, RichText};
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowKind {
+    Equal,
+    Deleted,
+    Inserted,
+    Changed,
+}
+
+// Строка сравнения: номер и текст слева и справа, пустая сторона - None
+#[derive(Debug, Clone)]
+pub struct DiffRow {
+    pub kind: RowKind,
+    pub left: Option<(usize, String)>,
+    pub right: Option<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffSummary {
+    pub deleted: usize,
+    pub inserted: usize,
+    pub changed: usize,
+}
+
+fn line_text(line: &str) -> String {
+    line.trim_end_matches(['\n', '\r']).to_string()
+}
+
+// Построчное сравнение, выровненное для показа двух колонок рядом
+pub fn diff_rows(left: &str, right: &str) -> Vec<DiffRow> {
+    let diff = TextDiff::from_lines(left, right);
+    let old = diff.old_slices();
+    let new = diff.new_slices();
+    let mut rows = Vec::new();
+
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let len = old_range.len().max(new_range.len());
+        for offset in 0..len {
+            let left = (offset < old_range.len()).then(|| {
+                let index = old_range.start + offset;
+                (index + 1, line_text(old[index]))
+            });
+            let right = (offset < new_range.len()).then(|| {
+                let index = new_range.start + offset;
+                (index + 1, line_text(new[index]))
+            });
+            let kind = match (tag, &left, &right) {
+                (DiffTag::Equal, _, _) => RowKind::Equal,
+                (_, Some(_), Some(_)) => RowKind::Changed,
+                (_, Some(_), None) => RowKind::Deleted,
+                _ => RowKind::Inserted,
+            };
+            rows.push(DiffRow { kind, left, right });
+        }
+    }
+
+    rows
+}
+
+// Строка однострочного сравнения: без пары левой и правой стороны, как в unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineDiff {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+pub fn line_diff(old: &str, new: &str) -> Vec<LineDiff> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| {
+            let text = line_text(change.value());
+            match change.tag() {
+                ChangeTag::Equal => LineDiff::Unchanged(text),
+                ChangeTag::Insert => LineDiff::Added(text),
+                ChangeTag::Delete => LineDiff::Removed(text),
+            }
+        })
+        .collect()
+}
+
+// Число добавленных и удаленных строк
+pub fn count_changes(lines: &[LineDiff]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(added, removed), line| match line {
+        LineDiff::Unchanged(_) => (added, removed),
+        LineDiff::Added(_) => (added + 1, removed),
+        LineDiff::Removed(_) => (added, removed + 1),
+    })
+}
+
+pub fn summarize(rows: &[DiffRow]) -> DiffSummary {
+    let mut summary = DiffSummary::default();
+    for row in rows {
+        match row.kind {
+            RowKind::Equal => {}
+            RowKind::Deleted => summary.deleted += 1,
+            RowKind::Inserted => summary.inserted += 1,
+            RowKind::Changed => summary.changed += 1,
+        }
+    }
+    summary
+}
+
+fn side_colors(kind: RowKind, is_left: bool, present: bool) -> (Color32, Option<Color32>) {
+    if !present {
+        return (Color32::GRAY, Some(Color32::from_gray(128).gamma_multiply(0.15)));
+    }
+    match kind {
+        RowKind::Equal => (Color32::GRAY, None),
+        RowKind::Deleted => (Color32::from_rgb(200, 60, 60), Some(Color32::from_rgb(200, 60, 60).gamma_multiply(0.15))),
+        RowKind::Inserted => (Color32::from_rgb(40, 160, 60), Some(Color32::from_rgb(40, 160, 60).gamma_multiply(0.15))),
+        // Измененная строка: слева как удаленная, справа как добавленная
+        RowKind::Changed => side_colors(if is_left { RowKind::Deleted } else { RowKind::Inserted }, is_left, true),
+    }
+}
+
+fn show_side(ui: &mut egui::Ui, row: &DiffRow, is_left: bool, width: f32, height: f32, font_size: f32) {
+    let side = if is_left { &row.left } else { &row.right };
+    let (color, background) = side_colors(row.kind, is_left, side.is_some());
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+    if let Some(background) = background {
+        ui.painter().rect_filled(rect, 0.0, background);
+    }
+    if let Some((number, text)) = side {
+        let mut child = ui.child_ui(rect, egui::Layout::left_to_right(egui::Align::Center));
+        child.add_sized(
+            [font_size * 3.0, height],
+            egui::Label::new(RichText::new(number.to_string()).monospace().size(font_size).weak()),
+        );
+        child.add(egui::Label::new(RichText::new(text).monospace().size(font_size).color(color)).truncate(true));
+    }
+}
+
+pub fn render_line_diff(ui: &mut egui::Ui, lines: &[LineDiff], font_size: f32) {
+    let row_height = ui.fonts(|f| f.row_height(&egui::FontId::monospace(font_size)));
+    ui.spacing_mut().item_spacing.y = 0.0;
+    egui::ScrollArea::vertical()
+        .id_source("line_diff_scroll")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, lines.len(), |ui, range| {
+            for line in &lines[range] {
+                let (text, color) = match line {
+                    LineDiff::Unchanged(text) => (format!("  {}", text), Color32::GRAY),
+                    LineDiff::Added(text) => (format!("+ {}", text), Color32::from_rgb(40, 160, 60)),
+                    LineDiff::Removed(text) => (format!("- {}", text), Color32::from_rgb(200, 60, 60)),
+                };
+                ui.add(egui::Label::new(RichText::new(text).monospace().size(font_size).color(color)).truncate(true));
+            }
+        });
+}
+
+// Две колонки в одной области прокрутки, поэтому стороны прокручиваются вместе
+pub fn render_diff_view(ui: &mut egui::Ui, rows: &[DiffRow], font_size: f32) {
+    let row_height = ui.fonts(|f| f.row_height(&egui::FontId::monospace(font_size)));
+    ui.spacing_mut().item_spacing.y = 0.0;
+    egui::ScrollArea::vertical()
+        .id_source("diff_view_scroll")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, rows.len(), |ui, range| {
+            let half = (ui.available_width() - ui.spacing().item_spacing.x) / 2.0;
+            for row in &rows[range] {
+                ui.horizontal(|ui| {
+                    show_side(ui, row, true, half, row_height, font_size);
+                    show_side(ui, row, false, half, row_height, font_size);
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_text_is_unchanged() {
+        let text = "one\ntwo\r\nthree";
+        let lines = line_diff(text, text);
+        assert_eq!(
+            lines,
+            [
+                LineDiff::Unchanged("one".to_string()),
+                LineDiff::Unchanged("two".to_string()),
+                LineDiff::Unchanged("three".to_string()),
+            ]
+        );
+        assert_eq!(count_changes(&lines), (0, 0));
+    }
+
+    #[test]
+    fn additions_and_deletions_are_classified() {
+        let lines = line_diff("one\ntwo\nthree\n", "one\nтри\nthree\nfour\n");
+        assert_eq!(
+            lines,
+            [
+                LineDiff::Unchanged("one".to_string()),
+                LineDiff::Removed("two".to_string()),
+                LineDiff::Added("три".to_string()),
+                LineDiff::Unchanged("three".to_string()),
+                LineDiff::Added("four".to_string()),
+            ]
+        );
+        assert_eq!(count_changes(&lines), (2, 1));
+    }
+
+    #[test]
+    fn diff_rows_pair_changed_lines() {
+        let rows = diff_rows("a\nb\nc\n", "a\nB\nc\nd\n");
+        let kinds: Vec<RowKind> = rows.iter().map(|row| row.kind).collect();
+        assert_eq!(kinds, [RowKind::Equal, RowKind::Changed, RowKind::Equal, RowKind::Inserted]);
+        assert_eq!(rows[1].left, Some((2, "b".to_string())));
+        assert_eq!(rows[1].right, Some((2, "B".to_string())));
+        assert_eq!(rows[3].left, None);
+
+        let summary = summarize(&rows);
+        assert_eq!((summary.deleted, summary.inserted, summary.changed), (0, 1, 1));
+    }
+}
@@ -1,5 +1,14 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::sync::OnceLock;
+
+use crate::diff::{self, LineDiff};
+use crate::markdown;
 
 #[derive(Debug, Clone)]
 pub struct Document {
@@ -7,6 +16,8 @@ pub struct Document {
     pub content: String,
     path: Option<PathBuf>,
     modified: bool,
+    // Pinned tabs can't be closed until unpinned
+    pinned: bool,
 
     // Undo/Redo
     undo_stack: VecDeque<String>,
@@ -23,6 +34,235 @@ pub struct Document {
     // Cursor position
     cursor_position: usize,
     selection: Option<(usize, usize)>,
+    // Set when the cursor was moved by the document itself and the editor widget must follow
+    cursor_changed: bool,
+
+    // Content as of the last editor frame, used to detect edits made by the text widget
+    last_content: String,
+    // Incremented on every content change so views can cache derived data
+    generation: u64,
+    // Content as of the last successful save or load; None for a never-saved document
+    saved_content: Option<String>,
+
+    // Line ending found on load; updated when the user converts the document
+    pub detected_line_ending: LineEnding,
+
+    // Rectangular selection: (anchor row, anchor column, active row, active column), columns in chars
+    column_selection: Option<(usize, usize, usize, usize)>,
+
+    // Named snapshots: (label, content)
+    checkpoints: Vec<(String, String)>,
+
+    // Tail mode follows a growing file: bytes appended on disk are appended here;
+    // tail_file_pos is how much of the file has been read
+    pub tail_mode: bool,
+    tail_file_pos: u64,
+
+    // Opened for reference: the editor ignores input, and undo/redo and clipboard edits do nothing
+    pub read_only: bool,
+
+    // Bookmarked lines; metadata only, so undo/redo leaves them alone
+    bookmarks: Vec<Bookmark>,
+
+    // Failure of the last text transformation, shown and cleared by the UI
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    // 1-based, like cursor_line
+    pub line: usize,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+    Mixed,
+}
+
+impl LineEnding {
+    pub fn all() -> [LineEnding; 3] {
+        [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+            LineEnding::Mixed => "Смешанные",
+        }
+    }
+
+    fn sequence(&self) -> Option<&'static str> {
+        match self {
+            LineEnding::Lf => Some("\n"),
+            LineEnding::CrLf => Some("\r\n"),
+            LineEnding::Cr => Some("\r"),
+            LineEnding::Mixed => None,
+        }
+    }
+
+    // Detects the line ending from the first 4 KB of text
+    pub fn detect(text: &str) -> Self {
+        let bytes = &text.as_bytes()[..text.len().min(4096)];
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    crlf += 1;
+                    i += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+            i += 1;
+        }
+
+        match (lf > 0, crlf > 0, cr > 0) {
+            (_, true, false) if lf == 0 => LineEnding::CrLf,
+            (false, false, true) => LineEnding::Cr,
+            (_, false, false) => LineEnding::Lf,
+            _ => LineEnding::Mixed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentMode {
+    Indent,
+    Outdent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseMode {
+    Upper,
+    Lower,
+    Title,
+}
+
+impl CaseMode {
+    pub fn all() -> [CaseMode; 3] {
+        [CaseMode::Upper, CaseMode::Lower, CaseMode::Title]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CaseMode::Upper => "ВЕРХНИЙ РЕГИСТР",
+            CaseMode::Lower => "нижний регистр",
+            CaseMode::Title => "Каждое Слово С Заглавной",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            CaseMode::Upper => text.to_uppercase(),
+            CaseMode::Lower => text.to_lowercase(),
+            CaseMode::Title => {
+                let mut result = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric();
+                }
+                result
+            }
+        }
+    }
+}
+
+// Delimiter pairs offered for wrapping a selection
+pub const WRAP_DELIMITERS: [(&str, &str); 7] = [
+    ("\"", "\""),
+    ("'", "'"),
+    ("(", ")"),
+    ("[", "]"),
+    ("{", "}"),
+    ("<", ">"),
+    ("**", "**"),
+];
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Closing character for brackets and quotes that are closed automatically
+fn closing_pair(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+fn is_closing_char(c: char) -> bool {
+    matches!(c, ')' | ']' | '}' | '"' | '\'')
+}
+
+// How much of a file is inspected when guessing whether it is binary
+pub const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+// Control bytes 0x00-0x08 are common in binary formats but practically absent from text
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_SNIFF_LEN).any(|&b| b <= 0x08)
+}
+
+// Rewrites leading whitespace with the same visual width using only spaces, or tabs
+// padded with spaces when the width is not a multiple of tab_size
+fn normalize_indent(indent: &str, indent_with_spaces: bool, tab_size: u8) -> String {
+    let tab_size = tab_size.max(1) as usize;
+    let width = indent.chars().fold(0, |width, c| if c == '\t' { (width / tab_size + 1) * tab_size } else { width + 1 });
+    if indent_with_spaces {
+        " ".repeat(width)
+    } else {
+        format!("{}{}", "\t".repeat(width / tab_size), " ".repeat(width % tab_size))
+    }
+}
+
+// Converts every line ending to "\n"
+fn to_lf(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+// Lines laid out above and below the visible part of a large document
+pub const LINE_WINDOW_MARGIN: usize = 50;
+
+// Lines to lay out when `visible_lines` lines are on screen and the view is scrolled
+// to `scroll_fraction` of the document (0.0 - top, 1.0 - bottom)
+pub fn line_window(total_lines: usize, scroll_fraction: f32, visible_lines: usize) -> Range<usize> {
+    let hidden = total_lines.saturating_sub(visible_lines);
+    let first_visible = (scroll_fraction.clamp(0.0, 1.0) * hidden as f32).round() as usize;
+    let start = first_visible.saturating_sub(LINE_WINDOW_MARGIN);
+    let end = (first_visible + visible_lines + LINE_WINDOW_MARGIN).min(total_lines);
+    start..end
+}
+
+// Lines that fit in the viewport, plus one cut off at the bottom edge
+pub fn visible_line_count(viewport_height: f32, row_height: f32) -> usize {
+    (viewport_height / row_height).ceil() as usize + 1
+}
+
+// Share of the scrollable distance above the viewport; 0.0 when the whole text fits
+pub fn scroll_fraction(scroll_offset: f32, content_height: f32, viewport_height: f32) -> f32 {
+    let max_offset = (content_height - viewport_height).max(0.0);
+    if max_offset > 0.0 {
+        scroll_offset / max_offset
+    } else {
+        0.0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +273,274 @@ pub struct DocumentStats {
     pub characters_no_spaces: usize,
     pub lines: usize,
     pub paragraphs: usize,
+    pub sentences: usize,
+    pub unique_words: usize,
+    pub flesch_reading_ease: f32,
+    pub flesch_kincaid_grade: f32,
+    // Share of sentences in the passive voice (English heuristic), 0.0 - 1.0
+    pub passive_voice_ratio: f32,
+    pub avg_sentence_length: f32,
+    pub reading_time_seconds: u64,
+    pub speaking_time_seconds: u64,
+}
+
+// Whole seconds needed to get through `words` at `wpm`; any text takes at least 1 s
+fn time_at_pace(words: usize, wpm: u64) -> u64 {
+    if words == 0 {
+        return 0;
+    }
+    (words as u64 * 60 / wpm).max(1)
+}
+
+// Formats a duration as "1 ч 2 мин 3 с", omitting zero parts
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let mut parts = Vec::new();
+    if hours > 0 {
+        parts.push(format!("{} ч", hours));
+    }
+    if minutes > 0 {
+        parts.push(format!("{} мин", minutes));
+    }
+    if seconds > 0 || parts.is_empty() {
+        parts.push(format!("{} с", seconds));
+    }
+    parts.join(" ")
+}
+
+impl DocumentStats {
+    pub fn for_text(text: &str) -> Self {
+        let characters = text.chars().count();
+        let characters_no_spaces = text.chars().filter(|c| !c.is_whitespace()).count();
+        let words = text.split_whitespace().count();
+        let lines = text.lines().count();
+        let paragraphs = text.split("\n\n").count();
+
+        // Estimate pages (assuming ~500 words per page)
+        let pages = (words as f32 / 500.0).ceil() as usize;
+
+        let (flesch_reading_ease, flesch_kincaid_grade) = readability(text, words);
+        let (passive_voice_ratio, avg_sentence_length) = passive_voice_stats(text, words);
+
+        Self {
+            pages,
+            words,
+            characters,
+            characters_no_spaces,
+            lines,
+            paragraphs,
+            sentences: count_sentences(text),
+            unique_words: unique_word_count(text),
+            flesch_reading_ease,
+            flesch_kincaid_grade,
+            passive_voice_ratio,
+            avg_sentence_length,
+            // Average adult pace: 200 wpm silently, 130 wpm aloud
+            reading_time_seconds: time_at_pace(words, 200),
+            speaking_time_seconds: time_at_pace(words, 130),
+        }
+    }
+
+    // Share of the word count target reached; may exceed 1.0 once the target is passed
+    pub fn target_progress(&self, target: usize) -> f32 {
+        if target == 0 {
+            return 1.0;
+        }
+        self.words as f32 / target as f32
+    }
+}
+
+// Heuristic syllable count: vowel groups, minus a silent trailing 'e'
+pub fn count_syllables(word: &str) -> usize {
+    let word: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if word.is_empty() {
+        return 0;
+    }
+
+    let is_vowel = |c: char| "aeiouyаеёиоуыэюя".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &word {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // "make", "note": the final 'e' is silent, but "the", "be" and "table" keep it
+    let len = word.len();
+    if count > 1 && word[len - 1] == 'e' && !is_vowel(word[len - 2]) {
+        let ends_with_le = word[len - 2] == 'l' && len > 2 && !is_vowel(word[len - 3]);
+        if !ends_with_le {
+            count -= 1;
+        }
+    }
+
+    count.max(1)
+}
+
+// Where the sentence scanner is relative to the last sentence end
+#[derive(Clone, Copy, PartialEq)]
+enum SentenceState {
+    // No letters or digits since the last sentence ended
+    Empty,
+    InSentence,
+    // Just after a run of terminators and closing quotes
+    Terminated { only_dots: bool, abbreviation: bool },
+}
+
+// Byte offsets where sentences end: ".", "!", "?" runs followed by whitespace or the
+// end of text. A dot directly followed by a non-space ("3.14", "e.g") never ends a
+// sentence; a dot after an abbreviation ("Dr. Smith", "A. Pushkin") or before a lowercase
+// word ("etc. and") doesn't either
+fn sentence_ends(text: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut state = SentenceState::Empty;
+    let mut token_start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        state = match state {
+            SentenceState::Terminated { only_dots, abbreviation } if c.is_whitespace() => {
+                while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                let next_lower = chars.peek().is_some_and(|(_, c)| c.is_lowercase());
+                if only_dots && (abbreviation || next_lower) {
+                    SentenceState::InSentence
+                } else {
+                    ends.push(i);
+                    SentenceState::Empty
+                }
+            }
+            SentenceState::Terminated { only_dots, abbreviation } if matches!(c, '.' | '!' | '?') => {
+                SentenceState::Terminated { only_dots: only_dots && c == '.', abbreviation }
+            }
+            SentenceState::Terminated { .. } if matches!(c, '"' | '\'' | ')' | ']' | '»' | '”') => state,
+            SentenceState::InSentence if matches!(c, '.' | '!' | '?') => SentenceState::Terminated {
+                only_dots: c == '.',
+                abbreviation: c == '.' && is_abbreviation(&text[token_start..=i]),
+            },
+            _ if c.is_alphanumeric() => SentenceState::InSentence,
+            SentenceState::Terminated { .. } => SentenceState::InSentence,
+            _ => state,
+        };
+
+        if c.is_whitespace() {
+            token_start = i + c.len_utf8();
+        }
+    }
+
+    if matches!(state, SentenceState::Terminated { .. }) {
+        ends.push(text.len());
+    }
+    ends
+}
+
+pub fn count_sentences(text: &str) -> usize {
+    sentence_ends(text).len()
+}
+
+// Sentences of the text, including an unterminated one at the end
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut start = 0;
+    let mut sentences = Vec::new();
+    for end in sentence_ends(text).into_iter().chain([text.len()]) {
+        let sentence = text[start..end].trim();
+        if sentence.chars().any(char::is_alphanumeric) {
+            sentences.push(sentence);
+        }
+        start = end;
+    }
+    sentences
+}
+
+// A word ending in an abbreviation dot, after which a line should not be broken: a known
+// title or Latin abbreviation ("Dr.", "e.g."), a single capital ("J.") or dotted pairs of
+// lowercase letters ("a.m.")
+fn is_abbreviation(word: &str) -> bool {
+    static KNOWN: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    let known = KNOWN.get_or_init(|| HashSet::from(["Dr", "Mr", "Mrs", "e.g", "i.e", "vs"]));
+    let Some(token) = word.trim_start_matches(|c: char| !c.is_alphanumeric()).strip_suffix('.') else {
+        return false;
+    };
+    let mut chars = token.chars();
+    let single_capital = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase());
+    let dotted_lowercase = token.len() == 3 && {
+        let bytes = token.as_bytes();
+        bytes[0].is_ascii_lowercase() && bytes[1] == b'.' && bytes[2].is_ascii_lowercase()
+    };
+    known.contains(token) || single_capital || dotted_lowercase
+}
+
+// A form of "to be" ("is being", "has been" included), an optional "-ly" adverb and the next word
+fn passive_pair_regex() -> &'static regex::Regex {
+    static REGEX: OnceLock<regex::Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        regex::Regex::new(r"(?i)\b(?:is|are|was|were|be|been|being)\s+(?:(?:being|been)\s+)?(?:[a-z]+ly\s+)?([a-z]+)\b")
+            .expect("valid passive voice pattern")
+    })
+}
+
+// Rough past participle test: regular "-ed" and "-en" forms plus common irregular ones
+fn looks_like_past_participle(word: &str) -> bool {
+    const IRREGULAR: [&str; 24] = [
+        "made", "done", "built", "sent", "found", "held", "told", "paid", "kept", "left", "brought", "bought",
+        "thought", "taught", "caught", "sold", "put", "set", "read", "run", "won", "shut", "lost", "meant",
+    ];
+    const NOT_PARTICIPLES: [&str; 6] = ["often", "even", "open", "when", "then", "need"];
+    let word = word.to_lowercase();
+    if NOT_PARTICIPLES.contains(&word.as_str()) {
+        return false;
+    }
+    let regular = word.len() > 3 && (word.ends_with("ed") || word.ends_with("en"));
+    regular || IRREGULAR.contains(&word.as_str())
+}
+
+// Share of sentences with a passive construction and the average sentence length in words
+fn passive_voice_stats(text: &str, words: usize) -> (f32, f32) {
+    let sentences = split_sentences(text);
+    if sentences.is_empty() {
+        return (0.0, 0.0);
+    }
+    let passive = sentences
+        .iter()
+        .filter(|sentence| {
+            passive_pair_regex()
+                .captures_iter(sentence)
+                .any(|captures| looks_like_past_participle(&captures[1]))
+        })
+        .count();
+    (passive as f32 / sentences.len() as f32, words as f32 / sentences.len() as f32)
+}
+
+// Distinct words ignoring case and surrounding punctuation
+pub fn unique_word_count(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+// Flesch reading ease and Flesch-Kincaid grade level
+fn readability(text: &str, words: usize) -> (f32, f32) {
+    if words == 0 {
+        return (0.0, 0.0);
+    }
+
+    let sentences = count_sentences(text).max(1);
+    let syllables: usize = text.split_whitespace().map(count_syllables).sum();
+
+    let words_per_sentence = words as f32 / sentences as f32;
+    let syllables_per_word = syllables as f32 / words as f32;
+
+    let ease = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+    let grade = 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59;
+    (ease.clamp(0.0, 100.0), grade)
 }
 
 impl Document {
@@ -42,9 +550,10 @@ impl Document {
             content: String::new(),
             path: None,
             modified: false,
+            pinned: false,
             undo_stack: VecDeque::new(),
             redo_stack: VecDeque::new(),
-            max_undo_steps: 100,
+            max_undo_steps: 50,
             find_text: String::new(),
             replace_text: String::new(),
             match_case: false,
@@ -52,24 +561,72 @@ impl Document {
             current_find_pos: 0,
             cursor_position: 0,
             selection: None,
+            cursor_changed: false,
+            last_content: String::new(),
+            generation: 0,
+            saved_content: None,
+            detected_line_ending: LineEnding::default(),
+            column_selection: None,
+            tail_mode: false,
+            tail_file_pos: 0,
+            read_only: false,
+            checkpoints: Vec::new(),
+            bookmarks: Vec::new(),
+            last_error: None,
         }
     }
 
+    // New unsaved document that starts with the given text, e.g. from a template
+    pub fn with_content(title: &str, content: String) -> Self {
+        let mut doc = Self::new(title);
+        doc.last_content = content.clone();
+        doc.content = content;
+        doc
+    }
+
+    // Unsaved text restored after a crash; marked modified so it isn't lost again
+    pub fn recovered(title: &str, content: String) -> Self {
+        let mut doc = Self::with_content(title, content);
+        doc.modified = true;
+        doc
+    }
+
     pub fn load(path: &Path) -> Result<Self, std::io::Error> {
         let content = std::fs::read_to_string(path)?;
+        Ok(Self::from_file_content(path, content))
+    }
+
+    // Opens a file whatever its contents; invalid UTF-8 is replaced with U+FFFD
+    pub fn load_lossy(path: &Path) -> Result<Self, std::io::Error> {
+        let bytes = std::fs::read(path)?;
+        let content = String::from_utf8(bytes)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        Ok(Self::from_file_content(path, content))
+    }
+
+    fn from_file_content(path: &Path, content: String) -> Self {
+        let detected_line_ending = LineEnding::detect(&content);
+        // The editor works with "\n"; mixed files are kept as is so saving doesn't alter them
+        let content = if detected_line_ending == LineEnding::Mixed {
+            content
+        } else {
+            to_lf(&content)
+        };
         let title = path.file_name()
             .and_then(|n| n.to_str())
-            .unwrap_or("Untitled")
+            .unwrap_or("Безымянный")
             .to_string();
 
-        Ok(Self {
+        Self {
             title,
-            content,
+            saved_content: Some(content.clone()),
+            content: content.clone(),
             path: Some(path.to_path_buf()),
             modified: false,
+            pinned: false,
             undo_stack: VecDeque::new(),
             redo_stack: VecDeque::new(),
-            max_undo_steps: 100,
+            max_undo_steps: 50,
             find_text: String::new(),
             replace_text: String::new(),
             match_case: false,
@@ -77,16 +634,31 @@ impl Document {
             current_find_pos: 0,
             cursor_position: 0,
             selection: None,
-        })
+            cursor_changed: false,
+            last_content: content,
+            generation: 0,
+            detected_line_ending,
+            column_selection: None,
+            tail_mode: false,
+            tail_file_pos: 0,
+            read_only: false,
+            checkpoints: Vec::new(),
+            bookmarks: Vec::new(),
+            last_error: None,
+        }
     }
 
     pub fn save(&mut self, path: &Path) -> Result<(), std::io::Error> {
-        std::fs::write(path, &self.content)?;
+        match self.detected_line_ending.sequence() {
+            Some("\n") | None => std::fs::write(path, &self.content)?,
+            Some(ending) => std::fs::write(path, self.content.replace('\n', ending))?,
+        }
         self.path = Some(path.to_path_buf());
         self.modified = false;
+        self.saved_content = Some(self.content.clone());
         self.title = path.file_name()
             .and_then(|n| n.to_str())
-            .unwrap_or("Untitled")
+            .unwrap_or("Безымянный")
             .to_string();
         Ok(())
     }
@@ -95,6 +667,11 @@ impl Document {
         self.save(path)
     }
 
+    // Line changes since the last save; a never-saved document is compared with empty text
+    pub fn diff_from_saved(&self) -> Vec<LineDiff> {
+        diff::line_diff(self.saved_content.as_deref().unwrap_or(""), &self.content)
+    }
+
     pub fn title(&self) -> &str {
         &self.title
     }
@@ -103,12 +680,88 @@ impl Document {
         self.path.as_deref()
     }
 
+    // Converts all line endings in the document; they are written on the next save
+    pub fn normalize_line_endings(&mut self, target: LineEnding) {
+        if self.read_only {
+            return;
+        }
+        if target == LineEnding::Mixed || target == self.detected_line_ending {
+            return;
+        }
+
+        let content = to_lf(&self.content);
+        if content != self.content {
+            self.push_undo_state();
+            self.content = content;
+            self.clamp_cursor();
+        }
+        self.detected_line_ending = target;
+        self.mark_edited();
+    }
+
     pub fn is_modified(&self) -> bool {
         self.modified
     }
 
-    pub fn set_modified(&mut self, modified: bool) {
-        self.modified = modified;
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn extension(&self) -> Option<String> {
+        self.path
+            .as_deref()
+            .and_then(|p| p.extension())
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+    }
+
+    pub fn is_markdown(&self) -> bool {
+        matches!(self.extension().as_deref(), Some("md") | Some("markdown"))
+    }
+
+    // Standalone HTML5 page; Markdown documents are rendered, everything else is escaped text
+    pub fn to_html(&self) -> String {
+        let body = if self.is_markdown() {
+            markdown::to_html(&self.content)
+        } else {
+            escape_html(&self.content).replace('\n', "<br>\n")
+        };
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+            escape_html(&self.title),
+            body
+        )
+    }
+
+    // Marks a programmatic edit of `content` so the editor does not record it twice
+    fn mark_edited(&mut self) {
+        self.last_content = self.content.clone();
+        self.modified = true;
+        self.generation += 1;
+    }
+
+    // Called after the text widget reported a change
+    pub fn update_last_content(&mut self) {
+        if self.content != self.last_content {
+            if self.undo_stack.back() != Some(&self.last_content) {
+                if self.undo_stack.len() >= self.max_undo_steps {
+                    self.undo_stack.pop_front();
+                }
+                self.undo_stack.push_back(self.last_content.clone());
+                self.redo_stack.clear();
+            }
+            self.last_content = self.content.clone();
+            self.modified = true;
+            self.generation += 1;
+        }
     }
 
     // Undo/Redo functionality
@@ -121,83 +774,286 @@ impl Document {
     }
 
     pub fn undo(&mut self) {
+        if self.read_only {
+            return;
+        }
         if let Some(previous_state) = self.undo_stack.pop_back() {
             self.redo_stack.push_back(self.content.clone());
             self.content = previous_state;
-            self.modified = true;
+            self.mark_edited();
+            self.clamp_cursor();
         }
     }
 
     pub fn redo(&mut self) {
+        if self.read_only {
+            return;
+        }
         if let Some(next_state) = self.redo_stack.pop_back() {
             self.undo_stack.push_back(self.content.clone());
             self.content = next_state;
-            self.modified = true;
+            self.mark_edited();
+            self.clamp_cursor();
         }
     }
 
-    // Find/Replace functionality
-    pub fn find_next(&mut self) -> bool {
-        if self.find_text.is_empty() {
+    // Named checkpoints
+    pub fn checkpoints(&self) -> &[(String, String)] {
+        &self.checkpoints
+    }
+
+    pub fn create_checkpoint(&mut self, label: &str) {
+        self.checkpoints.push((label.to_string(), self.content.clone()));
+    }
+
+    // Replaces the content with a checkpoint; the replacement itself can be undone
+    pub fn restore_checkpoint(&mut self, index: usize) -> bool {
+        if self.read_only {
             return false;
         }
-
-        let content = if self.match_case {
-            self.content.clone()
-        } else {
-            self.content.to_lowercase()
+        let Some((_, snapshot)) = self.checkpoints.get(index) else {
+            return false;
         };
+        if *snapshot != self.content {
+            let snapshot = snapshot.clone();
+            self.push_undo_state();
+            self.content = snapshot;
+            self.mark_edited();
+            self.clamp_cursor();
+        }
+        true
+    }
 
-        let find_text = if self.match_case {
-            self.find_text.clone()
-        } else {
-            self.find_text.to_lowercase()
-        };
+    // Reformats the content as indented JSON; invalid JSON leaves it untouched
+    pub fn format_as_json(&mut self) -> Result<(), serde_json::Error> {
+        let value: serde_json::Value = serde_json::from_str(&self.content)?;
+        self.replace_json(serde_json::to_string_pretty(&value)?);
+        Ok(())
+    }
 
-        if let Some(pos) = content[self.current_find_pos..].find(&find_text) {
-            self.current_find_pos += pos;
-            self.selection = Some((self.current_find_pos, self.current_find_pos + find_text.len()));
-            self.current_find_pos += find_text.len();
-            true
-        } else {
-            self.current_find_pos = 0;
-            false
+    // Rewrites the content as compact single-line JSON
+    pub fn minify_json(&mut self) -> Result<(), serde_json::Error> {
+        let value: serde_json::Value = serde_json::from_str(&self.content)?;
+        self.replace_json(serde_json::to_string(&value)?);
+        Ok(())
+    }
+
+    fn replace_json(&mut self, mut json: String) {
+        if self.read_only {
+            return;
+        }
+        if self.content.ends_with('\n') {
+            json.push('\n');
+        }
+        if json != self.content {
+            self.push_undo_state();
+            self.content = json;
+            self.mark_edited();
+            self.clamp_cursor();
+            self.cursor_changed = true;
         }
     }
 
-    pub fn replace_next(&mut self) -> bool {
-        if let Some((start, end)) = self.selection {
-            if self.content[start..end] == self.find_text ||
-                (!self.match_case && self.content[start..end].eq_ignore_ascii_case(&self.find_text)) {
+    // Bookmarks
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
 
-                self.push_undo_state();
-                self.content.replace_range(start..end, &self.replace_text);
-                self.modified = true;
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<Bookmark>) {
+        self.bookmarks = bookmarks;
+    }
 
-                // Adjust selection to replaced text
-                self.selection = Some((start, start + self.replace_text.len()));
-                self.current_find_pos = start + self.replace_text.len();
+    pub fn add_bookmark(&mut self, label: &str) {
+        self.bookmarks.push(Bookmark {
+            line: self.cursor_line(),
+            label: label.to_string(),
+        });
+    }
 
-                return true;
-            }
+    pub fn remove_bookmark(&mut self, index: usize) {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
         }
+    }
 
-        self.find_next()
+    pub fn goto_bookmark(&mut self, index: usize) {
+        if let Some(bookmark) = self.bookmarks.get(index) {
+            let pos = self.row_col_to_offset(bookmark.line.saturating_sub(1), 0);
+            self.set_cursor_position(pos);
+        }
     }
 
-    pub fn replace_all(&mut self) {
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    // Find/Replace functionality
+    // The find text as a literal pattern, case-insensitive unless match_case is set. Matching
+    // runs on the content itself: lowercasing it first can change byte lengths ('İ')
+    fn find_regex(&self) -> regex::Regex {
+        regex::RegexBuilder::new(&regex::escape(&self.find_text))
+            .case_insensitive(!self.match_case)
+            .build()
+            .expect("escaped literal is a valid pattern")
+    }
+
+    pub fn find_next(&mut self) -> bool {
         if self.find_text.is_empty() {
-            return;
+            return false;
         }
 
-        self.push_undo_state();
-        let count = if self.match_case {
-            let matches = self.content.matches(&self.find_text).count();
-            self.content = self.content.replace(&self.find_text, &self.replace_text);
-            matches
-        } else {
-            // Case-insensitive replacement
-            let mut result = String::new();
+        let regex = self.find_regex();
+        let content = &self.content;
+        if self.current_find_pos > content.len() || !content.is_char_boundary(self.current_find_pos) {
+            self.current_find_pos = 0;
+        }
+
+        // After find_previous the position points at the selected match, so skip over it
+        if let Some((start, end)) = self.selection {
+            if start == self.current_find_pos && content.is_char_boundary(end) {
+                self.current_find_pos = end;
+            }
+        }
+
+        if let Some(found) = regex.find_at(content, self.current_find_pos) {
+            self.selection = Some((found.start(), found.end()));
+            self.current_find_pos = found.end();
+            self.cursor_position = self.current_find_pos;
+            self.cursor_changed = true;
+            true
+        } else {
+            self.current_find_pos = 0;
+            false
+        }
+    }
+
+    // Search-as-you-type: a query extending the previous one re-matches from the start
+    // of the current match, so the selection grows in place; any other query restarts
+    // from the top. Wraps around once, an empty query clears the selection
+    pub fn find_incremental(&mut self, previous: &str) -> bool {
+        if self.find_text.is_empty() {
+            self.selection = None;
+            self.current_find_pos = 0;
+            return false;
+        }
+
+        let extends = !previous.is_empty() && self.find_text.starts_with(previous);
+        self.current_find_pos = match self.selection.take() {
+            Some((start, _)) if extends => start,
+            _ => 0,
+        };
+        self.find_next() || self.find_next()
+    }
+
+    // Searches backward from the start of the current match
+    pub fn find_previous(&mut self) -> bool {
+        if self.find_text.is_empty() {
+            return false;
+        }
+
+        let regex = self.find_regex();
+        let content = &self.content;
+
+        // After find_next the position points past the selected match, so skip over it
+        let mut limit = match self.selection {
+            Some((start, end)) if end == self.current_find_pos => start,
+            _ => self.current_find_pos,
+        };
+        if limit > content.len() || !content.is_char_boundary(limit) {
+            limit = content.len();
+        }
+
+        // The last match starting before limit, overlapping ones included
+        let haystack = &content[..limit];
+        let mut last = None;
+        let mut from = 0;
+        while let Some(found) = regex.find_at(haystack, from) {
+            last = Some((found.start(), found.end()));
+            match haystack[found.start()..].chars().next() {
+                Some(c) => from = found.start() + c.len_utf8(),
+                None => break,
+            }
+        }
+
+        if let Some((start, end)) = last {
+            self.current_find_pos = start;
+            self.selection = Some((start, end));
+            self.cursor_position = start;
+            self.cursor_changed = true;
+            true
+        } else {
+            self.current_find_pos = content.len();
+            false
+        }
+    }
+
+    // All matches of the query as byte ranges; plain queries are escaped so one code path serves both
+    pub fn find_all_positions(
+        &self,
+        query: &str,
+        match_case: bool,
+        whole_word: bool,
+        use_regex: bool,
+    ) -> Result<Vec<(usize, usize)>, regex::Error> {
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pattern = if use_regex { query.to_string() } else { regex::escape(query) };
+        let pattern = if whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+        let regex = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(!match_case)
+            .build()?;
+
+        Ok(regex
+            .find_iter(&self.content)
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect())
+    }
+
+    pub fn replace_next(&mut self) -> bool {
+        if self.read_only {
+            return false;
+        }
+        if let Some((start, end)) = self.selection {
+            if self.content[start..end] == self.find_text ||
+                (!self.match_case && self.content[start..end].eq_ignore_ascii_case(&self.find_text)) {
+
+                self.push_undo_state();
+                self.content.replace_range(start..end, &self.replace_text);
+                self.mark_edited();
+
+                // Adjust selection to replaced text
+                self.selection = Some((start, start + self.replace_text.len()));
+                self.current_find_pos = start + self.replace_text.len();
+                self.cursor_position = self.current_find_pos;
+                self.cursor_changed = true;
+
+                return true;
+            }
+        }
+
+        self.find_next()
+    }
+
+    pub fn replace_all(&mut self) {
+        if self.read_only || self.find_text.is_empty() {
+            return;
+        }
+
+        self.push_undo_state();
+        let count = if self.match_case {
+            let matches = self.content.matches(&self.find_text).count();
+            self.content = self.content.replace(&self.find_text, &self.replace_text);
+            matches
+        } else {
+            // Case-insensitive replacement
+            let mut result = String::new();
             let mut last_end = 0;
             let find_lower = self.find_text.to_lowercase();
             let content_lower = self.content.to_lowercase();
@@ -219,79 +1075,1748 @@ impl Document {
         };
 
         if count > 0 {
-            self.modified = true;
+            self.mark_edited();
+            self.clamp_cursor();
         }
     }
 
     // Copy/Cut/Paste functionality
-    pub fn copy(&self) {
-        if let Some((start, end)) = self.selection {
-            let selected_text = &self.content[start..end];
-            // For now, we'll just print since clipboard might have issues
-            println!("Copied: {}", selected_text);
+    pub fn copy(&self) -> Option<String> {
+        if self.column_selection.is_some() {
+            return self.get_column_selection_text();
         }
+        self.selection
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| self.content[start..end].to_string())
     }
 
-    pub fn cut(&mut self) {
-        if let Some((start, end)) = self.selection {
-            let selected_text = &self.content[start..end].to_string();
-            println!("Cut: {}", selected_text); // Simulate clipboard
-
+    pub fn cut(&mut self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+        let selected_text = self.copy()?;
+        if self.column_selection.is_some() {
+            self.replace_column_selection("");
+        } else if let Some((start, end)) = self.selection.take() {
             self.push_undo_state();
             self.content.replace_range(start..end, "");
-            self.modified = true;
-            self.selection = None;
+            self.mark_edited();
+            self.cursor_position = start;
+            self.cursor_changed = true;
         }
+        Some(selected_text)
     }
 
-    pub fn paste(&mut self) {
-        // Simulate paste - in real implementation, use arboard
-        let text = "pasted_text"; // This would come from clipboard
-        if let Some((start, end)) = self.selection {
-            self.push_undo_state();
+    pub fn paste(&mut self, text: &str) {
+        if self.read_only {
+            return;
+        }
+        if self.column_selection.is_some() {
+            self.replace_column_selection(text);
+        } else if !text.is_empty() {
+            self.insert_at_cursor(text);
+        }
+    }
+
+    // Enter with auto-indent: the new line repeats the whitespace that precedes the cursor
+    // on its line, re-expressed as spaces or tabs according to the indent settings
+    pub fn insert_newline_with_indent(&mut self, indent_with_spaces: bool, tab_size: u8) {
+        let pos = self.selection.map_or(self.cursor_position, |(start, _)| start);
+        let line_start = self.content[..pos].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.content[line_start..pos];
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        let text = format!("\n{}", normalize_indent(indent, indent_with_spaces, tab_size));
+        self.insert_at_cursor(&text);
+    }
+
+    pub fn tail_file_pos(&self) -> u64 {
+        self.tail_file_pos
+    }
+
+    // Text read from the followed file is not an edit: no undo step, no modified flag
+    pub fn append_tail(&mut self, text: &str, file_pos: u64) {
+        let text = if self.detected_line_ending == LineEnding::Mixed { text.to_string() } else { to_lf(text) };
+        self.content.push_str(&text);
+        if let Some(saved) = &mut self.saved_content {
+            saved.push_str(&text);
+        }
+        self.tail_file_pos = file_pos;
+        self.last_content = self.content.clone();
+        self.generation += 1;
+    }
+
+    // Replaces the content with the file as re-read from the start, e.g. after log rotation
+    pub fn reload_tail(&mut self, text: &str, file_pos: u64) {
+        self.content.clear();
+        self.saved_content = Some(String::new());
+        self.selection = None;
+        self.cursor_position = 0;
+        self.append_tail(text, file_pos);
+    }
+
+    // Inserts text at the cursor, replacing the selection if there is one
+    pub fn insert_at_cursor(&mut self, text: &str) {
+        if self.read_only {
+            return;
+        }
+        self.push_undo_state();
+        let start = if let Some((start, end)) = self.selection.take() {
             self.content.replace_range(start..end, text);
+            start
         } else {
-            self.push_undo_state();
             self.content.insert_str(self.cursor_position, text);
+            self.cursor_position
+        };
+        self.cursor_position = start + text.len();
+        self.cursor_changed = true;
+        self.mark_edited();
+    }
+
+    // Handles a typed bracket or quote: skips over an existing closer,
+    // wraps the selection in the pair or inserts the pair around the cursor
+    pub fn type_pair_char(&mut self, c: char) {
+        if self.read_only {
+            return;
         }
-        self.modified = true;
+        let next = self.content[self.cursor_position..].chars().next();
+        if self.selection.is_none() && next == Some(c) && is_closing_char(c) {
+            self.cursor_position += c.len_utf8();
+            self.cursor_changed = true;
+            return;
+        }
+
+        // Angle brackets only wrap a selection; typed alone they are usually comparisons
+        let close = closing_pair(c).or((c == '<' && self.selection.is_some()).then_some('>'));
+        let Some(close) = close else {
+            self.insert_at_cursor(c.encode_utf8(&mut [0; 4]));
+            return;
+        };
+
+        if let Some(selection) = self.selection {
+            self.wrap_selection(selection, c.encode_utf8(&mut [0; 4]), close.encode_utf8(&mut [0; 4]));
+            return;
+        }
+
+        // An apostrophe or quote right after a letter is most likely not an opener
+        let previous = self.content[..self.cursor_position].chars().next_back();
+        if c == close && previous.is_some_and(|p| p.is_alphanumeric()) {
+            self.insert_at_cursor(c.encode_utf8(&mut [0; 4]));
+            return;
+        }
+
+        self.insert_at_cursor(&format!("{}{}", c, close));
+        self.cursor_position -= close.len_utf8();
     }
 
-    pub fn select_all(&mut self) {
-        self.selection = Some((0, self.content.len()));
+    // Surrounds the selection with open and close; returns the selection inside them
+    pub fn wrap_selection(&mut self, selection: (usize, usize), open: &str, close: &str) -> (usize, usize) {
+        if self.read_only {
+            return selection;
+        }
+        let (start, end) = selection;
+        self.push_undo_state();
+        self.content.insert_str(end, close);
+        self.content.insert_str(start, open);
+        let inner = (start + open.len(), end + open.len());
+        self.selection = Some(inner);
+        self.cursor_position = inner.1;
+        self.cursor_changed = true;
+        self.mark_edited();
+        inner
     }
 
-    // Cursor and selection management
-    pub fn cursor_line(&self) -> usize {
-        self.content[..self.cursor_position].matches('\n').count() + 1
+    // Byte range of the line containing pos, without its newline
+    fn line_bounds(&self, pos: usize) -> (usize, usize) {
+        let pos = pos.min(self.content.len());
+        let start = self.content[..pos].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.content[pos..].find('\n').map_or(self.content.len(), |i| pos + i);
+        (start, end)
     }
 
-    pub fn cursor_column(&self) -> usize {
-        if let Some(last_newline) = self.content[..self.cursor_position].rfind('\n') {
-            self.cursor_position - last_newline - 1
+    // Inserts a copy of the cursor line below it; returns the cursor on the copy
+    pub fn duplicate_line(&mut self, cursor_pos: usize) -> usize {
+        if self.read_only {
+            return cursor_pos;
+        }
+        let (start, end) = self.line_bounds(cursor_pos);
+        let line = format!("\n{}", &self.content[start..end]);
+        self.push_undo_state();
+        self.content.insert_str(end, &line);
+        self.mark_edited();
+        cursor_pos.min(end) + line.len()
+    }
+
+    // Removes the cursor line; returns the cursor at the start of the following line
+    pub fn delete_line(&mut self, cursor_pos: usize) -> usize {
+        if self.read_only {
+            return cursor_pos;
+        }
+        let (start, end) = self.line_bounds(cursor_pos);
+        self.push_undo_state();
+        let cursor = if end < self.content.len() {
+            self.content.replace_range(start..=end, "");
+            start
+        } else if start > 0 {
+            // The last line has no newline of its own, so the preceding one goes with it
+            self.content.replace_range(start - 1..end, "");
+            self.line_bounds(start - 1).0
         } else {
-            self.cursor_position
+            self.content.clear();
+            0
+        };
+        self.mark_edited();
+        cursor
+    }
+
+    // Swaps the cursor line with the one above; returns the cursor at the same column
+    pub fn move_line_up(&mut self, cursor_pos: usize) -> usize {
+        let (start, end) = self.line_bounds(cursor_pos);
+        if self.read_only || start == 0 {
+            return cursor_pos;
         }
+
+        let (previous_start, _) = self.line_bounds(start - 1);
+        let swapped = format!("{}\n{}", &self.content[start..end], &self.content[previous_start..start - 1]);
+        self.push_undo_state();
+        self.content.replace_range(previous_start..end, &swapped);
+        self.mark_edited();
+        previous_start + (cursor_pos - start)
     }
 
-    // Statistics
-    pub fn calculate_stats(&self) -> DocumentStats {
-        let characters = self.content.chars().count();
-        let characters_no_spaces = self.content.chars().filter(|c| !c.is_whitespace()).count();
-        let words = self.content.split_whitespace().count();
-        let lines = self.content.lines().count();
-        let paragraphs = self.content.split("\n\n").count();
+    // Swaps the cursor line with the one below; returns the cursor at the same column
+    pub fn move_line_down(&mut self, cursor_pos: usize) -> usize {
+        let (start, end) = self.line_bounds(cursor_pos);
+        if self.read_only || end == self.content.len() {
+            return cursor_pos;
+        }
 
-        // Estimate pages (assuming ~500 words per page)
-        let pages = (words as f32 / 500.0).ceil() as usize;
+        let (_, next_end) = self.line_bounds(end + 1);
+        let next_len = next_end - (end + 1);
+        let swapped = format!("{}\n{}", &self.content[end + 1..next_end], &self.content[start..end]);
+        self.push_undo_state();
+        self.content.replace_range(start..next_end, &swapped);
+        self.mark_edited();
+        start + next_len + 1 + (cursor_pos - start)
+    }
 
-        DocumentStats {
-            pages,
-            words,
-            characters,
-            characters_no_spaces,
-            lines,
-            paragraphs,
+    // Byte offsets where the lines touched by the selection (or the cursor line) start
+    fn selected_line_starts(&self) -> Vec<usize> {
+        self.line_starts(self.selection.unwrap_or((self.cursor_position, self.cursor_position)))
+    }
+
+    // Byte offsets where the lines touched by (start, end) start
+    fn line_starts(&self, (start, mut end): (usize, usize)) -> Vec<usize> {
+        // A selection ending right after a newline doesn't include the next line
+        if end > start && self.content[..end].ends_with('\n') {
+            end -= 1;
+        }
+        let first = self.content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let mut starts = vec![first];
+        starts.extend(self.content[first..end].match_indices('\n').map(|(i, _)| first + i + 1));
+        starts
+    }
+
+    // Indents or outdents the lines touched by selection; returns the adjusted selection
+    pub fn indent_selection(
+        &mut self,
+        selection: (usize, usize),
+        mode: IndentMode,
+        indent_with_spaces: bool,
+        tab_size: u8,
+    ) -> Option<(usize, usize)> {
+        self.selection = Some(selection).filter(|(start, end)| start < end);
+        self.cursor_position = selection.1;
+        match mode {
+            IndentMode::Indent if indent_with_spaces => self.indent_lines(&" ".repeat(tab_size as usize)),
+            IndentMode::Indent => self.indent_lines("\t"),
+            IndentMode::Outdent => self.outdent_lines(tab_size as usize),
+        }
+        self.selection
+    }
+
+    // Prepends the indent to every selected line and selects the lines
+    pub fn indent_lines(&mut self, indent: &str) {
+        if self.read_only {
+            return;
+        }
+        let starts = self.selected_line_starts();
+        self.push_undo_state();
+        for &line_start in starts.iter().rev() {
+            self.content.insert_str(line_start, indent);
+        }
+
+        let end = self.selection.map_or(self.cursor_position, |(_, end)| end);
+        let end = end + indent.len() * starts.iter().filter(|&&start| start < end).count();
+        self.selection = Some((starts[0], end));
+        self.cursor_position = end;
+        self.cursor_changed = true;
+        self.mark_edited();
+    }
+
+    // Removes one tab or up to tab_size spaces from the start of every selected line
+    pub fn outdent_lines(&mut self, tab_size: usize) {
+        if self.read_only {
+            return;
+        }
+        let removals: Vec<(usize, usize)> = self
+            .selected_line_starts()
+            .into_iter()
+            .map(|start| {
+                let line = &self.content[start..];
+                let len = if line.starts_with('\t') {
+                    1
+                } else {
+                    line.bytes().take(tab_size).take_while(|b| *b == b' ').count()
+                };
+                (start, len)
+            })
+            .filter(|(_, len)| *len > 0)
+            .collect();
+        if removals.is_empty() {
+            return;
+        }
+
+        self.push_undo_state();
+        for &(start, len) in removals.iter().rev() {
+            self.content.replace_range(start..start + len, "");
+        }
+
+        let shift = |pos: usize| {
+            pos - removals
+                .iter()
+                .map(|&(start, len)| pos.saturating_sub(start).min(len))
+                .sum::<usize>()
+        };
+        self.selection = self
+            .selection
+            .map(|(start, end)| (shift(start), shift(end)))
+            .filter(|(start, end)| start < end);
+        self.cursor_position = shift(self.cursor_position);
+        self.cursor_changed = true;
+        self.mark_edited();
+    }
+
+    // Byte range of the complete lines touched by the selection without the final newline;
+    // None when the selection is empty
+    fn lines_in_range(&self, (start, end): (usize, usize)) -> Option<(usize, usize)> {
+        if start >= end {
+            return None;
+        }
+        let starts = self.line_starts((start, end));
+        let last = starts[starts.len() - 1];
+        let end = self.content[last..].find('\n').map_or(self.content.len(), |i| last + i);
+        Some((starts[0], end))
+    }
+
+    // Replaces the lines in range and selects the result
+    fn replace_lines(&mut self, (start, end): (usize, usize), lines: Vec<&str>) -> bool {
+        let replacement = lines.join("\n");
+        if self.read_only || replacement == self.content[start..end] {
+            return false;
+        }
+
+        self.push_undo_state();
+        self.content.replace_range(start..end, &replacement);
+        self.selection = Some((start, start + replacement.len()));
+        self.cursor_position = start + replacement.len();
+        self.cursor_changed = true;
+        self.mark_edited();
+        true
+    }
+
+    // Joins the selected lines with single spaces, dropping whitespace around each break
+    // and blank lines in between
+    pub fn join_lines(&mut self, selection: (usize, usize)) -> bool {
+        let text = &self.content[selection.0..selection.1];
+        let Some((first, rest)) = text.split_once('\n') else {
+            return false;
+        };
+        let (middle, last) = rest.rsplit_once('\n').unwrap_or(("", rest));
+        let mut parts = vec![first.trim_end()];
+        parts.extend(middle.split('\n').map(str::trim).filter(|line| !line.is_empty()));
+        parts.push(last.trim_start());
+        let joined = parts.into_iter().filter(|part| !part.is_empty()).collect::<Vec<_>>().join(" ");
+
+        let changed = self.replace_selection(selection, &joined);
+        self.cursor_changed |= changed;
+        changed
+    }
+
+    // Re-wraps every line touched by the selection at the last space that keeps it within
+    // max_cols chars; continuation lines keep the indentation, longer words stay whole.
+    // A break right after an abbreviation moves back to the previous space when there is one
+    pub fn wrap_to_width(&mut self, selection: (usize, usize), max_cols: usize) -> bool {
+        let start = self.line_bounds(selection.0).0;
+        let end = self.line_bounds(selection.1).1.max(start);
+        let text = self.content[start..end].to_string();
+
+        let mut lines = Vec::new();
+        for line in text.split('\n') {
+            if line.chars().count() <= max_cols {
+                lines.push(line.to_string());
+                continue;
+            }
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let indent_cols = indent.chars().count();
+            let width = |words: &[&str]| words.iter().map(|w| w.chars().count() + 1).sum::<usize>().saturating_sub(1);
+            let mut current: Vec<&str> = Vec::new();
+            for word in line.split_whitespace() {
+                if !current.is_empty() && indent_cols + width(&current) + 1 + word.chars().count() > max_cols {
+                    let split = (1..=current.len())
+                        .rev()
+                        .find(|&i| !is_abbreviation(current[i - 1]))
+                        .unwrap_or(current.len());
+                    let carried = current.split_off(split);
+                    lines.push(format!("{}{}", indent, current.join(" ")));
+                    current = carried;
+                }
+                current.push(word);
+            }
+            lines.push(format!("{}{}", indent, current.join(" ")));
+        }
+
+        self.replace_lines((start, end), lines.iter().map(String::as_str).collect())
+    }
+
+    pub fn sort_lines_in_range(&mut self, selection: (usize, usize), reverse: bool, case_insensitive: bool) -> bool {
+        let Some(range) = self.lines_in_range(selection) else {
+            return false;
+        };
+        let text = self.content[range.0..range.1].to_string();
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        if case_insensitive {
+            lines.sort_by_cached_key(|line| line.to_lowercase());
+        } else {
+            lines.sort();
+        }
+        if reverse {
+            lines.reverse();
+        }
+        self.replace_lines(range, lines)
+    }
+
+    // Keeps the first occurrence of every line
+    pub fn deduplicate_lines_in_range(&mut self, selection: (usize, usize)) -> bool {
+        let Some(range) = self.lines_in_range(selection) else {
+            return false;
+        };
+        let text = self.content[range.0..range.1].to_string();
+        let mut seen = std::collections::HashSet::new();
+        let lines: Vec<&str> = text.split('\n').filter(|line| seen.insert(*line)).collect();
+        self.replace_lines(range, lines)
+    }
+
+    // Line comment marker for the file type; None when the language has no line comments
+    pub fn comment_prefix(&self) -> Option<&'static str> {
+        match self.extension().as_deref()? {
+            "rs" | "js" | "ts" => Some("//"),
+            "py" | "toml" | "ini" => Some("#"),
+            _ => None,
+        }
+    }
+
+    // Comments out each selected line, or uncomments it if it is already commented.
+    // Blank lines are left alone; returns false when nothing was changed.
+    // Without a selection only the cursor line is toggled
+    pub fn toggle_line_comment(&mut self, selection: Option<(usize, usize)>) -> bool {
+        if self.read_only {
+            return false;
+        }
+        let Some(prefix) = self.comment_prefix() else {
+            return false;
+        };
+        let marker = format!("{} ", prefix);
+
+        // (position, removed bytes, inserted text) for every affected line
+        let edits: Vec<(usize, usize, &str)> = self
+            .line_starts(selection.unwrap_or((self.cursor_position, self.cursor_position)))
+            .into_iter()
+            .filter_map(|start| {
+                let line = self.content[start..].split('\n').next().unwrap_or("");
+                let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+                let rest = &line[indent..];
+                if rest.is_empty() {
+                    None
+                } else if let Some(after) = rest.strip_prefix(prefix) {
+                    let len = prefix.len() + usize::from(after.starts_with(' '));
+                    Some((start + indent, len, ""))
+                } else {
+                    Some((start + indent, 0, marker.as_str()))
+                }
+            })
+            .collect();
+        if edits.is_empty() {
+            return false;
+        }
+
+        let mut content = self.content.clone();
+        for &(pos, len, insert) in edits.iter().rev() {
+            content.replace_range(pos..pos + len, insert);
+        }
+
+        let shift = |p: usize| {
+            edits.iter().fold(p as isize, |shifted, &(pos, len, insert)| {
+                let removed = p.saturating_sub(pos).min(len) as isize;
+                let inserted = if pos < p { insert.len() as isize } else { 0 };
+                shifted - removed + inserted
+            }) as usize
+        };
+        self.selection = self.selection.map(|(start, end)| (shift(start), shift(end)));
+        self.cursor_position = shift(self.cursor_position);
+
+        self.push_undo_state();
+        self.content = content;
+        self.cursor_changed = true;
+        self.mark_edited();
+        true
+    }
+
+    // Changes the case of the selection, or of the word at the cursor
+    pub fn convert_case(&mut self, mode: CaseMode) {
+        if self.read_only {
+            return;
+        }
+        let (start, end) = self.selection.unwrap_or_else(|| {
+            let is_word = |c: char| c.is_alphanumeric();
+            let start = self.content[..self.cursor_position]
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| is_word(*c))
+                .last()
+                .map_or(self.cursor_position, |(i, _)| i);
+            let end = self.content[self.cursor_position..]
+                .char_indices()
+                .find(|(_, c)| !is_word(*c))
+                .map_or(self.content.len(), |(i, _)| self.cursor_position + i);
+            (start, end)
+        });
+        let converted = mode.apply(&self.content[start..end]);
+        if converted == self.content[start..end] {
+            return;
+        }
+
+        self.push_undo_state();
+        self.content.replace_range(start..end, &converted);
+        if self.selection.is_some() {
+            self.selection = Some((start, start + converted.len()));
+            self.cursor_position = start + converted.len();
         }
+        self.clamp_cursor();
+        self.cursor_changed = true;
+        self.mark_edited();
     }
-}
\ No newline at end of file
+
+    pub fn url_encode_selection(&mut self, selection: (usize, usize)) -> bool {
+        let encoded = utf8_percent_encode(&self.content[selection.0..selection.1], NON_ALPHANUMERIC).to_string();
+        self.replace_selection(selection, &encoded)
+    }
+
+    // Leaves the text untouched when the decoded bytes are not valid UTF-8
+    pub fn url_decode_selection(&mut self, selection: (usize, usize)) -> Result<bool, std::str::Utf8Error> {
+        let decoded = percent_decode_str(&self.content[selection.0..selection.1])
+            .decode_utf8()?
+            .into_owned();
+        Ok(self.replace_selection(selection, &decoded))
+    }
+
+    pub fn base64_encode_selection(&mut self, selection: (usize, usize)) -> bool {
+        let encoded = BASE64.encode(&self.content[selection.0..selection.1]);
+        self.replace_selection(selection, &encoded)
+    }
+
+    // Invalid base64 or non-UTF-8 output sets last_error and keeps the content
+    pub fn base64_decode_selection(&mut self, selection: (usize, usize)) -> bool {
+        let decoded = BASE64
+            .decode(self.content[selection.0..selection.1].trim())
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()));
+        match decoded {
+            Ok(text) => self.replace_selection(selection, &text),
+            Err(e) => {
+                self.last_error = Some(e);
+                false
+            }
+        }
+    }
+
+    pub fn take_last_error(&mut self) -> Option<String> {
+        self.last_error.take()
+    }
+
+    // Replaces the range and selects the inserted text; returns false when nothing changed
+    fn replace_selection(&mut self, selection: (usize, usize), text: &str) -> bool {
+        let (start, end) = selection;
+        if self.read_only || self.content[start..end] == *text {
+            return false;
+        }
+
+        self.push_undo_state();
+        self.content.replace_range(start..end, text);
+        self.selection = Some((start, start + text.len()));
+        self.cursor_position = start + text.len();
+        self.clamp_cursor();
+        self.mark_edited();
+        true
+    }
+
+    // Backspace: removes the selection or count chars before the cursor
+    pub fn delete_before_cursor(&mut self, count: usize) {
+        if self.read_only {
+            return;
+        }
+        let (start, end) = match self.selection.take() {
+            Some(range) => range,
+            None => {
+                let start = self.content[..self.cursor_position]
+                    .char_indices()
+                    .rev()
+                    .nth(count.saturating_sub(1))
+                    .map_or(0, |(i, _)| i);
+                (start, self.cursor_position)
+            }
+        };
+        if start == end {
+            return;
+        }
+
+        self.push_undo_state();
+        self.content.replace_range(start..end, "");
+        self.cursor_position = start;
+        self.cursor_changed = true;
+        self.mark_edited();
+    }
+
+    // Moves the cursor by a number of chars, dropping the selection
+    pub fn move_cursor(&mut self, delta: isize) {
+        let target = self.byte_to_char(self.cursor_position).saturating_add_signed(delta);
+        self.set_cursor_position(self.char_to_byte(target));
+    }
+
+    pub fn select_all(&mut self) {
+        self.selection = Some((0, self.content.len()));
+        self.cursor_position = self.content.len();
+        self.cursor_changed = true;
+    }
+
+    // Cursor and selection management
+    pub fn cursor_position(&self) -> usize {
+        self.cursor_position
+    }
+
+    // Moves the cursor, dropping the selection
+    pub fn set_cursor_position(&mut self, pos: usize) {
+        self.cursor_position = pos;
+        self.selection = None;
+        self.clamp_cursor();
+        self.cursor_changed = true;
+    }
+
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        self.selection
+    }
+
+    // Keeps cursor and selection on valid char boundaries after the content changed
+    fn clamp_cursor(&mut self) {
+        let mut pos = self.cursor_position.min(self.content.len());
+        while !self.content.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        self.cursor_position = pos;
+
+        if let Some((start, end)) = self.selection {
+            if end > self.content.len()
+                || !self.content.is_char_boundary(start)
+                || !self.content.is_char_boundary(end)
+            {
+                self.selection = None;
+            }
+        }
+        self.cursor_changed = true;
+    }
+
+    pub fn char_to_byte(&self, char_index: usize) -> usize {
+        self.content
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.content.len())
+    }
+
+    pub fn byte_to_char(&self, byte_index: usize) -> usize {
+        self.content[..byte_index.min(self.content.len())].chars().count()
+    }
+
+    // Converts a byte offset to (row, column); the column counts chars
+    pub fn offset_to_row_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.content[..offset.min(self.content.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (before.matches('\n').count(), before[line_start..].chars().count())
+    }
+
+    // Converts (row, column) to a byte offset, clamped to the end of that line
+    pub fn row_col_to_offset(&self, row: usize, col: usize) -> usize {
+        let line_start = if row == 0 {
+            0
+        } else {
+            match self.content.match_indices('\n').nth(row - 1) {
+                Some((i, _)) => i + 1,
+                None => return self.content.len(),
+            }
+        };
+        let line = &self.content[line_start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        line_start + line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+    }
+
+    // Column selection management
+    pub fn column_selection(&self) -> Option<(usize, usize, usize, usize)> {
+        self.column_selection
+    }
+
+    pub fn set_column_selection(&mut self, anchor: (usize, usize), active: (usize, usize)) {
+        self.column_selection = Some((anchor.0, anchor.1, active.0, active.1));
+        self.selection = None;
+        self.cursor_position = self.row_col_to_offset(active.0, active.1);
+    }
+
+    pub fn clear_column_selection(&mut self) {
+        self.column_selection = None;
+    }
+
+    // Moves the active corner of the column selection, starting one at the cursor if needed
+    pub fn extend_column_selection(&mut self, rows: isize, cols: isize) {
+        let (anchor_row, anchor_col, row, col) = self.column_selection.unwrap_or_else(|| {
+            let (row, col) = self.offset_to_row_col(self.cursor_position);
+            (row, col, row, col)
+        });
+        let last_row = self.content.matches('\n').count();
+        let row = row.saturating_add_signed(rows).min(last_row);
+        let col = col.saturating_add_signed(cols);
+        self.set_column_selection((anchor_row, anchor_col), (row, col));
+    }
+
+    // Byte range covered by the column selection in each of its rows; short rows give empty ranges
+    pub fn column_ranges(&self) -> Vec<(usize, usize)> {
+        let Some((anchor_row, anchor_col, row, col)) = self.column_selection else {
+            return Vec::new();
+        };
+        let last_row = self.content.matches('\n').count();
+        let (left, right) = (anchor_col.min(col), anchor_col.max(col));
+        (anchor_row.min(row)..=anchor_row.max(row).min(last_row))
+            .map(|row| (self.row_col_to_offset(row, left), self.row_col_to_offset(row, right)))
+            .collect()
+    }
+
+    pub fn get_column_selection_text(&self) -> Option<String> {
+        self.column_selection?;
+        let rows: Vec<&str> = self
+            .column_ranges()
+            .into_iter()
+            .map(|(start, end)| &self.content[start..end])
+            .collect();
+        Some(rows.join("\n"))
+    }
+
+    // Replaces the selected range of every row with text, as a single undo step;
+    // the selection then collapses so further typing continues on all rows
+    pub fn replace_column_selection(&mut self, text: &str) {
+        if self.read_only {
+            return;
+        }
+        let Some((anchor_row, anchor_col, row, col)) = self.column_selection else {
+            return;
+        };
+        let ranges = self.column_ranges();
+        self.push_undo_state();
+        for &(start, end) in ranges.iter().rev() {
+            self.content.replace_range(start..end, text);
+        }
+
+        let col = anchor_col.min(col) + text.chars().count();
+        self.set_column_selection((anchor_row, col), (row, col));
+        self.cursor_changed = true;
+        self.mark_edited();
+    }
+
+    // Backspace with a collapsed column selection removes the char before it on every row
+    pub fn delete_column_backward(&mut self) {
+        match self.column_selection {
+            Some((anchor_row, anchor_col, row, col)) if anchor_col == col && col > 0 => {
+                self.column_selection = Some((anchor_row, col - 1, row, col));
+                self.replace_column_selection("");
+            }
+            Some(_) => self.replace_column_selection(""),
+            None => {}
+        }
+    }
+
+    // Updates cursor and selection from the editor widget (char indices)
+    pub fn sync_cursor_from_editor(&mut self, primary: usize, secondary: usize) {
+        self.sync_cursor_bytes(self.char_to_byte(primary), self.char_to_byte(secondary));
+    }
+
+    // Same as sync_cursor_from_editor, with byte offsets
+    pub fn sync_cursor_bytes(&mut self, primary: usize, secondary: usize) {
+        self.cursor_position = primary;
+        self.selection = if primary == secondary {
+            None
+        } else {
+            Some((primary.min(secondary), primary.max(secondary)))
+        };
+    }
+
+    // Returns the (anchor, cursor) byte range the editor widget must show, once
+    pub fn take_cursor_update(&mut self) -> Option<(usize, usize)> {
+        if !self.cursor_changed {
+            return None;
+        }
+        self.cursor_changed = false;
+        Some(self.anchor_and_cursor())
+    }
+
+    // Selection as (anchor, cursor) byte offsets; both equal the cursor without a selection
+    pub fn anchor_and_cursor(&self) -> (usize, usize) {
+        match self.selection {
+            Some((start, end)) if end == self.cursor_position => (start, end),
+            Some((start, end)) => (end, start),
+            None => (self.cursor_position, self.cursor_position),
+        }
+    }
+
+    // Word characters immediately before the cursor
+    pub fn word_before_cursor(&self) -> &str {
+        let before = &self.content[..self.cursor_position];
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(self.cursor_position);
+        &before[start..]
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.content.matches('\n').count() + 1
+    }
+
+    // Bytes of the given lines, without the newline ending the last of them
+    pub fn line_byte_range(&self, lines: Range<usize>) -> Range<usize> {
+        let line_start = |line: usize| match line {
+            0 => Some(0),
+            _ => self.content.match_indices('\n').nth(line - 1).map(|(i, _)| i + 1),
+        };
+        let start = line_start(lines.start).unwrap_or(self.content.len());
+        let end = line_start(lines.end).map_or(self.content.len(), |next| next.saturating_sub(1)).max(start);
+        start..end
+    }
+
+    // Byte offset where the 1-based line starts; past the last line gives the end of the text
+    pub fn line_start_offset(&self, line_number: usize) -> usize {
+        self.line_byte_range(line_number.saturating_sub(1)..line_number).start
+    }
+
+    pub fn cursor_line(&self) -> usize {
+        self.content[..self.cursor_position].matches('\n').count() + 1
+    }
+
+    pub fn cursor_column(&self) -> usize {
+        if let Some(last_newline) = self.content[..self.cursor_position].rfind('\n') {
+            self.cursor_position - last_newline - 1
+        } else {
+            self.cursor_position
+        }
+    }
+
+    // Statistics
+    pub fn calculate_stats(&self) -> DocumentStats {
+        DocumentStats::for_text(&self.content)
+    }
+
+    // Statistics for the selected text only, if anything is selected
+    pub fn calculate_stats_for_selection(&self) -> Option<DocumentStats> {
+        self.selection
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| DocumentStats::for_text(&self.content[start..end]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(text: &str) -> Document {
+        Document::with_content("test", text.to_string())
+    }
+
+    fn file_doc(name: &str, text: &str) -> Document {
+        Document::from_file_content(Path::new(name), text.to_string())
+    }
+
+    const COMMENTED: [(&str, &str); 6] =
+        [("rs", "//"), ("js", "//"), ("ts", "//"), ("py", "#"), ("toml", "#"), ("ini", "#")];
+
+    #[test]
+    fn abbreviations_and_decimals_do_not_end_sentences() {
+        assert_eq!(count_sentences("Dr. Smith paid 3.50."), 1);
+        assert_eq!(count_sentences("A. Pushkin wrote it, e.g. this one etc. and more."), 1);
+        assert_eq!(count_sentences("It works! Does it?! Yes... \"Fine.\" Done"), 4);
+        assert_eq!(count_sentences(""), 0);
+    }
+
+    #[test]
+    fn unique_words_ignore_case_and_punctuation() {
+        assert_eq!(unique_word_count("The cat saw the CAT, then the dog."), 5);
+        assert_eq!(unique_word_count(" -- "), 0);
+    }
+
+    #[test]
+    fn readability_counts_sentences_like_the_stats() {
+        let stats = DocumentStats::for_text("Dr. Smith paid 3.50.");
+        assert_eq!(stats.sentences, 1);
+        // 4 words, 3 syllables ("3.50" has none) in one sentence
+        let grade = 0.39 * 4.0 + 11.8 * 0.75 - 15.59;
+        assert!((stats.flesch_kincaid_grade - grade).abs() < 1e-4, "{}", stats.flesch_kincaid_grade);
+        assert_eq!(stats.flesch_reading_ease, 100.0);
+    }
+
+    fn selected(doc: &Document) -> &str {
+        let (start, end) = doc.selection().expect("a match is selected");
+        &doc.content[start..end]
+    }
+
+    #[test]
+    fn case_insensitive_find_keeps_offsets_after_dotted_capital_i() {
+        // 'İ' lowercases to two chars, which used to shift every later match
+        let mut doc = doc("İstanbul cat CAT");
+        doc.find_text = "cat".to_string();
+        assert!(doc.find_next());
+        assert_eq!(doc.selection(), Some((10, 13)));
+        assert!(doc.find_next());
+        assert_eq!(selected(&doc), "CAT");
+        assert!(doc.find_previous());
+        assert_eq!(doc.selection(), Some((10, 13)));
+    }
+
+    #[test]
+    fn case_sensitive_find_skips_other_cases() {
+        let mut doc = doc("Cat cat CAT cat");
+        doc.find_text = "cat".to_string();
+        doc.match_case = true;
+        assert!(doc.find_next());
+        assert_eq!(doc.selection(), Some((4, 7)));
+        assert!(doc.find_next());
+        assert_eq!(doc.selection(), Some((12, 15)));
+        assert!(!doc.find_next());
+    }
+
+    #[test]
+    fn find_previous_wraps_to_the_last_match() {
+        let mut doc = doc("aXa xa İ");
+        doc.find_text = "xa".to_string();
+        assert!(doc.find_next());
+        assert_eq!(doc.selection(), Some((1, 3)));
+        // Nothing before the first match: the next search starts from the end
+        assert!(!doc.find_previous());
+        assert!(doc.find_previous());
+        assert_eq!(doc.selection(), Some((4, 6)));
+    }
+
+    #[test]
+    fn find_previous_finds_overlapping_matches() {
+        let mut doc = doc("aaa");
+        doc.find_text = "aa".to_string();
+        doc.current_find_pos = 3;
+        assert!(doc.find_previous());
+        assert_eq!(doc.selection(), Some((1, 3)));
+    }
+
+    #[test]
+    fn read_only_documents_ignore_undo_and_redo() {
+        let mut doc = doc("one");
+        doc.insert_at_cursor("two ");
+        doc.undo();
+        doc.read_only = true;
+        doc.redo();
+        assert_eq!(doc.content, "one");
+
+        doc.read_only = false;
+        doc.redo();
+        doc.read_only = true;
+        doc.undo();
+        assert_eq!(doc.content, "two one");
+        assert!(doc.can_undo());
+    }
+
+    #[test]
+    fn read_only_documents_ignore_wrapping_and_column_edits() {
+        let mut doc = doc("ab\ncd");
+        doc.read_only = true;
+        assert_eq!(doc.wrap_selection((0, 2), "(", ")"), (0, 2));
+        doc.set_column_selection((0, 0), (1, 1));
+        doc.replace_column_selection("x");
+        assert_eq!(doc.content, "ab\ncd");
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn read_only_documents_ignore_replace_and_case_conversion() {
+        let mut doc = doc("foo bar foo");
+        doc.read_only = true;
+        doc.find_text = "foo".into();
+        doc.replace_text = "baz".into();
+        doc.replace_all();
+        assert!(!doc.replace_next());
+        doc.selection = Some((0, 7));
+        doc.convert_case(CaseMode::Upper);
+        assert_eq!(doc.content, "foo bar foo");
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn sorting_an_empty_selection_changes_nothing() {
+        let mut doc = doc("b\na\n");
+        assert!(!doc.sort_lines_in_range((2, 2), false, false));
+        assert!(!doc.deduplicate_lines_in_range((2, 2)));
+        assert_eq!(doc.content, "b\na\n");
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn sorting_a_single_line_changes_nothing() {
+        let mut doc = doc("zeta\nb\nalpha");
+        assert!(!doc.sort_lines_in_range((5, 6), true, true));
+        assert_eq!(doc.content, "zeta\nb\nalpha");
+    }
+
+    #[test]
+    fn sorting_keeps_the_text_around_the_selected_lines() {
+        let mut doc = doc("head\nc\nB\na\ntail");
+        // From the middle of "c" to the middle of "a": both lines are sorted whole
+        assert!(doc.sort_lines_in_range((5, 10), false, true));
+        assert_eq!(doc.content, "head\na\nB\nc\ntail");
+        doc.undo();
+        assert_eq!(doc.content, "head\nc\nB\na\ntail");
+    }
+
+    #[test]
+    fn deduplicating_identical_lines_leaves_one() {
+        let mut doc = doc("x\nx\nx");
+        assert!(doc.deduplicate_lines_in_range((0, 5)));
+        assert_eq!(doc.content, "x");
+    }
+
+    #[test]
+    fn toggle_comment_comments_plain_lines() {
+        for (ext, prefix) in COMMENTED {
+            let mut doc = file_doc(&format!("a.{}", ext), "one\n  two\n");
+            assert!(doc.toggle_line_comment(Some((0, 9))), "{}", ext);
+            assert_eq!(doc.content, format!("{p} one\n  {p} two\n", p = prefix), "{}", ext);
+        }
+    }
+
+    #[test]
+    fn toggle_comment_uncomments_commented_lines() {
+        for (ext, prefix) in COMMENTED {
+            let text = format!("{p} one\n  {p}two\n", p = prefix);
+            let mut doc = file_doc(&format!("a.{}", ext), &text);
+            assert!(doc.toggle_line_comment(Some((0, text.len()))), "{}", ext);
+            assert_eq!(doc.content, "one\n  two\n", "{}", ext);
+        }
+    }
+
+    #[test]
+    fn toggle_comment_flips_each_line_of_a_mixed_selection() {
+        for (ext, prefix) in COMMENTED {
+            let text = format!("{p} one\ntwo\n\n{p} three", p = prefix);
+            let mut doc = file_doc(&format!("a.{}", ext), &text);
+            assert!(doc.toggle_line_comment(Some((0, text.len()))), "{}", ext);
+            assert_eq!(doc.content, format!("one\n{p} two\n\nthree", p = prefix), "{}", ext);
+            // The whole toggle is a single undo step
+            doc.undo();
+            assert_eq!(doc.content, text, "{}", ext);
+        }
+    }
+
+    #[test]
+    fn toggle_comment_without_selection_affects_the_cursor_line() {
+        let mut doc = file_doc("main.rs", "one\ntwo");
+        doc.set_cursor_position(5);
+        assert!(doc.toggle_line_comment(None));
+        assert_eq!(doc.content, "one\n// two");
+    }
+
+    #[test]
+    fn toggle_comment_skips_files_without_line_comments() {
+        let mut doc = file_doc("index.html", "<p>one</p>");
+        assert!(!doc.toggle_line_comment(Some((0, 10))));
+        assert_eq!(doc.content, "<p>one</p>");
+    }
+
+    #[test]
+    fn sorting_sorted_lines_is_idempotent() {
+        let mut doc = doc("c\na\nb");
+        assert!(doc.sort_lines_in_range((0, 5), false, false));
+        assert_eq!(doc.content, "a\nb\nc");
+        assert!(!doc.sort_lines_in_range((0, 5), false, false));
+        assert_eq!(doc.content, "a\nb\nc");
+    }
+
+    #[test]
+    fn selection_stats_cover_only_the_selected_text() {
+        let mut doc = doc("Один два три.\nЧетыре пять");
+        assert!(doc.calculate_stats_for_selection().is_none());
+
+        // "два три.\nЧет"
+        let start = "Один ".len();
+        doc.sync_cursor_bytes(start, start + "два три.\nЧет".len());
+        let stats = doc.calculate_stats_for_selection().unwrap();
+        assert_eq!(stats.words, 3);
+        assert_eq!(stats.characters, 12);
+        assert_eq!(stats.characters_no_spaces, 10);
+        assert_eq!(stats.lines, 2);
+        assert_eq!(doc.calculate_stats().words, 5);
+    }
+
+    #[test]
+    fn syllables_are_counted_by_vowel_groups() {
+        let counts: Vec<usize> = ["the", "make", "table", "reading", "rhythm", "Hello,", "42"]
+            .into_iter()
+            .map(count_syllables)
+            .collect();
+        assert_eq!(counts, [1, 1, 2, 2, 1, 2, 0]);
+    }
+
+    #[test]
+    fn flesch_kincaid_scores_match_the_formula() {
+        // 3 words, 6 syllables (2 + 1 + 3), 1 sentence
+        let (ease, grade) = readability("Reading is enjoyable.", 3);
+        assert!((ease - (206.835 - 1.015 * 3.0 - 84.6 * 2.0)).abs() < 1e-3, "{}", ease);
+        assert!((grade - (0.39 * 3.0 + 11.8 * 2.0 - 15.59)).abs() < 1e-3, "{}", grade);
+
+        // One syllable per word pushes the ease past 100, so it is clamped
+        let (ease, grade) = readability("The cat sat on the mat.", 6);
+        assert_eq!(ease, 100.0);
+        assert!((grade - (0.39 * 6.0 + 11.8 - 15.59)).abs() < 1e-3, "{}", grade);
+
+        assert_eq!(readability("", 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn target_progress_handles_zero_and_exceeded_targets() {
+        let stats = DocumentStats::for_text("one two three four five");
+        assert_eq!(stats.target_progress(10), 0.5);
+        assert_eq!(stats.target_progress(5), 1.0);
+        assert_eq!(stats.target_progress(2), 2.5);
+        // No target counts as reached
+        assert_eq!(stats.target_progress(0), 1.0);
+        assert_eq!(DocumentStats::for_text("").target_progress(100), 0.0);
+    }
+
+    #[test]
+    fn line_endings_are_detected_by_kind() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a\nb"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no breaks"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\nc"), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn crlf_file_normalized_to_lf_saves_without_cr() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("windows.txt");
+        std::fs::write(&path, "one\r\ntwo\r\n").unwrap();
+
+        let mut doc = Document::load(&path).unwrap();
+        assert_eq!(doc.detected_line_ending, LineEnding::CrLf);
+        assert_eq!(doc.content, "one\ntwo\n");
+        // Saved as loaded, the file keeps its CRLF
+        doc.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\r\ntwo\r\n");
+
+        doc.normalize_line_endings(LineEnding::Lf);
+        doc.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        assert_eq!(Document::load(&path).unwrap().detected_line_ending, LineEnding::Lf);
+    }
+
+    #[test]
+    fn mixed_file_normalized_to_crlf_saves_one_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mixed.txt");
+        std::fs::write(&path, "a\r\nb\nc\rd").unwrap();
+
+        let mut doc = Document::load(&path).unwrap();
+        assert_eq!(doc.detected_line_ending, LineEnding::Mixed);
+        doc.normalize_line_endings(LineEnding::CrLf);
+        doc.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\nb\r\nc\r\nd");
+    }
+
+    #[test]
+    fn indent_adds_spaces_or_tabs_to_every_selected_line() {
+        let mut spaces = doc("one\ntwo\nthree");
+        // From inside "one" to inside "two": "three" is untouched
+        let selection = spaces.indent_selection((1, 6), IndentMode::Indent, true, 4);
+        assert_eq!(spaces.content, "    one\n    two\nthree");
+        assert_eq!(selection, Some((0, 14)));
+
+        let mut tabs = doc("one\ntwo\n");
+        tabs.indent_selection((0, 8), IndentMode::Indent, false, 4);
+        // A selection ending after the newline doesn't take in the following empty line
+        assert_eq!(tabs.content, "\tone\n\ttwo\n");
+    }
+
+    #[test]
+    fn outdent_removes_one_level_from_every_selected_line() {
+        let mut doc = doc("\tone\n      two\n  three\nfour");
+        doc.indent_selection((0, doc.content.len()), IndentMode::Outdent, true, 4);
+        assert_eq!(doc.content, "one\n  two\nthree\nfour");
+        doc.undo();
+        assert_eq!(doc.content, "\tone\n      two\n  three\nfour");
+    }
+
+    #[test]
+    fn typing_an_opener_inserts_the_pair_around_the_cursor() {
+        let mut call = doc("f");
+        call.set_cursor_position(1);
+        call.type_pair_char('(');
+        assert_eq!(call.content, "f()");
+        assert_eq!(call.cursor_position(), 2);
+        // An apostrophe after a letter is not closed
+        let mut word = doc("don");
+        word.set_cursor_position(3);
+        word.type_pair_char('\'');
+        assert_eq!(word.content, "don'");
+    }
+
+    #[test]
+    fn typing_an_opener_wraps_the_selection() {
+        let mut doc = doc("a < b");
+        doc.sync_cursor_bytes(0, 5);
+        doc.type_pair_char('[');
+        assert_eq!(doc.content, "[a < b]");
+        assert_eq!(doc.selection(), Some((1, 6)));
+        doc.type_pair_char('<');
+        assert_eq!(doc.content, "[<a < b>]");
+    }
+
+    #[test]
+    fn typing_the_closer_steps_over_it() {
+        let mut doc = doc("");
+        doc.type_pair_char('"');
+        doc.type_pair_char('"');
+        assert_eq!(doc.content, "\"\"");
+        assert_eq!(doc.cursor_position(), 2);
+    }
+
+    #[test]
+    fn duplicate_line_copies_the_cursor_line_below() {
+        let mut first = doc("one\ntwo");
+        assert_eq!(first.duplicate_line(1), 5);
+        assert_eq!(first.content, "one\none\ntwo");
+
+        let mut last = doc("one\ntwo");
+        assert_eq!(last.duplicate_line(7), 11);
+        assert_eq!(last.content, "one\ntwo\ntwo");
+
+        let mut single = doc("solo");
+        single.duplicate_line(0);
+        assert_eq!(single.content, "solo\nsolo");
+
+        let mut unicode = doc("а\nпривет 👋\nб");
+        let cursor = unicode.duplicate_line("а\nпри".len());
+        assert_eq!(unicode.content, "а\nпривет 👋\nпривет 👋\nб");
+        assert_eq!(&unicode.content[..cursor], "а\nпривет 👋\nпри");
+    }
+
+    #[test]
+    fn delete_line_removes_the_cursor_line() {
+        let mut first = doc("one\ntwo\nthree");
+        assert_eq!(first.delete_line(2), 0);
+        assert_eq!(first.content, "two\nthree");
+
+        // The last line takes the preceding newline with it
+        let mut last = doc("one\ntwo\nthree");
+        assert_eq!(last.delete_line(10), 4);
+        assert_eq!(last.content, "one\ntwo");
+
+        let mut single = doc("solo");
+        assert_eq!(single.delete_line(2), 0);
+        assert_eq!(single.content, "");
+        single.undo();
+        assert_eq!(single.content, "solo");
+
+        let mut unicode = doc("а\nпривет 👋\nб");
+        assert_eq!(unicode.delete_line("а\nпривет".len()), "а\n".len());
+        assert_eq!(unicode.content, "а\nб");
+    }
+
+    #[test]
+    fn move_line_up_swaps_with_the_previous_line() {
+        let mut doc = doc("one\ntwo\nthree");
+        // Cursor after "tw": it stays after "tw" on the first line
+        assert_eq!(doc.move_line_up(6), 2);
+        assert_eq!(doc.content, "two\none\nthree");
+        // Already at the top
+        assert_eq!(doc.move_line_up(2), 2);
+        assert_eq!(doc.content, "two\none\nthree");
+    }
+
+    #[test]
+    fn move_line_down_swaps_with_the_next_line() {
+        let mut doc = doc("one\ntwo\nthree");
+        // Cursor after "tw" ends up after "tw" on the last line
+        let cursor = doc.move_line_down(6);
+        assert_eq!(doc.content, "one\nthree\ntwo");
+        assert_eq!(cursor, 12);
+        assert_eq!(doc.offset_to_row_col(cursor), (2, 2));
+        // Already at the bottom
+        assert_eq!(doc.move_line_down(cursor), cursor);
+        assert_eq!(doc.content, "one\nthree\ntwo");
+        doc.undo();
+        assert_eq!(doc.content, "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn column_selection_extracts_ragged_rows() {
+        let mut doc = doc("abcdef\nab\nабвгде\na");
+        // Dragged from the bottom right up to the top left
+        doc.set_column_selection((3, 4), (0, 2));
+        assert_eq!(doc.get_column_selection_text().unwrap(), "cd\n\nвг\n");
+    }
+
+    #[test]
+    fn column_replacement_edits_every_row_in_one_undo_step() {
+        let mut doc = doc("abcdef\nab\nабвгде\na");
+        doc.set_column_selection((0, 2), (3, 4));
+        doc.replace_column_selection("X");
+        // Rows too short for the selection get the text at their end
+        assert_eq!(doc.content, "abXef\nabX\nабXде\naX");
+        assert_eq!(doc.column_selection(), Some((0, 3, 3, 3)));
+
+        // The last row is shorter than the column, so its "X" is out of reach
+        doc.delete_column_backward();
+        assert_eq!(doc.content, "abef\nab\nабде\naX");
+        doc.undo();
+        doc.undo();
+        assert_eq!(doc.content, "abcdef\nab\nабвгде\na");
+    }
+
+    #[test]
+    fn html_export_escapes_script_tags() {
+        let mut plain = doc("<script>alert(1)</script>\na & b");
+        plain.title = "<b>title</b>".to_string();
+        let html = plain.to_html();
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;<br>\na &amp; b"), "{}", html);
+        assert!(html.contains("<title>&lt;b&gt;title&lt;/b&gt;</title>"), "{}", html);
+        assert!(!html.contains("<script>"));
+
+        let markdown = file_doc("notes.md", "# Title\n\n<script>alert(1)</script>");
+        let html = markdown.to_html();
+        assert!(html.contains("<h1>Title</h1>"), "{}", html);
+        assert!(!html.contains("<script>"), "{}", html);
+    }
+
+    #[test]
+    fn restoring_a_checkpoint_can_be_undone() {
+        let mut doc = doc("draft");
+        doc.create_checkpoint("first");
+        doc.set_cursor_position(5);
+        doc.insert_at_cursor(" two");
+        assert_eq!(doc.checkpoints()[0], ("first".to_string(), "draft".to_string()));
+
+        assert!(doc.restore_checkpoint(0));
+        assert_eq!(doc.content, "draft");
+        assert!(doc.cursor_position() <= doc.content.len());
+        doc.undo();
+        assert_eq!(doc.content, "draft two");
+        doc.redo();
+        assert_eq!(doc.content, "draft");
+
+        assert!(!doc.restore_checkpoint(1));
+    }
+
+    #[test]
+    fn bookmarks_remember_the_cursor_line() {
+        let mut doc = doc("один\nдва\n\nчетыре");
+        let offsets = [(0, 1), ("один".len(), 1), ("один\n".len(), 2), ("один\nдва\n".len(), 3), (doc.content.len(), 4)];
+        for (offset, line) in offsets {
+            doc.set_cursor_position(offset);
+            assert_eq!(doc.cursor_line(), line, "offset {}", offset);
+        }
+
+        doc.set_cursor_position("один\nдв".len());
+        doc.add_bookmark("два");
+        doc.set_cursor_position(doc.content.len());
+        doc.add_bookmark("конец");
+        assert_eq!(doc.bookmarks().iter().map(|b| b.line).collect::<Vec<_>>(), [2, 4]);
+
+        // Jumping goes to the start of the bookmarked line
+        doc.goto_bookmark(0);
+        assert_eq!(doc.cursor_position(), "один\n".len());
+        doc.remove_bookmark(0);
+        assert_eq!(doc.bookmarks()[0].label, "конец");
+    }
+
+    #[test]
+    fn formatting_pretty_json_is_idempotent() {
+        let pretty = "{\n  \"b\": [\n    1,\n    2\n  ],\n  \"a\": null\n}\n";
+        let mut doc = doc(pretty);
+        doc.format_as_json().unwrap();
+        assert_eq!(doc.content, pretty);
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn minified_json_is_expanded_keeping_key_order() {
+        let mut doc = doc(r#"{"b":[1,2],"a":{"x":"y"}}"#);
+        doc.format_as_json().unwrap();
+        assert_eq!(doc.content, "{\n  \"b\": [\n    1,\n    2\n  ],\n  \"a\": {\n    \"x\": \"y\"\n  }\n}");
+        doc.minify_json().unwrap();
+        assert_eq!(doc.content, r#"{"b":[1,2],"a":{"x":"y"}}"#);
+    }
+
+    #[test]
+    fn invalid_json_leaves_the_content_alone() {
+        let mut doc = doc("{\"a\": 1,}");
+        assert!(doc.format_as_json().is_err());
+        assert!(doc.minify_json().is_err());
+        assert_eq!(doc.content, "{\"a\": 1,}");
+        assert!(!doc.is_modified());
+    }
+
+    #[test]
+    fn format_duration_edge_cases() {
+        assert_eq!(format_duration(0), "0 с");
+        assert_eq!(format_duration(59), "59 с");
+        assert_eq!(format_duration(60), "1 мин");
+        assert_eq!(format_duration(62), "1 мин 2 с");
+        assert_eq!(format_duration(3600), "1 ч");
+        assert_eq!(format_duration(3722), "1 ч 2 мин 2 с");
+    }
+
+    #[test]
+    fn reading_time_at_pace() {
+        assert_eq!(DocumentStats::for_text("").reading_time_seconds, 0);
+        assert_eq!(DocumentStats::for_text("word").reading_time_seconds, 1);
+        let text = "word ".repeat(400);
+        let stats = DocumentStats::for_text(&text);
+        assert_eq!(stats.reading_time_seconds, 120);
+        assert_eq!(stats.speaking_time_seconds, 400 * 60 / 130);
+    }
+
+    #[test]
+    fn url_encoding_uses_known_escapes() {
+        let text = "a b/é";
+        let mut encoded = doc(text);
+        assert!(encoded.url_encode_selection((0, text.len())));
+        assert_eq!(encoded.content, "a%20b%2F%C3%A9");
+        assert_eq!(encoded.selection(), Some((0, encoded.content.len())));
+
+        let len = encoded.content.len();
+        assert_eq!(encoded.url_decode_selection((0, len)), Ok(true));
+        assert_eq!(encoded.content, text);
+
+        encoded.undo();
+        assert_eq!(encoded.content, "a%20b%2F%C3%A9");
+    }
+
+    #[test]
+    fn url_decoding_invalid_utf8_keeps_text() {
+        let mut invalid = doc("x=%C3");
+        assert!(invalid.url_decode_selection((2, 5)).is_err());
+        assert_eq!(invalid.content, "x=%C3");
+        assert!(!invalid.can_undo());
+    }
+
+    #[test]
+    fn base64_round_trip_restores_text() {
+        let text = "Привет, мир!";
+        let mut encoded = doc(text);
+        assert!(encoded.base64_encode_selection((0, text.len())));
+        assert_eq!(encoded.content, "0J/RgNC40LLQtdGCLCDQvNC40YAh");
+
+        let len = encoded.content.len();
+        assert!(encoded.base64_decode_selection((0, len)));
+        assert_eq!(encoded.content, text);
+        assert_eq!(encoded.take_last_error(), None);
+    }
+
+    #[test]
+    fn invalid_base64_sets_last_error() {
+        let mut invalid = doc("not base64!");
+        assert!(!invalid.base64_decode_selection((0, 11)));
+        assert_eq!(invalid.content, "not base64!");
+        assert!(!invalid.can_undo());
+        assert!(invalid.take_last_error().is_some());
+        assert_eq!(invalid.take_last_error(), None);
+    }
+
+    #[test]
+    fn join_lines_collapses_trailing_spaces() {
+        let text = "one  \n  two \n\n three\nrest";
+        let mut joined = doc(text);
+        assert!(joined.join_lines((0, 20)));
+        assert_eq!(joined.content, "one two three\nrest");
+        assert_eq!(joined.selection(), Some((0, 13)));
+
+        let mut single = doc("one line");
+        assert!(!single.join_lines((0, 8)));
+    }
+
+    #[test]
+    fn wrap_to_width_breaks_at_boundary() {
+        let mut fits = doc("aaaa bbbb cccc");
+        assert!(fits.wrap_to_width((0, 0), 9));
+        assert_eq!(fits.content, "aaaa bbbb\ncccc");
+
+        let mut narrow = doc("aaaa bbbb cccc");
+        assert!(narrow.wrap_to_width((0, 0), 8));
+        assert_eq!(narrow.content, "aaaa\nbbbb\ncccc");
+
+        let mut indented = doc("  aaaa bbbb\nshort");
+        assert!(indented.wrap_to_width((0, 17), 8));
+        assert_eq!(indented.content, "  aaaa\n  bbbb\nshort");
+    }
+
+    #[test]
+    fn wrap_to_width_counts_unicode_chars() {
+        let mut wrapped = doc("Съешь же ещё этих мягких");
+        assert!(wrapped.wrap_to_width((0, 0), 12));
+        assert_eq!(wrapped.content, "Съешь же ещё\nэтих мягких");
+
+        let mut long_word = doc("ширококолейный путь");
+        assert!(long_word.wrap_to_width((0, 0), 5));
+        assert_eq!(long_word.content, "ширококолейный\nпуть");
+    }
+
+    #[test]
+    fn is_binary_detects_control_bytes() {
+        assert!(is_binary(b"MZ\x90\x00\x03"));
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"));
+        assert!(!is_binary(b"plain ASCII text\r\n\twith tabs\n"));
+        assert!(!is_binary("Привет, мир".as_bytes()));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn is_binary_only_sniffs_the_start() {
+        let mut bytes = vec![b'a'; BINARY_SNIFF_LEN];
+        bytes.push(0);
+        assert!(!is_binary(&bytes));
+        bytes[BINARY_SNIFF_LEN - 1] = 0;
+        assert!(is_binary(&bytes));
+    }
+
+    #[test]
+    fn diff_from_saved_tracks_edits() {
+        let mut edited = file_doc("notes.txt", "one\ntwo\n");
+        assert!(edited.diff_from_saved().iter().all(|line| matches!(line, LineDiff::Unchanged(_))));
+
+        edited.content = "one\n2\nthree\n".to_string();
+        assert_eq!(diff::count_changes(&edited.diff_from_saved()), (2, 1));
+    }
+
+    #[test]
+    fn passive_ratio_for_mixed_sentences() {
+        let stats = DocumentStats::for_text("The cat chased the mouse. The report was written by Anna.");
+        assert_eq!(stats.passive_voice_ratio, 0.5);
+        assert_eq!(stats.avg_sentence_length, 5.5);
+    }
+
+    #[test]
+    fn passive_ratio_for_all_passive_text() {
+        let text = "The house was built in 1900. Mistakes were made. The letters are being sent today. \
+                    The bill has been quickly paid.";
+        let stats = DocumentStats::for_text(text);
+        assert!((stats.passive_voice_ratio - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn passive_ratio_ignores_look_alikes() {
+        // "tired" still counts: the heuristic cannot tell adjectives from participles
+        let stats = DocumentStats::for_text("The door is open. She was often late. He is tired.");
+        assert!((stats.passive_voice_ratio - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(DocumentStats::for_text("").passive_voice_ratio, 0.0);
+    }
+
+    #[test]
+    fn wrap_keeps_abbreviation_with_next_word() {
+        let mut wrapped = doc("Dr. Smith visited the clinic.");
+        assert!(wrapped.wrap_to_width((0, 0), 10));
+        assert_eq!(wrapped.content, "Dr. Smith\nvisited\nthe\nclinic.");
+
+        let mut moved = doc("Hello Dr. Smith");
+        assert!(moved.wrap_to_width((0, 0), 10));
+        assert_eq!(moved.content, "Hello\nDr. Smith");
+    }
+
+    #[test]
+    fn wrap_breaks_after_abbreviation_without_other_space() {
+        let mut wrapped = doc("Dr. Smithsonian");
+        assert!(wrapped.wrap_to_width((0, 0), 10));
+        assert_eq!(wrapped.content, "Dr.\nSmithsonian");
+    }
+
+    #[test]
+    fn abbreviation_patterns() {
+        for word in ["Dr.", "Mrs.", "e.g.", "i.e.", "vs.", "J.", "a.m.", "(Dr."] {
+            assert!(is_abbreviation(word), "{}", word);
+        }
+        for word in ["Dr", "end.", "USA.", "ab.", "3.50."] {
+            assert!(!is_abbreviation(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn newline_without_indent_starts_at_column_zero() {
+        let mut doc = doc("fn main() {");
+        doc.cursor_position = doc.content.len();
+        doc.insert_newline_with_indent(true, 4);
+        assert_eq!(doc.content, "fn main() {\n");
+        assert_eq!(doc.cursor_position, doc.content.len());
+    }
+
+    #[test]
+    fn newline_keeps_space_indent() {
+        let mut doc = doc("    let x = 1;");
+        doc.cursor_position = doc.content.len();
+        doc.insert_newline_with_indent(true, 4);
+        assert_eq!(doc.content, "    let x = 1;\n    ");
+        assert_eq!(doc.cursor_position, doc.content.len());
+    }
+
+    #[test]
+    fn newline_keeps_tab_indent() {
+        let mut tabbed = doc("\tlet x = 1;");
+        tabbed.cursor_position = tabbed.content.len();
+        tabbed.insert_newline_with_indent(false, 4);
+        assert_eq!(tabbed.content, "\tlet x = 1;\n\t");
+
+        // With spaces preferred the tab is expanded to tab_size columns
+        let mut spaced = doc("\tlet x = 1;");
+        spaced.cursor_position = spaced.content.len();
+        spaced.insert_newline_with_indent(true, 4);
+        assert_eq!(spaced.content, "\tlet x = 1;\n    ");
+    }
+
+    #[test]
+    fn newline_on_whitespace_only_line_copies_the_whitespace() {
+        let mut doc = doc("a\n  \t");
+        doc.cursor_position = doc.content.len();
+        doc.insert_newline_with_indent(false, 4);
+        assert_eq!(doc.content, "a\n  \t\n\t");
+        assert_eq!(doc.cursor_position, doc.content.len());
+    }
+
+    // Wraps "word" in "a word b" and checks the content and the inner selection
+    fn assert_wraps(open: &str, close: &str) {
+        assert!(WRAP_DELIMITERS.contains(&(open, close)));
+        let mut doc = doc("a word b");
+        let inner = doc.wrap_selection((2, 6), open, close);
+        assert_eq!(doc.content, format!("a {}word{} b", open, close));
+        assert_eq!(inner, (2 + open.len(), 6 + open.len()));
+        assert_eq!(&doc.content[inner.0..inner.1], "word");
+        assert_eq!(doc.selection, Some(inner));
+        assert_eq!(doc.cursor_position, inner.1);
+    }
+
+    #[test]
+    fn wrap_selection_in_double_quotes() {
+        assert_wraps("\"", "\"");
+    }
+
+    #[test]
+    fn wrap_selection_in_single_quotes() {
+        assert_wraps("'", "'");
+    }
+
+    #[test]
+    fn wrap_selection_in_parentheses() {
+        assert_wraps("(", ")");
+    }
+
+    #[test]
+    fn wrap_selection_in_brackets() {
+        assert_wraps("[", "]");
+    }
+
+    #[test]
+    fn wrap_selection_in_braces() {
+        assert_wraps("{", "}");
+    }
+
+    #[test]
+    fn wrap_selection_in_angle_brackets() {
+        assert_wraps("<", ">");
+    }
+
+    #[test]
+    fn wrap_selection_in_markdown_bold() {
+        assert_wraps("**", "**");
+    }
+
+    #[test]
+    fn line_window_at_the_top() {
+        assert_eq!(line_window(10_000, 0.0, 40), 0..40 + LINE_WINDOW_MARGIN);
+    }
+
+    #[test]
+    fn line_window_in_the_middle() {
+        // 9960 hidden lines, half of them above the viewport
+        assert_eq!(line_window(10_000, 0.5, 40), 4980 - LINE_WINDOW_MARGIN..5020 + LINE_WINDOW_MARGIN);
+    }
+
+    #[test]
+    fn line_window_at_the_bottom() {
+        assert_eq!(line_window(10_000, 1.0, 40), 9960 - LINE_WINDOW_MARGIN..10_000);
+    }
+
+    #[test]
+    fn line_window_is_clamped_to_the_document() {
+        assert_eq!(line_window(10_000, 1.5, 40), 9960 - LINE_WINDOW_MARGIN..10_000);
+        assert_eq!(line_window(10_000, -0.5, 40), 0..40 + LINE_WINDOW_MARGIN);
+        // A document shorter than the viewport is laid out whole
+        assert_eq!(line_window(20, 1.0, 40), 0..20);
+    }
+
+    #[test]
+    fn viewport_geometry_for_line_window() {
+        assert_eq!(visible_line_count(400.0, 20.0), 21);
+        assert_eq!(visible_line_count(410.0, 20.0), 22);
+        assert_eq!(scroll_fraction(0.0, 2000.0, 400.0), 0.0);
+        assert_eq!(scroll_fraction(800.0, 2000.0, 400.0), 0.5);
+        assert_eq!(scroll_fraction(1600.0, 2000.0, 400.0), 1.0);
+        assert_eq!(scroll_fraction(0.0, 300.0, 400.0), 0.0);
+    }
+
+    #[test]
+    fn incremental_search_follows_each_typed_char() {
+        let mut doc = doc("xa ab abc");
+        let mut typed = String::new();
+        for (c, expected) in [('a', (1, 2)), ('b', (3, 5)), ('c', (6, 9))] {
+            let previous = typed.clone();
+            typed.push(c);
+            doc.find_text = typed.clone();
+            assert!(doc.find_incremental(&previous));
+            assert_eq!(doc.selection, Some(expected), "after typing {:?}", typed);
+        }
+    }
+
+    #[test]
+    fn incremental_search_extends_the_current_match_in_place() {
+        let mut doc = doc("ab ab");
+        doc.find_text = "a".into();
+        assert!(doc.find_incremental(""));
+        doc.find_next();
+        assert_eq!(doc.selection, Some((3, 4)));
+        doc.find_text = "ab".into();
+        assert!(doc.find_incremental("a"));
+        assert_eq!(doc.selection, Some((3, 5)));
+        doc.find_text.clear();
+        assert!(!doc.find_incremental("ab"));
+        assert_eq!(doc.selection, None);
+    }
+}
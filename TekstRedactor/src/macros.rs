@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::document::{CaseMode, Document};
+
+// Одно действие записанного макроса
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MacroAction {
+    InsertText(String),
+    DeleteChars(usize),
+    MoveCursor(isize),
+    FindNext,
+    ReplaceOne,
+    ConvertCase(CaseMode),
+}
+
+impl MacroAction {
+    pub fn apply(&self, doc: &mut Document, auto_close_pairs: bool) {
+        match self {
+            MacroAction::InsertText(text) => match pair_char(text) {
+                Some(c) if auto_close_pairs => doc.type_pair_char(c),
+                _ => doc.insert_at_cursor(text),
+            },
+            MacroAction::DeleteChars(count) => doc.delete_before_cursor(*count),
+            MacroAction::MoveCursor(delta) => doc.move_cursor(*delta),
+            MacroAction::FindNext => {
+                if !doc.find_next() {
+                    doc.find_next();
+                }
+            }
+            MacroAction::ReplaceOne => {
+                doc.replace_next();
+            }
+            MacroAction::ConvertCase(mode) => doc.convert_case(*mode),
+        }
+    }
+}
+
+// Скобка или кавычка, которую редактор может закрыть автоматически
+fn pair_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if "([{\"')]}".contains(c) => Some(c),
+        _ => None,
+    }
+}
+
+// Добавляет действие, склеивая подряд идущий ввод и перемещения;
+// скобки остаются отдельными, чтобы при повторе закрываться так же, как при записи
+pub fn push_action(actions: &mut Vec<MacroAction>, action: MacroAction) {
+    match (actions.last_mut(), &action) {
+        (Some(MacroAction::InsertText(last)), MacroAction::InsertText(text))
+            if pair_char(last).is_none() && pair_char(text).is_none() =>
+        {
+            last.push_str(text)
+        }
+        (Some(MacroAction::DeleteChars(last)), MacroAction::DeleteChars(count)) => *last += count,
+        (Some(MacroAction::MoveCursor(last)), MacroAction::MoveCursor(delta)) => *last += delta,
+        _ => actions.push(action),
+    }
+}
+
+pub fn play(actions: &[MacroAction], doc: &mut Document, times: usize, auto_close_pairs: bool) {
+    for _ in 0..times {
+        for action in actions {
+            action.apply(doc, auto_close_pairs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(actions: impl IntoIterator<Item = MacroAction>) -> Vec<MacroAction> {
+        let mut recorded = Vec::new();
+        for action in actions {
+            push_action(&mut recorded, action);
+        }
+        recorded
+    }
+
+    fn typed(text: &str) -> MacroAction {
+        MacroAction::InsertText(text.to_string())
+    }
+
+    #[test]
+    fn recording_merges_typing_but_keeps_brackets_apart() {
+        let recorded = record([
+            typed("a"),
+            typed("b"),
+            typed("("),
+            typed("x"),
+            MacroAction::MoveCursor(1),
+            MacroAction::MoveCursor(1),
+        ]);
+        assert_eq!(recorded, [typed("ab"), typed("("), typed("x"), MacroAction::MoveCursor(2)]);
+    }
+
+    #[test]
+    fn replay_reproduces_typing_with_and_without_auto_close() {
+        let recorded = record([
+            typed("f"),
+            typed("("),
+            typed("x"),
+            MacroAction::MoveCursor(1),
+            typed(";"),
+            typed("!"),
+            MacroAction::DeleteChars(1),
+        ]);
+
+        let mut doc = Document::new("Тест");
+        play(&recorded, &mut doc, 1, true);
+        assert_eq!(doc.content, "f(x);");
+
+        // Без автозакрытия скобка не закрывается, а перемещение упирается в конец текста
+        let mut doc = Document::new("Тест");
+        play(&recorded, &mut doc, 1, false);
+        assert_eq!(doc.content, "f(x;");
+    }
+
+    #[test]
+    fn repeated_replay_replaces_each_match_in_turn() {
+        let mut doc = Document::with_content("Тест", "foo bar foo baz foo".to_string());
+        doc.find_text = "foo".to_string();
+        doc.replace_text = "qux".to_string();
+        let recorded =
+            record([MacroAction::FindNext, MacroAction::ReplaceOne, MacroAction::ConvertCase(CaseMode::Upper)]);
+
+        play(&recorded, &mut doc, 2, true);
+        assert_eq!(doc.content, "QUX bar QUX baz foo");
+    }
+}
@@ -1,22 +1,56 @@
 mod app;
+mod autocomplete;
+mod clipboard;
+mod diff;
+mod document;
+mod macros;
+mod markdown;
+mod minimap;
+mod print;
+mod recovery;
+mod search;
+mod search_panel;
+mod session;
+mod settings;
+mod tail;
 
 use eframe::NativeOptions;
 use app::TextEditorApp;
+use settings::WindowGeometry;
 use anyhow::Result;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+// Путь из аргумента "--open <путь>", с которым окно запускается из меню "Открыть в новом окне"
+fn parse_open_arg(mut args: impl Iterator<Item = OsString>) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == "--open" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
 
 fn main() -> Result<(), eframe::Error> {
+    let open = parse_open_arg(std::env::args_os().skip(1));
+    let geometry = WindowGeometry::load();
+    let mut viewport = eframe::egui::ViewportBuilder::default()
+        .with_title("Редактор им. Жмыха Ящерицы")
+        .with_min_inner_size([800.0, 600.0])
+        .with_inner_size([geometry.window_width, geometry.window_height])
+        .with_icon(load_icon().unwrap()); // Добавляем иконку
+    if let (Some(x), Some(y)) = (geometry.window_x, geometry.window_y) {
+        viewport = viewport.with_position([x as f32, y as f32]);
+    }
     let native_options = NativeOptions {
-        viewport: eframe::egui::ViewportBuilder::default()
-            .with_title("Редактор им. Жмыха Ящерицы")
-            .with_min_inner_size([800.0, 600.0])
-            .with_icon(load_icon().unwrap()), // Добавляем иконку
+        viewport,
         ..Default::default()
     };
 
     eframe::run_native(
         "Редактор им. Жмыха Ящерицы",
         native_options,
-        Box::new(|cc| Box::new(TextEditorApp::new(cc))),
+        Box::new(|cc| Box::new(TextEditorApp::new(cc, open))),
     )
 }
 
@@ -34,4 +68,29 @@ fn load_icon() -> anyhow::Result<eframe::egui::IconData> {
         width,
         height,
     })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> impl Iterator<Item = OsString> {
+        values.iter().map(OsString::from).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn open_argument_gives_the_path() {
+        assert_eq!(parse_open_arg(args(&["--open", "C:\\Документы\\план.txt"])), Some(PathBuf::from("C:\\Документы\\план.txt")));
+    }
+
+    #[test]
+    fn open_argument_without_a_value_is_ignored() {
+        assert_eq!(parse_open_arg(args(&["--open"])), None);
+    }
+
+    #[test]
+    fn no_open_argument_gives_no_path() {
+        assert_eq!(parse_open_arg(args(&[])), None);
+        assert_eq!(parse_open_arg(args(&["план.txt"])), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,295 @@
+use eframe::egui::{self, RichText};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    Paragraph,
+    Heading(u8),
+    ListItem { depth: usize, marker: String },
+    CodeBlock,
+    Quote,
+    Rule,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub strikethrough: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub spans: Vec<Span>,
+}
+
+// Заголовок для панели структуры документа; line - номер строки, начиная с 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineEntry {
+    pub level: u8,
+    pub title: String,
+    pub line: usize,
+}
+
+// Заголовки вида "# Текст" построчно; строки внутри блоков кода ``` и ~~~ пропускаются
+pub fn parse_outline(source: &str) -> Vec<OutlineEntry> {
+    let mut entries = Vec::new();
+    let mut fence: Option<char> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let marker = if trimmed.starts_with("```") {
+            Some('`')
+        } else if trimmed.starts_with("~~~") {
+            Some('~')
+        } else {
+            None
+        };
+        if let Some(marker) = marker {
+            match fence {
+                None => fence = Some(marker),
+                Some(open) if open == marker => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let rest = &line[level..];
+        if !(1..=6).contains(&level) || !rest.starts_with([' ', '\t']) {
+            continue;
+        }
+        // Закрывающие решетки "## Текст ##" в название не входят
+        let title = rest.trim().trim_end_matches('#').trim_end();
+        if !title.is_empty() {
+            entries.push(OutlineEntry { level: level as u8, title: title.to_string(), line: index + 1 });
+        }
+    }
+
+    entries
+}
+
+fn parser_options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS
+}
+
+// Встроенный в документ HTML выводится как текст, чтобы экспорт не исполнял чужие скрипты
+pub fn to_html(source: &str) -> String {
+    let parser = Parser::new_ext(source, parser_options()).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut output = String::new();
+    html::push_html(&mut output, parser);
+    output
+}
+
+// Разбирает Markdown в плоский список блоков для отрисовки в egui
+pub fn parse_blocks(source: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut style = Span::default();
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut quote_depth = 0usize;
+    let mut in_item = false;
+
+    for event in Parser::new_ext(source, parser_options()) {
+        match event {
+            // Параграф внутри пункта списка продолжает сам пункт
+            Event::Start(Tag::Paragraph) if !in_item => {
+                let kind = if quote_depth > 0 { BlockKind::Quote } else { BlockKind::Paragraph };
+                start_block(&mut blocks, kind);
+            }
+            Event::End(TagEnd::Paragraph) => in_item = false,
+            Event::Start(Tag::Heading { level, .. }) => {
+                start_block(&mut blocks, BlockKind::Heading(level as u8));
+            }
+            Event::Start(Tag::CodeBlock(_)) => start_block(&mut blocks, BlockKind::CodeBlock),
+            Event::Start(Tag::BlockQuote(_)) => quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth = quote_depth.saturating_sub(1),
+            Event::Start(Tag::List(first)) => lists.push(first),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let depth = lists.len().saturating_sub(1);
+                let marker = match lists.last_mut() {
+                    Some(Some(number)) => {
+                        let marker = format!("{}.", number);
+                        *number += 1;
+                        marker
+                    }
+                    _ => "•".to_string(),
+                };
+                start_block(&mut blocks, BlockKind::ListItem { depth, marker });
+                in_item = true;
+            }
+            Event::End(TagEnd::Item) => in_item = false,
+            Event::Start(Tag::Strong) => style.bold = true,
+            Event::End(TagEnd::Strong) => style.bold = false,
+            Event::Start(Tag::Emphasis) => style.italic = true,
+            Event::End(TagEnd::Emphasis) => style.italic = false,
+            Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+            Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+            Event::Rule => start_block(&mut blocks, BlockKind::Rule),
+            Event::Text(text) => push_span(&mut blocks, &style, &text, false),
+            Event::Code(text) => push_span(&mut blocks, &style, &text, true),
+            Event::SoftBreak => push_span(&mut blocks, &style, " ", false),
+            Event::HardBreak => push_span(&mut blocks, &style, "\n", false),
+            Event::TaskListMarker(checked) => {
+                push_span(&mut blocks, &style, if checked { "☑ " } else { "☐ " }, false)
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+fn start_block(blocks: &mut Vec<Block>, kind: BlockKind) {
+    blocks.push(Block { kind, spans: Vec::new() });
+}
+
+fn push_span(blocks: &mut Vec<Block>, style: &Span, text: &str, code: bool) {
+    if !matches!(blocks.last(), Some(b) if b.kind != BlockKind::Rule) {
+        blocks.push(Block { kind: BlockKind::Paragraph, spans: Vec::new() });
+    }
+    if let Some(block) = blocks.last_mut() {
+        block.spans.push(Span {
+            text: text.to_string(),
+            code: code || block.kind == BlockKind::CodeBlock,
+            ..style.clone()
+        });
+    }
+}
+
+fn span_text(span: &Span, size: f32) -> RichText {
+    let mut text = RichText::new(&span.text).size(size);
+    if span.bold {
+        text = text.strong();
+    }
+    if span.italic {
+        text = text.italics();
+    }
+    if span.code {
+        text = text.code();
+    }
+    if span.strikethrough {
+        text = text.strikethrough();
+    }
+    text
+}
+
+pub fn show_blocks(ui: &mut egui::Ui, blocks: &[Block], font_size: f32) {
+    for block in blocks {
+        match &block.kind {
+            BlockKind::Heading(level) => {
+                let size = font_size * (2.0 - 0.2 * (*level as f32 - 1.0)).max(1.0);
+                ui.horizontal_wrapped(|ui| {
+                    for span in &block.spans {
+                        ui.label(span_text(span, size).strong());
+                    }
+                });
+            }
+            BlockKind::Paragraph => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for span in &block.spans {
+                        ui.label(span_text(span, font_size));
+                    }
+                });
+            }
+            BlockKind::ListItem { depth, marker } => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(*depth as f32 * 16.0);
+                    ui.label(RichText::new(marker).size(font_size));
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for span in &block.spans {
+                        ui.label(span_text(span, font_size));
+                    }
+                });
+            }
+            BlockKind::CodeBlock => {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    let code: String = block.spans.iter().map(|s| s.text.as_str()).collect();
+                    ui.label(RichText::new(code.trim_end()).monospace().size(font_size));
+                });
+            }
+            BlockKind::Quote => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("▌").weak().size(font_size));
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for span in &block.spans {
+                        ui.label(span_text(span, font_size).weak());
+                    }
+                });
+            }
+            BlockKind::Rule => {
+                ui.separator();
+            }
+        }
+        ui.add_space(font_size * 0.4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heading_and_bold_render_to_html() {
+        assert_eq!(to_html("# heading"), "<h1>heading</h1>\n");
+        assert_eq!(to_html("**bold**"), "<p><strong>bold</strong></p>\n");
+    }
+
+    #[test]
+    fn embedded_html_is_escaped() {
+        assert_eq!(to_html("<script>x</script>"), "&lt;script&gt;x&lt;/script&gt;");
+    }
+
+    #[test]
+    fn preview_blocks_keep_heading_level_and_bold_spans() {
+        let blocks = parse_blocks("## Title\n\nplain **bold**");
+        assert_eq!(blocks[0].kind, BlockKind::Heading(2));
+        assert_eq!(blocks[1].kind, BlockKind::Paragraph);
+        let spans: Vec<(&str, bool)> = blocks[1].spans.iter().map(|s| (s.text.as_str(), s.bold)).collect();
+        assert_eq!(spans, [("plain ", false), ("bold", true)]);
+    }
+
+    fn entry(level: u8, title: &str, line: usize) -> OutlineEntry {
+        OutlineEntry { level, title: title.to_string(), line }
+    }
+
+    #[test]
+    fn outline_extracts_all_six_levels() {
+        let source = "# Один\n## Два\n### Три\n#### Четыре\n##### Пять\n###### Шесть\n####### Семь\n";
+        assert_eq!(
+            parse_outline(source),
+            [
+                entry(1, "Один", 1),
+                entry(2, "Два", 2),
+                entry(3, "Три", 3),
+                entry(4, "Четыре", 4),
+                entry(5, "Пять", 5),
+                entry(6, "Шесть", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn outline_skips_non_headings() {
+        let source = "#хэштег\n#\n  # с отступом\n## Закрытый ##\nтекст # не заголовок\n";
+        assert_eq!(parse_outline(source), [entry(2, "Закрытый", 4)]);
+    }
+
+    #[test]
+    fn outline_ignores_hashes_in_code_fences() {
+        let source = "# Начало\n```bash\n# комментарий\n~~~\n# все еще код\n```\n~~~\n## В тильдах\n~~~\n## Конец\n";
+        assert_eq!(parse_outline(source), [entry(1, "Начало", 1), entry(2, "Конец", 10)]);
+    }
+}
@@ -0,0 +1,155 @@
+use eframe::egui::{self, Color32, Sense};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+// Высота строки и ширина символа на миникарте, в пикселях
+const LINE_HEIGHT: f32 = 2.0;
+const CHAR_WIDTH: f32 = 1.0;
+// Доля прокрутки хранится целым числом миллионных долей
+const FRACTION_SCALE: f32 = 1_000_000.0;
+
+// Общая доля прокрутки редактора и миникарты: редактор записывает ее после кадра,
+// миникарта прокручивается на ту же долю своей высоты
+#[derive(Debug, Clone, Default)]
+pub struct SyncScroll {
+    scroll_fraction: Arc<AtomicU32>,
+}
+
+impl SyncScroll {
+    pub fn fraction(&self) -> f32 {
+        self.scroll_fraction.load(Ordering::Relaxed) as f32 / FRACTION_SCALE
+    }
+
+    pub fn set_fraction(&self, fraction: f32) {
+        let value = (fraction.clamp(0.0, 1.0) * FRACTION_SCALE).round() as u32;
+        self.scroll_fraction.store(value, Ordering::Relaxed);
+    }
+
+    pub fn update_from<R>(&self, output: &egui::scroll_area::ScrollAreaOutput<R>) {
+        let max_offset = output.content_size.y - output.inner_rect.height();
+        self.set_fraction(if max_offset > 0.0 { output.state.offset.y / max_offset } else { 0.0 });
+    }
+
+    // Смещение области прокрутки высотой visible с содержимым высотой content
+    pub fn offset_for(&self, content: f32, visible: f32) -> f32 {
+        self.fraction() * (content - visible).max(0.0)
+    }
+}
+
+// Положение прокрутки редактора: смещение, высота видимой области и всего текста
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Viewport {
+    pub offset: f32,
+    pub visible: f32,
+    pub content: f32,
+}
+
+impl Viewport {
+    // Видимая часть текста как доли от 0 до 1 его высоты
+    pub fn visible_fraction(&self) -> (f32, f32) {
+        if self.content <= 0.0 {
+            return (0.0, 1.0);
+        }
+        let top = (self.offset / self.content).clamp(0.0, 1.0);
+        let bottom = ((self.offset + self.visible) / self.content).clamp(top, 1.0);
+        (top, bottom)
+    }
+
+    // Смещение, при котором точка fraction оказывается в середине видимой области
+    pub fn offset_for_fraction(&self, fraction: f32) -> f32 {
+        let max_offset = (self.content - self.visible).max(0.0);
+        (fraction.clamp(0.0, 1.0) * self.content - self.visible / 2.0).clamp(0.0, max_offset)
+    }
+}
+
+// Рисует строки документа полосками в области прокрутки, синхронной с редактором;
+// возвращает новое смещение прокрутки редактора после щелчка
+pub fn show_minimap(ui: &mut egui::Ui, text: &str, viewport: Viewport, sync: &SyncScroll) -> Option<f32> {
+    let line_count = text.lines().count().max(1);
+    let map_height = LINE_HEIGHT * line_count as f32;
+    let offset = sync.offset_for(map_height, ui.available_height());
+
+    egui::ScrollArea::vertical()
+        .id_source("minimap_scroll_area")
+        .vertical_scroll_offset(offset)
+        .enable_scrolling(false)
+        .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
+        .auto_shrink([false, false])
+        .show_viewport(ui, |ui, visible| {
+            let size = egui::vec2(ui.available_width(), map_height.max(visible.height()));
+            let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+            let painter = ui.painter_at(rect);
+            let color = ui.visuals().text_color().gamma_multiply(0.4);
+
+            // Рисуются только строки, попавшие в видимую часть
+            let first = (visible.min.y / LINE_HEIGHT) as usize;
+            let count = (visible.height() / LINE_HEIGHT).ceil() as usize + 1;
+            for (i, line) in text.lines().enumerate().skip(first).take(count) {
+                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                let length = line.trim_end().chars().count();
+                if length <= indent {
+                    continue;
+                }
+                let y = rect.top() + i as f32 * LINE_HEIGHT;
+                let x_start = (rect.left() + indent as f32 * CHAR_WIDTH).min(rect.right());
+                let x_end = (rect.left() + length as f32 * CHAR_WIDTH).min(rect.right());
+                let line_rect = egui::Rect::from_min_max(
+                    egui::pos2(x_start, y),
+                    egui::pos2(x_end, y + LINE_HEIGHT * 0.7),
+                );
+                painter.rect_filled(line_rect, 0.0, color);
+            }
+
+            let (top, bottom) = viewport.visible_fraction();
+            let visible_rect = egui::Rect::from_x_y_ranges(
+                rect.x_range(),
+                rect.top() + top * map_height..=rect.top() + bottom * map_height,
+            );
+            painter.rect_filled(visible_rect, 0.0, Color32::from_gray(128).gamma_multiply(0.25));
+
+            let pointer = response.interact_pointer_pos().filter(|_| response.clicked() || response.dragged())?;
+            let fraction = (pointer.y - rect.top()) / map_height;
+            Some(viewport.offset_for_fraction(fraction))
+        })
+        .inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(offset: f32, visible: f32, content: f32) -> Viewport {
+        Viewport { offset, visible, content }
+    }
+
+    #[test]
+    fn visible_fraction_for_document_sizes() {
+        assert_eq!(viewport(0.0, 500.0, 1000.0).visible_fraction(), (0.0, 0.5));
+        assert_eq!(viewport(500.0, 500.0, 1000.0).visible_fraction(), (0.5, 1.0));
+        assert_eq!(viewport(900.0, 100.0, 10_000.0).visible_fraction(), (0.09, 0.1));
+        // Короткий документ целиком помещается в видимую область
+        assert_eq!(viewport(0.0, 500.0, 200.0).visible_fraction(), (0.0, 1.0));
+        assert_eq!(viewport(0.0, 500.0, 0.0).visible_fraction(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn click_centers_view_on_fraction() {
+        let long = viewport(0.0, 100.0, 1000.0);
+        assert_eq!(long.offset_for_fraction(0.5), 450.0);
+        assert_eq!(long.offset_for_fraction(0.0), 0.0);
+        assert_eq!(long.offset_for_fraction(1.0), 900.0);
+        assert_eq!(viewport(0.0, 500.0, 200.0).offset_for_fraction(0.8), 0.0);
+    }
+
+    #[test]
+    fn minimap_scrolls_by_editor_fraction() {
+        let sync = SyncScroll::default();
+        sync.set_fraction(0.25);
+        assert_eq!(sync.fraction(), 0.25);
+        assert_eq!(sync.offset_for(2000.0, 400.0), 400.0);
+        assert_eq!(sync.offset_for(300.0, 400.0), 0.0);
+
+        sync.set_fraction(1.5);
+        assert_eq!(sync.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+// Печать через ассоциированное приложение: в Windows действие "Print" оболочки, иначе lp
+fn spawn_print(path: &Path) -> std::io::Result<Child> {
+    if cfg!(windows) {
+        let path = path.display().to_string().replace('\'', "''");
+        Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                &format!("Start-Process -FilePath '{}' -Verb Print -PassThru | Wait-Process", path),
+            ])
+            .spawn()
+    } else {
+        Command::new("lp").arg(path).spawn()
+    }
+}
+
+// Временный файл для печати несохраненного текста; расширение сохраняется для выбора приложения
+pub fn temp_print_path(title: &str) -> PathBuf {
+    let name: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    std::env::temp_dir().join(format!("tekst_print_{}_{}", std::process::id(), name))
+}
+
+// Сохраненный файл печатается как есть, иначе текст пишется во временный файл,
+// который удаляется после завершения печати
+pub fn print_document(path: Option<&Path>, title: &str, content: &str) -> std::io::Result<()> {
+    if let Some(path) = path {
+        spawn_print(path)?;
+        return Ok(());
+    }
+
+    let temp = temp_print_path(title);
+    std::fs::write(&temp, content)?;
+    let mut child = match spawn_print(&temp) {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+    };
+    std::thread::spawn(move || {
+        let _ = child.wait();
+        let _ = std::fs::remove_file(&temp);
+    });
+    Ok(())
+}
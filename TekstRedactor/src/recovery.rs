@@ -0,0 +1,33 @@
+use std::path::{Path, PathBuf};
+
+// Копии несохраненных документов во временной папке на случай аварийного завершения
+const FILE_PREFIX: &str = "TekstRedactor_recovery_";
+
+pub fn recovery_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{}{}.txt", FILE_PREFIX, index))
+}
+
+pub fn write(dir: &Path, index: usize, content: &str) -> std::io::Result<()> {
+    std::fs::write(recovery_path(dir, index), content)
+}
+
+pub fn remove(dir: &Path, index: usize) {
+    let _ = std::fs::remove_file(recovery_path(dir, index));
+}
+
+// Найденные файлы восстановления, упорядоченные по номеру документа
+pub fn find_all(dir: &Path) -> Vec<(usize, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(usize, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index = name.to_str()?.strip_prefix(FILE_PREFIX)?.strip_suffix(".txt")?.parse().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
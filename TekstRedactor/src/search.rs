@@ -0,0 +1,115 @@
+use crate::document::Document;
+use std::collections::VecDeque;
+
+pub const FIND_HISTORY_CAPACITY: usize = 20;
+
+// Запрос из диалога "Найти и заменить" ставится первым; повтор переносится наверх
+pub fn push_find_history(history: &mut VecDeque<String>, query: &str) {
+    if query.is_empty() {
+        return;
+    }
+    history.retain(|entry| entry != query);
+    history.push_front(query.to_string());
+    history.truncate(FIND_HISTORY_CAPACITY);
+}
+
+// Параметры поиска по всем открытым документам
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub match_case: bool,
+    pub whole_word: bool,
+    pub use_regex: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalSearchResult {
+    pub doc_index: usize,
+    pub doc_title: String,
+    pub line_number: usize,
+    pub line_text: String,
+    pub match_start_in_line: usize,
+    // Положение совпадения в документе, для перехода к нему
+    pub match_range: (usize, usize),
+}
+
+pub fn search_documents(
+    documents: &[Document],
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<GlobalSearchResult>, regex::Error> {
+    let mut results = Vec::new();
+
+    for (doc_index, doc) in documents.iter().enumerate() {
+        let positions = doc.find_all_positions(query, options.match_case, options.whole_word, options.use_regex)?;
+        for (start, end) in positions {
+            let line_start = doc.content[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = doc.content[start..].find('\n').map_or(doc.content.len(), |i| start + i);
+            results.push(GlobalSearchResult {
+                doc_index,
+                doc_title: doc.title().to_string(),
+                line_number: doc.content[..start].matches('\n').count() + 1,
+                line_text: doc.content[line_start..line_end].to_string(),
+                match_start_in_line: doc.content[line_start..start].chars().count(),
+                match_range: (start, end),
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents() -> Vec<Document> {
+        vec![
+            Document::with_content("a.txt", "первая строка\nнайти тут".to_string()),
+            Document::with_content("b.txt", "здесь ничего нет".to_string()),
+            Document::with_content("c.txt", "Найти и найти".to_string()),
+        ]
+    }
+
+    #[test]
+    fn search_finds_matches_only_in_documents_with_the_term() {
+        let results = search_documents(&documents(), "найти", &SearchOptions::default()).unwrap();
+        let found: Vec<(usize, usize, usize)> =
+            results.iter().map(|r| (r.doc_index, r.line_number, r.match_start_in_line)).collect();
+        assert_eq!(found, [(0, 2, 0), (2, 1, 0), (2, 1, 8)]);
+        assert_eq!(results[0].doc_title, "a.txt");
+        assert_eq!(results[0].line_text, "найти тут");
+        assert_eq!(results[2].match_range, ("Найти и ".len(), "Найти и найти".len()));
+    }
+
+    #[test]
+    fn search_options_narrow_the_matches() {
+        let options = SearchOptions { match_case: true, ..Default::default() };
+        let results = search_documents(&documents(), "Найти", &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].doc_index, 2);
+
+        let options = SearchOptions { use_regex: true, ..Default::default() };
+        assert!(search_documents(&documents(), "(", &options).is_err());
+    }
+
+    #[test]
+    fn find_history_moves_repeats_to_front() {
+        let mut history = VecDeque::new();
+        push_find_history(&mut history, "TODO");
+        push_find_history(&mut history, "fn main");
+        push_find_history(&mut history, "");
+        push_find_history(&mut history, "TODO");
+        assert_eq!(history, ["TODO", "fn main"]);
+    }
+
+    #[test]
+    fn find_history_is_capped() {
+        let mut history = VecDeque::new();
+        for i in 0..FIND_HISTORY_CAPACITY + 5 {
+            push_find_history(&mut history, &format!("запрос {}", i));
+        }
+        assert_eq!(history.len(), FIND_HISTORY_CAPACITY);
+        assert_eq!(history.front().unwrap(), "запрос 24");
+        assert_eq!(history.back().unwrap(), "запрос 5");
+    }
+}
@@ -0,0 +1,116 @@
+use eframe::egui::{self, Key, Modifiers, RichText};
+
+use crate::search::GlobalSearchResult;
+
+// Что пользователь сделал в панели результатов за кадр
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanelAction {
+    None,
+    Jump(usize),
+    Close,
+}
+
+// Результаты поиска по всем документам; панель остается открытой после поиска,
+// пока ее не закроют
+#[derive(Debug, Clone, Default)]
+pub struct SearchResultsPanel {
+    pub results: Vec<GlobalSearchResult>,
+    pub last_query: String,
+    pub selected_result: Option<usize>,
+    // Стрелки и Enter управляют панелью после щелчка по ней, пока фокус не перейдет
+    // в другой элемент
+    keyboard_active: bool,
+}
+
+impl SearchResultsPanel {
+    pub fn new(query: &str, results: Vec<GlobalSearchResult>) -> Self {
+        Self {
+            selected_result: (!results.is_empty()).then_some(0),
+            results,
+            last_query: query.to_string(),
+            keyboard_active: false,
+        }
+    }
+
+    // Возвращает действие и признак того, что выделение сдвинулось стрелками
+    fn handle_keys(&mut self, ctx: &egui::Context) -> (PanelAction, bool) {
+        if !self.keyboard_active || self.results.is_empty() {
+            return (PanelAction::None, false);
+        }
+        if ctx.memory(|m| m.focused().is_some()) {
+            self.keyboard_active = false;
+            return (PanelAction::None, false);
+        }
+
+        let (down, up, enter) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+            )
+        });
+        let last = self.results.len() - 1;
+        if down {
+            self.selected_result = Some(self.selected_result.map_or(0, |i| (i + 1).min(last)));
+        }
+        if up {
+            self.selected_result = Some(self.selected_result.map_or(0, |i| i.saturating_sub(1)));
+        }
+        let action = match self.selected_result {
+            Some(index) if enter => PanelAction::Jump(index),
+            _ => PanelAction::None,
+        };
+        (action, down || up)
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> PanelAction {
+        let (mut action, scroll_to_selected) = self.handle_keys(ctx);
+
+        egui::SidePanel::right("search_results")
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("Результаты: «{}»", self.last_query)).strong());
+                    if ui.small_button("✖").on_hover_text("Закрыть").clicked() {
+                        action = PanelAction::Close;
+                    }
+                });
+                ui.label(RichText::new(format!("Найдено: {}", self.results.len())).weak());
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_source("search_results_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        let mut start = 0;
+                        // Результаты идут по документам подряд, поэтому группируем соседние
+                        for group in self.results.chunk_by(|a, b| a.doc_index == b.doc_index) {
+                            let header = format!("{} ({})", group[0].doc_title, group.len());
+                            egui::CollapsingHeader::new(header)
+                                .id_source(("search_results_group", group[0].doc_index))
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for (offset, result) in group.iter().enumerate() {
+                                        let index = start + offset;
+                                        let selected = self.selected_result == Some(index);
+                                        let text = format!("{}: {}", result.line_number, result.line_text.trim());
+                                        let response = ui.selectable_label(selected, text);
+                                        if selected && scroll_to_selected {
+                                            response.scroll_to_me(None);
+                                        }
+                                        if response.clicked() {
+                                            self.selected_result = Some(index);
+                                            self.keyboard_active = true;
+                                            action = PanelAction::Jump(index);
+                                        }
+                                    }
+                                });
+                            start += group.len();
+                        }
+                    });
+            });
+
+        action
+    }
+}
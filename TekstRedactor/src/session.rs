@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::document::Bookmark;
+use crate::settings::AppSettings;
+
+// Открытые при выходе документы, восстанавливаемые при следующем запуске
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub documents: Vec<SessionDocument>,
+    pub active_document: usize,
+    // История буфера обмена, последний скопированный фрагмент первым
+    pub clipboard_history: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDocument {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Session {
+    fn session_path() -> PathBuf {
+        AppSettings::config_dir().join("session.json")
+    }
+
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::session_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(AppSettings::config_dir())?;
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(Self::session_path(), content)?;
+        Ok(())
+    }
+}
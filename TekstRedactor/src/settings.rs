@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use eframe::egui;
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+use crate::macros::MacroAction;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Theme {
     Light,
     Dark,
+    Custom(CustomTheme),
 }
 
 impl Theme {
@@ -13,22 +18,429 @@ impl Theme {
         [Theme::Light, Theme::Dark]
     }
 
+    pub fn name(&self) -> &str {
+        match self {
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::Custom(theme) => &theme.name,
+        }
+    }
+
     pub fn egui_visuals(&self) -> egui::Visuals {
         match self {
             Theme::Light => egui::Visuals::light(),
             Theme::Dark => egui::Visuals::dark(),
+            Theme::Custom(theme) => theme.to_egui_visuals(),
         }
     }
 }
 
+// Пользовательская тема; цвета хранятся как RGBA без предумножения.
+// Лишние ключи в файле темы считаются ошибкой, а не молча пропускаются
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomTheme {
+    pub name: String,
+    pub background: [u8; 4],
+    pub text: [u8; 4],
+    pub selection: [u8; 4],
+    pub widget_bg: [u8; 4],
+    pub panel_bg: [u8; 4],
+}
+
+fn color([r, g, b, a]: [u8; 4]) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+impl CustomTheme {
+    // Начальные цвета новой темы берутся из встроенной светлой или темной
+    pub fn from_visuals(name: &str, visuals: &egui::Visuals) -> Self {
+        let text = visuals.override_text_color.unwrap_or_else(|| visuals.text_color());
+        Self {
+            name: name.to_string(),
+            background: visuals.extreme_bg_color.to_srgba_unmultiplied(),
+            text: text.to_srgba_unmultiplied(),
+            selection: visuals.selection.bg_fill.to_srgba_unmultiplied(),
+            widget_bg: visuals.widgets.inactive.bg_fill.to_srgba_unmultiplied(),
+            panel_bg: visuals.panel_fill.to_srgba_unmultiplied(),
+        }
+    }
+
+    // Остальные параметры берутся из светлой или темной темы в зависимости от яркости фона
+    pub fn to_egui_visuals(&self) -> egui::Visuals {
+        let [r, g, b, _] = self.background;
+        let brightness = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        let mut visuals = if brightness < 128 { egui::Visuals::dark() } else { egui::Visuals::light() };
+
+        visuals.extreme_bg_color = color(self.background);
+        visuals.override_text_color = Some(color(self.text));
+        visuals.selection.bg_fill = color(self.selection);
+        visuals.widgets.inactive.bg_fill = color(self.widget_bg);
+        visuals.widgets.inactive.weak_bg_fill = color(self.widget_bg);
+        visuals.panel_fill = color(self.panel_bg);
+        visuals.window_fill = color(self.panel_bg);
+        visuals
+    }
+
+    pub fn themes_dir() -> PathBuf {
+        AppSettings::config_dir().join("themes")
+    }
+
+    fn file_path(&self) -> PathBuf {
+        Self::themes_dir().join(json_file_name(&self.name))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(Self::themes_dir())?;
+        std::fs::write(self.file_path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Все темы из папки themes; поврежденные файлы пропускаются
+    pub fn load_all() -> Vec<CustomTheme> {
+        let mut themes: Vec<CustomTheme> = load_json_dir(&Self::themes_dir());
+        themes.sort_by(|a, b| a.name.cmp(&b.name));
+        themes
+    }
+}
+
+// Имя файла без символов, недопустимых в путях
+fn json_file_name(name: &str) -> String {
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == ' ' { c } else { '_' })
+        .collect();
+    format!("{}.json", file_name.trim())
+}
+
+fn load_json_dir<T: serde::de::DeserializeOwned>(dir: &std::path::Path) -> Vec<T> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect()
+}
+
+// Заготовка нового документа; extension без точки, пустое - для любых файлов
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentTemplate {
+    pub name: String,
+    pub extension: String,
+    pub content: String,
+}
+
+impl DocumentTemplate {
+    pub fn templates_dir() -> PathBuf {
+        AppSettings::config_dir().join("templates")
+    }
+
+    fn file_path(&self) -> PathBuf {
+        Self::templates_dir().join(json_file_name(&self.name))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(Self::templates_dir())?;
+        std::fs::write(self.file_path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn delete(&self) -> std::io::Result<()> {
+        std::fs::remove_file(self.file_path())
+    }
+
+    pub fn load_all() -> Vec<DocumentTemplate> {
+        let mut templates: Vec<DocumentTemplate> = load_json_dir(&Self::templates_dir());
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+}
+
+// Индексы шаблонов для расширения; None - все шаблоны
+pub fn filter_templates(templates: &[DocumentTemplate], extension: Option<&str>) -> Vec<usize> {
+    templates
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| extension.is_none_or(|ext| t.extension.eq_ignore_ascii_case(ext)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Имя семейства egui, под которым регистрируется шрифт из файла
+pub const CUSTOM_FONT_FAMILY: &str = "editor_custom";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FontFamily {
+    Monospace,
+    Proportional,
+    // Путь к файлу TTF/OTF
+    Custom(String),
+}
+
+impl FontFamily {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FontFamily::Monospace => "Моноширинный",
+            FontFamily::Proportional => "Пропорциональный",
+            FontFamily::Custom(_) => "Из файла",
+        }
+    }
+
+    pub fn egui_family(&self) -> egui::FontFamily {
+        match self {
+            FontFamily::Monospace => egui::FontFamily::Monospace,
+            FontFamily::Proportional => egui::FontFamily::Proportional,
+            FontFamily::Custom(_) => egui::FontFamily::Name(CUSTOM_FONT_FAMILY.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    New,
+    Open,
+    Save,
+    SaveAs,
+    SaveAll,
+    Print,
+    Find,
+    FindPrevious,
+    GlobalSearch,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    DuplicateLine,
+    DeleteLine,
+    MoveLineUp,
+    MoveLineDown,
+    FormatJson,
+    DistractionFree,
+    ToggleComment,
+}
+
+impl ShortcutAction {
+    pub fn all() -> &'static [ShortcutAction] {
+        &[
+            ShortcutAction::New,
+            ShortcutAction::Open,
+            ShortcutAction::Save,
+            ShortcutAction::SaveAs,
+            ShortcutAction::SaveAll,
+            ShortcutAction::Print,
+            ShortcutAction::Find,
+            ShortcutAction::FindPrevious,
+            ShortcutAction::GlobalSearch,
+            ShortcutAction::SelectAll,
+            ShortcutAction::Copy,
+            ShortcutAction::Cut,
+            ShortcutAction::Paste,
+            ShortcutAction::Undo,
+            ShortcutAction::Redo,
+            ShortcutAction::DuplicateLine,
+            ShortcutAction::DeleteLine,
+            ShortcutAction::MoveLineUp,
+            ShortcutAction::MoveLineDown,
+            ShortcutAction::FormatJson,
+            ShortcutAction::DistractionFree,
+            ShortcutAction::ToggleComment,
+        ]
+    }
+
+    // Действия, меняющие текст; в документах только для чтения они не выполняются
+    pub fn modifies_text(&self) -> bool {
+        matches!(
+            self,
+            ShortcutAction::Cut
+                | ShortcutAction::Paste
+                | ShortcutAction::Undo
+                | ShortcutAction::Redo
+                | ShortcutAction::DuplicateLine
+                | ShortcutAction::DeleteLine
+                | ShortcutAction::MoveLineUp
+                | ShortcutAction::MoveLineDown
+                | ShortcutAction::FormatJson
+                | ShortcutAction::ToggleComment
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortcutAction::New => "Создать",
+            ShortcutAction::Open => "Открыть",
+            ShortcutAction::Save => "Сохранить",
+            ShortcutAction::SaveAs => "Сохранить как",
+            ShortcutAction::SaveAll => "Сохранить все",
+            ShortcutAction::Print => "Печать",
+            ShortcutAction::Find => "Найти/Заменить",
+            ShortcutAction::FindPrevious => "Найти предыдущее",
+            ShortcutAction::GlobalSearch => "Поиск в проекте",
+            ShortcutAction::SelectAll => "Выделить всё",
+            ShortcutAction::Copy => "Копировать",
+            ShortcutAction::Cut => "Вырезать",
+            ShortcutAction::Paste => "Вставить",
+            ShortcutAction::Undo => "Отменить",
+            ShortcutAction::Redo => "Повторить",
+            ShortcutAction::DuplicateLine => "Дублировать строку",
+            ShortcutAction::DeleteLine => "Удалить строку",
+            ShortcutAction::MoveLineUp => "Переместить строку вверх",
+            ShortcutAction::MoveLineDown => "Переместить строку вниз",
+            ShortcutAction::FormatJson => "Форматировать JSON",
+            ShortcutAction::DistractionFree => "Режим без отвлечений",
+            ShortcutAction::ToggleComment => "Закомментировать строки",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KeyCombo {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyCombo {
+    pub fn new(modifiers: Modifiers, key: Key) -> Self {
+        Self { key, modifiers }
+    }
+
+    pub fn to_shortcut(self) -> KeyboardShortcut {
+        KeyboardShortcut::new(self.modifiers, self.key)
+    }
+
+    // Количество модификаторов: более специфичные сочетания проверяются первыми
+    pub fn modifier_count(&self) -> usize {
+        [self.modifiers.ctrl || self.modifiers.command, self.modifiers.shift, self.modifiers.alt]
+            .iter()
+            .filter(|m| **m)
+            .count()
+    }
+}
+
+// Список колонок через запятую; нераспознанные и нулевые значения пропускаются
+pub fn parse_column_list(text: &str) -> Vec<u8> {
+    let mut columns: Vec<u8> = text
+        .split(',')
+        .filter_map(|part| part.trim().parse().ok())
+        .filter(|column| *column > 0)
+        .collect();
+    columns.sort_unstable();
+    columns.dedup();
+    columns
+}
+
+pub fn format_column_list(columns: &[u8]) -> String {
+    columns.iter().map(|column| column.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+pub fn default_shortcuts() -> HashMap<ShortcutAction, KeyCombo> {
+    HashMap::from([
+        (ShortcutAction::New, KeyCombo::new(Modifiers::CTRL, Key::N)),
+        (ShortcutAction::Open, KeyCombo::new(Modifiers::CTRL, Key::O)),
+        (ShortcutAction::Save, KeyCombo::new(Modifiers::CTRL, Key::S)),
+        (ShortcutAction::SaveAs, KeyCombo::new(Modifiers::CTRL | Modifiers::ALT, Key::S)),
+        (ShortcutAction::SaveAll, KeyCombo::new(Modifiers::CTRL | Modifiers::SHIFT, Key::S)),
+        (ShortcutAction::Print, KeyCombo::new(Modifiers::CTRL, Key::P)),
+        (ShortcutAction::Find, KeyCombo::new(Modifiers::CTRL, Key::F)),
+        (ShortcutAction::FindPrevious, KeyCombo::new(Modifiers::SHIFT, Key::F3)),
+        (ShortcutAction::GlobalSearch, KeyCombo::new(Modifiers::CTRL | Modifiers::SHIFT, Key::F)),
+        (ShortcutAction::SelectAll, KeyCombo::new(Modifiers::CTRL, Key::A)),
+        (ShortcutAction::Copy, KeyCombo::new(Modifiers::CTRL, Key::C)),
+        (ShortcutAction::Cut, KeyCombo::new(Modifiers::CTRL, Key::X)),
+        (ShortcutAction::Paste, KeyCombo::new(Modifiers::CTRL, Key::V)),
+        (ShortcutAction::Undo, KeyCombo::new(Modifiers::CTRL, Key::Z)),
+        (ShortcutAction::Redo, KeyCombo::new(Modifiers::CTRL, Key::Y)),
+        (ShortcutAction::DuplicateLine, KeyCombo::new(Modifiers::CTRL, Key::D)),
+        (ShortcutAction::DeleteLine, KeyCombo::new(Modifiers::CTRL | Modifiers::SHIFT, Key::K)),
+        (ShortcutAction::MoveLineUp, KeyCombo::new(Modifiers::ALT, Key::ArrowUp)),
+        (ShortcutAction::MoveLineDown, KeyCombo::new(Modifiers::ALT, Key::ArrowDown)),
+        (ShortcutAction::FormatJson, KeyCombo::new(Modifiers::CTRL | Modifiers::ALT, Key::F)),
+        (ShortcutAction::DistractionFree, KeyCombo::new(Modifiers::NONE, Key::F11)),
+        (ShortcutAction::ToggleComment, KeyCombo::new(Modifiers::CTRL, Key::Slash)),
+    ])
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub theme: Theme,
     pub font_size: f32,
+    pub font_family: FontFamily,
     pub auto_save_enabled: bool,
     pub auto_save_interval_secs: u64,
     #[serde(skip)]
     pub auto_save_interval: Duration,
+    // Куда автосохранение записывает документы без пути; None - такие документы пропускаются
+    pub default_save_directory: Option<PathBuf>,
+    pub shortcuts: HashMap<ShortcutAction, KeyCombo>,
+    pub autocomplete_enabled: bool,
+    pub autocomplete_accept_key: Key,
+    pub markdown_preview: bool,
+    pub show_minimap: bool,
+    // Панель заголовков слева для Markdown-документов
+    pub show_outline: bool,
+    // Документы больше порога раскладываются только в видимой части и без переноса строк
+    pub large_file_threshold_bytes: usize,
+    pub word_count_target: Option<usize>,
+    pub word_wrap: bool,
+    pub wrap_at_column: Option<usize>,
+    pub column_rulers: Vec<u8>,
+    pub ruler_color: egui::Color32,
+    pub tab_size: u8,
+    pub indent_with_spaces: bool,
+    // Новая строка начинается с отступа текущей
+    pub auto_indent: bool,
+    pub auto_close_pairs: bool,
+    // Поиск по мере ввода в диалоге "Найти и заменить"
+    pub incremental_search: bool,
+    // Как часто проверяется файл в режиме просмотра хвоста
+    pub tail_poll_interval_ms: u64,
+    pub last_macro: Vec<MacroAction>,
+    // Запросы диалога "Найти и заменить", последний первым
+    pub find_history: VecDeque<String>,
+    // Последний выбранный шаблон для каждого фильтра расширения; "" - без фильтра
+    pub last_templates: HashMap<String, String>,
+    // Размер и положение окна при последнем запуске, в логических точках
+    pub window_width: f32,
+    pub window_height: f32,
+    pub window_x: Option<i32>,
+    pub window_y: Option<i32>,
+}
+
+// Размер и положение окна из файла настроек; читается до создания окна,
+// не дожидаясь фоновой загрузки остальных настроек
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub window_x: Option<i32>,
+    pub window_y: Option<i32>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            window_width: 800.0,
+            window_height: 600.0,
+            window_x: None,
+            window_y: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    pub fn load() -> Self {
+        std::fs::read_to_string(AppSettings::settings_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
 }
 
 impl Default for AppSettings {
@@ -36,21 +448,289 @@ impl Default for AppSettings {
         Self {
             theme: Theme::Light,
             font_size: 16.0,
+            font_family: FontFamily::Monospace,
             auto_save_enabled: true,
             auto_save_interval_secs: 30,
             auto_save_interval: Duration::from_secs(30),
+            default_save_directory: None,
+            shortcuts: default_shortcuts(),
+            autocomplete_enabled: true,
+            autocomplete_accept_key: Key::Tab,
+            markdown_preview: true,
+            show_minimap: false,
+            show_outline: true,
+            large_file_threshold_bytes: 1024 * 1024,
+            word_count_target: None,
+            word_wrap: true,
+            wrap_at_column: None,
+            column_rulers: vec![80, 100],
+            ruler_color: egui::Color32::from_gray(128).gamma_multiply(0.4),
+            tab_size: 4,
+            indent_with_spaces: false,
+            auto_indent: true,
+            auto_close_pairs: true,
+            incremental_search: true,
+            tail_poll_interval_ms: 500,
+            last_macro: Vec::new(),
+            find_history: VecDeque::new(),
+            last_templates: HashMap::new(),
+            window_width: WindowGeometry::default().window_width,
+            window_height: WindowGeometry::default().window_height,
+            window_x: None,
+            window_y: None,
         }
     }
 }
 
 impl AppSettings {
+    pub fn config_dir() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("TekstRedactor")
+    }
+
+    fn settings_path() -> PathBuf {
+        Self::config_dir().join("settings.json")
+    }
+
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        // For now, just return default settings
-        Ok(Self::default())
+        let path = Self::settings_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    // Разбор содержимого файла настроек с переносом устаревших значений
+    fn from_json(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut settings: AppSettings = serde_json::from_str(content)?;
+        settings.auto_save_interval = Duration::from_secs(settings.auto_save_interval_secs);
+
+        // Ctrl+Shift+S раньше означало "Сохранить как", теперь оно у "Сохранить все"
+        let old_save_as = KeyCombo::new(Modifiers::CTRL | Modifiers::SHIFT, Key::S);
+        if !settings.shortcuts.contains_key(&ShortcutAction::SaveAll)
+            && settings.shortcuts.get(&ShortcutAction::SaveAs) == Some(&old_save_as)
+        {
+            settings.shortcuts.remove(&ShortcutAction::SaveAs);
+        }
+
+        // Действия, добавленные после сохранения файла, получают сочетания по умолчанию
+        for (action, combo) in default_shortcuts() {
+            settings.shortcuts.entry(action).or_insert(combo);
+        }
+
+        Ok(settings)
     }
 
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // For now, just succeed
+        std::fs::create_dir_all(Self::config_dir())?;
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(Self::settings_path(), content)?;
+        Ok(())
+    }
+
+    pub fn window_geometry(&self) -> WindowGeometry {
+        WindowGeometry {
+            window_width: self.window_width,
+            window_height: self.window_height,
+            window_x: self.window_x,
+            window_y: self.window_y,
+        }
+    }
+
+    pub fn set_window_geometry(&mut self, geometry: WindowGeometry) {
+        self.window_width = geometry.window_width;
+        self.window_height = geometry.window_height;
+        self.window_x = geometry.window_x;
+        self.window_y = geometry.window_y;
+    }
+
+    // Ширина области текста при переносе по колонке; None - перенос по границе панели
+    pub fn wrap_width(&self, char_width: f32) -> Option<f32> {
+        match self.wrap_at_column {
+            Some(columns) if self.word_wrap => Some(columns as f32 * char_width),
+            _ => None,
+        }
+    }
+
+    // Текст, вставляемый клавишей Tab
+    pub fn indent_unit(&self) -> String {
+        if self.indent_with_spaces {
+            " ".repeat(self.tab_size as usize)
+        } else {
+            "\t".to_string()
+        }
+    }
+
+    // Текущая пользовательская тема отдельным файлом, чтобы ею можно было поделиться
+    pub fn export_theme(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let Theme::Custom(theme) = &self.theme else {
+            return Err("выбрана встроенная тема, экспортировать можно только пользовательскую".into());
+        };
+        std::fs::write(path, serde_json::to_string_pretty(theme)?)?;
+        Ok(())
+    }
+
+    // Цвета хранятся в u8, поэтому значения вне 0..=255 отклоняются при разборе
+    pub fn import_theme(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let theme: CustomTheme = serde_json::from_str(&content)?;
+        self.theme = Theme::Custom(theme);
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Сочетания, отсортированные так, чтобы Ctrl+Shift+S проверялось раньше Ctrl+S
+    pub fn sorted_shortcuts(&self) -> Vec<(ShortcutAction, KeyCombo)> {
+        let mut shortcuts: Vec<(ShortcutAction, KeyCombo)> =
+            self.shortcuts.iter().map(|(a, c)| (*a, *c)).collect();
+        shortcuts.sort_by_key(|(_, combo)| std::cmp::Reverse(combo.modifier_count()));
+        shortcuts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remapped_shortcut_is_read_back() {
+        let mut settings = AppSettings::default();
+        let combo = KeyCombo::new(Modifiers::CTRL | Modifiers::SHIFT, Key::W);
+        settings.shortcuts.insert(ShortcutAction::Save, combo);
+
+        let loaded = AppSettings::from_json(&serde_json::to_string_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(loaded.shortcuts.get(&ShortcutAction::Save), Some(&combo));
+        assert_eq!(loaded.shortcuts.len(), default_shortcuts().len());
+    }
+
+    #[test]
+    fn missing_shortcuts_get_defaults() {
+        // Файл от старой версии, где было только одно сочетание
+        let combo = KeyCombo::new(Modifiers::ALT, Key::U);
+        let settings = AppSettings { shortcuts: HashMap::from([(ShortcutAction::Undo, combo)]), ..Default::default() };
+
+        let loaded = AppSettings::from_json(&serde_json::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(loaded.shortcuts[&ShortcutAction::Undo], combo);
+        assert_eq!(loaded.shortcuts[&ShortcutAction::Redo], default_shortcuts()[&ShortcutAction::Redo]);
+    }
+
+    #[test]
+    fn wrap_width_is_columns_times_char_width() {
+        let settings = AppSettings { wrap_at_column: Some(80), ..Default::default() };
+        assert_eq!(settings.wrap_width(9.5), Some(760.0));
+        assert_eq!(AppSettings { word_wrap: false, ..settings.clone() }.wrap_width(9.5), None);
+        // Без колонки перенос идет по границе панели
+        assert_eq!(AppSettings::default().wrap_width(9.5), None);
+    }
+
+    fn custom_theme(background: [u8; 4]) -> CustomTheme {
+        CustomTheme {
+            name: "Тест".to_string(),
+            background,
+            text: [200, 201, 202, 255],
+            selection: [10, 20, 30, 255],
+            widget_bg: [40, 50, 60, 255],
+            panel_bg: [70, 80, 90, 255],
+        }
+    }
+
+    #[test]
+    fn custom_theme_maps_every_color() {
+        let visuals = custom_theme([15, 16, 17, 255]).to_egui_visuals();
+        assert!(visuals.dark_mode);
+        assert_eq!(visuals.extreme_bg_color, egui::Color32::from_rgb(15, 16, 17));
+        assert_eq!(visuals.override_text_color, Some(egui::Color32::from_rgb(200, 201, 202)));
+        assert_eq!(visuals.selection.bg_fill, egui::Color32::from_rgb(10, 20, 30));
+        assert_eq!(visuals.widgets.inactive.bg_fill, egui::Color32::from_rgb(40, 50, 60));
+        assert_eq!(visuals.widgets.inactive.weak_bg_fill, egui::Color32::from_rgb(40, 50, 60));
+        assert_eq!(visuals.panel_fill, egui::Color32::from_rgb(70, 80, 90));
+        assert_eq!(visuals.window_fill, egui::Color32::from_rgb(70, 80, 90));
+    }
+
+    #[test]
+    fn bright_background_uses_light_base() {
+        assert!(!custom_theme([250, 250, 250, 255]).to_egui_visuals().dark_mode);
+    }
+
+    #[test]
+    fn theme_survives_visuals_round_trip() {
+        let theme = custom_theme([15, 16, 17, 255]);
+        assert_eq!(CustomTheme::from_visuals("Тест", &theme.to_egui_visuals()), theme);
+    }
+
+    fn import_from(json: &str) -> Result<AppSettings, Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("theme.json");
+        std::fs::write(&path, json).unwrap();
+        let mut settings = AppSettings::default();
+        settings.import_theme(&path)?;
+        Ok(settings)
+    }
+
+    const THEME_JSON: &str = r#"{
+        "name": "Тест",
+        "background": [15, 16, 17, 255],
+        "text": [200, 201, 202, 255],
+        "selection": [10, 20, 30, 255],
+        "widget_bg": [40, 50, 60, 255],
+        "panel_bg": [70, 80, 90, 255]
+    }"#;
+
+    #[test]
+    fn valid_theme_is_imported() {
+        let settings = import_from(THEME_JSON).unwrap();
+        assert_eq!(settings.theme, Theme::Custom(custom_theme([15, 16, 17, 255])));
+    }
+
+    #[test]
+    fn exported_theme_is_imported_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("theme.json");
+        let mut settings = AppSettings::default();
+        assert!(settings.export_theme(&path).is_err());
+
+        settings.theme = Theme::Custom(custom_theme([250, 250, 250, 255]));
+        settings.export_theme(&path).unwrap();
+        let mut imported = AppSettings::default();
+        imported.import_theme(&path).unwrap();
+        assert_eq!(imported.theme, settings.theme);
+    }
+
+    #[test]
+    fn over_range_color_is_rejected() {
+        let json = THEME_JSON.replace("[15, 16, 17, 255]", "[15, 16, 256, 255]");
+        assert!(import_from(&json).is_err());
+    }
+
+    #[test]
+    fn unknown_theme_key_is_rejected() {
+        let json = THEME_JSON.replace("\"name\"", "\"author\": \"x\", \"name\"");
+        let error = import_from(&json).unwrap_err();
+        assert!(error.to_string().contains("author"), "{}", error);
+    }
+
+    fn templates() -> Vec<DocumentTemplate> {
+        [("Заметки", ""), ("Класс", "rs"), ("Скрипт", "py"), ("Модуль", "RS")]
+            .into_iter()
+            .map(|(name, extension)| DocumentTemplate {
+                name: name.to_string(),
+                extension: extension.to_string(),
+                content: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn template_filter_matches_extension_ignoring_case() {
+        assert_eq!(filter_templates(&templates(), Some("rs")), [1, 3]);
+        assert_eq!(filter_templates(&templates(), Some("py")), [2]);
+        assert!(filter_templates(&templates(), Some("md")).is_empty());
+    }
+
+    #[test]
+    fn template_filter_without_extension_keeps_all() {
+        assert_eq!(filter_templates(&templates(), None), [0, 1, 2, 3]);
+        assert_eq!(filter_templates(&templates(), Some("")), [0]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// Изменение файла, найденное фоновым потоком
+#[derive(Debug, Clone, PartialEq)]
+pub enum TailUpdate {
+    // В конец файла дописан текст; position - размер прочитанной части файла
+    Appended { text: String, position: u64 },
+    // Файл стал короче (например, после ротации журнала) и прочитан заново
+    Reloaded { text: String, position: u64 },
+}
+
+// Часть буфера, которая декодируется целиком; неполный символ UTF-8 в конце
+// остается в буфере до следующего чтения, неверные байты заменяются на U+FFFD
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..complete]).into_owned();
+    pending.drain(..complete);
+    text
+}
+
+fn read_from(path: &Path, position: u64, pending: &mut Vec<u8>) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(position))?;
+    let read = file.read_to_end(pending)?;
+    Ok(position + read as u64)
+}
+
+// Фоновый опрос файла: каждые interval проверяется размер, и дописанные байты
+// читаются начиная с прочитанной позиции. Поток завершается вместе с наблюдателем
+pub struct TailWatcher {
+    path: PathBuf,
+    receiver: Receiver<TailUpdate>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TailWatcher {
+    pub fn start(path: PathBuf, start_position: u64, interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let watched = path.clone();
+        let stop = stopped.clone();
+        thread::spawn(move || {
+            let mut position = start_position;
+            let mut pending = Vec::new();
+            loop {
+                thread::sleep(interval);
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(len) = std::fs::metadata(&watched).map(|metadata| metadata.len()) else {
+                    continue;
+                };
+                let update = if len < position {
+                    pending.clear();
+                    let Ok(read) = read_from(&watched, 0, &mut pending) else {
+                        continue;
+                    };
+                    position = read;
+                    TailUpdate::Reloaded { text: take_utf8(&mut pending), position }
+                } else if len > position {
+                    let Ok(read) = read_from(&watched, position, &mut pending) else {
+                        continue;
+                    };
+                    position = read;
+                    TailUpdate::Appended { text: take_utf8(&mut pending), position }
+                } else {
+                    continue;
+                };
+                if sender.send(update).is_err() {
+                    return;
+                }
+            }
+        });
+        Self { path, receiver, stopped }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Все накопившиеся изменения; None, если поток завершился
+    pub fn poll(&self) -> Option<Vec<TailUpdate>> {
+        let mut updates = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(update) => updates.push(update),
+                Err(TryRecvError::Empty) => return Some(updates),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for TailWatcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}